@@ -0,0 +1,12 @@
+DROP TABLE IF EXISTS beneficiaries;
+DROP TABLE IF EXISTS claims;
+DROP TABLE IF EXISTS plans;
+DROP TABLE IF EXISTS kyc_records;
+DROP TABLE IF EXISTS user_activities;
+DROP TABLE IF EXISTS faqs;
+DROP TABLE IF EXISTS notifications;
+DROP TABLE IF EXISTS auth_nonces;
+DROP TABLE IF EXISTS users;
+
+DROP TYPE IF EXISTS plan_status;
+DROP TYPE IF EXISTS claim_status;
@@ -0,0 +1,121 @@
+-- Everything that used to be created by the startup batch. Kept idempotent so
+-- that databases created before versioned migrations can adopt this history.
+
+CREATE TABLE IF NOT EXISTS users (
+    id SERIAL PRIMARY KEY,
+    email VARCHAR(255) UNIQUE,
+    password_hash TEXT,
+    wallet_address VARCHAR(66) UNIQUE,
+    roles TEXT[] NOT NULL DEFAULT '{owner}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (email IS NOT NULL OR wallet_address IS NOT NULL)
+);
+
+CREATE TABLE IF NOT EXISTS auth_nonces (
+    nonce VARCHAR(64) PRIMARY KEY,
+    wallet_address VARCHAR(66) NOT NULL,
+    chain VARCHAR(20) NOT NULL,
+    issued_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+CREATE INDEX IF NOT EXISTS auth_nonces_wallet_address_idx ON auth_nonces (wallet_address);
+
+CREATE TABLE IF NOT EXISTS notifications (
+    id SERIAL PRIMARY KEY,
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    is_read BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS faqs (
+    id SERIAL PRIMARY KEY,
+    question TEXT NOT NULL,
+    answer TEXT NOT NULL
+);
+ALTER TABLE faqs ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE faqs ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE TABLE IF NOT EXISTS user_activities (
+    id SERIAL PRIMARY KEY,
+    user_id VARCHAR(255) NOT NULL,
+    date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    activity_type VARCHAR(50) NOT NULL,
+    details TEXT NOT NULL,
+    action_type VARCHAR(50) NOT NULL,
+    action_link TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+DO $$ BEGIN
+    CREATE TYPE claim_status AS ENUM ('pending', 'approved', 'rejected');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE IF NOT EXISTS claims (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    amount DECIMAL(10,2) NOT NULL,
+    status claim_status NOT NULL DEFAULT 'pending',
+    description TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS kyc_records (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    full_name VARCHAR(255) NOT NULL,
+    date_of_birth VARCHAR(50) NOT NULL,
+    id_type VARCHAR(50) NOT NULL,
+    id_number VARCHAR(100) NOT NULL,
+    address TEXT NOT NULL,
+    verification_status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ
+);
+
+DO $$ BEGIN
+    CREATE TYPE plan_status AS ENUM ('draft', 'active', 'triggered', 'executed', 'cancelled');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE IF NOT EXISTS plans (
+    id SERIAL PRIMARY KEY,
+    owner_id INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    assets JSONB NOT NULL DEFAULT '[]',
+    status plan_status NOT NULL DEFAULT 'draft',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS plans_owner_id_idx ON plans (owner_id);
+
+CREATE TABLE IF NOT EXISTS beneficiaries (
+    id SERIAL PRIMARY KEY,
+    plan_id INTEGER NOT NULL REFERENCES plans(id) ON DELETE CASCADE,
+    user_id INTEGER,
+    name VARCHAR(255) NOT NULL,
+    wallet_address VARCHAR(255) NOT NULL,
+    relationship VARCHAR(100),
+    email VARCHAR(255),
+    allocation_type VARCHAR(20) NOT NULL DEFAULT 'percentage',
+    share_bps INTEGER CHECK (share_bps > 0 AND share_bps <= 10000),
+    fixed_asset VARCHAR(50),
+    fixed_amount BIGINT CHECK (fixed_amount > 0),
+    position INTEGER NOT NULL DEFAULT 0,
+    CHECK (
+        (allocation_type = 'percentage' AND share_bps IS NOT NULL)
+        OR (allocation_type = 'fixed' AND fixed_asset IS NOT NULL AND fixed_amount IS NOT NULL)
+    )
+);
+
+CREATE INDEX IF NOT EXISTS beneficiaries_plan_id_idx ON beneficiaries (plan_id);
+
+ALTER TABLE claims ADD COLUMN IF NOT EXISTS plan_id INTEGER REFERENCES plans(id);
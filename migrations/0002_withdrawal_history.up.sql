@@ -0,0 +1,8 @@
+CREATE TABLE withdrawal_history (
+    id BIGSERIAL PRIMARY KEY,
+    plan_id VARCHAR(255) NOT NULL,
+    wallet_id VARCHAR(255) NOT NULL,
+    amount BIGINT NOT NULL,
+    payer_name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,11 @@
+DROP TABLE withdrawal_history;
+DROP TYPE withdrawal_status;
+
+CREATE TABLE withdrawal_history (
+    id BIGSERIAL PRIMARY KEY,
+    plan_id VARCHAR(255) NOT NULL,
+    wallet_id VARCHAR(255) NOT NULL,
+    amount BIGINT NOT NULL,
+    payer_name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
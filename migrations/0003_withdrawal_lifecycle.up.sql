@@ -0,0 +1,23 @@
+-- record_withdrawal could never insert into the 0002 table, so it is rebuilt
+-- rather than altered.
+DROP TABLE withdrawal_history;
+
+CREATE TYPE withdrawal_status AS ENUM ('requested', 'approved', 'broadcast', 'confirmed', 'failed');
+
+CREATE TABLE withdrawal_history (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    plan_id INTEGER NOT NULL REFERENCES plans(id),
+    beneficiary_id INTEGER REFERENCES beneficiaries(id) ON DELETE SET NULL,
+    wallet_id VARCHAR(255) NOT NULL,
+    asset VARCHAR(50) NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    status withdrawal_status NOT NULL DEFAULT 'requested',
+    tx_hash VARCHAR(100),
+    failure_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX withdrawal_history_user_id_idx ON withdrawal_history (user_id);
+CREATE INDEX withdrawal_history_plan_id_idx ON withdrawal_history (plan_id);
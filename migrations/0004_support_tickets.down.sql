@@ -0,0 +1,2 @@
+DROP TABLE ticket_replies;
+DROP TABLE support_tickets;
@@ -0,0 +1,30 @@
+CREATE TABLE support_tickets (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL,
+    category VARCHAR(20) NOT NULL
+        CHECK (category IN ('account', 'plan', 'kyc', 'withdrawal', 'technical', 'other')),
+    priority VARCHAR(20) NOT NULL DEFAULT 'normal'
+        CHECK (priority IN ('low', 'normal', 'high', 'urgent')),
+    status VARCHAR(20) NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'pending', 'resolved', 'closed')),
+    assignee_id INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX support_tickets_user_id_idx ON support_tickets (user_id);
+CREATE INDEX support_tickets_assignee_id_idx ON support_tickets (assignee_id);
+CREATE INDEX support_tickets_status_idx ON support_tickets (status);
+
+CREATE TABLE ticket_replies (
+    id SERIAL PRIMARY KEY,
+    ticket_id INTEGER NOT NULL REFERENCES support_tickets(id) ON DELETE CASCADE,
+    author_id INTEGER NOT NULL,
+    author_role VARCHAR(10) NOT NULL CHECK (author_role IN ('user', 'agent')),
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ticket_replies_ticket_id_idx ON ticket_replies (ticket_id);
@@ -0,0 +1,3 @@
+DROP INDEX notifications_user_id_idx;
+ALTER TABLE notifications DROP COLUMN user_id;
+DROP TABLE plan_check_ins;
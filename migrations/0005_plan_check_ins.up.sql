@@ -0,0 +1,18 @@
+-- Proof-of-life schedule, one per plan. The scheduler walks `escalation`
+-- through reminder, warning and trigger_pending before triggering the plan.
+CREATE TABLE plan_check_ins (
+    plan_id INTEGER PRIMARY KEY REFERENCES plans(id) ON DELETE CASCADE,
+    interval_secs BIGINT NOT NULL CHECK (interval_secs > 0),
+    grace_secs BIGINT NOT NULL CHECK (grace_secs > 0),
+    last_check_in_at TIMESTAMPTZ NOT NULL,
+    escalation VARCHAR(20) NOT NULL DEFAULT 'none'
+        CHECK (escalation IN ('none', 'reminder', 'warning', 'trigger_pending', 'triggered')),
+    escalated_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Notifications addressed to a single user; NULL keeps the old broadcast ones
+ALTER TABLE notifications ADD COLUMN user_id INTEGER;
+
+CREATE INDEX notifications_user_id_idx ON notifications (user_id);
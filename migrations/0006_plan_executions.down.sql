@@ -0,0 +1,3 @@
+DROP INDEX withdrawal_history_execution_id_idx;
+ALTER TABLE withdrawal_history DROP COLUMN execution_id;
+DROP TABLE plan_executions;
@@ -0,0 +1,14 @@
+-- One row per executed plan. The unique plan_id is what makes re-running the
+-- executor safe: a second execution of the same plan cannot be recorded.
+CREATE TABLE plan_executions (
+    id BIGSERIAL PRIMARY KEY,
+    plan_id INTEGER NOT NULL UNIQUE REFERENCES plans(id),
+    executed_by INTEGER NOT NULL,
+    executed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Payouts created by an execution point back at it
+ALTER TABLE withdrawal_history ADD COLUMN execution_id BIGINT REFERENCES plan_executions(id);
+
+CREATE UNIQUE INDEX withdrawal_history_execution_id_idx
+    ON withdrawal_history (execution_id, beneficiary_id, asset);
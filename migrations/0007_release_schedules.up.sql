@@ -0,0 +1,4 @@
+-- How each beneficiary's entitlement is released after execution, see
+-- ReleaseSchedule. Existing allocations keep paying out in one go.
+ALTER TABLE beneficiaries
+    ADD COLUMN release_schedule JSONB NOT NULL DEFAULT '{"type": "lump_sum"}';
@@ -0,0 +1,5 @@
+DROP TABLE guardian_attestations;
+DROP INDEX guardian_rounds_open_idx;
+DROP TABLE guardian_rounds;
+DROP TABLE plan_trigger_policies;
+DROP TABLE plan_guardians;
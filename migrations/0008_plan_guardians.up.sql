@@ -0,0 +1,44 @@
+-- Trusted contacts an owner nominates to confirm their death or incapacity
+CREATE TABLE plan_guardians (
+    id SERIAL PRIMARY KEY,
+    plan_id INTEGER NOT NULL REFERENCES plans(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (plan_id, user_id)
+);
+
+-- What may trigger a plan. Plans without a row are triggered by missed
+-- check-ins only, as before.
+CREATE TABLE plan_trigger_policies (
+    plan_id INTEGER PRIMARY KEY REFERENCES plans(id) ON DELETE CASCADE,
+    policy VARCHAR(20) NOT NULL CHECK (policy IN ('inactivity', 'guardians', 'either')),
+    quorum INTEGER NOT NULL CHECK (quorum > 0),
+    window_secs BIGINT NOT NULL CHECK (window_secs > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- A round of guardian attestations. The first attestation opens it; it is
+-- confirmed once a quorum has attested, cancelled when the owner checks in
+-- and expires at `expires_at`.
+CREATE TABLE guardian_rounds (
+    id BIGSERIAL PRIMARY KEY,
+    plan_id INTEGER NOT NULL REFERENCES plans(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'confirmed', 'cancelled', 'expired')),
+    opened_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    closed_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX guardian_rounds_open_idx ON guardian_rounds (plan_id) WHERE status = 'open';
+
+CREATE TABLE guardian_attestations (
+    round_id BIGINT NOT NULL REFERENCES guardian_rounds(id) ON DELETE CASCADE,
+    guardian_id INTEGER NOT NULL REFERENCES plan_guardians(id) ON DELETE CASCADE,
+    attested_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    PRIMARY KEY (round_id, guardian_id)
+);
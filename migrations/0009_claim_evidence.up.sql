@@ -0,0 +1,15 @@
+-- Documents backing a claim, such as a death certificate or court order. The
+-- bytes live in object storage under `storage_key`.
+CREATE TABLE claim_evidence (
+    id SERIAL PRIMARY KEY,
+    claim_id INTEGER NOT NULL REFERENCES claims(id) ON DELETE CASCADE,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    sha256 CHAR(64) NOT NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    uploaded_by INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX claim_evidence_claim_idx ON claim_evidence (claim_id);
@@ -0,0 +1,17 @@
+DROP TABLE claim_status_history;
+
+-- Enum values cannot be dropped, so the type is rebuilt. Claims in one of the
+-- new states go back to the closest of the old ones.
+ALTER TABLE claims ALTER COLUMN status DROP DEFAULT;
+ALTER TYPE claim_status RENAME TO claim_status_old;
+CREATE TYPE claim_status AS ENUM ('pending', 'approved', 'rejected');
+ALTER TABLE claims ALTER COLUMN status TYPE claim_status USING (
+    CASE status::TEXT
+        WHEN 'paid' THEN 'approved'
+        WHEN 'approved' THEN 'approved'
+        WHEN 'rejected' THEN 'rejected'
+        ELSE 'pending'
+    END
+)::claim_status;
+ALTER TABLE claims ALTER COLUMN status SET DEFAULT 'pending';
+DROP TYPE claim_status_old;
@@ -0,0 +1,19 @@
+-- Claims now go through review before they are decided, and can be disputed
+-- and paid out afterwards
+ALTER TYPE claim_status ADD VALUE IF NOT EXISTS 'under_review';
+ALTER TYPE claim_status ADD VALUE IF NOT EXISTS 'needs_information';
+ALTER TYPE claim_status ADD VALUE IF NOT EXISTS 'disputed';
+ALTER TYPE claim_status ADD VALUE IF NOT EXISTS 'paid';
+
+-- Every status a claim has been in. `from_status` is NULL for the filing.
+CREATE TABLE claim_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    claim_id INTEGER NOT NULL REFERENCES claims(id) ON DELETE CASCADE,
+    from_status claim_status,
+    to_status claim_status NOT NULL,
+    actor_id INTEGER NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX claim_status_history_claim_idx ON claim_status_history (claim_id);
@@ -0,0 +1,3 @@
+ALTER TABLE claims ALTER COLUMN amount TYPE DECIMAL(10, 2);
+ALTER TABLE claims DROP COLUMN asset;
+ALTER TABLE withdrawal_history ALTER COLUMN amount TYPE BIGINT;
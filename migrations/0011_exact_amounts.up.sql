@@ -0,0 +1,10 @@
+-- Amounts are whole numbers of the asset's smallest unit. NUMERIC(38, 0)
+-- holds 10^20 tokens of an asset with 18 decimals.
+ALTER TABLE withdrawal_history ALTER COLUMN amount TYPE NUMERIC(38, 0);
+
+-- Claims name the asset they are for. The API could never store a claim
+-- amount in the old DECIMAL(10, 2) column, so rows can only have been added
+-- by hand and are marked as being for an unknown asset.
+ALTER TABLE claims ADD COLUMN asset VARCHAR(50) NOT NULL DEFAULT 'unknown';
+ALTER TABLE claims ALTER COLUMN asset DROP DEFAULT;
+ALTER TABLE claims ALTER COLUMN amount TYPE NUMERIC(38, 0) USING ROUND(amount);
@@ -0,0 +1,9 @@
+DROP INDEX claims_assignee_id_idx;
+DROP INDEX claims_queue_idx;
+
+ALTER TABLE claims
+    DROP COLUMN overdue_at,
+    DROP COLUMN assigned_at,
+    DROP COLUMN assignee_id,
+    DROP COLUMN due_at,
+    DROP COLUMN priority;
@@ -0,0 +1,19 @@
+-- Claims are worked from a review queue. The priority sets the deadline for
+-- a decision, assignee_id is the reviewer working the claim, and overdue_at
+-- is when the SLA monitor flagged it for missing its deadline.
+ALTER TABLE claims
+    ADD COLUMN priority VARCHAR(20) NOT NULL DEFAULT 'normal'
+        CHECK (priority IN ('low', 'normal', 'high', 'urgent')),
+    ADD COLUMN due_at TIMESTAMPTZ,
+    ADD COLUMN assignee_id INTEGER,
+    ADD COLUMN assigned_at TIMESTAMPTZ,
+    ADD COLUMN overdue_at TIMESTAMPTZ;
+
+-- Existing claims get the default deadline for normal priority
+UPDATE claims SET due_at = created_at + INTERVAL '7 days';
+ALTER TABLE claims ALTER COLUMN due_at SET NOT NULL;
+
+-- Unassigned pending claims, soonest deadline first
+CREATE INDEX claims_queue_idx ON claims (due_at, id)
+    WHERE status = 'pending' AND assignee_id IS NULL;
+CREATE INDEX claims_assignee_id_idx ON claims (assignee_id);
@@ -0,0 +1,5 @@
+DROP INDEX claims_queue_idx;
+CREATE INDEX claims_queue_idx ON claims (due_at, id)
+    WHERE status = 'pending' AND assignee_id IS NULL;
+
+DROP TABLE claim_disputes;
@@ -0,0 +1,27 @@
+-- Disputes of a claim decision: the claimant appealing a rejection, or a
+-- co-beneficiary challenging an approval before it is paid. decided_by is
+-- the reviewer who made the contested decision and may not settle it.
+CREATE TABLE claim_disputes (
+    id SERIAL PRIMARY KEY,
+    claim_id INTEGER NOT NULL REFERENCES claims(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('appeal', 'challenge')),
+    contested_status claim_status NOT NULL,
+    decided_by INTEGER,
+    filed_by INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    outcome VARCHAR(20) CHECK (outcome IN ('upheld', 'reopened')),
+    resolved_by INTEGER,
+    resolution TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ
+);
+
+CREATE INDEX claim_disputes_claim_id_idx ON claim_disputes (claim_id);
+-- A claim has at most one dispute open at a time
+CREATE UNIQUE INDEX claim_disputes_open_idx ON claim_disputes (claim_id)
+    WHERE outcome IS NULL;
+
+-- Disputed claims wait in the review queue alongside new ones
+DROP INDEX claims_queue_idx;
+CREATE INDEX claims_queue_idx ON claims (due_at, id)
+    WHERE status IN ('pending', 'disputed') AND assignee_id IS NULL;
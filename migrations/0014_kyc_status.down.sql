@@ -0,0 +1,9 @@
+ALTER TABLE kyc_records DROP CONSTRAINT kyc_records_rejection_check;
+ALTER TABLE kyc_records DROP COLUMN rejection_note, DROP COLUMN rejection_reason;
+
+ALTER TABLE kyc_records ALTER COLUMN verification_status DROP DEFAULT;
+ALTER TABLE kyc_records ALTER COLUMN verification_status TYPE VARCHAR(50)
+    USING verification_status::TEXT;
+ALTER TABLE kyc_records ALTER COLUMN verification_status SET DEFAULT 'pending';
+
+DROP TYPE kyc_status;
@@ -0,0 +1,35 @@
+-- KYC verification goes through a fixed set of states instead of free text.
+-- Statuses written before this are mapped onto the closest state.
+CREATE TYPE kyc_status AS ENUM (
+    'not_started', 'pending', 'in_review', 'verified', 'rejected', 'expired', 'suspended'
+);
+
+ALTER TABLE kyc_records ALTER COLUMN verification_status DROP DEFAULT;
+ALTER TABLE kyc_records ALTER COLUMN verification_status TYPE kyc_status USING (
+    CASE LOWER(TRIM(verification_status))
+        WHEN 'in_review' THEN 'in_review'
+        WHEN 'verified' THEN 'verified'
+        WHEN 'approved' THEN 'verified'
+        WHEN 'rejected' THEN 'rejected'
+        WHEN 'expired' THEN 'expired'
+        WHEN 'suspended' THEN 'suspended'
+        ELSE 'pending'
+    END
+)::kyc_status;
+ALTER TABLE kyc_records ALTER COLUMN verification_status SET DEFAULT 'pending';
+
+-- Why a record was rejected, from the catalogue the user is shown, and what
+-- the reviewer added to it
+ALTER TABLE kyc_records
+    ADD COLUMN rejection_reason VARCHAR(40)
+        CHECK (rejection_reason IN (
+            'document_unreadable', 'document_expired', 'unsupported_document',
+            'name_mismatch', 'date_of_birth_mismatch', 'id_number_invalid',
+            'address_unverifiable', 'selfie_mismatch', 'other'
+        )),
+    ADD COLUMN rejection_note TEXT;
+
+UPDATE kyc_records SET rejection_reason = 'other' WHERE verification_status = 'rejected';
+
+ALTER TABLE kyc_records ADD CONSTRAINT kyc_records_rejection_check
+    CHECK ((verification_status = 'rejected') = (rejection_reason IS NOT NULL));
@@ -0,0 +1,17 @@
+-- Identity document images backing a KYC record: both sides of the ID and a
+-- selfie. The images live in object storage under `storage_key`, stripped
+-- of their metadata, and `sha256` is the hash of exactly what was stored.
+CREATE TABLE kyc_documents (
+    id SERIAL PRIMARY KEY,
+    kyc_id INTEGER NOT NULL REFERENCES kyc_records(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('id_front', 'id_back', 'selfie')),
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    width INTEGER NOT NULL CHECK (width > 0),
+    height INTEGER NOT NULL CHECK (height > 0),
+    sha256 CHAR(64) NOT NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    uploaded_by INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (kyc_id, kind)
+);
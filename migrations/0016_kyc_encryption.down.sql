@@ -0,0 +1,20 @@
+-- The database cannot decrypt sealed records, so refuse rather than lose them
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM kyc_records WHERE master_key_id IS NOT NULL) THEN
+        RAISE EXCEPTION 'kyc_records holds encrypted records, which would be lost';
+    END IF;
+END $$;
+
+ALTER TABLE kyc_records DROP CONSTRAINT kyc_records_encryption_check;
+ALTER TABLE kyc_records
+    DROP COLUMN encrypted_address,
+    DROP COLUMN encrypted_id_number,
+    DROP COLUMN encrypted_date_of_birth,
+    DROP COLUMN encrypted_full_name,
+    DROP COLUMN data_key,
+    DROP COLUMN master_key_id,
+    ALTER COLUMN full_name SET NOT NULL,
+    ALTER COLUMN date_of_birth SET NOT NULL,
+    ALTER COLUMN id_number SET NOT NULL,
+    ALTER COLUMN address SET NOT NULL;
@@ -0,0 +1,33 @@
+-- Personal details of KYC records are encrypted by the server. Each record
+-- has its own data key, stored wrapped by the master key named in
+-- `master_key_id`; each field is sealed with the data key in its
+-- `encrypted_*` column. Records written before this keep their plaintext
+-- columns until the re-encryption job seals them and clears the plaintext.
+ALTER TABLE kyc_records
+    ADD COLUMN master_key_id VARCHAR(32),
+    ADD COLUMN data_key BYTEA,
+    ADD COLUMN encrypted_full_name BYTEA,
+    ADD COLUMN encrypted_date_of_birth BYTEA,
+    ADD COLUMN encrypted_id_number BYTEA,
+    ADD COLUMN encrypted_address BYTEA,
+    ALTER COLUMN full_name DROP NOT NULL,
+    ALTER COLUMN date_of_birth DROP NOT NULL,
+    ALTER COLUMN id_number DROP NOT NULL,
+    ALTER COLUMN address DROP NOT NULL;
+
+-- A record is either all plaintext or all sealed
+ALTER TABLE kyc_records ADD CONSTRAINT kyc_records_encryption_check CHECK (
+    CASE WHEN master_key_id IS NULL THEN
+        data_key IS NULL
+        AND full_name IS NOT NULL AND date_of_birth IS NOT NULL
+        AND id_number IS NOT NULL AND address IS NOT NULL
+        AND encrypted_full_name IS NULL AND encrypted_date_of_birth IS NULL
+        AND encrypted_id_number IS NULL AND encrypted_address IS NULL
+    ELSE
+        data_key IS NOT NULL
+        AND full_name IS NULL AND date_of_birth IS NULL
+        AND id_number IS NULL AND address IS NULL
+        AND encrypted_full_name IS NOT NULL AND encrypted_date_of_birth IS NOT NULL
+        AND encrypted_id_number IS NOT NULL AND encrypted_address IS NOT NULL
+    END
+);
@@ -0,0 +1,3 @@
+DROP TABLE kyc_fraud_signals;
+DROP INDEX kyc_records_id_number_index_idx;
+ALTER TABLE kyc_records DROP COLUMN id_number_index;
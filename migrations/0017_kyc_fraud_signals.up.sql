@@ -0,0 +1,17 @@
+-- Keyed hash of the normalized ID type and number, so that records sharing
+-- an identity document can be found while the number stays encrypted.
+-- Records from before this get theirs from the re-encryption job.
+ALTER TABLE kyc_records ADD COLUMN id_number_index BYTEA;
+CREATE INDEX kyc_records_id_number_index_idx ON kyc_records (id_number_index);
+
+-- What reviewers should look at before verifying a record. A duplicate
+-- identity is raised on both records, each pointing at the other.
+CREATE TABLE kyc_fraud_signals (
+    id SERIAL PRIMARY KEY,
+    kyc_id INTEGER NOT NULL REFERENCES kyc_records(id) ON DELETE CASCADE,
+    kind VARCHAR(40) NOT NULL CHECK (kind IN ('duplicate_identity')),
+    related_kyc_id INTEGER REFERENCES kyc_records(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (kyc_id, kind, related_kyc_id)
+);
+CREATE INDEX kyc_fraud_signals_kyc_id_idx ON kyc_fraud_signals (kyc_id);
@@ -0,0 +1,3 @@
+DROP INDEX kyc_records_user_id_idx;
+DROP INDEX kyc_records_current_idx;
+ALTER TABLE kyc_records DROP COLUMN is_current;
@@ -0,0 +1,8 @@
+-- A user's submissions are all kept, rejected ones included, and one of
+-- them is current: the one their verification status comes from. Before
+-- this, the latest submission of each user stands in.
+ALTER TABLE kyc_records ADD COLUMN is_current BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE kyc_records SET is_current = TRUE
+WHERE id IN (SELECT MAX(id) FROM kyc_records GROUP BY user_id);
+CREATE UNIQUE INDEX kyc_records_current_idx ON kyc_records (user_id) WHERE is_current;
+CREATE INDEX kyc_records_user_id_idx ON kyc_records (user_id);
@@ -0,0 +1,2 @@
+DROP TABLE kyc_webhook_events;
+DROP TABLE kyc_provider_checks;
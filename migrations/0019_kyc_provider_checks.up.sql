@@ -0,0 +1,30 @@
+-- Submissions handed to an outside verification provider, and what it
+-- decided. The provider's reference is how its webhook calls find them.
+CREATE TABLE kyc_provider_checks (
+    id SERIAL PRIMARY KEY,
+    kyc_id INTEGER NOT NULL REFERENCES kyc_records(id) ON DELETE CASCADE,
+    provider VARCHAR(40) NOT NULL,
+    reference VARCHAR(255) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'rejected')),
+    rejection_reason VARCHAR(40)
+        CHECK (rejection_reason IN (
+            'document_unreadable', 'document_expired', 'unsupported_document',
+            'name_mismatch', 'date_of_birth_mismatch', 'id_number_invalid',
+            'address_unverifiable', 'selfie_mismatch', 'other'
+        )),
+    rejection_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    UNIQUE (provider, reference),
+    CONSTRAINT kyc_provider_checks_rejection_check
+        CHECK ((status = 'rejected') = (rejection_reason IS NOT NULL))
+);
+CREATE INDEX kyc_provider_checks_kyc_id_idx ON kyc_provider_checks (kyc_id);
+
+-- Webhook calls already acted on, so that one sent again is ignored
+CREATE TABLE kyc_webhook_events (
+    event_id VARCHAR(255) PRIMARY KEY,
+    check_id INTEGER NOT NULL REFERENCES kyc_provider_checks(id) ON DELETE CASCADE,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
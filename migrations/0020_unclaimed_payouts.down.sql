@@ -0,0 +1,6 @@
+UPDATE withdrawal_history w
+SET user_id = p.owner_id
+FROM plans p
+WHERE w.plan_id = p.id
+  AND w.user_id IS NULL;
+ALTER TABLE withdrawal_history ALTER COLUMN user_id SET NOT NULL;
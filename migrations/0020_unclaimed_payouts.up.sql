@@ -0,0 +1,9 @@
+-- Payouts to beneficiaries without an account were recorded against the plan
+-- owner. They now belong to nobody while the beneficiary has no account.
+ALTER TABLE withdrawal_history ALTER COLUMN user_id DROP NOT NULL;
+UPDATE withdrawal_history w
+SET user_id = b.user_id
+FROM beneficiaries b
+WHERE w.beneficiary_id = b.id
+  AND w.execution_id IS NOT NULL
+  AND w.user_id IS DISTINCT FROM b.user_id;
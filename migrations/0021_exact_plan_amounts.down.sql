@@ -0,0 +1 @@
+ALTER TABLE beneficiaries ALTER COLUMN fixed_amount TYPE BIGINT;
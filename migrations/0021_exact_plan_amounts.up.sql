@@ -0,0 +1,2 @@
+-- Fixed allocations are amounts like any other, see 0011_exact_amounts.
+ALTER TABLE beneficiaries ALTER COLUMN fixed_amount TYPE NUMERIC(38, 0);
@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use log::LevelFilter;
+use starknet_crypto::FieldElement;
+
+use crate::models::claim::ClaimSla;
+use crate::models::kyc_document_models::KycDocumentLimits;
+use crate::utils::blind_index::BlindIndexKey;
+use crate::utils::envelope::Keyring;
+use crate::utils::wallet_signature::parse_felt;
+use crate::utils::webhook_signature::WebhookSecret;
+
+/// Environment variable naming an optional TOML file to read settings from.
+pub const CONFIG_FILE_VAR: &str = "INHERITX_CONFIG";
+
+/// Everything the server needs to start, loaded once in `main`.
+///
+/// Each key can be given as an environment variable or in the TOML file
+/// named by `INHERITX_CONFIG`, where `[database] host = ...` is the same key
+/// as `DATABASE_HOST`. Environment variables win over the file.
+#[derive(Clone)]
+pub struct Settings {
+    pub database: DatabaseSettings,
+    pub bind_address: SocketAddr,
+    pub jwt_secret: String,
+    pub starknet_account_class_hash: Option<FieldElement>,
+    pub log_level: LevelFilter,
+    /// How often the check-in scheduler looks for missed check-ins.
+    pub check_in_poll_secs: u64,
+    pub storage: StorageSettings,
+    /// Largest claim evidence file accepted, in bytes.
+    pub claim_evidence_max_bytes: usize,
+    pub claim_sla: ClaimSla,
+    /// How often the SLA monitor looks for overdue claims.
+    pub claim_sla_poll_secs: u64,
+    /// Days a claimant has to appeal a rejected claim.
+    pub claim_appeal_window_days: i64,
+    pub kyc_documents: KycDocumentLimits,
+    /// Master keys the personal details of KYC records are encrypted under.
+    pub kyc_master_keys: Keyring,
+    /// Key of the hashes KYC records are matched on, such as the ID number.
+    pub kyc_blind_index_key: BlindIndexKey,
+    /// How often the re-encryption job looks for records to move onto the
+    /// current master key.
+    pub kyc_reencrypt_poll_secs: u64,
+    pub kyc_provider: KycProviderSettings,
+    pub features: FeatureToggles,
+}
+
+#[derive(Clone)]
+pub struct DatabaseSettings {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub name: String,
+    pub pool_size: usize,
+    pub tls_mode: TlsMode,
+}
+
+// Written out by hand to keep the secrets out of logs
+impl fmt::Debug for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Settings")
+            .field("database", &self.database)
+            .field("bind_address", &self.bind_address)
+            .field("jwt_secret", &"[redacted]")
+            .field(
+                "starknet_account_class_hash",
+                &self.starknet_account_class_hash,
+            )
+            .field("log_level", &self.log_level)
+            .field("check_in_poll_secs", &self.check_in_poll_secs)
+            .field("storage", &self.storage)
+            .field("claim_evidence_max_bytes", &self.claim_evidence_max_bytes)
+            .field("claim_sla", &self.claim_sla)
+            .field("claim_sla_poll_secs", &self.claim_sla_poll_secs)
+            .field("claim_appeal_window_days", &self.claim_appeal_window_days)
+            .field("kyc_documents", &self.kyc_documents)
+            .field("kyc_master_keys", &self.kyc_master_keys)
+            .field("kyc_blind_index_key", &self.kyc_blind_index_key)
+            .field("kyc_reencrypt_poll_secs", &self.kyc_reencrypt_poll_secs)
+            .field("kyc_provider", &self.kyc_provider)
+            .field("features", &self.features)
+            .finish()
+    }
+}
+
+impl fmt::Debug for DatabaseSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseSettings")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &"[redacted]")
+            .field("name", &self.name)
+            .field("pool_size", &self.pool_size)
+            .field("tls_mode", &self.tls_mode)
+            .finish()
+    }
+}
+
+/// How the connection to Postgres is secured, named after libpq's `sslmode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsMode {
+    Disable,
+    /// Encrypted, but the server certificate is not checked.
+    Require,
+    /// Encrypted, and the certificate must be valid for the host.
+    VerifyFull,
+}
+
+impl FromStr for TlsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disable" => Ok(TlsMode::Disable),
+            "require" => Ok(TlsMode::Require),
+            "verify-full" => Ok(TlsMode::VerifyFull),
+            _ => Err("expected disable, require or verify-full".to_string()),
+        }
+    }
+}
+
+/// Where uploaded files such as claim evidence are kept.
+#[derive(Debug, Clone)]
+pub struct StorageSettings {
+    pub backend: StorageBackend,
+    /// Directory the local backend writes under.
+    pub local_root: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// Files on the server's own disk.
+    Local,
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(StorageBackend::Local),
+            _ => Err("expected local".to_string()),
+        }
+    }
+}
+
+/// The outside service KYC submissions are handed to, and how its webhook
+/// calls are checked.
+#[derive(Debug, Clone)]
+pub struct KycProviderSettings {
+    pub backend: KycProviderBackend,
+    /// Signs the provider's webhook calls. Required when the provider is on.
+    pub webhook_secret: Option<WebhookSecret>,
+    /// How far a webhook call's timestamp may be from now, in seconds.
+    pub webhook_tolerance_secs: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KycProviderBackend {
+    /// Decides from fixed fixtures, without leaving the server.
+    Mock,
+}
+
+impl FromStr for KycProviderBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mock" => Ok(KycProviderBackend::Mock),
+            _ => Err("expected mock".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeatureToggles {
+    /// Serves `/auth/nonce` and `/auth/verify`.
+    pub wallet_login: bool,
+    /// Applies the schema on startup.
+    pub run_migrations: bool,
+    /// Escalates missed plan check-ins in the background.
+    pub check_in_scheduler: bool,
+    /// Flags overdue claims in the background and tells the supervisors.
+    pub claim_sla_monitor: bool,
+    /// Encrypts plaintext KYC records and moves them onto the current
+    /// master key in the background.
+    pub kyc_reencryption: bool,
+    /// Hands KYC submissions to the KYC provider and serves its webhook.
+    pub kyc_provider: bool,
+}
+
+/// Every missing or invalid key found while loading, so a bad deploy can be
+/// fixed in one go.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Settings {
+    /// Reads the process environment and, if `INHERITX_CONFIG` is set, the
+    /// TOML file it points to.
+    pub fn load() -> Result<Settings, ConfigError> {
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let file = match env.get(CONFIG_FILE_VAR) {
+            Some(path) => Some(std::fs::read_to_string(path).map_err(|err| ConfigError {
+                problems: vec![format!(
+                    "{}: cannot read {}: {}",
+                    CONFIG_FILE_VAR, path, err
+                )],
+            })?),
+            None => None,
+        };
+        Settings::from_sources(file.as_deref(), &env)
+    }
+
+    pub fn from_sources(
+        file: Option<&str>,
+        env: &HashMap<String, String>,
+    ) -> Result<Settings, ConfigError> {
+        let mut values = match file {
+            Some(contents) => flatten_toml(contents)?,
+            None => HashMap::new(),
+        };
+        values.extend(env.iter().map(|(key, value)| (key.clone(), value.clone())));
+
+        let mut reader = Reader {
+            values,
+            problems: Vec::new(),
+        };
+        let settings = read_settings(&mut reader);
+        match settings {
+            Some(settings) if reader.problems.is_empty() => Ok(settings),
+            _ => Err(ConfigError {
+                problems: reader.problems,
+            }),
+        }
+    }
+}
+
+// Reads every key before bailing out, so that all problems get reported.
+fn read_settings(reader: &mut Reader) -> Option<Settings> {
+    let host = reader.required("DATABASE_HOST");
+    let port = reader.with_default("DATABASE_PORT", 5432);
+    let user = reader.required("DATABASE_USER");
+    let password = reader.required("DATABASE_PASSWORD");
+    let name = reader.required("DATABASE_NAME");
+    let pool_size = reader.with_default("DATABASE_POOL_SIZE", 16);
+    let tls_mode = reader.with_default("DATABASE_TLS_MODE", TlsMode::Disable);
+    let bind_address =
+        reader.with_default("BIND_ADDRESS", SocketAddr::from(([127, 0, 0, 1], 8080)));
+    let jwt_secret: Option<String> = reader.required("JWT_SECRET");
+    let starknet_account_class_hash = reader.optional_felt("STARKNET_ACCOUNT_CLASS_HASH");
+    let log_level = reader.with_default("LOG_LEVEL", LevelFilter::Info);
+    let check_in_poll_secs = reader.with_default("CHECK_IN_POLL_SECS", 60);
+    let storage_backend = reader.with_default("STORAGE_BACKEND", StorageBackend::Local);
+    let storage_local_root = reader.with_default("STORAGE_LOCAL_ROOT", PathBuf::from("storage"));
+    let claim_evidence_max_bytes = reader.with_default("CLAIMS_EVIDENCE_MAX_BYTES", 10 << 20);
+    let sla_low_hours = reader.with_default("CLAIMS_SLA_LOW_HOURS", 14 * 24);
+    let sla_normal_hours = reader.with_default("CLAIMS_SLA_NORMAL_HOURS", 7 * 24);
+    let sla_high_hours = reader.with_default("CLAIMS_SLA_HIGH_HOURS", 3 * 24);
+    let sla_urgent_hours = reader.with_default("CLAIMS_SLA_URGENT_HOURS", 24);
+    let claim_sla_poll_secs = reader.with_default("CLAIMS_SLA_POLL_SECS", 300);
+    let claim_appeal_window_days = reader.with_default("CLAIMS_APPEAL_WINDOW_DAYS", 30);
+    let kyc_document_max_bytes = reader.with_default("KYC_DOCUMENT_MAX_BYTES", 10 << 20);
+    let kyc_document_min_side_px = reader.with_default("KYC_DOCUMENT_MIN_SIDE_PX", 480);
+    let kyc_document_max_side_px = reader.with_default("KYC_DOCUMENT_MAX_SIDE_PX", 8000);
+    let kyc_master_keys = reader.required_secret("KYC_MASTER_KEYS");
+    let kyc_blind_index_key = reader.required_secret("KYC_BLIND_INDEX_KEY");
+    let kyc_reencrypt_poll_secs = reader.with_default("KYC_REENCRYPT_POLL_SECS", 300);
+    let kyc_provider_backend = reader.with_default("KYC_PROVIDER", KycProviderBackend::Mock);
+    let kyc_provider_webhook_secret = reader.optional_secret("KYC_PROVIDER_WEBHOOK_SECRET");
+    let kyc_provider_webhook_tolerance_secs =
+        reader.with_default("KYC_PROVIDER_WEBHOOK_TOLERANCE_SECS", 300);
+    let wallet_login = reader.with_default("FEATURES_WALLET_LOGIN", true);
+    let run_migrations = reader.with_default("FEATURES_RUN_MIGRATIONS", true);
+    let check_in_scheduler = reader.with_default("FEATURES_CHECK_IN_SCHEDULER", true);
+    let claim_sla_monitor = reader.with_default("FEATURES_CLAIM_SLA_MONITOR", true);
+    let kyc_reencryption = reader.with_default("FEATURES_KYC_REENCRYPTION", true);
+    let kyc_provider = reader.with_default("FEATURES_KYC_PROVIDER", false);
+
+    if pool_size == Some(0) {
+        reader.problem("DATABASE_POOL_SIZE: must be at least 1".to_string());
+    }
+    if check_in_poll_secs == Some(0) {
+        reader.problem("CHECK_IN_POLL_SECS: must be at least 1".to_string());
+    }
+    if claim_evidence_max_bytes == Some(0) {
+        reader.problem("CLAIMS_EVIDENCE_MAX_BYTES: must be at least 1".to_string());
+    }
+    for (key, hours) in [
+        ("CLAIMS_SLA_LOW_HOURS", sla_low_hours),
+        ("CLAIMS_SLA_NORMAL_HOURS", sla_normal_hours),
+        ("CLAIMS_SLA_HIGH_HOURS", sla_high_hours),
+        ("CLAIMS_SLA_URGENT_HOURS", sla_urgent_hours),
+    ] {
+        if hours.is_some_and(|hours: i32| hours < 1) {
+            reader.problem(format!("{}: must be at least 1", key));
+        }
+    }
+    if claim_sla_poll_secs == Some(0) {
+        reader.problem("CLAIMS_SLA_POLL_SECS: must be at least 1".to_string());
+    }
+    if claim_appeal_window_days.is_some_and(|days: i64| days < 1) {
+        reader.problem("CLAIMS_APPEAL_WINDOW_DAYS: must be at least 1".to_string());
+    }
+    if kyc_document_max_bytes == Some(0) {
+        reader.problem("KYC_DOCUMENT_MAX_BYTES: must be at least 1".to_string());
+    }
+    if kyc_document_min_side_px == Some(0) {
+        reader.problem("KYC_DOCUMENT_MIN_SIDE_PX: must be at least 1".to_string());
+    }
+    if let (Some(min), Some(max)) = (kyc_document_min_side_px, kyc_document_max_side_px)
+        && max < min
+    {
+        reader.problem(format!(
+            "KYC_DOCUMENT_MAX_SIDE_PX: must be at least KYC_DOCUMENT_MIN_SIDE_PX ({})",
+            min
+        ));
+    }
+    if kyc_reencrypt_poll_secs == Some(0) {
+        reader.problem("KYC_REENCRYPT_POLL_SECS: must be at least 1".to_string());
+    }
+    if kyc_provider_webhook_tolerance_secs.is_some_and(|secs: i64| secs < 1) {
+        reader.problem("KYC_PROVIDER_WEBHOOK_TOLERANCE_SECS: must be at least 1".to_string());
+    }
+    if kyc_provider == Some(true) && matches!(kyc_provider_webhook_secret, Some(None)) {
+        reader.problem(
+            "KYC_PROVIDER_WEBHOOK_SECRET: is required when FEATURES_KYC_PROVIDER is on".to_string(),
+        );
+    }
+    if jwt_secret.as_ref().is_some_and(|secret| secret.len() < 32) {
+        reader.problem("JWT_SECRET: must be at least 32 characters".to_string());
+    }
+
+    Some(Settings {
+        database: DatabaseSettings {
+            host: host?,
+            port: port?,
+            user: user?,
+            password: password?,
+            name: name?,
+            pool_size: pool_size?,
+            tls_mode: tls_mode?,
+        },
+        bind_address: bind_address?,
+        jwt_secret: jwt_secret?,
+        starknet_account_class_hash: starknet_account_class_hash?,
+        log_level: log_level?,
+        check_in_poll_secs: check_in_poll_secs?,
+        storage: StorageSettings {
+            backend: storage_backend?,
+            local_root: storage_local_root?,
+        },
+        claim_evidence_max_bytes: claim_evidence_max_bytes?,
+        claim_sla: ClaimSla {
+            low_hours: sla_low_hours?,
+            normal_hours: sla_normal_hours?,
+            high_hours: sla_high_hours?,
+            urgent_hours: sla_urgent_hours?,
+        },
+        claim_sla_poll_secs: claim_sla_poll_secs?,
+        claim_appeal_window_days: claim_appeal_window_days?,
+        kyc_documents: KycDocumentLimits {
+            max_bytes: kyc_document_max_bytes?,
+            min_side_px: kyc_document_min_side_px?,
+            max_side_px: kyc_document_max_side_px?,
+        },
+        kyc_master_keys: kyc_master_keys?,
+        kyc_blind_index_key: kyc_blind_index_key?,
+        kyc_reencrypt_poll_secs: kyc_reencrypt_poll_secs?,
+        kyc_provider: KycProviderSettings {
+            backend: kyc_provider_backend?,
+            webhook_secret: kyc_provider_webhook_secret?,
+            webhook_tolerance_secs: kyc_provider_webhook_tolerance_secs?,
+        },
+        features: FeatureToggles {
+            wallet_login: wallet_login?,
+            run_migrations: run_migrations?,
+            check_in_scheduler: check_in_scheduler?,
+            claim_sla_monitor: claim_sla_monitor?,
+            kyc_reencryption: kyc_reencryption?,
+            kyc_provider: kyc_provider?,
+        },
+    })
+}
+
+/// Looks keys up, recording a problem whenever it returns `None`.
+struct Reader {
+    values: HashMap<String, String>,
+    problems: Vec<String>,
+}
+
+impl Reader {
+    fn problem(&mut self, problem: String) {
+        self.problems.push(problem);
+    }
+
+    fn value(&self, key: &str) -> Option<String> {
+        self.values
+            .get(key)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn required<T: FromStr>(&mut self, key: &str) -> Option<T>
+    where
+        T::Err: fmt::Display,
+    {
+        match self.value(key) {
+            Some(raw) => self.parse(key, &raw),
+            None => {
+                self.problem(format!("{}: is required", key));
+                None
+            }
+        }
+    }
+
+    /// Like `required`, but problems never repeat the value.
+    fn required_secret<T: FromStr>(&mut self, key: &str) -> Option<T>
+    where
+        T::Err: fmt::Display,
+    {
+        let raw = self.value(key);
+        match raw.map(|raw| raw.parse::<T>()) {
+            Some(Ok(value)) => Some(value),
+            Some(Err(err)) => {
+                self.problem(format!("{}: invalid value ({})", key, err));
+                None
+            }
+            None => {
+                self.problem(format!("{}: is required", key));
+                None
+            }
+        }
+    }
+
+    /// Like `required_secret`, for keys that can be left out.
+    fn optional_secret<T: FromStr>(&mut self, key: &str) -> Option<Option<T>>
+    where
+        T::Err: fmt::Display,
+    {
+        match self.value(key).map(|raw| raw.parse::<T>()) {
+            Some(Ok(value)) => Some(Some(value)),
+            Some(Err(err)) => {
+                self.problem(format!("{}: invalid value ({})", key, err));
+                None
+            }
+            None => Some(None),
+        }
+    }
+
+    fn with_default<T: FromStr>(&mut self, key: &str, default: T) -> Option<T>
+    where
+        T::Err: fmt::Display,
+    {
+        match self.value(key) {
+            Some(raw) => self.parse(key, &raw),
+            None => Some(default),
+        }
+    }
+
+    fn optional_felt(&mut self, key: &str) -> Option<Option<FieldElement>> {
+        match self.value(key) {
+            Some(raw) => match parse_felt(&raw) {
+                Ok(felt) => Some(Some(felt)),
+                Err(_) => {
+                    self.problem(format!("{}: expected a hex field element", key));
+                    None
+                }
+            },
+            None => Some(None),
+        }
+    }
+
+    fn parse<T: FromStr>(&mut self, key: &str, raw: &str) -> Option<T>
+    where
+        T::Err: fmt::Display,
+    {
+        match raw.parse() {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.problem(format!("{}: invalid value {:?} ({})", key, raw, err));
+                None
+            }
+        }
+    }
+}
+
+/// Turns `[database] host = "db"` into `DATABASE_HOST = "db"`.
+fn flatten_toml(contents: &str) -> Result<HashMap<String, String>, ConfigError> {
+    let table: toml::Table = contents.parse().map_err(|err| ConfigError {
+        problems: vec![format!("{}: {}", CONFIG_FILE_VAR, err)],
+    })?;
+
+    let mut values = HashMap::new();
+    let mut problems = Vec::new();
+    flatten_table("", &table, &mut values, &mut problems);
+    if problems.is_empty() {
+        Ok(values)
+    } else {
+        Err(ConfigError { problems })
+    }
+}
+
+fn flatten_table(
+    prefix: &str,
+    table: &toml::Table,
+    values: &mut HashMap<String, String>,
+    problems: &mut Vec<String>,
+) {
+    for (key, value) in table {
+        let key = format!("{}{}", prefix, key.to_ascii_uppercase());
+        match value {
+            toml::Value::Table(table) => {
+                flatten_table(&format!("{}_", key), table, values, problems)
+            }
+            toml::Value::String(value) => {
+                values.insert(key, value.clone());
+            }
+            toml::Value::Integer(_) | toml::Value::Boolean(_) | toml::Value::Float(_) => {
+                values.insert(key, value.to_string());
+            }
+            _ => problems.push(format!("{}: unsupported value in config file", key)),
+        }
+    }
+}
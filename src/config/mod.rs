@@ -1,3 +1 @@
-pub mod faq_controller;
-pub mod notification_controller;
-pub mod user_support_controller;
+pub mod env;
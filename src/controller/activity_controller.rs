@@ -1,7 +1,7 @@
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpResponse, web};
 use deadpool_postgres::Pool;
-use serde_json::json;
 
+use crate::middlewares::error_middleware::AppError;
 use crate::models::activity_models::{
     CreateUserActivityRequest, UserActivitiesResponse, UserActivityResponse,
 };
@@ -10,22 +10,12 @@ use crate::repositories::activity_repository;
 pub async fn create_user_activity(
     db_pool: web::Data<Pool>,
     activity_request: web::Json<CreateUserActivityRequest>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
 
-    match activity_repository::create_activity(&client, &activity_request.into_inner()).await {
-        Ok(activity) => HttpResponse::Created().json(activity),
-        Err(e) => {
-            eprintln!("Failed to create activity: {:?}", e);
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create activity"}))
-        }
-    }
+    let activity =
+        activity_repository::create_activity(&client, &activity_request.into_inner()).await?;
+    Ok(HttpResponse::Created().json(activity))
 }
 
 #[derive(serde::Deserialize)]
@@ -38,52 +28,38 @@ pub async fn get_user_activities_endpoint(
     db_pool: web::Data<Pool>,
     path: web::Path<String>,
     query: web::Query<PaginationParams>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let user_id = path.into_inner();
     let page = query.page.unwrap_or(1);
     let page_size = query.page_size.unwrap_or(10);
 
     if page < 1 || page_size < 1 {
-        return HttpResponse::BadRequest()
-            .json(json!({"error": "Page and page_size must be positive integers"}));
+        return Err(AppError::Validation(
+            "Page and page_size must be positive integers".to_string(),
+        ));
     }
 
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
-
-    match activity_repository::get_user_activities(&client, &user_id, page, page_size).await {
-        Ok((activities, total)) => {
-            let activities_response: Vec<UserActivityResponse> = activities
-                .into_iter()
-                .map(|activity| UserActivityResponse {
-                    id: activity.id,
-                    user_id: activity.user_id,
-                    date: activity.date.format("%d-%m-%Y").to_string(),
-                    activity_type: activity.activity_type,
-                    details: activity.details,
-                    action_type: activity.action_type,
-                    action_link: activity.action_link,
-                })
-                .collect();
+    let client = db_pool.get().await?;
 
-            let response = UserActivitiesResponse {
-                activities: activities_response,
-                total,
-                page,
-                page_size,
-            };
+    let (activities, total) =
+        activity_repository::get_user_activities(&client, &user_id, page, page_size).await?;
+    let activities_response: Vec<UserActivityResponse> = activities
+        .into_iter()
+        .map(|activity| UserActivityResponse {
+            id: activity.id,
+            user_id: activity.user_id,
+            date: activity.date.format("%d-%m-%Y").to_string(),
+            activity_type: activity.activity_type,
+            details: activity.details,
+            action_type: activity.action_type,
+            action_link: activity.action_link,
+        })
+        .collect();
 
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("Failed to get user activities: {:?}", e);
-            HttpResponse::InternalServerError()
-                .json(json!({"error": "Failed to get user activities"}))
-        }
-    }
+    Ok(HttpResponse::Ok().json(UserActivitiesResponse {
+        activities: activities_response,
+        total,
+        page,
+        page_size,
+    }))
 }
@@ -1,47 +1,54 @@
+use crate::middlewares::error_middleware::AppError;
 use crate::models::activity_models::ActivityLog;
-use actix_web::{HttpResponse, Responder, web};
-use std::sync::Mutex;
+use actix_web::{HttpResponse, web};
+use std::sync::{Mutex, MutexGuard};
 
 pub async fn create(
     log: web::Json<ActivityLog>,
     data: web::Data<Mutex<Vec<ActivityLog>>>,
-) -> impl Responder {
-    let mut logs = data.lock().unwrap();
+) -> Result<HttpResponse, AppError> {
+    let mut logs = lock(&data)?;
     logs.push(log.into_inner());
-    HttpResponse::Created().json("Activity log created")
+    Ok(HttpResponse::Created().json("Activity log created"))
 }
 
-pub async fn get_all(data: web::Data<Mutex<Vec<ActivityLog>>>) -> impl Responder {
-    let logs = data.lock().unwrap();
-    HttpResponse::Ok().json(&*logs)
+pub async fn get_all(data: web::Data<Mutex<Vec<ActivityLog>>>) -> Result<HttpResponse, AppError> {
+    let logs = lock(&data)?;
+    Ok(HttpResponse::Ok().json(&*logs))
 }
 
 pub async fn update(
     path: web::Path<u32>,
     log: web::Json<ActivityLog>,
     data: web::Data<Mutex<Vec<ActivityLog>>>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let id = path.into_inner();
-    let mut logs = data.lock().unwrap();
-    if let Some(existing) = logs.iter_mut().find(|x| x.id == id) {
-        *existing = log.into_inner();
-        HttpResponse::Ok().json("Updated")
-    } else {
-        HttpResponse::NotFound().json("Not found")
-    }
+    let mut logs = lock(&data)?;
+    let existing = logs.iter_mut().find(|x| x.id == id).ok_or_else(not_found)?;
+    *existing = log.into_inner();
+    Ok(HttpResponse::Ok().json("Updated"))
 }
 
 pub async fn delete(
     path: web::Path<u32>,
     data: web::Data<Mutex<Vec<ActivityLog>>>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let id = path.into_inner();
-    let mut logs = data.lock().unwrap();
+    let mut logs = lock(&data)?;
     let len_before = logs.len();
     logs.retain(|x| x.id != id);
     if logs.len() < len_before {
-        HttpResponse::Ok().json("Deleted")
+        Ok(HttpResponse::Ok().json("Deleted"))
     } else {
-        HttpResponse::NotFound().json("Not found")
+        Err(not_found())
     }
 }
+
+fn lock(data: &Mutex<Vec<ActivityLog>>) -> Result<MutexGuard<'_, Vec<ActivityLog>>, AppError> {
+    data.lock()
+        .map_err(|_| AppError::Internal("Activity log lock poisoned".to_string()))
+}
+
+fn not_found() -> AppError {
+    AppError::NotFound("Activity log not found".to_string())
+}
@@ -0,0 +1,234 @@
+use crate::middlewares::auth_middleware::{AuthConfig, AuthenticatedUser, RequireRole};
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::{
+    AssignRoles, AuthNonce, Chain, LoginRequest, NonceQuery, NonceResponse, RegisterRequest, Role,
+    TokenResponse, User, WalletVerifyRequest,
+};
+use crate::repositories::{auth_nonce_repository, user_repository};
+use crate::utils::wallet_signature;
+use actix_web::{HttpResponse, web};
+use argon2::Argon2;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use chrono::Utc;
+use deadpool_postgres::Pool;
+use serde_json::json;
+
+pub async fn register(
+    db_pool: web::Data<Pool>,
+    request: web::Json<RegisterRequest>,
+) -> Result<HttpResponse, AppError> {
+    let request = request.into_inner();
+    request.validate().map_err(AppError::Validation)?;
+
+    let client = db_pool.get().await?;
+
+    if user_repository::get_credentials_by_email(&client, &request.email)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::Conflict(
+            "An account with this email already exists".to_string(),
+        ));
+    }
+
+    // Hashing is deliberately slow, keep it off the async workers
+    let password = request.password;
+    let password_hash = web::block(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Password hashing was cancelled: {}", e)))?
+    .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
+
+    let user = user_repository::create_user(&client, &request.email, &password_hash).await?;
+    Ok(HttpResponse::Created().json(user))
+}
+
+pub async fn login(
+    db_pool: web::Data<Pool>,
+    auth_config: web::Data<AuthConfig>,
+    request: web::Json<LoginRequest>,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let invalid_credentials = || AppError::Unauthorized("Invalid email or password".to_string());
+    let (user, password_hash) =
+        match user_repository::get_credentials_by_email(&client, &request.email).await? {
+            Some((user, Some(password_hash))) => (user, password_hash),
+            _ => return Err(invalid_credentials()),
+        };
+
+    let password = request.into_inner().password;
+    let verified = web::block(move || {
+        PasswordHash::new(&password_hash)
+            .map(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    })
+    .await
+    .unwrap_or(false);
+
+    if !verified {
+        return Err(invalid_credentials());
+    }
+
+    token_response(&auth_config, &user)
+}
+
+fn ensure_wallet_login(auth_config: &AuthConfig) -> Result<(), AppError> {
+    if auth_config.wallet_login {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("Wallet login is disabled".to_string()))
+    }
+}
+
+/// Issues a single-use challenge for a wallet to sign.
+pub async fn issue_nonce(
+    db_pool: web::Data<Pool>,
+    auth_config: web::Data<AuthConfig>,
+    query: web::Query<NonceQuery>,
+) -> Result<HttpResponse, AppError> {
+    ensure_wallet_login(&auth_config)?;
+    let wallet_address = wallet_signature::normalize_address(query.chain, &query.wallet_address)
+        .map_err(AppError::Validation)?;
+
+    let client = db_pool.get().await?;
+
+    let issued_at = Utc::now();
+    let nonce = AuthNonce {
+        nonce: hex::encode(rand::random::<[u8; 16]>()),
+        wallet_address,
+        chain: query.chain,
+        issued_at,
+        expires_at: issued_at + auth_config.nonce_ttl,
+        used_at: None,
+    };
+
+    auth_nonce_repository::create(&client, &nonce).await?;
+    Ok(HttpResponse::Ok().json(NonceResponse {
+        message: nonce.message(),
+        nonce: nonce.nonce,
+        expires_at: nonce.expires_at,
+    }))
+}
+
+/// Checks a wallet's signature over a previously issued nonce and logs the
+/// wallet in, creating its account on first use.
+pub async fn verify_wallet(
+    db_pool: web::Data<Pool>,
+    auth_config: web::Data<AuthConfig>,
+    request: web::Json<WalletVerifyRequest>,
+) -> Result<HttpResponse, AppError> {
+    ensure_wallet_login(&auth_config)?;
+    let request = request.into_inner();
+    let wallet_address =
+        wallet_signature::normalize_address(request.chain, &request.wallet_address)
+            .map_err(AppError::Validation)?;
+
+    let client = db_pool.get().await?;
+
+    let nonce = match auth_nonce_repository::get(&client, &request.nonce).await? {
+        Some(nonce) if nonce.wallet_address == wallet_address && nonce.chain == request.chain => {
+            nonce
+        }
+        _ => return Err(AppError::Unauthorized("Unknown nonce".to_string())),
+    };
+    if nonce.used_at.is_some() {
+        return Err(nonce_already_used());
+    }
+    if nonce.expires_at <= Utc::now() {
+        return Err(AppError::Unauthorized("Nonce has expired".to_string()));
+    }
+
+    let message = nonce.message();
+    match request.chain {
+        Chain::Evm => match request.signature.as_slice() {
+            [signature] => wallet_signature::verify_evm(&wallet_address, &message, signature),
+            _ => Err("EVM signatures must be a single hex string".to_string()),
+        },
+        Chain::Starknet => {
+            let class_hash = auth_config
+                .starknet_account_class_hash
+                .ok_or_else(|| AppError::Validation("Starknet login is not enabled".to_string()))?;
+            let public_key = request.public_key.as_deref().ok_or_else(|| {
+                AppError::Validation("public_key is required for Starknet login".to_string())
+            })?;
+            wallet_signature::verify_starknet(
+                &wallet_address,
+                public_key,
+                &class_hash,
+                &message,
+                &request.signature,
+            )
+        }
+    }
+    .map_err(AppError::Unauthorized)?;
+
+    // Only burn the nonce once the signature checks out, and atomically, so a
+    // replayed signature loses the race even if it got this far
+    if !auth_nonce_repository::consume(&client, &nonce.nonce).await? {
+        return Err(nonce_already_used());
+    }
+
+    let user = user_repository::get_or_create_by_wallet(&client, &wallet_address).await?;
+    token_response(&auth_config, &user)
+}
+
+pub async fn me(user: AuthenticatedUser) -> HttpResponse {
+    HttpResponse::Ok().json(json!({"user_id": user.user_id, "roles": user.roles}))
+}
+
+/// Takes effect the next time the user logs in, tokens already issued keep
+/// the roles they were issued with.
+pub async fn assign_roles(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    request: web::Json<AssignRoles>,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    match user_repository::set_roles(&client, path.into_inner(), &request.roles).await? {
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+        None => Err(AppError::NotFound("User not found".to_string())),
+    }
+}
+
+fn token_response(auth_config: &AuthConfig, user: &User) -> Result<HttpResponse, AppError> {
+    let token = auth_config
+        .issue_token(user.id, &user.roles)
+        .map_err(|e| AppError::Internal(format!("Failed to issue token: {:?}", e)))?;
+
+    Ok(HttpResponse::Ok().json(TokenResponse {
+        access_token: token,
+        token_type: "Bearer".to_string(),
+        expires_in: auth_config.token_ttl.num_seconds(),
+    }))
+}
+
+fn nonce_already_used() -> AppError {
+    AppError::Unauthorized("Nonce has already been used".to_string())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/auth")
+            .route("/register", web::post().to(register))
+            .route("/login", web::post().to(login))
+            .route("/nonce", web::get().to(issue_nonce))
+            .route("/verify", web::post().to(verify_wallet))
+            .route("/me", web::get().to(me))
+            .service(
+                web::resource("/users/{id}/roles")
+                    .wrap(RequireRole::any_of(&[Role::Admin]))
+                    .route(web::put().to(assign_roles)),
+            ),
+    );
+}
@@ -0,0 +1,288 @@
+use crate::controller::plan_controller::{load_plan, owned_plan};
+use crate::middlewares::auth_middleware::AuthenticatedUser;
+use crate::middlewares::error_middleware::AppError;
+use crate::models::activity_models::CreateUserActivityRequest;
+use crate::models::auth_models::Role;
+use crate::models::beneficiary_models::{
+    Allocation, LinkAccount, NewBeneficiary, ReorderBeneficiaries, UpdateBeneficiary,
+    validate_allocations,
+};
+use crate::models::plan_models::Plan;
+use crate::models::vesting_models::{ReleaseQuery, release_status};
+use crate::repositories::{
+    activity_repository, beneficiary_repository, execution_repository,
+    withdrawal_history_repository,
+};
+use actix_web::{HttpResponse, web};
+use chrono::Utc;
+use deadpool_postgres::{Client, Pool};
+
+pub async fn get_beneficiaries(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let plan = owned_plan(&client, path.into_inner(), &user).await?;
+    Ok(HttpResponse::Ok().json(plan.beneficiaries))
+}
+
+pub async fn add_beneficiary(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    beneficiary: web::Json<NewBeneficiary>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let beneficiary = beneficiary.into_inner();
+    beneficiary.validate().map_err(AppError::Validation)?;
+
+    let client = db_pool.get().await?;
+
+    let plan = editable_plan(&client, plan_id, &user).await?;
+
+    let mut allocations: Vec<&Allocation> =
+        plan.beneficiaries.iter().map(|b| &b.allocation).collect();
+    allocations.push(&beneficiary.allocation);
+    check_allocations(&plan, &allocations)?;
+
+    let created = beneficiary_repository::create(&client, plan_id, &beneficiary).await?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+pub async fn replace_beneficiaries(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    beneficiaries: web::Json<Vec<NewBeneficiary>>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let beneficiaries = beneficiaries.into_inner();
+    for beneficiary in &beneficiaries {
+        beneficiary.validate().map_err(AppError::Validation)?;
+    }
+
+    let mut client = db_pool.get().await?;
+
+    let plan = editable_plan(&client, plan_id, &user).await?;
+
+    let allocations: Vec<&Allocation> = beneficiaries.iter().map(|b| &b.allocation).collect();
+    check_allocations(&plan, &allocations)?;
+
+    let replaced =
+        beneficiary_repository::replace_all(&mut client, plan_id, &beneficiaries).await?;
+    Ok(HttpResponse::Ok().json(replaced))
+}
+
+pub async fn update_beneficiary(
+    db_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    beneficiary: web::Json<UpdateBeneficiary>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (plan_id, id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+
+    let plan = editable_plan(&client, plan_id, &user).await?;
+
+    let existing = plan
+        .beneficiaries
+        .iter()
+        .find(|b| b.id == id)
+        .ok_or_else(beneficiary_not_found)?;
+
+    let updated = beneficiary.apply(existing).map_err(AppError::Validation)?;
+
+    let allocations: Vec<&Allocation> = plan
+        .beneficiaries
+        .iter()
+        .map(|b| {
+            if b.id == id {
+                &updated.allocation
+            } else {
+                &b.allocation
+            }
+        })
+        .collect();
+    check_allocations(&plan, &allocations)?;
+
+    let saved = beneficiary_repository::update(&client, id, &updated).await?;
+    Ok(HttpResponse::Ok().json(saved))
+}
+
+/// Links a beneficiary to their account. Unlike other edits this works after
+/// execution too, so that payouts left unclaimed reach them. Only the owner
+/// or an admin may link, and a linked account can't be swapped for another.
+pub async fn link_account(
+    db_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    link: web::Json<LinkAccount>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (plan_id, id) = path.into_inner();
+
+    let mut client = db_pool.get().await?;
+
+    let plan = load_plan(&client, plan_id).await?;
+    if plan.owner_id != user.user_id && !user.has_any_role(&[Role::Admin]) {
+        return Err(AppError::Forbidden(
+            "This plan belongs to another user".to_string(),
+        ));
+    }
+    if !plan.beneficiaries.iter().any(|b| b.id == id) {
+        return Err(beneficiary_not_found());
+    }
+
+    let (linked, claimed) = beneficiary_repository::link_account(&mut client, id, link.user_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::Conflict("This beneficiary is linked to another account".to_string())
+        })?;
+
+    if claimed > 0 {
+        let activity = CreateUserActivityRequest {
+            user_id: link.user_id.to_string(),
+            activity_type: "plan_execution".to_string(),
+            details: format!(
+                "{} unclaimed payouts from plan {} were moved to your account",
+                claimed, plan.name
+            ),
+            action_type: "payouts_claimed".to_string(),
+            action_link: Some(format!("/plans/{}/execution", plan.id)),
+        };
+        activity_repository::create_activity(&client, &activity).await?;
+    }
+
+    Ok(HttpResponse::Ok().json(linked))
+}
+
+pub async fn remove_beneficiary(
+    db_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (plan_id, id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+
+    let plan = editable_plan(&client, plan_id, &user).await?;
+
+    if !plan.beneficiaries.iter().any(|b| b.id == id) {
+        return Err(beneficiary_not_found());
+    }
+
+    let allocations: Vec<&Allocation> = plan
+        .beneficiaries
+        .iter()
+        .filter(|b| b.id != id)
+        .map(|b| &b.allocation)
+        .collect();
+    check_allocations(&plan, &allocations)?;
+
+    beneficiary_repository::delete(&client, id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn reorder_beneficiaries(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    order: web::Json<ReorderBeneficiaries>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+
+    let mut client = db_pool.get().await?;
+
+    let plan = editable_plan(&client, plan_id, &user).await?;
+
+    let mut requested = order.beneficiary_ids.clone();
+    let mut current: Vec<i32> = plan.beneficiaries.iter().map(|b| b.id).collect();
+    requested.sort_unstable();
+    current.sort_unstable();
+    if requested != current {
+        return Err(AppError::Validation(
+            "The new order must list every beneficiary of the plan exactly once".to_string(),
+        ));
+    }
+
+    let reordered =
+        beneficiary_repository::reorder(&mut client, plan_id, &order.beneficiary_ids).await?;
+    Ok(HttpResponse::Ok().json(reordered))
+}
+
+/// What a beneficiary has been released and can still withdraw, as of now or
+/// the `as_of` date given. Visible to the plan owner and the beneficiary.
+pub async fn get_release(
+    db_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    query: web::Query<ReleaseQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (plan_id, id) = path.into_inner();
+
+    let client = db_pool.get().await?;
+
+    let plan = load_plan(&client, plan_id).await?;
+    let beneficiary = plan
+        .beneficiaries
+        .iter()
+        .find(|b| b.id == id)
+        .ok_or_else(beneficiary_not_found)?;
+    if plan.owner_id != user.user_id
+        && beneficiary.user_id != Some(user.user_id)
+        && !user.has_any_role(&[Role::Admin])
+    {
+        return Err(AppError::Forbidden(
+            "Only the plan owner or the beneficiary can see this release".to_string(),
+        ));
+    }
+
+    let started_at = execution_repository::executed_at(&client, plan_id).await?;
+    let withdrawn = withdrawal_history_repository::withdrawn_by_beneficiary(&client, id).await?;
+    let as_of = query.as_of.unwrap_or_else(Utc::now);
+    let release =
+        release_status(&plan, id, started_at, &withdrawn, as_of).map_err(AppError::Validation)?;
+    Ok(HttpResponse::Ok().json(release))
+}
+
+/// Loads a plan whose beneficiaries may still be changed.
+async fn editable_plan(
+    client: &Client,
+    plan_id: i32,
+    user: &AuthenticatedUser,
+) -> Result<Plan, AppError> {
+    let plan = owned_plan(client, plan_id, user).await?;
+    if !plan.status.is_editable() {
+        return Err(AppError::Conflict(format!(
+            "Beneficiaries of a {} plan can no longer be changed",
+            plan.status
+        )));
+    }
+    Ok(plan)
+}
+
+/// Every change, drafts included, has to leave the plan's percentage shares
+/// summing to exactly 100%.
+fn check_allocations(plan: &Plan, allocations: &[&Allocation]) -> Result<(), AppError> {
+    validate_allocations(allocations, &plan.assets).map_err(AppError::Validation)
+}
+
+fn beneficiary_not_found() -> AppError {
+    AppError::NotFound("Beneficiary not found".to_string())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/plans/{plan_id}/beneficiaries")
+            .route("", web::get().to(get_beneficiaries))
+            .route("", web::post().to(add_beneficiary))
+            .route("", web::put().to(replace_beneficiaries))
+            .route("/order", web::put().to(reorder_beneficiaries))
+            .route("/{id}", web::put().to(update_beneficiary))
+            .route("/{id}", web::delete().to(remove_beneficiary))
+            .route("/{id}/account", web::put().to(link_account))
+            .route("/{id}/release", web::get().to(get_release)),
+    );
+}
@@ -0,0 +1,98 @@
+use crate::controller::plan_controller::owned_plan;
+use crate::middlewares::auth_middleware::AuthenticatedUser;
+use crate::middlewares::error_middleware::AppError;
+use crate::models::activity_models::CreateUserActivityRequest;
+use crate::models::check_in_models::{CheckInStatus, ConfigureCheckIn};
+use crate::models::plan_models::PlanStatus;
+use crate::repositories::{activity_repository, check_in_repository};
+use crate::utils::clock::Clock;
+use actix_web::{HttpResponse, web};
+use deadpool_postgres::Pool;
+
+pub async fn get_check_in(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    owned_plan(&client, plan_id, &user).await?;
+
+    let schedule = check_in_repository::get_by_plan(&client, plan_id)
+        .await?
+        .ok_or_else(not_configured)?;
+    Ok(HttpResponse::Ok().json(CheckInStatus::from(schedule)))
+}
+
+pub async fn configure_check_in(
+    db_pool: web::Data<Pool>,
+    clock: web::Data<dyn Clock>,
+    path: web::Path<i32>,
+    schedule: web::Json<ConfigureCheckIn>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let schedule = schedule.into_inner();
+    schedule.validate().map_err(AppError::Validation)?;
+
+    let client = db_pool.get().await?;
+
+    let plan = owned_plan(&client, plan_id, &user).await?;
+    if !plan.status.is_editable() {
+        return Err(AppError::Conflict(format!(
+            "Check-ins of a {} plan can no longer be changed",
+            plan.status
+        )));
+    }
+
+    let configured =
+        check_in_repository::configure(&client, plan_id, &schedule, clock.now()).await?;
+    Ok(HttpResponse::Ok().json(CheckInStatus::from(configured)))
+}
+
+pub async fn check_in(
+    db_pool: web::Data<Pool>,
+    clock: web::Data<dyn Clock>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let mut client = db_pool.get().await?;
+
+    let plan = owned_plan(&client, plan_id, &user).await?;
+    if matches!(plan.status, PlanStatus::Executed | PlanStatus::Cancelled) {
+        return Err(AppError::Conflict(format!(
+            "Cannot check in on a {} plan",
+            plan.status
+        )));
+    }
+
+    let schedule = check_in_repository::record_check_in(&mut client, plan_id, clock.now())
+        .await?
+        .ok_or_else(not_configured)?;
+
+    let activity = CreateUserActivityRequest {
+        user_id: user.user_id.to_string(),
+        activity_type: "check_in".to_string(),
+        details: format!("Checked in on plan {}", plan.name),
+        action_type: "check_in".to_string(),
+        action_link: Some(format!("/plans/{}/check-in", plan_id)),
+    };
+    activity_repository::create_activity(&client, &activity).await?;
+
+    Ok(HttpResponse::Ok().json(CheckInStatus::from(schedule)))
+}
+
+fn not_configured() -> AppError {
+    AppError::NotFound("Check-ins are not configured for this plan".to_string())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/plans/{plan_id}/check-in")
+            .route("", web::get().to(get_check_in))
+            .route("", web::put().to(configure_check_in))
+            .route("", web::post().to(check_in)),
+    );
+}
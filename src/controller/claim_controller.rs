@@ -1,147 +1,432 @@
-use crate::models::claim::{ClaimStatus, CreateClaim, UpdateClaim};
-use crate::repositories::claim_repository;
-use actix_web::{HttpResponse, Responder, web};
-use deadpool_postgres::Pool;
-use serde_json::json;
-
-pub async fn get_claims(db_pool: web::Data<Pool>) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+use crate::middlewares::auth_middleware::{AuthenticatedUser, RequireRole};
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::claim::{
+    AssignClaim, Claim, ClaimSla, ClaimStatus, CreateClaim, PrioritizeClaim, UpdateClaim,
+};
+use crate::models::notification::CreateNotification;
+use crate::models::plan_models::PlanStatus;
+use crate::repositories::{
+    beneficiary_repository, claim_dispute_repository, claim_repository, notification_repository,
+    plan_repository, user_repository,
+};
+use actix_web::{HttpResponse, guard, web};
+use deadpool_postgres::{Client, Pool};
 
-    match claim_repository::get_all(&client).await {
-        Ok(claims) => HttpResponse::Ok().json(claims),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to fetch claims"}))
-        }
-    }
+pub async fn get_claims(db_pool: web::Data<Pool>) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let claims = claim_repository::get_all(&client).await?;
+    Ok(HttpResponse::Ok().json(claims))
 }
 
-pub async fn get_user_claims(db_pool: web::Data<Pool>, path: web::Path<i32>) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+pub async fn get_user_claims(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    require_claimant_or_reviewer(&user, user_id)?;
+    let client = db_pool.get().await?;
 
-    match claim_repository::get_by_user_id(&client, path.into_inner()).await {
-        Ok(claims) => HttpResponse::Ok().json(claims),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(json!({"error": "Failed to fetch user claims"})),
-    }
+    let claims = claim_repository::get_by_user_id(&client, user_id).await?;
+    Ok(HttpResponse::Ok().json(claims))
 }
 
 pub async fn get_claims_by_status(
     db_pool: web::Data<Pool>,
     path: web::Path<String>,
-) -> impl Responder {
-    let status = match path.into_inner().to_lowercase().as_str() {
-        "pending" => ClaimStatus::Pending,
-        "approved" => ClaimStatus::Approved,
-        "rejected" => ClaimStatus::Rejected,
-        _ => return HttpResponse::BadRequest().json(json!({"error": "Invalid status"})),
-    };
+) -> Result<HttpResponse, AppError> {
+    let status = parse_status(&path.into_inner())?;
+    let client = db_pool.get().await?;
 
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
-
-    match claim_repository::get_by_status(&client, status).await {
-        Ok(claims) => HttpResponse::Ok().json(claims),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(json!({"error": "Failed to fetch claims by status"})),
-    }
+    let claims = claim_repository::get_by_status(&client, status).await?;
+    Ok(HttpResponse::Ok().json(claims))
 }
 
 pub async fn get_user_claims_by_status(
     db_pool: web::Data<Pool>,
     path: web::Path<(i32, String)>,
-) -> impl Responder {
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
     let (user_id, status_str) = path.into_inner();
-    let status = match status_str.to_lowercase().as_str() {
-        "pending" => ClaimStatus::Pending,
-        "approved" => ClaimStatus::Approved,
-        "rejected" => ClaimStatus::Rejected,
-        _ => return HttpResponse::BadRequest().json(json!({"error": "Invalid status"})),
-    };
-
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    require_claimant_or_reviewer(&user, user_id)?;
+    let status = parse_status(&status_str)?;
+    let client = db_pool.get().await?;
 
-    match claim_repository::get_by_user_and_status(&client, user_id, status).await {
-        Ok(claims) => HttpResponse::Ok().json(claims),
-        Err(_) => HttpResponse::InternalServerError()
-            .json(json!({"error": "Failed to fetch user claims by status"})),
-    }
+    let claims = claim_repository::get_by_user_and_status(&client, user_id, status).await?;
+    Ok(HttpResponse::Ok().json(claims))
 }
 
 pub async fn create_claim(
     db_pool: web::Data<Pool>,
+    sla: web::Data<ClaimSla>,
     claim: web::Json<CreateClaim>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    if claim.user_id != user.user_id {
+        return Err(AppError::Forbidden(
+            "Claims can only be filed on your own behalf".to_string(),
+        ));
+    }
 
-    match claim_repository::create(&client, &claim.into_inner()).await {
-        Ok(created) => HttpResponse::Created().json(created),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create claim"}))
-        }
+    claim.money.validate().map_err(AppError::Validation)?;
+
+    let mut client = db_pool.get().await?;
+
+    let claim = claim.into_inner();
+    if !beneficiary_repository::is_beneficiary(&client, claim.plan_id, claim.user_id).await? {
+        return Err(AppError::Forbidden(
+            "Only a beneficiary of the plan can file a claim on it".to_string(),
+        ));
+    }
+    let plan = plan_repository::get_by_id(&client, claim.plan_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Plan not found".to_string()))?;
+    if !plan
+        .assets
+        .iter()
+        .any(|asset| asset.asset == claim.money.asset)
+    {
+        return Err(AppError::Validation(format!(
+            "Plan holds no {}",
+            claim.money.asset
+        )));
     }
+
+    let created = claim_repository::create(&mut client, &claim, &sla).await?;
+    Ok(HttpResponse::Created().json(created))
 }
 
+/// Edits the description and moves the claim along its review lifecycle.
+/// Reviewers make most transitions, the claimant answers requests for
+/// information and disputes rejections. Once a claim is assigned, only its
+/// reviewer and supervisors make the reviewer's transitions. The description
+/// is the claimant's, and is only edited while the claim accepts evidence.
 pub async fn update_claim(
     db_pool: web::Data<Pool>,
     path: web::Path<i32>,
     claim: web::Json<UpdateClaim>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let update = claim.into_inner();
+    update.validate().map_err(AppError::Validation)?;
+
+    let mut client = db_pool.get().await?;
+
+    let mut claim = claim_repository::get_by_id(&client, id)
+        .await?
+        .ok_or_else(claim_not_found)?;
+
+    if update.description.is_some() {
+        if claim.user_id != user.user_id {
+            return Err(AppError::Forbidden(
+                "Only the claimant can edit a claim's description".to_string(),
+            ));
         }
-    };
+        if !claim.status.accepts_evidence() {
+            return Err(AppError::Conflict(format!(
+                "The description of a {} claim can no longer be edited",
+                claim.status
+            )));
+        }
+    }
+
+    if let Some(next) = update.status {
+        if claim.status == ClaimStatus::Disputed || next == ClaimStatus::Disputed {
+            return Err(AppError::Conflict(format!(
+                "Disputes are filed and settled through /claims/{}/disputes",
+                id
+            )));
+        }
+        if claim.status.is_claimant_transition(next) {
+            if claim.user_id != user.user_id && !user.has_any_role(&[Role::Admin]) {
+                return Err(AppError::Forbidden(format!(
+                    "Only the claimant can move a claim from {} to {}",
+                    claim.status, next
+                )));
+            }
+        } else {
+            user.require_any_role(&[Role::ClaimReviewer])?;
+            if claim.user_id == user.user_id {
+                return Err(AppError::Forbidden(
+                    "Reviewers cannot review their own claims".to_string(),
+                ));
+            }
+            if claim
+                .assignee_id
+                .is_some_and(|assignee| assignee != user.user_id)
+                && !user.has_any_role(&[Role::Admin])
+            {
+                return Err(AppError::Forbidden(
+                    "Claim is assigned to another reviewer".to_string(),
+                ));
+            }
+        }
+        if !claim.status.can_transition_to(next) {
+            return Err(AppError::Conflict(format!(
+                "Cannot move claim from {} to {}",
+                claim.status, next
+            )));
+        }
+        if next == ClaimStatus::Approved {
+            require_confirmed_plan(&client, &claim).await?;
+        }
+
+        claim = claim_repository::transition(
+            &mut client,
+            id,
+            claim.status,
+            next,
+            user.user_id,
+            update.reason.as_deref(),
+        )
+        .await?
+        .ok_or_else(|| AppError::Conflict("Claim was updated concurrently".to_string()))?;
+    }
+
+    if let Some(description) = &update.description {
+        claim = claim_repository::update_description(&client, id, claim.status, description)
+            .await?
+            .ok_or_else(|| AppError::Conflict("Claim was updated concurrently".to_string()))?;
+    }
+
+    Ok(HttpResponse::Ok().json(claim))
+}
+
+/// Every status the claim has been in, with who moved it and why.
+pub async fn get_claim_history(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    let claim = claim_repository::get_by_id(&client, id)
+        .await?
+        .ok_or_else(claim_not_found)?;
+    if claim.user_id != user.user_id && !user.has_any_role(&[Role::ClaimReviewer]) {
+        return Err(AppError::Forbidden(
+            "Only the claimant and reviewers can see a claim's history".to_string(),
+        ));
+    }
+
+    let history = claim_repository::get_history(&client, id).await?;
+    Ok(HttpResponse::Ok().json(history))
+}
 
-    match claim_repository::update(&client, path.into_inner(), &claim.into_inner()).await {
-        Ok(updated) => HttpResponse::Ok().json(updated),
-        Err(_) => {
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to update claim"}))
+/// Gives the reviewer the pending claim with the nearest deadline and starts
+/// its review. Answers 204 when nothing is waiting.
+pub async fn take_next_claim(
+    db_pool: web::Data<Pool>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let mut client = db_pool.get().await?;
+
+    match claim_repository::take_next(&mut client, user.user_id).await? {
+        Some(claim) => Ok(HttpResponse::Ok().json(claim)),
+        None => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+/// The open claims assigned to the caller, nearest deadline first.
+pub async fn get_my_queue(
+    db_pool: web::Data<Pool>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let claims = claim_repository::get_assigned(&client, user.user_id).await?;
+    Ok(HttpResponse::Ok().json(claims))
+}
+
+/// Assigns an open claim to a reviewer, reassigns it, or puts it back in the
+/// hands of nobody. The new reviewer is notified.
+pub async fn assign_claim(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    assignment: web::Json<AssignClaim>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let assignee_id = assignment.assignee_id;
+    let client = db_pool.get().await?;
+
+    let claim = open_claim(&client, id).await?;
+    if let Some(assignee_id) = assignee_id {
+        let is_reviewer = user_repository::get_by_id(&client, assignee_id)
+            .await?
+            .is_some_and(|assignee| {
+                assignee
+                    .roles
+                    .iter()
+                    .any(|role| matches!(role, Role::ClaimReviewer | Role::Admin))
+            });
+        if !is_reviewer {
+            return Err(AppError::Validation(format!(
+                "User {} is not a claim reviewer",
+                assignee_id
+            )));
+        }
+        if assignee_id == claim.user_id {
+            return Err(AppError::Validation(
+                "Claims cannot be assigned to their claimant".to_string(),
+            ));
         }
+        let party_to_dispute = claim_dispute_repository::get_open(&client, id)
+            .await?
+            .is_some_and(|dispute| {
+                dispute.decided_by == Some(assignee_id) || dispute.filed_by == assignee_id
+            });
+        if party_to_dispute {
+            return Err(AppError::Validation(format!(
+                "Reviewer {} is party to the claim's dispute",
+                assignee_id
+            )));
+        }
+    }
+
+    let claim = claim_repository::assign(&client, id, assignee_id)
+        .await?
+        .ok_or_else(claim_decided)?;
+
+    if let Some(assignee_id) = assignee_id.filter(|assignee| *assignee != user.user_id) {
+        let notification = CreateNotification {
+            user_id: Some(assignee_id),
+            title: "Claim assigned to you".to_string(),
+            body: format!(
+                "Claim {} ({} priority) is now in your queue and due for a decision by {}.",
+                claim.id,
+                claim.priority,
+                claim.due_at.format("%Y-%m-%d %H:%M UTC")
+            ),
+        };
+        notification_repository::create(&client, &notification).await?;
     }
+
+    Ok(HttpResponse::Ok().json(claim))
+}
+
+/// Changes an open claim's priority, which moves its deadline.
+pub async fn prioritize_claim(
+    db_pool: web::Data<Pool>,
+    sla: web::Data<ClaimSla>,
+    path: web::Path<i32>,
+    priority: web::Json<PrioritizeClaim>,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    open_claim(&client, id).await?;
+    let claim = claim_repository::prioritize(&client, id, priority.priority, &sla)
+        .await?
+        .ok_or_else(claim_decided)?;
+
+    Ok(HttpResponse::Ok().json(claim))
+}
+
+async fn open_claim(client: &Client, id: i32) -> Result<Claim, AppError> {
+    let claim = claim_repository::get_by_id(client, id)
+        .await?
+        .ok_or_else(claim_not_found)?;
+    if !claim.status.is_open() {
+        return Err(AppError::Conflict(format!(
+            "Claim is {} and no longer in the review queue",
+            claim.status
+        )));
+    }
+    Ok(claim)
+}
+
+/// A claim can only be approved once the owner's death or incapacity has been
+/// confirmed, which is when the plan is triggered, whether by its guardians or
+/// by missed check-ins.
+async fn require_confirmed_plan(client: &Client, claim: &Claim) -> Result<(), AppError> {
+    let Some(plan_id) = claim.plan_id else {
+        return Ok(());
+    };
+
+    let plan = plan_repository::get_by_id(client, plan_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Plan not found".to_string()))?;
+    if !matches!(plan.status, PlanStatus::Triggered | PlanStatus::Executed) {
+        return Err(AppError::Conflict(format!(
+            "Claims on a {} plan cannot be approved before the owner's death or incapacity is confirmed",
+            plan.status
+        )));
+    }
+    Ok(())
+}
+
+fn require_claimant_or_reviewer(user: &AuthenticatedUser, user_id: i32) -> Result<(), AppError> {
+    if user_id != user.user_id && !user.has_any_role(&[Role::ClaimReviewer]) {
+        return Err(AppError::Forbidden(
+            "Only reviewers can see other users' claims".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_status(status: &str) -> Result<ClaimStatus, AppError> {
+    status
+        .to_lowercase()
+        .parse()
+        .map_err(|_| AppError::Validation("Invalid status".to_string()))
+}
+
+fn claim_not_found() -> AppError {
+    AppError::NotFound("Claim not found".to_string())
+}
+
+fn claim_decided() -> AppError {
+    AppError::Conflict("Claim was decided concurrently".to_string())
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/claims")
-            .route("", web::get().to(get_claims))
+            // Everyone's claims are for reviewers, claimants list their own
+            .service(
+                web::resource("")
+                    .guard(guard::Get())
+                    .wrap(RequireRole::any_of(&[Role::ClaimReviewer]))
+                    .route(web::get().to(get_claims)),
+            )
             .route("", web::post().to(create_claim))
+            .service(
+                web::resource("/queue/next")
+                    .wrap(RequireRole::any_of(&[Role::ClaimReviewer]))
+                    .route(web::post().to(take_next_claim)),
+            )
+            .service(
+                web::resource("/queue/mine")
+                    .wrap(RequireRole::any_of(&[Role::ClaimReviewer]))
+                    .route(web::get().to(get_my_queue)),
+            )
             .route("/user/{user_id}", web::get().to(get_user_claims))
-            .route("/status/{status}", web::get().to(get_claims_by_status))
+            .service(
+                web::resource("/status/{status}")
+                    .wrap(RequireRole::any_of(&[Role::ClaimReviewer]))
+                    .route(web::get().to(get_claims_by_status)),
+            )
             .route(
                 "/user/{user_id}/status/{status}",
                 web::get().to(get_user_claims_by_status),
             )
-            .route("/{id}", web::put().to(update_claim)),
+            .route("/{id}", web::put().to(update_claim))
+            .route("/{id}/history", web::get().to(get_claim_history))
+            // Supervising the queue is for admins
+            .service(
+                web::resource("/{id}/assignee")
+                    .wrap(RequireRole::any_of(&[Role::Admin]))
+                    .route(web::put().to(assign_claim)),
+            )
+            .service(
+                web::resource("/{id}/priority")
+                    .wrap(RequireRole::any_of(&[Role::Admin]))
+                    .route(web::put().to(prioritize_claim)),
+            ),
     );
 }
@@ -0,0 +1,251 @@
+use crate::middlewares::auth_middleware::{AuthenticatedUser, RequireRole};
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::claim::{Claim, ClaimSla, ClaimStatus};
+use crate::models::claim_dispute_models::{
+    ClaimDispute, DisputeKind, DisputeLimits, DisputeOutcome, FileDispute, NewClaimDispute,
+    ResolveDispute,
+};
+use crate::models::notification::CreateNotification;
+use crate::repositories::{
+    beneficiary_repository, claim_dispute_repository, claim_repository, notification_repository,
+};
+use actix_web::{HttpResponse, web};
+use chrono::Utc;
+use deadpool_postgres::{Client, Pool};
+
+/// Disputes a decided claim. The claimant appeals a rejection within the
+/// appeal window; co-beneficiaries on the plan challenge an approval until
+/// it is paid. Either way the claim goes back to the review queue, where the
+/// reviewer who made the decision cannot pick it up.
+pub async fn file_dispute(
+    db_pool: web::Data<Pool>,
+    sla: web::Data<ClaimSla>,
+    limits: web::Data<DisputeLimits>,
+    path: web::Path<i32>,
+    dispute: web::Json<FileDispute>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let claim_id = path.into_inner();
+    let dispute = dispute.into_inner();
+    dispute.validate().map_err(AppError::Validation)?;
+
+    let mut client = db_pool.get().await?;
+
+    let claim = load_claim(&client, claim_id).await?;
+    let kind = DisputeKind::for_decision(claim.status).ok_or_else(|| {
+        AppError::Conflict(format!(
+            "Only approved or rejected claims can be disputed, this one is {}",
+            claim.status
+        ))
+    })?;
+    match kind {
+        DisputeKind::Appeal if claim.user_id != user.user_id => {
+            return Err(AppError::Forbidden(
+                "Only the claimant can appeal a rejection".to_string(),
+            ));
+        }
+        DisputeKind::Challenge => require_co_beneficiary(&client, &claim, &user).await?,
+        DisputeKind::Appeal => {}
+    }
+
+    let decision = claim_repository::last_change_to(&client, claim_id, claim.status).await?;
+    // Settling a dispute in favour of the decision is final
+    if decision
+        .as_ref()
+        .is_some_and(|decision| decision.from_status == Some(ClaimStatus::Disputed))
+    {
+        return Err(AppError::Conflict(format!(
+            "The claim was {} when it was last disputed and cannot be disputed again",
+            claim.status
+        )));
+    }
+    if kind == DisputeKind::Appeal {
+        let rejected_at = decision
+            .as_ref()
+            .map_or(claim.updated_at, |decision| decision.created_at);
+        let closes_at = limits.appeal_closes_at(rejected_at);
+        if Utc::now() > closes_at {
+            return Err(AppError::Conflict(format!(
+                "The rejection could only be appealed until {}",
+                closes_at.format("%Y-%m-%d %H:%M UTC")
+            )));
+        }
+    }
+
+    let new_dispute = NewClaimDispute {
+        kind,
+        contested_status: claim.status,
+        decided_by: decision.map(|decision| decision.actor_id),
+        filed_by: user.user_id,
+        reason: dispute.reason,
+    };
+    let (created, claim) = claim_dispute_repository::file(
+        &mut client,
+        claim_id,
+        &new_dispute,
+        sla.hours(claim.priority),
+    )
+    .await?
+    .ok_or_else(|| AppError::Conflict("Claim was updated concurrently".to_string()))?;
+
+    if created.filed_by != claim.user_id {
+        let notification = CreateNotification {
+            user_id: Some(claim.user_id),
+            title: "Claim approval challenged".to_string(),
+            body: format!(
+                "A co-beneficiary challenged the approval of your claim {}. It will be reviewed again before it is paid.",
+                claim.id
+            ),
+        };
+        notification_repository::create(&client, &notification).await?;
+    }
+
+    Ok(HttpResponse::Created().json(created))
+}
+
+/// The claim's disputes, for the claimant, reviewers and whoever filed one.
+pub async fn get_disputes(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let claim_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    let claim = load_claim(&client, claim_id).await?;
+    let disputes = claim_dispute_repository::get_by_claim(&client, claim_id).await?;
+
+    let filed_one = disputes
+        .iter()
+        .any(|dispute| dispute.filed_by == user.user_id);
+    if claim.user_id != user.user_id && !filed_one && !user.has_any_role(&[Role::ClaimReviewer]) {
+        return Err(AppError::Forbidden(
+            "Only the claimant, reviewers and disputants can see a claim's disputes".to_string(),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(disputes))
+}
+
+/// Upholds the disputed decision or reopens the claim for review. Has to be
+/// done by a reviewer other than the one who made the decision.
+pub async fn resolve_dispute(
+    db_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    resolution: web::Json<ResolveDispute>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (claim_id, id) = path.into_inner();
+    let resolution = resolution.into_inner();
+    resolution.validate().map_err(AppError::Validation)?;
+
+    let mut client = db_pool.get().await?;
+
+    let claim = load_claim(&client, claim_id).await?;
+    let dispute = claim_dispute_repository::get_by_id(&client, claim_id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Dispute not found".to_string()))?;
+    if dispute.outcome.is_some() {
+        return Err(AppError::Conflict(
+            "Dispute was already settled".to_string(),
+        ));
+    }
+    if dispute.decided_by == Some(user.user_id) {
+        return Err(AppError::Forbidden(
+            "A dispute has to be settled by a different reviewer than the one who made the decision"
+                .to_string(),
+        ));
+    }
+    if dispute.filed_by == user.user_id || claim.user_id == user.user_id {
+        return Err(AppError::Forbidden(
+            "Reviewers cannot settle disputes they are party to".to_string(),
+        ));
+    }
+    if claim
+        .assignee_id
+        .is_some_and(|assignee| assignee != user.user_id)
+        && !user.has_any_role(&[Role::Admin])
+    {
+        return Err(AppError::Forbidden(
+            "Claim is assigned to another reviewer".to_string(),
+        ));
+    }
+
+    let (settled, claim) = claim_dispute_repository::resolve(
+        &mut client,
+        &dispute,
+        resolution.outcome,
+        user.user_id,
+        &resolution.reason,
+    )
+    .await?
+    .ok_or_else(|| AppError::Conflict("Dispute was settled concurrently".to_string()))?;
+
+    let mut told = vec![settled.filed_by];
+    if claim.user_id != settled.filed_by {
+        told.push(claim.user_id);
+    }
+    for recipient in told {
+        let notification = CreateNotification {
+            user_id: Some(recipient),
+            title: "Claim dispute settled".to_string(),
+            body: settlement_notice(&settled, &claim),
+        };
+        notification_repository::create(&client, &notification).await?;
+    }
+
+    Ok(HttpResponse::Ok().json(settled))
+}
+
+fn settlement_notice(dispute: &ClaimDispute, claim: &Claim) -> String {
+    let result = match dispute.outcome {
+        Some(DisputeOutcome::Reopened) => "the claim is back under review".to_string(),
+        _ => format!("the claim stays {}", claim.status),
+    };
+    format!(
+        "The {} of claim {} was settled and {}: {}",
+        dispute.kind,
+        claim.id,
+        result,
+        dispute.resolution.as_deref().unwrap_or_default()
+    )
+}
+
+async fn require_co_beneficiary(
+    client: &Client,
+    claim: &Claim,
+    user: &AuthenticatedUser,
+) -> Result<(), AppError> {
+    let is_co_beneficiary = match claim.plan_id {
+        Some(plan_id) if claim.user_id != user.user_id => {
+            beneficiary_repository::is_beneficiary(client, plan_id, user.user_id).await?
+        }
+        _ => false,
+    };
+    if !is_co_beneficiary {
+        return Err(AppError::Forbidden(
+            "Only other beneficiaries of the plan can challenge an approval".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn load_claim(client: &Client, claim_id: i32) -> Result<Claim, AppError> {
+    claim_repository::get_by_id(client, claim_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Claim not found".to_string()))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/claims/{claim_id}/disputes")
+            .route("", web::get().to(get_disputes))
+            .route("", web::post().to(file_dispute))
+            .service(
+                web::resource("/{id}/resolve")
+                    .wrap(RequireRole::any_of(&[Role::ClaimReviewer]))
+                    .route(web::post().to(resolve_dispute)),
+            ),
+    );
+}
@@ -0,0 +1,199 @@
+use crate::controller::uploads::{discard, in_store, read_file};
+use crate::middlewares::auth_middleware::AuthenticatedUser;
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::claim::ClaimStatus;
+use crate::models::claim_evidence_models::{EvidenceLimits, NewClaimEvidence, sniff_content_type};
+use crate::repositories::claim_evidence_repository;
+use crate::storage::ObjectStore;
+use actix_multipart::Multipart;
+use actix_web::http::header::ContentDisposition;
+use actix_web::{HttpResponse, web};
+use deadpool_postgres::{Client, Pool};
+use sha2::{Digest, Sha256};
+
+/// Attaches a PDF, PNG or JPEG to one of the caller's open or disputed
+/// claims, sent as `multipart/form-data` in a field named `file`.
+pub async fn upload_evidence(
+    db_pool: web::Data<Pool>,
+    store: web::Data<dyn ObjectStore>,
+    limits: web::Data<EvidenceLimits>,
+    path: web::Path<i32>,
+    payload: Multipart,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let claim_id = path.into_inner();
+    let mut client = db_pool.get().await?;
+
+    let status = claimant_only(&client, claim_id, &user).await?;
+    if !status.accepts_new_evidence() {
+        return Err(evidence_closed());
+    }
+
+    let (file_name, bytes) = read_file(payload, limits.max_bytes).await?;
+    let content_type = sniff_content_type(&bytes).ok_or_else(|| {
+        AppError::UnsupportedMediaType("Evidence must be a PDF, PNG or JPEG file".to_string())
+    })?;
+
+    let evidence = NewClaimEvidence {
+        file_name,
+        content_type,
+        size_bytes: bytes.len() as i64,
+        sha256: hex::encode(Sha256::digest(&bytes)),
+        storage_key: format!(
+            "claims/{}/{}",
+            claim_id,
+            hex::encode(rand::random::<[u8; 16]>())
+        ),
+        uploaded_by: user.user_id,
+    };
+
+    let key = evidence.storage_key.clone();
+    in_store(&store, move |store| store.put(&key, &bytes)).await?;
+
+    let created = claim_evidence_repository::create(&mut client, claim_id, &evidence).await;
+    match created {
+        Ok(Some(created)) => Ok(HttpResponse::Created().json(created)),
+        // The claim moved on while the file was being stored
+        Ok(None) => {
+            discard(&store, evidence.storage_key).await;
+            Err(evidence_closed())
+        }
+        Err(err) => {
+            discard(&store, evidence.storage_key).await;
+            Err(err.into())
+        }
+    }
+}
+
+pub async fn get_evidence(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let claim_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    claimant_or_reviewer(&client, claim_id, &user).await?;
+
+    let evidence = claim_evidence_repository::get_by_claim(&client, claim_id).await?;
+    Ok(HttpResponse::Ok().json(evidence))
+}
+
+/// Sends the file back as an attachment, under the type it was sniffed as.
+pub async fn download_evidence(
+    db_pool: web::Data<Pool>,
+    store: web::Data<dyn ObjectStore>,
+    path: web::Path<(i32, i32)>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (claim_id, id) = path.into_inner();
+    let client = db_pool.get().await?;
+
+    claimant_or_reviewer(&client, claim_id, &user).await?;
+
+    let evidence = claim_evidence_repository::get_by_id(&client, claim_id, id)
+        .await?
+        .ok_or_else(evidence_not_found)?;
+    let key = evidence.storage_key.clone();
+    let bytes = in_store(&store, move |store| store.get(&key)).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(evidence.content_type)
+        .insert_header(ContentDisposition::attachment(evidence.file_name))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .body(bytes))
+}
+
+pub async fn delete_evidence(
+    db_pool: web::Data<Pool>,
+    store: web::Data<dyn ObjectStore>,
+    path: web::Path<(i32, i32)>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (claim_id, id) = path.into_inner();
+    let mut client = db_pool.get().await?;
+
+    let status = claimant_only(&client, claim_id, &user).await?;
+    require_removable(&status)?;
+    claim_evidence_repository::get_by_id(&client, claim_id, id)
+        .await?
+        .ok_or_else(evidence_not_found)?;
+
+    let deleted = claim_evidence_repository::delete(&mut client, claim_id, id)
+        .await?
+        .ok_or_else(evidence_locked)?;
+    discard(&store, deleted.storage_key).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Checks that the caller filed the claim and returns its status.
+async fn claimant_only(
+    client: &Client,
+    claim_id: i32,
+    user: &AuthenticatedUser,
+) -> Result<ClaimStatus, AppError> {
+    let (claimant, status) = load_claim(client, claim_id).await?;
+
+    if claimant != user.user_id {
+        return Err(AppError::Forbidden(
+            "Only the claimant can change a claim's evidence".to_string(),
+        ));
+    }
+    Ok(status)
+}
+
+/// Evidence can be seen by whoever filed the claim and by claim reviewers.
+async fn claimant_or_reviewer(
+    client: &Client,
+    claim_id: i32,
+    user: &AuthenticatedUser,
+) -> Result<(), AppError> {
+    let (claimant, _) = load_claim(client, claim_id).await?;
+
+    if claimant != user.user_id && !user.has_any_role(&[Role::ClaimReviewer]) {
+        return Err(AppError::Forbidden(
+            "Only the claimant and reviewers can see a claim's evidence".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn load_claim(client: &Client, claim_id: i32) -> Result<(i32, ClaimStatus), AppError> {
+    claim_evidence_repository::get_claim_state(client, claim_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Claim not found".to_string()))
+}
+
+fn require_removable(status: &ClaimStatus) -> Result<(), AppError> {
+    if !status.allows_evidence_removal() {
+        return Err(evidence_locked());
+    }
+    Ok(())
+}
+
+fn evidence_locked() -> AppError {
+    AppError::Conflict("Evidence can only be removed while the claim is pending".to_string())
+}
+
+fn evidence_closed() -> AppError {
+    AppError::Conflict(
+        "Evidence can only be added while the claim is pending, needs information or is disputed"
+            .to_string(),
+    )
+}
+
+fn evidence_not_found() -> AppError {
+    AppError::NotFound("Evidence not found".to_string())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/claims/{claim_id}/evidence")
+            .route("", web::get().to(get_evidence))
+            .route("", web::post().to(upload_evidence))
+            .route("/{id}", web::get().to(download_evidence))
+            .route("/{id}", web::delete().to(delete_evidence)),
+    );
+}
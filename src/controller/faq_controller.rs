@@ -1,91 +1,76 @@
-use crate::models::faq::FAQ;
-use actix_web::{HttpResponse, Responder, web};
-use chrono::Utc;
+use crate::middlewares::auth_middleware::{AuthenticatedUser, RequireRole};
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::faq::FaqRequest;
+use crate::repositories::faq_repository;
+use actix_web::{HttpResponse, guard, web};
 use deadpool_postgres::Pool;
 use serde_json::json;
-use tokio_postgres::Row;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/faqs")
             .route("", web::get().to(get_faqs))
-            .route("", web::post().to(create_faq))
-            .route("/{id}", web::put().to(update_faq))
-            .route("/{id}", web::delete().to(delete_faq)),
+            .service(
+                web::resource("")
+                    .guard(guard::Post())
+                    .wrap(RequireRole::any_of(&[Role::SupportAgent]))
+                    .route(web::post().to(create_faq)),
+            )
+            .service(
+                web::resource("/{id}")
+                    .wrap(RequireRole::any_of(&[Role::SupportAgent]))
+                    .route(web::put().to(update_faq))
+                    .route(web::delete().to(delete_faq)),
+            ),
     );
 }
 
-pub async fn get_faqs(pool: web::Data<Pool>) -> impl Responder {
-    let client = pool.get().await.unwrap();
+pub async fn get_faqs(pool: web::Data<Pool>) -> Result<HttpResponse, AppError> {
+    let client = pool.get().await?;
 
-    let rows = client
-        .query(
-            "SELECT id, question, answer, created_at, updated_at FROM faqs",
-            &[],
-        )
-        .await
-        .unwrap();
-
-    let faqs: Vec<FAQ> = rows.iter().map(|row| row_to_faq(row)).collect();
-
-    HttpResponse::Ok().json(faqs)
+    let faqs = faq_repository::get_all(&client).await?;
+    Ok(HttpResponse::Ok().json(faqs))
 }
 
-pub async fn create_faq(pool: web::Data<Pool>, faq: web::Json<FAQ>) -> impl Responder {
-    let client = pool.get().await.unwrap();
-
-    let row = client
-        .query_one(
-            "INSERT INTO faqs (question, answer, created_at, updated_at) VALUES ($1, $2, $3, $4) RETURNING id, question, answer, created_at, updated_at",
-            &[&faq.question, &faq.answer, &Utc::now(), &Utc::now()],
-        )
-        .await
-        .unwrap();
-
-    let new_faq = row_to_faq(&row);
-
-    HttpResponse::Created().json(json!({"message": "FAQ created", "data": new_faq}))
+pub async fn create_faq(
+    pool: web::Data<Pool>,
+    faq: web::Json<FaqRequest>,
+    _user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    faq.validate().map_err(AppError::Validation)?;
+    let client = pool.get().await?;
+
+    let new_faq = faq_repository::create(&client, &faq).await?;
+    Ok(HttpResponse::Created().json(json!({"message": "FAQ created", "data": new_faq})))
 }
 
 pub async fn update_faq(
     pool: web::Data<Pool>,
     path: web::Path<i32>,
-    faq: web::Json<FAQ>,
-) -> impl Responder {
+    faq: web::Json<FaqRequest>,
+    _user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    faq.validate().map_err(AppError::Validation)?;
     let id = path.into_inner();
-    let client = pool.get().await.unwrap();
-
-    let row = client
-        .query_one(
-            "UPDATE faqs SET question = $1, answer = $2, updated_at = $3 WHERE id = $4 RETURNING id, question, answer, created_at, updated_at",
-            &[&faq.question, &faq.answer, &Utc::now(), &id],
-        )
-        .await
-        .unwrap();
+    let client = pool.get().await?;
 
-    let updated_faq = row_to_faq(&row);
-
-    HttpResponse::Ok().json(json!({"message": "FAQ updated", "data": updated_faq}))
+    let updated_faq = faq_repository::update(&client, id, &faq)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("FAQ with id {} not found", id)))?;
+    Ok(HttpResponse::Ok().json(json!({"message": "FAQ updated", "data": updated_faq})))
 }
 
-pub async fn delete_faq(pool: web::Data<Pool>, path: web::Path<i32>) -> impl Responder {
+pub async fn delete_faq(
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    _user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
     let id = path.into_inner();
-    let client = pool.get().await.unwrap();
-
-    client
-        .execute("DELETE FROM faqs WHERE id = $1", &[&id])
-        .await
-        .unwrap();
-
-    HttpResponse::Ok().json(json!({"message": format!("FAQ with id {} deleted", id)}))
-}
+    let client = pool.get().await?;
 
-fn row_to_faq(row: &Row) -> FAQ {
-    FAQ {
-        id: row.get("id"),
-        question: row.get("question"),
-        answer: row.get("answer"),
-        created_at: row.get("created_at"),
-        updated_at: row.get("updated_at"),
+    if !faq_repository::delete(&client, id).await? {
+        return Err(AppError::NotFound(format!("FAQ with id {} not found", id)));
     }
+    Ok(HttpResponse::Ok().json(json!({"message": format!("FAQ with id {} deleted", id)})))
 }
@@ -0,0 +1,282 @@
+use crate::controller::plan_controller::{load_plan, owned_plan};
+use crate::middlewares::auth_middleware::AuthenticatedUser;
+use crate::middlewares::error_middleware::AppError;
+use crate::models::activity_models::CreateUserActivityRequest;
+use crate::models::auth_models::Role;
+use crate::models::guardian_models::{ConfigureGuardianPolicy, Guardian, NewGuardian};
+use crate::models::plan_models::{Plan, PlanStatus};
+use crate::repositories::{activity_repository, check_in_repository, guardian_repository};
+use crate::utils::clock::Clock;
+use actix_web::{HttpResponse, web};
+use deadpool_postgres::{Client, Pool};
+
+pub async fn get_guardians(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    visible_plan(&client, plan_id, &user).await?;
+
+    let guardians = guardian_repository::get_by_plan(&client, plan_id).await?;
+    Ok(HttpResponse::Ok().json(guardians))
+}
+
+pub async fn add_guardian(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    guardian: web::Json<NewGuardian>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let guardian = guardian.into_inner();
+    let client = db_pool.get().await?;
+
+    let plan = editable_plan(&client, plan_id, &user).await?;
+    guardian
+        .validate(plan.owner_id)
+        .map_err(AppError::Validation)?;
+
+    let created = guardian_repository::create(&client, plan_id, &guardian)
+        .await?
+        .ok_or_else(|| {
+            AppError::Conflict("This user is already a guardian of the plan".to_string())
+        })?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+pub async fn remove_guardian(
+    db_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (plan_id, id) = path.into_inner();
+    let client = db_pool.get().await?;
+
+    editable_plan(&client, plan_id, &user).await?;
+
+    let guardians = guardian_repository::get_by_plan(&client, plan_id).await?;
+    if !guardians.iter().any(|guardian| guardian.id == id) {
+        return Err(AppError::NotFound("Guardian not found".to_string()));
+    }
+    if let Some(policy) = guardian_repository::get_policy(&client, plan_id).await?
+        && policy.policy.allows_guardians()
+        && guardians.len() - 1 < policy.quorum as usize
+    {
+        return Err(AppError::Conflict(format!(
+            "The plan needs at least {} guardians for its quorum, lower the quorum first",
+            policy.quorum
+        )));
+    }
+
+    guardian_repository::delete(&client, plan_id, id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_policy(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    visible_plan(&client, plan_id, &user).await?;
+
+    let policy = guardian_repository::get_policy(&client, plan_id)
+        .await?
+        .ok_or_else(no_policy)?;
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+/// Sets what may trigger the plan. Guardian policies need check-ins to be
+/// configured, since checking in is how the owner cancels a round of
+/// attestations they disagree with.
+pub async fn configure_policy(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    policy: web::Json<ConfigureGuardianPolicy>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let policy = policy.into_inner();
+    let client = db_pool.get().await?;
+
+    editable_plan(&client, plan_id, &user).await?;
+
+    let guardians = guardian_repository::get_by_plan(&client, plan_id).await?;
+    policy
+        .validate(guardians.len())
+        .map_err(AppError::Validation)?;
+    if policy.policy.allows_guardians()
+        && check_in_repository::get_by_plan(&client, plan_id)
+            .await?
+            .is_none()
+    {
+        return Err(AppError::Conflict(
+            "Configure check-ins before letting guardians trigger the plan".to_string(),
+        ));
+    }
+
+    let configured = guardian_repository::configure_policy(&client, plan_id, &policy).await?;
+    Ok(HttpResponse::Ok().json(configured))
+}
+
+pub async fn get_round(
+    db_pool: web::Data<Pool>,
+    clock: web::Data<dyn Clock>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    visible_plan(&client, plan_id, &user).await?;
+
+    let round = guardian_repository::get_latest_round(&client, plan_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No guardian has attested on this plan".to_string()))?;
+    Ok(HttpResponse::Ok().json(round.at(clock.now())))
+}
+
+/// A guardian attests that the owner has died or can no longer manage the
+/// plan. The plan is triggered once a quorum has attested within the window.
+pub async fn attest(
+    db_pool: web::Data<Pool>,
+    clock: web::Data<dyn Clock>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let mut client = db_pool.get().await?;
+
+    let (plan, guardian) = guarded_plan(&client, plan_id, &user).await?;
+    if plan.status != PlanStatus::Active {
+        return Err(AppError::Conflict(format!(
+            "Cannot attest on a {} plan",
+            plan.status
+        )));
+    }
+    let policy = guardian_repository::get_policy(&client, plan_id)
+        .await?
+        .filter(|policy| policy.policy.allows_guardians())
+        .ok_or_else(|| {
+            AppError::Conflict("This plan is not triggered by its guardians".to_string())
+        })?;
+
+    let now = clock.now();
+    let expires_at = policy.round_expires_at(now).ok_or_else(|| {
+        AppError::Validation("The plan's attestation window is out of range".to_string())
+    })?;
+    let round =
+        guardian_repository::attest(&mut client, &plan, &policy, &guardian, now, expires_at)
+            .await?;
+
+    let activity = CreateUserActivityRequest {
+        user_id: user.user_id.to_string(),
+        activity_type: "guardian".to_string(),
+        details: format!(
+            "Attested on plan {} ({} of {} guardians needed)",
+            plan.name,
+            round.standing(),
+            policy.quorum
+        ),
+        action_type: "attested".to_string(),
+        action_link: Some(format!("/plans/{}/guardians/round", plan_id)),
+    };
+    activity_repository::create_activity(&client, &activity).await?;
+
+    Ok(HttpResponse::Ok().json(round))
+}
+
+pub async fn revoke_attestation(
+    db_pool: web::Data<Pool>,
+    clock: web::Data<dyn Clock>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    let (_, guardian) = guarded_plan(&client, plan_id, &user).await?;
+
+    let round = guardian_repository::revoke(&client, plan_id, guardian.id, clock.now())
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound("You have no attestation in an open round".to_string())
+        })?;
+    Ok(HttpResponse::Ok().json(round))
+}
+
+/// Loads a plan the caller owns and can still change.
+async fn editable_plan(
+    client: &Client,
+    id: i32,
+    user: &AuthenticatedUser,
+) -> Result<Plan, AppError> {
+    let plan = owned_plan(client, id, user).await?;
+    if !plan.status.is_editable() {
+        return Err(AppError::Conflict(format!(
+            "Guardians of a {} plan can no longer be changed",
+            plan.status
+        )));
+    }
+    Ok(plan)
+}
+
+/// Loads a plan its owner, one of its guardians or an admin is looking at.
+async fn visible_plan(
+    client: &Client,
+    id: i32,
+    user: &AuthenticatedUser,
+) -> Result<Plan, AppError> {
+    let plan = load_plan(client, id).await?;
+
+    if plan.owner_id != user.user_id
+        && !user.has_any_role(&[Role::Admin])
+        && guardian_repository::get_by_user(client, id, user.user_id)
+            .await?
+            .is_none()
+    {
+        return Err(AppError::Forbidden(
+            "Only the owner and guardians can see this plan's guardians".to_string(),
+        ));
+    }
+    Ok(plan)
+}
+
+/// Loads a plan the caller guards, along with their guardian entry.
+async fn guarded_plan(
+    client: &Client,
+    id: i32,
+    user: &AuthenticatedUser,
+) -> Result<(Plan, Guardian), AppError> {
+    let plan = load_plan(client, id).await?;
+
+    let guardian = guardian_repository::get_by_user(client, id, user.user_id)
+        .await?
+        .ok_or_else(|| AppError::Forbidden("You are not a guardian of this plan".to_string()))?;
+    Ok((plan, guardian))
+}
+
+fn no_policy() -> AppError {
+    AppError::NotFound(
+        "No trigger policy is configured, the plan is triggered by missed check-ins".to_string(),
+    )
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/plans/{plan_id}/guardians")
+            .route("", web::get().to(get_guardians))
+            .route("", web::post().to(add_guardian))
+            .route("/policy", web::get().to(get_policy))
+            .route("/policy", web::put().to(configure_policy))
+            .route("/round", web::get().to(get_round))
+            .route("/attest", web::post().to(attest))
+            .route("/attest", web::delete().to(revoke_attestation))
+            .route("/{id}", web::delete().to(remove_guardian)),
+    );
+}
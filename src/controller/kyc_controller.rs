@@ -1,174 +1,326 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{HttpRequest, HttpResponse, web};
 use deadpool_postgres::Pool;
-use serde_json::json;
 
+use crate::kyc_provider::KycProvider;
+
+use crate::middlewares::auth_middleware::{AuthenticatedUser, RequireRole};
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
 use crate::models::kyc_models::{
-    CreateKycRequest, KycQueryById, KycQueryByUserId, KycRecordResponse, KycVerificationRequest,
+    CreateKycRequest, KycFraudSignalQuery, KycQueryById, KycQueryByUserId, KycRecordResponse,
+    KycRejectionInfo, KycRejectionReason, KycStatus, KycSubmissionsResponse,
+    KycVerificationRequest,
+};
+use crate::models::kyc_provider_models::{
+    KycCheckStatus, KycWebhookConfig, KycWebhookEvent, KycWebhookOutcome, KycWebhookResponse,
 };
-use crate::repositories::kyc_repository;
+use crate::repositories::{kyc_provider_repository, kyc_repository, notification_repository};
+use crate::utils::blind_index::BlindIndexKey;
+use crate::utils::clock::Clock;
+use crate::utils::envelope::Keyring;
+use crate::utils::webhook_signature::{SIGNATURE_HEADER, TIMESTAMP_HEADER};
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/kyc")
             .route("/create", web::post().to(create_kyc))
-            .route("/verify", web::post().to(verify_kyc))
+            .service(
+                web::resource("/verify")
+                    .wrap(RequireRole::any_of(&[Role::KycReviewer]))
+                    .route(web::post().to(verify_kyc)),
+            )
             .route("/status", web::get().to(get_kyc_status))
-            .route("/user", web::get().to(get_kyc_by_user)),
+            .route("/user", web::get().to(get_kyc_by_user))
+            .route("/rejection-reasons", web::get().to(get_rejection_reasons))
+            .service(
+                web::resource("/fraud-signals")
+                    .wrap(RequireRole::any_of(&[Role::KycReviewer]))
+                    .route(web::get().to(get_fraud_signals)),
+            )
+            .route("/{id}/provider-check", web::post().to(start_provider_check))
+            .route("/provider-webhook", web::post().to(provider_webhook)),
     );
 }
 
+/// Submits a KYC record, which becomes the user's current one. A rejected or
+/// expired submission is replaced and kept, any other is still in play.
+/// Submissions reusing another user's identity document are accepted, and
+/// flagged for the reviewers.
 pub async fn create_kyc(
     db_pool: web::Data<Pool>,
+    keyring: web::Data<Keyring>,
+    blind_index: web::Data<BlindIndexKey>,
     kyc_request: web::Json<CreateKycRequest>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    if kyc_request.user_id != user.user_id {
+        return Err(AppError::Forbidden(
+            "KYC can only be submitted for your own account".to_string(),
+        ));
+    }
 
-    match kyc_repository::create_kyc(&client, &kyc_request.into_inner()).await {
-        Ok(kyc) => {
-            let response = KycRecordResponse {
-                id: kyc.id,
-                user_id: kyc.user_id,
-                full_name: kyc.full_name,
-                date_of_birth: kyc.date_of_birth,
-                id_type: kyc.id_type,
-                id_number: kyc.id_number,
-                address: kyc.address,
-                verification_status: kyc.verification_status,
-                created_at: kyc.created_at.format("%d-%m-%Y").to_string(),
-                updated_at: kyc
-                    .updated_at
-                    .map(|dt| dt.format("%d-%m-%Y").to_string()),
-            };
-            HttpResponse::Created().json(response)
-        }
-        Err(e) => {
-            eprintln!("Failed to create KYC record: {:?}", e);
-            HttpResponse::InternalServerError()
-                .json(json!({"error": "Failed to create KYC record"}))
-        }
+    kyc_request.validate().map_err(AppError::Validation)?;
+
+    let mut client = db_pool.get().await?;
+
+    let current = kyc_repository::get_kyc_by_user_id(&client, &keyring, user.user_id).await?;
+    let status = current
+        .as_ref()
+        .map_or(KycStatus::NotStarted, |kyc| kyc.verification_status);
+    if !status.can_transition_to(KycStatus::Pending) {
+        return Err(AppError::Conflict(format!(
+            "Your KYC submission is {}, only a rejected or expired one can be replaced",
+            status
+        )));
     }
+
+    let kyc = kyc_repository::create_kyc(
+        &mut client,
+        &keyring,
+        &blind_index,
+        &kyc_request.into_inner(),
+        current.map(|kyc| kyc.id),
+    )
+    .await?
+    .ok_or_else(|| AppError::Conflict("KYC was submitted concurrently".to_string()))?;
+    Ok(HttpResponse::Created().json(KycRecordResponse::from(kyc)))
 }
 
+/// Moves a KYC record along its lifecycle. Rejections need a reason from
+/// the catalogue, which the user is told about.
 pub async fn verify_kyc(
     db_pool: web::Data<Pool>,
+    keyring: web::Data<Keyring>,
     verification_request: web::Json<KycVerificationRequest>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    _user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let request = verification_request.into_inner();
+    request.validate().map_err(AppError::Validation)?;
+    let next = request.verification_status;
+    if !next.is_reviewer_decision() {
+        return Err(AppError::Validation(format!(
+            "Reviewers cannot move a KYC record to {}",
+            next
+        )));
+    }
+
+    let client = db_pool.get().await?;
+
+    let kyc = kyc_repository::get_kyc_by_id(&client, &keyring, request.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("KYC record not found".to_string()))?;
+    if !kyc.verification_status.can_transition_to(next) {
+        return Err(AppError::Conflict(format!(
+            "KYC record cannot move from {} to {}",
+            kyc.verification_status, next
+        )));
+    }
 
-    match kyc_repository::update_kyc_verification_status(
+    let kyc = kyc_repository::update_kyc_verification_status(
         &client,
-        verification_request.id,
-        &verification_request.verification_status,
+        &keyring,
+        kyc.id,
+        kyc.verification_status,
+        next,
+        request.rejection_reason,
+        request.rejection_note.as_deref(),
     )
-    .await
-    {
-        Ok(kyc) => {
-            let response = KycRecordResponse {
-                id: kyc.id,
-                user_id: kyc.user_id,
-                full_name: kyc.full_name,
-                date_of_birth: kyc.date_of_birth,
-                id_type: kyc.id_type,
-                id_number: kyc.id_number,
-                address: kyc.address,
-                verification_status: kyc.verification_status,
-                created_at: kyc.created_at.format("%d-%m-%Y").to_string(),
-                updated_at: kyc
-                    .updated_at
-                    .map(|dt| dt.format("%d-%m-%Y").to_string()),
-            };
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("Failed to update KYC verification status: {:?}", e);
-            HttpResponse::InternalServerError()
-                .json(json!({"error": "Failed to update KYC verification status"}))
-        }
+    .await?
+    .ok_or_else(|| AppError::Conflict("KYC record was updated concurrently".to_string()))?;
+
+    if let Some(reason) = kyc.rejection_reason {
+        notification_repository::create(&client, &reason.notification(kyc.user_id)).await?;
     }
+
+    Ok(HttpResponse::Ok().json(KycRecordResponse::from(kyc)))
 }
 
+/// A KYC record, for the user it belongs to or a reviewer.
 pub async fn get_kyc_status(
     db_pool: web::Data<Pool>,
+    keyring: web::Data<Keyring>,
     query: web::Query<KycQueryById>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
 
-    match kyc_repository::get_kyc_by_id(&client, query.id).await {
-        Ok(kyc) => {
-            let response = KycRecordResponse {
-                id: kyc.id,
-                user_id: kyc.user_id,
-                full_name: kyc.full_name,
-                date_of_birth: kyc.date_of_birth,
-                id_type: kyc.id_type,
-                id_number: kyc.id_number,
-                address: kyc.address,
-                verification_status: kyc.verification_status,
-                created_at: kyc.created_at.format("%d-%m-%Y").to_string(),
-                updated_at: kyc
-                    .updated_at
-                    .map(|dt| dt.format("%d-%m-%Y").to_string()),
-            };
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("Failed to get KYC record: {:?}", e);
-            HttpResponse::NotFound().json(json!({"error": "KYC record not found"}))
-        }
+    let kyc = kyc_repository::get_kyc_by_id(&client, &keyring, query.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("KYC record not found".to_string()))?;
+    if kyc.user_id != user.user_id {
+        user.require_any_role(&[Role::KycReviewer])?;
     }
+
+    Ok(HttpResponse::Ok().json(KycRecordResponse::from(kyc)))
 }
 
+/// The user's current KYC status, with every submission they made. Users
+/// who never submitted are `not_started`. For the user themselves or a
+/// reviewer.
 pub async fn get_kyc_by_user(
     db_pool: web::Data<Pool>,
+    keyring: web::Data<Keyring>,
     query: web::Query<KycQueryByUserId>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    if query.user_id != user.user_id {
+        user.require_any_role(&[Role::KycReviewer])?;
+    }
+    let client = db_pool.get().await?;
+
+    let submissions = kyc_repository::get_kyc_submissions(&client, &keyring, query.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(KycSubmissionsResponse::new(query.user_id, submissions)))
+}
+
+/// Every reason a KYC record can be rejected for, and what to do about it.
+pub async fn get_rejection_reasons() -> HttpResponse {
+    let reasons: Vec<KycRejectionInfo> = KycRejectionReason::ALL
+        .into_iter()
+        .map(KycRejectionInfo::from)
+        .collect();
+
+    HttpResponse::Ok().json(reasons)
+}
+
+/// What reviewers should look at before verifying, such as identity
+/// documents submitted by more than one user.
+pub async fn get_fraud_signals(
+    db_pool: web::Data<Pool>,
+    query: web::Query<KycFraudSignalQuery>,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let signals = kyc_repository::get_fraud_signals(&client, query.kyc_id).await?;
+    Ok(HttpResponse::Ok().json(signals))
+}
+
+/// Hands a pending submission to the KYC provider, moving it into review.
+/// The applicant does this once their documents are uploaded, or a
+/// reviewer does it for them.
+pub async fn start_provider_check(
+    db_pool: web::Data<Pool>,
+    keyring: web::Data<Keyring>,
+    provider: Option<web::Data<dyn KycProvider>>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let provider = provider_enabled(provider)?;
+    let kyc_id = path.into_inner();
+    let mut client = db_pool.get().await?;
+
+    let kyc = kyc_repository::get_kyc_by_id(&client, &keyring, kyc_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("KYC record not found".to_string()))?;
+    if kyc.user_id != user.user_id {
+        user.require_any_role(&[Role::KycReviewer])?;
+    }
+    if !kyc.is_current || kyc.verification_status != KycStatus::Pending {
+        return Err(AppError::Conflict(
+            "Only the current pending KYC submission can be handed to the provider".to_string(),
+        ));
+    }
+
+    let provider = provider.into_inner();
+    let name = provider.name();
+    let reference = web::block(move || provider.submit(&kyc))
+        .await
+        .map_err(|err| AppError::Internal(err.to_string()))?
+        .map_err(|err| AppError::Internal(err.to_string()))?;
+
+    let check = kyc_provider_repository::start_check(&mut client, kyc_id, name, &reference)
+        .await?
+        .ok_or_else(|| AppError::Conflict("KYC record was updated concurrently".to_string()))?;
+    Ok(HttpResponse::Accepted().json(check))
+}
+
+/// Called by the KYC provider once it has a result. Calls are signed with
+/// the shared secret over their timestamp and body, must be recent, and are
+/// acted on once per event. The result itself is fetched from the provider
+/// rather than taken from the call.
+pub async fn provider_webhook(
+    req: HttpRequest,
+    body: web::Bytes,
+    db_pool: web::Data<Pool>,
+    provider: Option<web::Data<dyn KycProvider>>,
+    webhook: Option<web::Data<KycWebhookConfig>>,
+    clock: web::Data<dyn Clock>,
+) -> Result<HttpResponse, AppError> {
+    let provider = provider_enabled(provider)?;
+    let webhook = provider_enabled(webhook)?;
+
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
     };
+    let timestamp: i64 = header(TIMESTAMP_HEADER)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| {
+            AppError::Unauthorized(format!("{} is missing or malformed", TIMESTAMP_HEADER))
+        })?;
+    let signature = header(SIGNATURE_HEADER).unwrap_or_default();
+    if !webhook.secret.verify(timestamp, &body, signature) {
+        return Err(AppError::Unauthorized(
+            "Invalid webhook signature".to_string(),
+        ));
+    }
+    // The timestamp is signed, so an old call cannot be made to look recent
+    if (clock.now().timestamp() - timestamp).abs() > webhook.tolerance.num_seconds() {
+        return Err(AppError::Unauthorized(
+            "Webhook call is outside the accepted time window".to_string(),
+        ));
+    }
+
+    let event: KycWebhookEvent =
+        serde_json::from_slice(&body).map_err(|err| AppError::Validation(err.to_string()))?;
+    event.validate().map_err(AppError::Validation)?;
 
-    match kyc_repository::get_kyc_by_user_id(&client, query.user_id).await {
-        Ok(kyc) => {
-            let response = KycRecordResponse {
-                id: kyc.id,
-                user_id: kyc.user_id,
-                full_name: kyc.full_name,
-                date_of_birth: kyc.date_of_birth,
-                id_type: kyc.id_type,
-                id_number: kyc.id_number,
-                address: kyc.address,
-                verification_status: kyc.verification_status,
-                created_at: kyc.created_at.format("%d-%m-%Y").to_string(),
-                updated_at: kyc
-                    .updated_at
-                    .map(|dt| dt.format("%d-%m-%Y").to_string()),
-            };
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("Failed to get KYC record: {:?}", e);
-            HttpResponse::NotFound().json(json!({"error": "KYC record not found for this user"}))
-        }
+    let mut client = db_pool.get().await?;
+    let check =
+        kyc_provider_repository::get_by_reference(&client, provider.name(), &event.reference)
+            .await?
+            .ok_or_else(|| AppError::NotFound("No KYC check has this reference".to_string()))?;
+    if check.status != KycCheckStatus::Pending
+        || kyc_provider_repository::is_event_processed(&client, &event.event_id).await?
+    {
+        let outcome = KycWebhookOutcome::Duplicate;
+        return Ok(HttpResponse::Ok().json(KycWebhookResponse { outcome, check }));
     }
+
+    let provider = provider.into_inner();
+    let reference = check.reference.clone();
+    let decision = web::block(move || provider.fetch_result(&reference))
+        .await
+        .map_err(|err| AppError::Internal(err.to_string()))?
+        .map_err(|err| AppError::Internal(err.to_string()))?;
+    let Some(decision) = decision else {
+        let outcome = KycWebhookOutcome::Pending;
+        return Ok(HttpResponse::Accepted().json(KycWebhookResponse { outcome, check }));
+    };
+
+    let response = match kyc_provider_repository::complete_check(
+        &mut client,
+        check.id,
+        &event.event_id,
+        &decision,
+    )
+    .await?
+    {
+        Some(check) => KycWebhookResponse {
+            outcome: KycWebhookOutcome::Processed,
+            check,
+        },
+        None => KycWebhookResponse {
+            outcome: KycWebhookOutcome::Duplicate,
+            check,
+        },
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// The provider and its webhook settings are only registered when
+/// `FEATURES_KYC_PROVIDER` is on.
+fn provider_enabled<T: ?Sized>(data: Option<web::Data<T>>) -> Result<web::Data<T>, AppError> {
+    data.ok_or_else(|| AppError::NotFound("The KYC provider is disabled".to_string()))
 }
@@ -0,0 +1,177 @@
+use crate::controller::uploads::{discard, in_store, read_file};
+use crate::middlewares::auth_middleware::AuthenticatedUser;
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::kyc_document_models::{
+    ImageRejection, KycDocumentKind, KycDocumentLimits, NewKycDocument, clean_image,
+};
+use crate::models::kyc_models::{KycRecord, KycStatus};
+use crate::repositories::{kyc_document_repository, kyc_repository};
+use crate::storage::ObjectStore;
+use crate::utils::envelope::Keyring;
+use actix_multipart::Multipart;
+use actix_web::http::header::ContentDisposition;
+use actix_web::{HttpResponse, web};
+use deadpool_postgres::{Client, Pool};
+use sha2::{Digest, Sha256};
+
+/// Uploads one of the images of the caller's KYC submission, sent as
+/// `multipart/form-data` in a field named `file`. The image is checked and
+/// stripped of its metadata before it is stored, and replaces any earlier
+/// image of the same kind.
+pub async fn upload_document(
+    db_pool: web::Data<Pool>,
+    keyring: web::Data<Keyring>,
+    store: web::Data<dyn ObjectStore>,
+    limits: web::Data<KycDocumentLimits>,
+    path: web::Path<(i32, KycDocumentKind)>,
+    payload: Multipart,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let (kyc_id, kind) = path.into_inner();
+    let mut client = db_pool.get().await?;
+
+    let kyc = load_record(&client, &keyring, kyc_id).await?;
+    if kyc.user_id != user.user_id {
+        return Err(AppError::Forbidden(
+            "Only the applicant can upload KYC documents".to_string(),
+        ));
+    }
+    if kyc.verification_status != KycStatus::Pending {
+        return Err(documents_locked());
+    }
+
+    let (_, bytes) = read_file(payload, limits.max_bytes).await?;
+    let limits = *limits.into_inner();
+    let image = web::block(move || clean_image(&bytes, &limits))
+        .await
+        .map_err(|err| AppError::Internal(err.to_string()))?
+        .map_err(|rejection| match rejection {
+            ImageRejection::UnsupportedType => {
+                AppError::UnsupportedMediaType(rejection.to_string())
+            }
+            rejection => AppError::Validation(rejection.to_string()),
+        })?;
+
+    let document = NewKycDocument {
+        kind,
+        content_type: image.content_type,
+        size_bytes: image.bytes.len() as i64,
+        width: image.width as i32,
+        height: image.height as i32,
+        sha256: hex::encode(Sha256::digest(&image.bytes)),
+        storage_key: format!("kyc/{}/{}", kyc_id, hex::encode(rand::random::<[u8; 16]>())),
+        uploaded_by: user.user_id,
+    };
+
+    let key = document.storage_key.clone();
+    in_store(&store, move |store| store.put(&key, &image.bytes)).await?;
+
+    let saved = kyc_document_repository::save(&mut client, kyc_id, &document).await;
+    match saved {
+        Ok(Some((saved, replaced))) => {
+            if let Some(replaced) = replaced {
+                discard(&store, replaced).await;
+            }
+            Ok(HttpResponse::Created().json(saved))
+        }
+        // The record went into review while the image was being stored
+        Ok(None) => {
+            discard(&store, document.storage_key).await;
+            Err(documents_locked())
+        }
+        Err(err) => {
+            discard(&store, document.storage_key).await;
+            Err(err.into())
+        }
+    }
+}
+
+/// The images of a KYC record, for the applicant and KYC reviewers.
+pub async fn get_documents(
+    db_pool: web::Data<Pool>,
+    keyring: web::Data<Keyring>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let kyc_id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    let kyc = load_record(&client, &keyring, kyc_id).await?;
+    if kyc.user_id != user.user_id && !user.has_any_role(&[Role::KycReviewer]) {
+        return Err(AppError::Forbidden(
+            "Only the applicant and KYC reviewers can see KYC documents".to_string(),
+        ));
+    }
+
+    let documents = kyc_document_repository::get_by_kyc(&client, kyc_id).await?;
+    Ok(HttpResponse::Ok().json(documents))
+}
+
+/// Sends an image to a KYC reviewer, after checking it still matches the
+/// hash taken when it was uploaded.
+pub async fn download_document(
+    db_pool: web::Data<Pool>,
+    store: web::Data<dyn ObjectStore>,
+    path: web::Path<(i32, KycDocumentKind)>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    user.require_any_role(&[Role::KycReviewer])?;
+    let (kyc_id, kind) = path.into_inner();
+    let client = db_pool.get().await?;
+
+    let document = kyc_document_repository::get_by_kind(&client, kyc_id, kind)
+        .await?
+        .ok_or_else(|| AppError::NotFound("KYC document not found".to_string()))?;
+    let key = document.storage_key.clone();
+    let bytes = in_store(&store, move |store| store.get(&key)).await?;
+
+    if hex::encode(Sha256::digest(&bytes)) != document.sha256 {
+        log::error!(
+            "KYC document {} of record {} does not match its hash",
+            document.id,
+            kyc_id
+        );
+        return Err(AppError::Internal(
+            "KYC document failed its integrity check".to_string(),
+        ));
+    }
+
+    let extension = if document.content_type == "image/png" {
+        "png"
+    } else {
+        "jpg"
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentDisposition::attachment(format!(
+            "kyc-{}-{}.{}",
+            kyc_id, kind, extension
+        )))
+        .content_type(document.content_type)
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(("Cache-Control", "no-store"))
+        .body(bytes))
+}
+
+async fn load_record(
+    client: &Client,
+    keyring: &Keyring,
+    kyc_id: i32,
+) -> Result<KycRecord, AppError> {
+    kyc_repository::get_kyc_by_id(client, keyring, kyc_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("KYC record not found".to_string()))
+}
+
+fn documents_locked() -> AppError {
+    AppError::Conflict("KYC documents can only change while the submission is pending".to_string())
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/kyc/{kyc_id}/documents")
+            .route("", web::get().to(get_documents))
+            .route("/{kind}", web::put().to(upload_document))
+            .route("/{kind}", web::get().to(download_document)),
+    );
+}
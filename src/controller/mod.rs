@@ -1,8 +1,18 @@
 pub mod activity_controller;
 pub mod activity_log_controller;
+pub mod auth_controller;
+pub mod beneficiary_controller;
+pub mod check_in_controller;
 pub mod claim_controller;
+pub mod claim_dispute_controller;
+pub mod claim_evidence_controller;
 pub mod faq_controller;
+pub mod guardian_controller;
 pub mod kyc_controller;
+pub mod kyc_document_controller;
 pub mod notification_controller;
+pub mod plan_controller;
+pub mod reply_controller;
+pub mod uploads;
 pub mod user_support_controller;
 pub mod withdrawal_history_controller;
@@ -0,0 +1,278 @@
+use crate::middlewares::auth_middleware::AuthenticatedUser;
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::beneficiary_models::validate_allocations;
+use crate::models::execution_models::{compute_payouts, released_on_execution};
+use crate::models::plan_models::{CreatePlan, Plan, PlanStatus, UpdatePlan};
+use crate::repositories::{execution_repository, plan_repository};
+use actix_web::{HttpResponse, web};
+use chrono::Utc;
+use deadpool_postgres::{Client, Pool};
+
+/// Every plan for admins, the caller's own plans for anyone else.
+pub async fn get_plans(
+    db_pool: web::Data<Pool>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let plans = if user.has_any_role(&[Role::Admin]) {
+        plan_repository::get_all(&client).await?
+    } else {
+        plan_repository::get_by_owner(&client, user.user_id).await?
+    };
+    Ok(HttpResponse::Ok().json(plans))
+}
+
+pub async fn get_owner_plans(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let owner_id = path.into_inner();
+    if owner_id != user.user_id {
+        return Err(AppError::Forbidden(
+            "You can only list your own plans".to_string(),
+        ));
+    }
+
+    let client = db_pool.get().await?;
+
+    let plans = plan_repository::get_by_owner(&client, owner_id).await?;
+    Ok(HttpResponse::Ok().json(plans))
+}
+
+pub async fn get_plan(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let plan = owned_plan(&client, path.into_inner(), &user).await?;
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+pub async fn create_plan(
+    db_pool: web::Data<Pool>,
+    plan: web::Json<CreatePlan>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let plan = plan.into_inner();
+    plan.validate().map_err(AppError::Validation)?;
+
+    let mut client = db_pool.get().await?;
+
+    let created = plan_repository::create(&mut client, user.user_id, &plan).await?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+pub async fn update_plan(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    plan: web::Json<UpdatePlan>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let plan = plan.into_inner();
+
+    let mut client = db_pool.get().await?;
+
+    let existing = owned_plan(&client, id, &user).await?;
+    if !existing.status.is_editable() {
+        return Err(AppError::Conflict(format!(
+            "A {} plan can no longer be edited",
+            existing.status
+        )));
+    }
+    plan.validate(&existing).map_err(AppError::Validation)?;
+
+    let updated = plan_repository::update(&mut client, id, &plan).await?;
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+pub async fn delete_plan(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    let existing = owned_plan(&client, id, &user).await?;
+    if existing.status != PlanStatus::Draft {
+        return Err(AppError::Conflict(
+            "Only draft plans can be deleted, cancel the plan instead".to_string(),
+        ));
+    }
+
+    plan_repository::delete(&client, id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn activate_plan(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    transition_plan(db_pool, path.into_inner(), PlanStatus::Active, &user).await
+}
+
+/// Turns a triggered plan into pending payouts for its beneficiaries, for
+/// whatever their release schedules free straight away. Safe to retry: once a
+/// plan has been executed this returns the existing execution.
+pub async fn execute_plan(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let mut client = db_pool.get().await?;
+
+    let plan = executable_plan(&client, id, &user).await?;
+
+    if let Some(execution) = execution_repository::get_by_plan(&client, id).await? {
+        return Ok(HttpResponse::Ok().json(execution));
+    }
+    if plan.status != PlanStatus::Triggered {
+        return Err(AppError::Conflict(format!(
+            "Only triggered plans can be executed, this one is {}",
+            plan.status
+        )));
+    }
+
+    let executed_at = Utc::now();
+    let entitlements = compute_payouts(&plan).map_err(AppError::Validation)?;
+    let payouts = released_on_execution(&plan, entitlements, executed_at);
+
+    match execution_repository::record(&mut client, &plan, user.user_id, executed_at, &payouts)
+        .await?
+    {
+        Some(execution) => Ok(HttpResponse::Created().json(execution)),
+        // Someone else got there first, or the plan moved on in between
+        None => match execution_repository::get_by_plan(&client, id).await? {
+            Some(execution) => Ok(HttpResponse::Ok().json(execution)),
+            None => Err(AppError::Conflict(
+                "The plan is no longer triggered".to_string(),
+            )),
+        },
+    }
+}
+
+pub async fn get_execution(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let client = db_pool.get().await?;
+
+    executable_plan(&client, id, &user).await?;
+
+    let execution = execution_repository::get_by_plan(&client, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("This plan has not been executed".to_string()))?;
+    Ok(HttpResponse::Ok().json(execution))
+}
+
+pub async fn cancel_plan(
+    db_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    transition_plan(db_pool, path.into_inner(), PlanStatus::Cancelled, &user).await
+}
+
+async fn transition_plan(
+    db_pool: web::Data<Pool>,
+    id: i32,
+    next: PlanStatus,
+    user: &AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let plan = owned_plan(&client, id, user).await?;
+
+    if !plan.status.can_transition_to(next) {
+        return Err(AppError::Conflict(format!(
+            "Cannot move plan from {} to {}",
+            plan.status, next
+        )));
+    }
+
+    if next == PlanStatus::Active {
+        if plan.beneficiaries.is_empty() {
+            return Err(AppError::Validation(
+                "A plan needs at least one beneficiary to be activated".to_string(),
+            ));
+        }
+        let allocations: Vec<_> = plan.beneficiaries.iter().map(|b| &b.allocation).collect();
+        validate_allocations(&allocations, &plan.assets).map_err(AppError::Validation)?;
+    }
+
+    // Executions, guardians and missed check-ins move plans along too
+    if !plan_repository::transition_status(&client, id, plan.status, next).await? {
+        return Err(AppError::Conflict(
+            "Plan was updated concurrently".to_string(),
+        ));
+    }
+
+    let updated = plan_repository::get_by_id(&client, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Plan not found".to_string()))?;
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+/// Loads a plan the caller owns.
+pub async fn owned_plan(
+    client: &Client,
+    id: i32,
+    user: &AuthenticatedUser,
+) -> Result<Plan, AppError> {
+    let plan = load_plan(client, id).await?;
+
+    if plan.owner_id != user.user_id {
+        return Err(AppError::Forbidden(
+            "This plan belongs to another user".to_string(),
+        ));
+    }
+    Ok(plan)
+}
+
+pub async fn load_plan(client: &Client, id: i32) -> Result<Plan, AppError> {
+    plan_repository::get_by_id(client, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Plan not found".to_string()))
+}
+
+/// Loads a plan for execution, which its owner or an admin may run.
+async fn executable_plan(
+    client: &Client,
+    id: i32,
+    user: &AuthenticatedUser,
+) -> Result<Plan, AppError> {
+    let plan = load_plan(client, id).await?;
+
+    if plan.owner_id != user.user_id && !user.has_any_role(&[Role::Admin]) {
+        return Err(AppError::Forbidden(
+            "This plan belongs to another user".to_string(),
+        ));
+    }
+    Ok(plan)
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/plans")
+            .route("", web::get().to(get_plans))
+            .route("", web::post().to(create_plan))
+            .route("/owner/{owner_id}", web::get().to(get_owner_plans))
+            .route("/{id}", web::get().to(get_plan))
+            .route("/{id}", web::put().to(update_plan))
+            .route("/{id}", web::delete().to(delete_plan))
+            .route("/{id}/activate", web::post().to(activate_plan))
+            .route("/{id}/execute", web::post().to(execute_plan))
+            .route("/{id}/execution", web::get().to(get_execution))
+            .route("/{id}/cancel", web::post().to(cancel_plan)),
+    );
+}
@@ -0,0 +1,43 @@
+use actix_web::{HttpResponse, web};
+use deadpool_postgres::Pool;
+
+use crate::controller::user_support_controller::visible_ticket;
+use crate::middlewares::auth_middleware::AuthenticatedUser;
+use crate::middlewares::error_middleware::AppError;
+use crate::models::support_models::{CreateReply, ReplyAuthor, TicketStatus};
+use crate::repositories::support_ticket_repository;
+
+/// Adds to a ticket's thread. A reply from the ticket's owner puts it back
+/// in the support queue, a reply from an agent waits on the user.
+pub async fn reply_to_ticket(
+    db_pool: web::Data<Pool>,
+    id: web::Path<i32>,
+    reply: web::Json<CreateReply>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    reply.validate().map_err(AppError::Validation)?;
+
+    let mut client = db_pool.get().await?;
+
+    let ticket = visible_ticket(&client, id.into_inner(), &user).await?;
+    if ticket.status == TicketStatus::Closed {
+        return Err(AppError::Conflict("Ticket is closed".to_string()));
+    }
+
+    let (author, status) = if ticket.user_id == user.user_id {
+        (ReplyAuthor::User, TicketStatus::Open)
+    } else {
+        (ReplyAuthor::Agent, TicketStatus::Pending)
+    };
+
+    let reply = support_ticket_repository::add_reply(
+        &mut client,
+        ticket.id,
+        user.user_id,
+        author,
+        &reply.body,
+        status,
+    )
+    .await?;
+    Ok(HttpResponse::Created().json(reply))
+}
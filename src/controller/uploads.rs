@@ -0,0 +1,77 @@
+use crate::middlewares::error_middleware::AppError;
+use crate::models::claim_evidence_models::clean_file_name;
+use crate::storage::{ObjectStore, StorageError};
+use actix_multipart::Multipart;
+use actix_web::web;
+use futures_util::StreamExt;
+
+/// Form field the file is uploaded in.
+const FILE_FIELD: &str = "file";
+
+/// Reads the `file` field, stopping as soon as it grows past `max_bytes`.
+/// Other fields are ignored.
+pub(crate) async fn read_file(
+    mut payload: Multipart,
+    max_bytes: usize,
+) -> Result<(String, Vec<u8>), AppError> {
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|err| AppError::Validation(err.to_string()))?;
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+
+        let file_name = clean_file_name(
+            field
+                .content_disposition()
+                .and_then(|disposition| disposition.get_filename()),
+        );
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|err| AppError::Validation(err.to_string()))?;
+            if bytes.len() + chunk.len() > max_bytes {
+                return Err(AppError::PayloadTooLarge(format!(
+                    "Uploads are limited to {} bytes",
+                    max_bytes
+                )));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        if bytes.is_empty() {
+            return Err(AppError::Validation(
+                "The uploaded file is empty".to_string(),
+            ));
+        }
+        return Ok((file_name, bytes));
+    }
+
+    Err(AppError::Validation(format!(
+        "Upload the file in a multipart field named {}",
+        FILE_FIELD
+    )))
+}
+
+/// Runs a blocking storage call off the async workers.
+pub(crate) async fn in_store<T, F>(
+    store: &web::Data<dyn ObjectStore>,
+    call: F,
+) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn ObjectStore) -> Result<T, StorageError> + Send + 'static,
+{
+    let store = store.clone().into_inner();
+    web::block(move || call(store.as_ref()))
+        .await
+        .map_err(|err| AppError::Internal(err.to_string()))?
+        .map_err(|err| AppError::Internal(err.to_string()))
+}
+
+/// Deletes an object no row points at any more. A failure only leaves an
+/// orphaned file behind, so it is logged rather than reported.
+pub(crate) async fn discard(store: &web::Data<dyn ObjectStore>, key: String) {
+    let deleted = in_store(store, move |store| store.delete(&key)).await;
+    if let Err(err) = deleted {
+        log::warn!("Failed to delete stored object: {}", err);
+    }
+}
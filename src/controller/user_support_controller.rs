@@ -1,29 +1,152 @@
-use actix_web::{HttpResponse, Responder, web};
-use serde_json::json;
+use actix_web::{HttpResponse, guard, web};
+use deadpool_postgres::Pool;
+
+use crate::controller::reply_controller;
+use crate::middlewares::auth_middleware::{AuthenticatedUser, RequireRole};
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::support_models::{
+    CreateTicket, SupportTicket, SupportTicketThread, SupportTicketsResponse, TicketFilter,
+    UpdateTicket,
+};
+use crate::repositories::support_ticket_repository;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/support")
             .route("/tickets", web::get().to(get_tickets))
             .route("/tickets", web::post().to(create_ticket))
-            .route("/tickets/{id}/reply", web::post().to(reply_to_ticket)),
+            .route("/tickets/{id}", web::get().to(get_ticket))
+            .service(
+                web::resource("/tickets/{id}")
+                    .guard(guard::Put())
+                    .wrap(RequireRole::any_of(&[Role::SupportAgent]))
+                    .route(web::put().to(update_ticket)),
+            )
+            .route(
+                "/tickets/{id}/reply",
+                web::post().to(reply_controller::reply_to_ticket),
+            ),
     );
 }
 
-pub async fn get_tickets() -> impl Responder {
-    // Placeholder logic for fetching tickets
-    HttpResponse::Ok().json(json!({"message": "List of tickets"}))
+/// Users see their own tickets, agents see everyone's.
+pub async fn get_tickets(
+    db_pool: web::Data<Pool>,
+    query: web::Query<TicketFilter>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(10);
+
+    if page < 1 || page_size < 1 {
+        return Err(AppError::Validation(
+            "Page and page_size must be positive integers".to_string(),
+        ));
+    }
+
+    let owner = if is_agent(&user) {
+        None
+    } else {
+        Some(user.user_id)
+    };
+
+    let client = db_pool.get().await?;
+
+    let (tickets, total) = support_ticket_repository::list(
+        &client,
+        owner,
+        query.status,
+        query.assignee_id,
+        page,
+        page_size,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(SupportTicketsResponse {
+        tickets,
+        total,
+        page,
+        page_size,
+    }))
+}
+
+pub async fn create_ticket(
+    db_pool: web::Data<Pool>,
+    ticket: web::Json<CreateTicket>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    ticket.validate().map_err(AppError::Validation)?;
+
+    let client = db_pool.get().await?;
+
+    let ticket = support_ticket_repository::create(&client, user.user_id, &ticket).await?;
+    Ok(HttpResponse::Created().json(ticket))
+}
+
+pub async fn get_ticket(
+    db_pool: web::Data<Pool>,
+    id: web::Path<i32>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
+
+    let ticket = visible_ticket(&client, id.into_inner(), &user).await?;
+    let replies = support_ticket_repository::get_replies(&client, ticket.id).await?;
+    Ok(HttpResponse::Ok().json(SupportTicketThread { ticket, replies }))
+}
+
+/// Status, priority and assignment changes by a support agent.
+pub async fn update_ticket(
+    db_pool: web::Data<Pool>,
+    id: web::Path<i32>,
+    update: web::Json<UpdateTicket>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let client = db_pool.get().await?;
+
+    let ticket = support_ticket_repository::get_by_id(&client, id)
+        .await?
+        .ok_or_else(ticket_not_found)?;
+    if let Some(next) = update.status
+        && next != ticket.status
+        && !ticket.status.can_transition_to(next)
+    {
+        return Err(AppError::Conflict(format!(
+            "Cannot move ticket from {} to {}",
+            ticket.status, next
+        )));
+    }
+
+    let updated = support_ticket_repository::update(&client, id, &update)
+        .await?
+        .ok_or_else(ticket_not_found)?;
+    Ok(HttpResponse::Ok().json(updated))
 }
 
-pub async fn create_ticket(ticket: web::Json<serde_json::Value>) -> impl Responder {
-    // Placeholder logic for creating a ticket
-    HttpResponse::Created().json(json!({"message": "Ticket created", "data": ticket.into_inner()}))
+pub fn is_agent(user: &AuthenticatedUser) -> bool {
+    user.has_any_role(&[Role::SupportAgent])
+}
+
+/// Fetches a ticket the user is allowed to see: their own, or any if they
+/// are an agent.
+pub async fn visible_ticket(
+    client: &deadpool_postgres::Client,
+    id: i32,
+    user: &AuthenticatedUser,
+) -> Result<SupportTicket, AppError> {
+    let ticket = support_ticket_repository::get_by_id(client, id)
+        .await?
+        .ok_or_else(ticket_not_found)?;
+
+    if ticket.user_id != user.user_id && !is_agent(user) {
+        return Err(AppError::Forbidden(
+            "This ticket belongs to another user".to_string(),
+        ));
+    }
+    Ok(ticket)
 }
 
-pub async fn reply_to_ticket(
-    path: web::Path<i32>,
-    reply: web::Json<serde_json::Value>,
-) -> impl Responder {
-    // Placeholder logic for replying to a ticket
-    HttpResponse::Ok().json(json!({"message": "Reply added to ticket", "id": path.into_inner(), "data": reply.into_inner()}))
+pub fn ticket_not_found() -> AppError {
+    AppError::NotFound("Ticket not found".to_string())
 }
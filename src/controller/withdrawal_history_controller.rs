@@ -1,12 +1,21 @@
-use actix_web::{HttpResponse, Responder, web};
+use std::collections::HashMap;
+
+use actix_web::{HttpResponse, web};
+use chrono::Utc;
 use deadpool_postgres::Pool;
-use serde_json::json;
 
+use crate::middlewares::auth_middleware::{AuthenticatedUser, RequireRole};
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::Role;
+use crate::models::money::Amount;
+use crate::models::vesting_models::release_status;
 use crate::models::withdrawal_history_models::{
-    CreateWithdrawalRecordRequest, SingleWithdrawalRecordRequest, WithdrawalRecord,
-    WithdrawalRecordResponse, WithdrawalRecordsResponse,
+    CreateWithdrawalRequest, SingleWithdrawalRecordRequest, UpdateWithdrawalStatus,
+    WithdrawalRecordResponse, WithdrawalRecordsResponse, WithdrawalStatus,
+};
+use crate::repositories::{
+    execution_repository, kyc_repository, plan_repository, withdrawal_history_repository,
 };
-use crate::repositories::{withdrawal_history_repository, kyc_repository};
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -15,55 +24,87 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("/history", web::get().to(get_withdrawal_history))
             .route("/delete", web::post().to(delete_withdrawal))
             .route("/single", web::get().to(get_single_withdrawal))
-            .route("/update", web::post().to(update_withdrawal))
-            .route("/user", web::get().to(get_withdrawal_history_by_user)),
+            .route("/user", web::get().to(get_withdrawal_history_by_user))
+            .service(
+                web::resource("/{id}/status")
+                    .wrap(RequireRole::any_of(&[Role::Admin]))
+                    .route(web::post().to(update_withdrawal_status)),
+            ),
     );
 }
 
+/// Requests a payout of one plan asset to a beneficiary's wallet, as listed
+/// in the plan. Only the beneficiary themselves may ask, only once their KYC
+/// is verified, and only for what their release schedule has freed so far.
 pub async fn record_withdrawal(
     db_pool: web::Data<Pool>,
-    withdrawal_history_request: web::Json<CreateWithdrawalRecordRequest>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    request: web::Json<CreateWithdrawalRequest>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let request = request.into_inner();
+    request.validate().map_err(AppError::Validation)?;
 
-    // Check KYC verification status before allowing withdrawal
-    let user_id = &withdrawal_history_request.user_id.parse::<i32>().unwrap_or(0); // Convert string to i32
-    match kyc_repository::is_kyc_verified(&client, user_id).await {
-        Ok(is_verified) => {
-            if !is_verified {
-                return HttpResponse::Forbidden().json(json!({
-                    "error": "KYC verification required",
-                    "message": "You must complete KYC verification before making withdrawals."
-                }));
-            }
-        },
-        Err(e) => {
-            eprintln!("Failed to check KYC verification status: {:?}", e);
-            return HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to verify KYC status"
-            }));
-        }
-    }
+    let mut client = db_pool.get().await?;
 
-    // Proceed with withdrawal if KYC is verified
-    match withdrawal_history_repository::record_withdrawal(
-        &client,
-        &withdrawal_history_request.into_inner(),
-    )
-    .await
+    let plan = plan_repository::get_by_id(&client, request.plan_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Plan not found".to_string()))?;
+    let beneficiary = plan
+        .beneficiaries
+        .iter()
+        .find(|beneficiary| beneficiary.id == request.beneficiary_id)
+        .ok_or_else(|| AppError::Validation("Beneficiary is not part of this plan".to_string()))?;
+
+    let Some(payee_id) = beneficiary.user_id.filter(|id| *id == user.user_id) else {
+        return Err(AppError::Forbidden(
+            "Only the beneficiary can request this withdrawal".to_string(),
+        ));
+    };
+    // The identity checked is that of the beneficiary being paid
+    if !kyc_repository::is_kyc_verified(&client, &payee_id).await? {
+        return Err(AppError::Forbidden("KYC verification required".to_string()));
+    }
+    if !plan
+        .assets
+        .iter()
+        .any(|asset| asset.asset == request.money.asset)
     {
-        Ok(activity) => HttpResponse::Created().json(activity),
-        Err(e) => {
-            eprintln!("Failed to create activity: {:?}", e);
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create activity"}))
-        }
+        return Err(AppError::Validation(format!(
+            "Plan holds no {}",
+            request.money.asset
+        )));
     }
+
+    let started_at = execution_repository::executed_at(&client, plan.id).await?;
+    let release = release_status(
+        &plan,
+        beneficiary.id,
+        started_at,
+        &HashMap::new(),
+        Utc::now(),
+    )
+    .map_err(AppError::Validation)?;
+    let unlocked = release
+        .assets
+        .iter()
+        .find(|asset| asset.asset == request.money.asset)
+        .map_or(Amount::ZERO, |asset| asset.unlocked);
+
+    let withdrawal = withdrawal_history_repository::record_within_unlocked(
+        &mut client,
+        user.user_id,
+        &request,
+        &beneficiary.wallet_address,
+        unlocked,
+    )
+    .await?
+    .ok_or_else(|| {
+        AppError::Validation(format!(
+            "Amount exceeds the unlocked {} balance of this beneficiary",
+            request.money.asset
+        ))
+    })?;
+    Ok(HttpResponse::Created().json(withdrawal))
 }
 
 #[derive(serde::Deserialize)]
@@ -77,155 +118,167 @@ pub struct GetSingleParams {
     pub id: Option<i64>,
 }
 
+/// The withdrawals the caller can see, newest first: all of them for admins.
 pub async fn get_withdrawal_history(
     db_pool: web::Data<Pool>,
     query: web::Query<PaginationParams>,
-) -> impl Responder {
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
     let page = query.page.unwrap_or(1);
     let page_size = query.page_size.unwrap_or(10);
 
     if page < 1 || page_size < 1 {
-        return HttpResponse::BadRequest()
-            .json(json!({"error": "Page and page_size must be positive integers"}));
+        return Err(AppError::Validation(
+            "Page and page_size must be positive integers".to_string(),
+        ));
     }
 
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    let client = db_pool.get().await?;
 
-    match withdrawal_history_repository::get_withdrawal_history(&client, page, page_size).await {
-        Ok((withdrawals, total)) => {
-            let withdrawals_response: Vec<WithdrawalRecordResponse> = withdrawals
-                .into_iter()
-                .map(|withdrawal| WithdrawalRecordResponse {
-                    id: withdrawal.id,
-                    plan_id: withdrawal.plan_id,
-                    wallet_id: withdrawal.wallet_id,
-                    amount: withdrawal.amount,
-                    payer_name: withdrawal.payer_name,
-                    created_at: withdrawal.created_at.format("%d-%m-%Y").to_string(),
-                })
-                .collect();
-
-            let response = WithdrawalRecordsResponse {
-                records: withdrawals_response,
-                total,
-                page,
-                page_size,
-            };
-
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("Failed to get user activities: {:?}", e);
-            HttpResponse::InternalServerError()
-                .json(json!({"error": "Failed to get user activities"}))
-        }
-    }
+    let (withdrawals, total) = withdrawal_history_repository::get_withdrawal_history(
+        &client,
+        viewer(&user),
+        page,
+        page_size,
+    )
+    .await?;
+    let withdrawals_response: Vec<WithdrawalRecordResponse> = withdrawals
+        .into_iter()
+        .map(WithdrawalRecordResponse::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(WithdrawalRecordsResponse {
+        records: withdrawals_response,
+        total,
+        page,
+        page_size,
+    }))
 }
 
+/// Withdraws a request that nobody has acted on yet.
 pub async fn delete_withdrawal(
     db_pool: web::Data<Pool>,
     withdrawal_history_request: web::Json<SingleWithdrawalRecordRequest>,
-) -> impl Responder {
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
+    let client = db_pool.get().await?;
 
-    match withdrawal_history_repository::delete_withdrawal(&client, withdrawal_history_request.id)
-        .await
-    {
-        Ok(activity) => HttpResponse::Ok().json(activity),
-        Err(e) => {
-            eprintln!("Failed to create activity: {:?}", e);
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create activity"}))
-        }
+    let withdrawal =
+        withdrawal_history_repository::get_withdrawal_by_id(&client, withdrawal_history_request.id)
+            .await?
+            .ok_or_else(withdrawal_not_found)?;
+    if withdrawal.user_id != Some(user.user_id) {
+        return Err(AppError::Forbidden(
+            "This withdrawal belongs to another user".to_string(),
+        ));
+    }
+    if withdrawal.status != WithdrawalStatus::Requested {
+        return Err(AppError::Conflict(format!(
+            "Cannot delete a withdrawal that is {}",
+            withdrawal.status
+        )));
+    }
+    // Re-running the execution would not bring it back
+    if withdrawal.execution_id.is_some() {
+        return Err(AppError::Conflict(
+            "Payouts scheduled by a plan execution cannot be deleted".to_string(),
+        ));
     }
+
+    if !withdrawal_history_repository::delete_withdrawal(&client, withdrawal.id).await? {
+        return Err(withdrawal_not_found());
+    }
+    Ok(HttpResponse::NoContent().finish())
 }
 
 pub async fn get_single_withdrawal(
     db_pool: web::Data<Pool>,
     query: web::Query<GetSingleParams>,
-) -> impl Responder {
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
     let id = query.id.unwrap_or(0);
     if id < 1 {
-        return HttpResponse::BadRequest().json(json!({"error": "ID must be a positive integer"}));
+        return Err(AppError::Validation(
+            "ID must be a positive integer".to_string(),
+        ));
     }
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
 
-    match withdrawal_history_repository::get_withdrawal_by_id(&client, id).await {
-        Ok(res) => HttpResponse::Ok().json(res),
-        Err(e) => {
-            eprintln!("Failed to create activity: {:?}", e);
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create activity"}))
-        }
+    let client = db_pool.get().await?;
+
+    let withdrawal = withdrawal_history_repository::get_withdrawal_by_id(&client, id)
+        .await?
+        .ok_or_else(withdrawal_not_found)?;
+    if let Some(viewer) = viewer(&user)
+        && !withdrawal_history_repository::is_visible_to(&client, id, viewer).await?
+    {
+        return Err(AppError::Forbidden(
+            "Only the plan owner, the beneficiary or an admin can see this withdrawal".to_string(),
+        ));
     }
+    Ok(HttpResponse::Ok().json(withdrawal))
 }
 
-pub async fn update_withdrawal(
+/// Moves a withdrawal along its lifecycle as the payout progresses.
+pub async fn update_withdrawal_status(
     db_pool: web::Data<Pool>,
-    withdrawal_history_request: web::Json<WithdrawalRecord>,
-) -> impl Responder {
-    // let record: WithdrawalRecord = withdrawal_history_request.into_inner();
-
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
+    id: web::Path<i64>,
+    update: web::Json<UpdateWithdrawalStatus>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    update.validate().map_err(AppError::Validation)?;
 
-    match withdrawal_history_repository::update_withdrawal(
-        &client,
-        &withdrawal_history_request,
-        // &record,
-    )
-    .await
-    {
-        Ok(activity) => HttpResponse::Ok().json(activity),
-        Err(e) => {
-            eprintln!("Failed to create activity: {:?}", e);
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create activity"}))
-        }
+    let client = db_pool.get().await?;
+
+    let withdrawal = withdrawal_history_repository::get_withdrawal_by_id(&client, id)
+        .await?
+        .ok_or_else(withdrawal_not_found)?;
+    if !withdrawal.status.can_transition_to(update.status) {
+        return Err(AppError::Conflict(format!(
+            "Cannot move withdrawal from {} to {}",
+            withdrawal.status, update.status
+        )));
     }
+
+    withdrawal_history_repository::update_status(&client, id, withdrawal.status, &update)
+        .await?
+        .map(|updated| HttpResponse::Ok().json(updated))
+        .ok_or_else(|| AppError::Conflict("Withdrawal was updated concurrently".to_string()))
 }
 
+/// The withdrawals a user requested, as far as the caller can see them.
 pub async fn get_withdrawal_history_by_user(
     db_pool: web::Data<Pool>,
     query: web::Query<GetSingleParams>,
-) -> impl Responder {
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, AppError> {
     let uid = query.id.unwrap_or(0);
+    let Ok(uid) = i32::try_from(uid) else {
+        return Err(AppError::Validation(
+            "ID must be a positive integer".to_string(),
+        ));
+    };
     if uid < 1 {
-        return HttpResponse::BadRequest().json(json!({"error": "ID must be a positive integer"}));
+        return Err(AppError::Validation(
+            "ID must be a positive integer".to_string(),
+        ));
     }
 
-    let client = match db_pool.get().await {
-        Ok(client) => client,
-        Err(err) => {
-            eprintln!("Failed to get DB client: {}", err);
-            return HttpResponse::InternalServerError().json(json!({"error": "Database error"}));
-        }
-    };
-    match withdrawal_history_repository::get_withdrawal_history_by_user_id(&client, &uid).await {
-        Ok(res) => HttpResponse::Ok().json(res),
-        Err(e) => {
-            eprintln!("Failed to create activity: {:?}", e);
-            HttpResponse::InternalServerError().json(json!({"error": "Failed to create activity"}))
-        }
-    }
+    let client = db_pool.get().await?;
+
+    let withdrawals = withdrawal_history_repository::get_withdrawal_history_by_user_id(
+        &client,
+        uid,
+        viewer(&user),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(withdrawals))
+}
+
+/// Who withdrawals are shown to: `None`, meaning everyone's, for admins.
+fn viewer(user: &AuthenticatedUser) -> Option<i32> {
+    (!user.has_any_role(&[Role::Admin])).then_some(user.user_id)
+}
+
+fn withdrawal_not_found() -> AppError {
+    AppError::NotFound("Withdrawal not found".to_string())
 }
@@ -1,82 +1,49 @@
-use deadpool_postgres::{Config, ManagerConfig, RecyclingMethod, Runtime};
+use deadpool_postgres::{Config, ManagerConfig, PoolConfig, RecyclingMethod, Runtime, SslMode};
+use postgres_native_tls::MakeTlsConnector;
 use tokio_postgres::NoTls;
 
-pub async fn create_pool() -> deadpool_postgres::Pool {
+use crate::config::env::{DatabaseSettings, TlsMode};
+
+pub mod migrations;
+
+pub async fn create_pool(settings: &DatabaseSettings) -> deadpool_postgres::Pool {
     let mut cfg = Config::new();
-    cfg.host = Some("localhost".to_string());
-    cfg.port = Some(5432);
-    cfg.user = Some("postgres".to_string());
-    cfg.password = Some("postgres".to_string());
-    cfg.dbname = Some("inheritx_db".to_string());
+    cfg.host = Some(settings.host.clone());
+    cfg.port = Some(settings.port);
+    cfg.user = Some(settings.user.clone());
+    cfg.password = Some(settings.password.clone());
+    cfg.dbname = Some(settings.name.clone());
+    cfg.pool = Some(PoolConfig::new(settings.pool_size));
     cfg.manager = Some(ManagerConfig {
         recycling_method: RecyclingMethod::Fast,
     });
 
-    cfg.create_pool(Some(Runtime::Tokio1), NoTls).unwrap()
+    let pool = match settings.tls_mode {
+        TlsMode::Disable => {
+            cfg.ssl_mode = Some(SslMode::Disable);
+            cfg.create_pool(Some(Runtime::Tokio1), NoTls)
+        }
+        TlsMode::Require | TlsMode::VerifyFull => {
+            let verify = settings.tls_mode == TlsMode::VerifyFull;
+            let connector = native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(!verify)
+                .danger_accept_invalid_hostnames(!verify)
+                .build()
+                .expect("Failed to set up TLS");
+            cfg.ssl_mode = Some(SslMode::Require);
+            cfg.create_pool(Some(Runtime::Tokio1), MakeTlsConnector::new(connector))
+        }
+    };
+
+    pool.expect("Failed to create database pool")
 }
 
+/// Brings the schema up to date, see [`migrations`].
 pub async fn run_migrations(pool: &deadpool_postgres::Pool) {
-    let client = pool.get().await.unwrap();
-
-    client
-        .batch_execute(
-            "
-        CREATE TABLE IF NOT EXISTS notifications (
-            id SERIAL PRIMARY KEY,
-            title VARCHAR(255) NOT NULL,
-            body TEXT NOT NULL,
-            is_read BOOLEAN NOT NULL DEFAULT FALSE,
-            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        );
-
-        CREATE TABLE IF NOT EXISTS faqs (
-            id SERIAL PRIMARY KEY,
-            question TEXT NOT NULL,
-            answer TEXT NOT NULL
-        );
-        
-        CREATE TABLE IF NOT EXISTS user_activities (
-            id SERIAL PRIMARY KEY,
-            user_id VARCHAR(255) NOT NULL,
-            date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            activity_type VARCHAR(50) NOT NULL,
-            details TEXT NOT NULL,
-            action_type VARCHAR(50) NOT NULL,
-            action_link TEXT,
-            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        );
-
-        DO $$ BEGIN
-            CREATE TYPE claim_status AS ENUM ('pending', 'approved', 'rejected');
-        EXCEPTION
-            WHEN duplicate_object THEN null;
-        END $$;
-
-        CREATE TABLE IF NOT EXISTS claims (
-            id SERIAL PRIMARY KEY,
-            user_id INTEGER NOT NULL,
-            amount DECIMAL(10,2) NOT NULL,
-            status claim_status NOT NULL DEFAULT 'pending',
-            description TEXT NOT NULL,
-            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        );
-
-        CREATE TABLE IF NOT EXISTS kyc_records (
-            id SERIAL PRIMARY KEY,
-            user_id INTEGER NOT NULL,
-            full_name VARCHAR(255) NOT NULL,
-            date_of_birth VARCHAR(50) NOT NULL,
-            id_type VARCHAR(50) NOT NULL,
-            id_number VARCHAR(100) NOT NULL,
-            address TEXT NOT NULL,
-            verification_status VARCHAR(50) NOT NULL DEFAULT 'pending',
-            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at TIMESTAMPTZ
-        );
-    ",
-        )
+    let applied = migrations::up(pool)
         .await
-        .unwrap();
+        .unwrap_or_else(|err| panic!("Failed to run migrations: {}", err));
+    for migration in applied {
+        log::info!("Applied migration {}", migration.name);
+    }
 }
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{GenericClient, Pool};
+use sha2::{Digest, Sha256};
+
+/// A numbered schema change, embedded in the binary from `migrations/`.
+///
+/// Applied migrations must never be edited: their checksum is recorded in
+/// `schema_migrations` and checked before every run. Add a new file instead.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        hex::encode(Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../../migrations/", $name, ".up.sql")),
+            down: include_str!(concat!("../../migrations/", $name, ".down.sql")),
+        }
+    };
+}
+
+/// Every migration, in the order it is applied.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial_schema"),
+    migration!(2, "0002_withdrawal_history"),
+    migration!(3, "0003_withdrawal_lifecycle"),
+    migration!(4, "0004_support_tickets"),
+    migration!(5, "0005_plan_check_ins"),
+    migration!(6, "0006_plan_executions"),
+    migration!(7, "0007_release_schedules"),
+    migration!(8, "0008_plan_guardians"),
+    migration!(9, "0009_claim_evidence"),
+    migration!(10, "0010_claim_status_history"),
+    migration!(11, "0011_exact_amounts"),
+    migration!(12, "0012_claim_review_queue"),
+    migration!(13, "0013_claim_disputes"),
+    migration!(14, "0014_kyc_status"),
+    migration!(15, "0015_kyc_documents"),
+    migration!(16, "0016_kyc_encryption"),
+    migration!(17, "0017_kyc_fraud_signals"),
+    migration!(18, "0018_kyc_submission_history"),
+    migration!(19, "0019_kyc_provider_checks"),
+    migration!(20, "0020_unclaimed_payouts"),
+    migration!(21, "0021_exact_plan_amounts"),
+];
+
+// Serializes migration runs between instances starting at the same time
+const LOCK_KEY: i64 = 0x696e_6865_7269_7478;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Failed to get DB client: {0}")]
+    Pool(#[from] deadpool_postgres::PoolError),
+    #[error("Database error: {0}")]
+    Database(#[from] tokio_postgres::Error),
+    #[error("Migration {name} has been edited since it was applied")]
+    ChecksumMismatch { version: i64, name: String },
+    #[error("Database has migration {0}, which this build does not know about")]
+    UnknownApplied(i64),
+    #[error("There is no migration {0}")]
+    UnknownVersion(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the file has changed since.
+    Modified,
+    /// Recorded in the database but not part of this build.
+    Unknown,
+}
+
+impl fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+            MigrationState::Unknown => "unknown",
+        };
+        write!(f, "{}", state)
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+struct AppliedMigration {
+    name: String,
+    checksum: String,
+    applied_at: DateTime<Utc>,
+}
+
+/// Applies every pending migration, each in its own transaction. Returns the
+/// ones that were applied by this call.
+pub async fn up(pool: &Pool) -> Result<Vec<&'static Migration>, MigrationError> {
+    let mut client = pool.get().await?;
+    ensure_table(&client).await?;
+    verify(&applied(&client).await?)?;
+
+    let mut ran = Vec::new();
+    for migration in MIGRATIONS {
+        let transaction = client.transaction().await?;
+        lock(&transaction).await?;
+
+        // Another instance may have got here first
+        let done = transaction
+            .query_opt(
+                "SELECT 1 FROM schema_migrations WHERE version = $1",
+                &[&migration.version],
+            )
+            .await?
+            .is_some();
+        if done {
+            continue;
+        }
+
+        transaction.batch_execute(migration.up).await?;
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)",
+                &[&migration.version, &migration.name, &migration.checksum()],
+            )
+            .await?;
+        transaction.commit().await?;
+        ran.push(migration);
+    }
+
+    Ok(ran)
+}
+
+/// Reverts applied migrations newer than `version`, newest first. Version 0
+/// reverts everything.
+pub async fn down_to(pool: &Pool, version: i64) -> Result<Vec<&'static Migration>, MigrationError> {
+    if version != 0
+        && !MIGRATIONS
+            .iter()
+            .any(|migration| migration.version == version)
+    {
+        return Err(MigrationError::UnknownVersion(version));
+    }
+
+    let mut client = pool.get().await?;
+    ensure_table(&client).await?;
+    verify(&applied(&client).await?)?;
+
+    let mut reverted = Vec::new();
+    for migration in MIGRATIONS.iter().rev().filter(|m| m.version > version) {
+        let transaction = client.transaction().await?;
+        lock(&transaction).await?;
+
+        let removed = transaction
+            .execute(
+                "DELETE FROM schema_migrations WHERE version = $1",
+                &[&migration.version],
+            )
+            .await?;
+        if removed == 0 {
+            continue;
+        }
+
+        transaction.batch_execute(migration.down).await?;
+        transaction.commit().await?;
+        reverted.push(migration);
+    }
+
+    Ok(reverted)
+}
+
+/// Every known migration plus any the database has that this build lacks.
+pub async fn status(pool: &Pool) -> Result<Vec<MigrationStatus>, MigrationError> {
+    let client = pool.get().await?;
+    ensure_table(&client).await?;
+    let mut applied = applied(&client).await?;
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let record = applied.remove(&migration.version);
+            let state = match &record {
+                None => MigrationState::Pending,
+                Some(record) if record.checksum != migration.checksum() => MigrationState::Modified,
+                Some(_) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state,
+                applied_at: record.map(|record| record.applied_at),
+            }
+        })
+        .collect();
+
+    statuses.extend(
+        applied
+            .into_iter()
+            .map(|(version, record)| MigrationStatus {
+                version,
+                name: record.name,
+                state: MigrationState::Unknown,
+                applied_at: Some(record.applied_at),
+            }),
+    );
+    statuses.sort_by_key(|status| status.version);
+
+    Ok(statuses)
+}
+
+async fn ensure_table(client: &impl GenericClient) -> Result<(), tokio_postgres::Error> {
+    client
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version BIGINT PRIMARY KEY,
+                name VARCHAR(255) NOT NULL,
+                checksum VARCHAR(64) NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )",
+        )
+        .await
+}
+
+async fn lock(client: &impl GenericClient) -> Result<(), tokio_postgres::Error> {
+    client
+        .execute("SELECT pg_advisory_xact_lock($1)", &[&LOCK_KEY])
+        .await?;
+    Ok(())
+}
+
+async fn applied(
+    client: &impl GenericClient,
+) -> Result<HashMap<i64, AppliedMigration>, tokio_postgres::Error> {
+    let rows = client
+        .query(
+            "SELECT version, name, checksum, applied_at FROM schema_migrations",
+            &[],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let migration = AppliedMigration {
+                name: row.get(1),
+                checksum: row.get(2),
+                applied_at: row.get(3),
+            };
+            (row.get(0), migration)
+        })
+        .collect())
+}
+
+/// Refuses to touch a database whose history doesn't match this build.
+fn verify(applied: &HashMap<i64, AppliedMigration>) -> Result<(), MigrationError> {
+    let mut versions: Vec<&i64> = applied.keys().collect();
+    versions.sort();
+
+    for version in versions {
+        let record = &applied[version];
+        match MIGRATIONS.iter().find(|m| m.version == *version) {
+            Some(migration) if migration.checksum() != record.checksum => {
+                return Err(MigrationError::ChecksumMismatch {
+                    version: *version,
+                    name: migration.name.to_string(),
+                });
+            }
+            Some(_) => {}
+            None => return Err(MigrationError::UnknownApplied(*version)),
+        }
+    }
+
+    Ok(())
+}
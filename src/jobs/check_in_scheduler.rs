@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+
+use crate::jobs::SchedulerError;
+use crate::models::activity_models::CreateUserActivityRequest;
+use crate::models::check_in_models::{CheckInSchedule, Escalation};
+use crate::models::notification::CreateNotification;
+use crate::models::plan_models::PlanStatus;
+use crate::repositories::{
+    activity_repository, check_in_repository, notification_repository, plan_repository,
+};
+use crate::utils::clock::Clock;
+
+/// Runs `run_pass` every `every` for as long as the server is up.
+pub fn spawn(pool: Pool, clock: Arc<dyn Clock>, every: Duration) {
+    actix_web::rt::spawn(async move {
+        let mut ticks = actix_web::rt::time::interval(every);
+        loop {
+            ticks.tick().await;
+            match run_pass(&pool, clock.as_ref()).await {
+                Ok(0) => {}
+                Ok(escalated) => log::info!("Escalated {} missed check-ins", escalated),
+                Err(err) => log::error!("Check-in scheduler pass failed: {}", err),
+            }
+        }
+    });
+}
+
+/// Takes one escalation step for every active plan whose owner has missed a
+/// check-in, and returns how many plans were escalated.
+///
+/// Safe to run from several instances at once: each step only applies if the
+/// schedule is still where this pass found it.
+pub async fn run_pass(pool: &Pool, clock: &dyn Clock) -> Result<usize, SchedulerError> {
+    let mut client = pool.get().await?;
+    let now = clock.now();
+
+    let mut escalated = 0;
+    for (owner_id, schedule) in check_in_repository::get_watched(&client).await? {
+        if schedule.escalation.next().is_some() && schedule.next_step().is_none() {
+            log::warn!(
+                "Skipping check-ins of plan {}, its next escalation date is out of range",
+                schedule.plan_id
+            );
+            continue;
+        }
+        let Some(step) = schedule.escalation_due(now) else {
+            continue;
+        };
+        if escalate(&mut client, owner_id, &schedule, step, now).await? {
+            escalated += 1;
+        }
+    }
+
+    Ok(escalated)
+}
+
+async fn escalate(
+    client: &mut deadpool_postgres::Client,
+    owner_id: i32,
+    schedule: &CheckInSchedule,
+    step: Escalation,
+    now: DateTime<Utc>,
+) -> Result<bool, SchedulerError> {
+    let plan_id = schedule.plan_id;
+    let transaction = client.transaction().await?;
+
+    let moved =
+        check_in_repository::escalate(&transaction, plan_id, schedule.escalation, step, now)
+            .await?;
+    if moved.is_none() {
+        return Ok(false);
+    }
+
+    if step == Escalation::Triggered
+        && !plan_repository::transition_status(
+            &transaction,
+            plan_id,
+            PlanStatus::Active,
+            PlanStatus::Triggered,
+        )
+        .await?
+    {
+        // The plan left the active state since this pass started
+        return Ok(false);
+    }
+
+    let (title, body) = notice(plan_id, step);
+    let notification = CreateNotification {
+        user_id: Some(owner_id),
+        title: title.to_string(),
+        body: body.clone(),
+    };
+    notification_repository::create(&transaction, &notification).await?;
+
+    let activity = CreateUserActivityRequest {
+        user_id: owner_id.to_string(),
+        activity_type: "check_in".to_string(),
+        details: body,
+        action_type: step.as_str().to_string(),
+        action_link: Some(format!("/plans/{}/check-in", plan_id)),
+    };
+    activity_repository::create_activity(&transaction, &activity).await?;
+
+    transaction.commit().await?;
+
+    log::info!("Plan {} escalated to {}", plan_id, step);
+    Ok(true)
+}
+
+fn notice(plan_id: i32, step: Escalation) -> (&'static str, String) {
+    match step {
+        Escalation::Reminder => (
+            "Check-in reminder",
+            format!(
+                "You missed a check-in for plan {}. Check in to keep it from being triggered.",
+                plan_id
+            ),
+        ),
+        Escalation::Warning => (
+            "Check-in overdue",
+            format!(
+                "You have still not checked in for plan {}. It will be triggered unless you check in.",
+                plan_id
+            ),
+        ),
+        Escalation::TriggerPending => (
+            "Plan about to be triggered",
+            format!(
+                "Plan {} will be triggered at the end of the grace period unless you check in now.",
+                plan_id
+            ),
+        ),
+        Escalation::Triggered | Escalation::None => (
+            "Plan triggered",
+            format!(
+                "Plan {} was triggered after repeated missed check-ins. Check in to restore it.",
+                plan_id
+            ),
+        ),
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use deadpool_postgres::Pool;
+
+use crate::jobs::SchedulerError;
+use crate::models::auth_models::Role;
+use crate::models::claim::Claim;
+use crate::models::notification::CreateNotification;
+use crate::repositories::{claim_repository, notification_repository, user_repository};
+use crate::utils::clock::Clock;
+
+/// Runs `run_pass` every `every` for as long as the server is up.
+pub fn spawn(pool: Pool, clock: Arc<dyn Clock>, every: Duration) {
+    actix_web::rt::spawn(async move {
+        let mut ticks = actix_web::rt::time::interval(every);
+        loop {
+            ticks.tick().await;
+            match run_pass(&pool, clock.as_ref()).await {
+                Ok(0) => {}
+                Ok(flagged) => log::info!("Flagged {} overdue claims", flagged),
+                Err(err) => log::error!("Claim SLA monitor pass failed: {}", err),
+            }
+        }
+    });
+}
+
+/// Flags every open claim that has missed its deadline, tells the supervisors
+/// about each, and returns how many were flagged.
+///
+/// Safe to run from several instances at once: a claim is only flagged, and
+/// so announced, by the first pass to find it overdue.
+pub async fn run_pass(pool: &Pool, clock: &dyn Clock) -> Result<usize, SchedulerError> {
+    let mut client = pool.get().await?;
+    let transaction = client.transaction().await?;
+
+    let overdue = claim_repository::flag_overdue(&transaction, clock.now()).await?;
+    if overdue.is_empty() {
+        return Ok(0);
+    }
+
+    let supervisors = user_repository::get_ids_with_role(&transaction, Role::Admin).await?;
+    if supervisors.is_empty() {
+        log::warn!("Claims are overdue but there are no supervisors to tell");
+    }
+    for claim in &overdue {
+        for supervisor in &supervisors {
+            let notification = CreateNotification {
+                user_id: Some(*supervisor),
+                title: "Claim overdue".to_string(),
+                body: notice(claim),
+            };
+            notification_repository::create(&transaction, &notification).await?;
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(overdue.len())
+}
+
+fn notice(claim: &Claim) -> String {
+    let assignee = match claim.assignee_id {
+        Some(reviewer) => format!("it is assigned to reviewer {}", reviewer),
+        None => "nobody has picked it up".to_string(),
+    };
+    format!(
+        "Claim {} ({} priority, {}) was due for a decision by {} and {}.",
+        claim.id,
+        claim.priority,
+        claim.status,
+        claim.due_at.format("%Y-%m-%d %H:%M UTC"),
+        assignee
+    )
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use deadpool_postgres::Pool;
+
+use crate::jobs::SchedulerError;
+use crate::repositories::kyc_repository;
+use crate::utils::blind_index::BlindIndexKey;
+use crate::utils::envelope::Keyring;
+
+/// Records moved per transaction, so rows are not held locked for long.
+const BATCH_SIZE: i64 = 100;
+
+/// Runs `run_pass` every `every` for as long as the server is up.
+pub fn spawn(pool: Pool, keyring: Keyring, blind_index: BlindIndexKey, every: Duration) {
+    actix_web::rt::spawn(async move {
+        let mut ticks = actix_web::rt::time::interval(every);
+        loop {
+            ticks.tick().await;
+            match run_pass(&pool, &keyring, &blind_index).await {
+                Ok(0) => {}
+                Ok(moved) => log::info!(
+                    "Moved {} KYC records onto master key {}",
+                    moved,
+                    keyring.current_key_id()
+                ),
+                Err(err) => log::error!("KYC re-encryption pass failed: {}", err),
+            }
+        }
+    });
+}
+
+/// Encrypts KYC records still held in plaintext and moves records under an
+/// older master key onto the current one, in batches, until none are left.
+/// Records missing their ID number index get it too. Returns how many
+/// records were moved.
+///
+/// Once a pass comes back with nothing to do, no record needs any key but
+/// the current one, and the older keys can be dropped from the settings.
+/// Safe to run from several instances at once, as each batch skips records
+/// another one has locked. A record that fails to decrypt fails the pass,
+/// and is reported again on every pass until it is looked at.
+pub async fn run_pass(
+    pool: &Pool,
+    keyring: &Keyring,
+    blind_index: &BlindIndexKey,
+) -> Result<usize, SchedulerError> {
+    let mut client = pool.get().await?;
+
+    let mut moved = 0;
+    loop {
+        let batch =
+            kyc_repository::reencrypt_batch(&mut client, keyring, blind_index, BATCH_SIZE).await?;
+        moved += batch;
+        if (batch as i64) < BATCH_SIZE {
+            return Ok(moved);
+        }
+    }
+}
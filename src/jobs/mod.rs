@@ -0,0 +1,15 @@
+pub mod check_in_scheduler;
+pub mod claim_sla_monitor;
+pub mod kyc_reencryption;
+
+/// Why a pass of a background job failed. The job carries on with the next
+/// pass.
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error("Failed to get DB client: {0}")]
+    Pool(#[from] deadpool_postgres::PoolError),
+    #[error("Database error: {0}")]
+    Database(#[from] tokio_postgres::Error),
+    #[error("{0}")]
+    Kyc(#[from] crate::repositories::kyc_repository::KycRepositoryError),
+}
@@ -0,0 +1,88 @@
+use super::{KycProvider, KycProviderError};
+use crate::models::kyc_models::{KycRecord, KycRejectionReason, normalize_id_number};
+use crate::models::kyc_provider_models::KycDecision;
+
+/// ID numbers, as normalized, that the mock does not approve, and what it
+/// does with them instead. Every other submission is approved.
+pub const FIXTURES: [(&str, MockOutcome); 4] = [
+    ("MOCKUNAVAILABLE", MockOutcome::Unavailable),
+    ("MOCKPENDING", MockOutcome::Pending),
+    (
+        "MOCKEXPIRED",
+        MockOutcome::Rejected(KycRejectionReason::DocumentExpired),
+    ),
+    (
+        "MOCKNAMEMISMATCH",
+        MockOutcome::Rejected(KycRejectionReason::NameMismatch),
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockOutcome {
+    Approved,
+    /// Refused, as if the provider were down.
+    Unavailable,
+    /// Never decided.
+    Pending,
+    Rejected(KycRejectionReason),
+}
+
+impl MockOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MockOutcome::Approved => "approved",
+            MockOutcome::Unavailable => "unavailable",
+            MockOutcome::Pending => "pending",
+            MockOutcome::Rejected(reason) => reason.as_str(),
+        }
+    }
+}
+
+/// Decides submissions on the spot from `FIXTURES`, so the provider flow
+/// can be run locally and in tests without a vendor. The outcome is kept in
+/// the reference, `mock-{kyc_id}-{outcome}`, so nothing is lost on restart
+/// and the same submission always gets the same reference.
+pub struct MockProvider;
+
+impl MockProvider {
+    pub fn outcome(kyc: &KycRecord) -> MockOutcome {
+        let id_number = normalize_id_number(&kyc.id_type, &kyc.id_number);
+        FIXTURES
+            .iter()
+            .find(|(fixture, _)| *fixture == id_number)
+            .map_or(MockOutcome::Approved, |(_, outcome)| *outcome)
+    }
+}
+
+impl KycProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn submit(&self, kyc: &KycRecord) -> Result<String, KycProviderError> {
+        match MockProvider::outcome(kyc) {
+            MockOutcome::Unavailable => Err(KycProviderError::Unavailable(
+                "the mock provider refuses this fixture".to_string(),
+            )),
+            outcome => Ok(format!("mock-{}-{}", kyc.id, outcome.as_str())),
+        }
+    }
+
+    fn fetch_result(&self, reference: &str) -> Result<Option<KycDecision>, KycProviderError> {
+        let unknown = || KycProviderError::UnknownReference(reference.to_string());
+        let (kyc_id, outcome) = reference
+            .strip_prefix("mock-")
+            .and_then(|rest| rest.split_once('-'))
+            .ok_or_else(unknown)?;
+        kyc_id.parse::<i32>().map_err(|_| unknown())?;
+
+        match outcome {
+            "approved" => Ok(Some(KycDecision::Approved)),
+            "pending" => Ok(None),
+            reason => Ok(Some(KycDecision::Rejected {
+                reason: reason.parse().map_err(|_| unknown())?,
+                note: Some("Decided by the mock KYC provider".to_string()),
+            })),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+pub mod mock;
+
+use std::sync::Arc;
+
+use crate::config::env::{KycProviderBackend, KycProviderSettings};
+use crate::models::kyc_models::KycRecord;
+use crate::models::kyc_provider_models::KycDecision;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KycProviderError {
+    #[error("Unknown KYC provider reference: {0}")]
+    UnknownReference(String),
+    #[error("KYC provider unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// An outside service KYC submissions are handed to for verification. It
+/// decides in its own time and calls the KYC webhook when it has, which
+/// fetches the result.
+///
+/// Calls block, so handlers run them through `web::block`.
+pub trait KycProvider: Send + Sync {
+    /// Stored with each check, so webhook calls are matched to the provider
+    /// that made it.
+    fn name(&self) -> &'static str;
+
+    /// Hands over a submission, returning the provider's reference for it.
+    fn submit(&self, kyc: &KycRecord) -> Result<String, KycProviderError>;
+
+    /// The decision on a check, or `None` while the provider is still on it.
+    fn fetch_result(&self, reference: &str) -> Result<Option<KycDecision>, KycProviderError>;
+}
+
+/// Builds the provider the settings ask for.
+pub fn from_settings(settings: &KycProviderSettings) -> Arc<dyn KycProvider> {
+    match settings.backend {
+        KycProviderBackend::Mock => {
+            log::warn!("KYC submissions are decided by the mock provider");
+            Arc::new(mock::MockProvider)
+        }
+    }
+}
@@ -1,38 +1,215 @@
+mod config;
 mod controller;
 mod db;
+mod jobs;
+mod kyc_provider;
+mod middlewares;
 mod models;
 mod repositories;
 mod routes;
+mod storage;
+#[cfg(test)]
+mod tests;
+mod utils;
 
-use actix_web::{App, HttpServer, web};
-use controller::{
-    claim_controller, faq_controller, kyc_controller, notification_controller,
-    user_support_controller,
-};
+use actix_web::{App, HttpServer, middleware, web};
+use config::env::Settings;
+use controller::{claim_controller, kyc_controller, notification_controller};
 use db::create_pool;
+use db::migrations;
+use deadpool_postgres::Pool;
+use middlewares::auth_middleware::{AuthConfig, JwtAuth};
+use middlewares::error_middleware::error_envelope;
+use models::activity_models::ActivityLog;
+use models::claim_dispute_models::DisputeLimits;
+use models::claim_evidence_models::EvidenceLimits;
+use models::kyc_provider_models::KycWebhookConfig;
+use std::sync::{Arc, Mutex};
+use utils::clock::{Clock, SystemClock};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // Refuse to start on a bad configuration rather than fail on first use
+    let settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    env_logger::Builder::new()
+        .filter_level(settings.log_level)
+        .init();
+
     // Create database connection pool
-    let pool = create_pool().await;
+    let pool = create_pool(&settings.database).await;
+
+    // `migrate ...` manages the schema instead of starting the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("migrate") => {
+            migrate(&pool, &args[1..]).await;
+            return Ok(());
+        }
+        Some(command) => {
+            eprintln!("Unknown command: {}\n{}", command, USAGE);
+            std::process::exit(2);
+        }
+    }
 
     // Run migrations
-    db::run_migrations(&pool).await;
+    if settings.features.run_migrations {
+        db::run_migrations(&pool).await;
+    }
+
+    // Bearer tokens are signed with a locally configured secret
+    let mut auth_config = AuthConfig::new(&settings.jwt_secret, chrono::Duration::hours(1));
+    if let Some(class_hash) = settings.starknet_account_class_hash {
+        auth_config = auth_config.with_starknet_account_class_hash(class_hash);
+    }
+    auth_config.wallet_login = settings.features.wallet_login;
+
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+
+    // Escalates missed check-ins from within the server process
+    if settings.features.check_in_scheduler {
+        jobs::check_in_scheduler::spawn(
+            pool.clone(),
+            clock.clone(),
+            std::time::Duration::from_secs(settings.check_in_poll_secs),
+        );
+    }
+
+    // Flags claims that miss their review deadline
+    if settings.features.claim_sla_monitor {
+        jobs::claim_sla_monitor::spawn(
+            pool.clone(),
+            clock.clone(),
+            std::time::Duration::from_secs(settings.claim_sla_poll_secs),
+        );
+    }
+    let claim_sla = settings.claim_sla;
+
+    // Encrypts legacy KYC records and moves them off retired master keys
+    let kyc_master_keys = settings.kyc_master_keys.clone();
+    let kyc_blind_index_key = settings.kyc_blind_index_key.clone();
+    if settings.features.kyc_reencryption {
+        jobs::kyc_reencryption::spawn(
+            pool.clone(),
+            kyc_master_keys.clone(),
+            kyc_blind_index_key.clone(),
+            std::time::Duration::from_secs(settings.kyc_reencrypt_poll_secs),
+        );
+    }
 
-    println!("Starting server at http://localhost:8080");
+    // Hands KYC submissions to an outside provider, whose results come back
+    // through its webhook
+    let kyc_provider_checks = settings.features.kyc_provider.then(|| {
+        let webhook = KycWebhookConfig {
+            secret: settings
+                .kyc_provider
+                .webhook_secret
+                .clone()
+                .expect("the webhook secret is required when the KYC provider is on"),
+            tolerance: chrono::Duration::seconds(settings.kyc_provider.webhook_tolerance_secs),
+        };
+        (kyc_provider::from_settings(&settings.kyc_provider), webhook)
+    });
+
+    let store = storage::from_settings(&settings.storage);
+    let evidence_limits = EvidenceLimits {
+        max_bytes: settings.claim_evidence_max_bytes,
+    };
+    let kyc_document_limits = settings.kyc_documents;
+    let dispute_limits = DisputeLimits {
+        appeal_window_days: settings.claim_appeal_window_days,
+    };
+
+    // In-memory activity log shared by all workers
+    let activity_logs = web::Data::new(Mutex::new(Vec::<ActivityLog>::new()));
+
+    log::info!("Starting server at http://{}", settings.bind_address);
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(activity_logs.clone())
+            .app_data(web::Data::new(auth_config.clone()))
+            .app_data(web::Data::from(clock.clone()))
+            .app_data(web::Data::from(store.clone()))
+            .app_data(web::Data::new(evidence_limits))
+            .app_data(web::Data::new(claim_sla))
+            .app_data(web::Data::new(dispute_limits))
+            .app_data(web::Data::new(kyc_document_limits))
+            .app_data(web::Data::new(kyc_master_keys.clone()))
+            .app_data(web::Data::new(kyc_blind_index_key.clone()))
+            .configure(|cfg| {
+                if let Some((provider, webhook)) = &kyc_provider_checks {
+                    cfg.app_data(web::Data::from(provider.clone()))
+                        .app_data(web::Data::new(webhook.clone()));
+                }
+            })
+            .wrap(JwtAuth::new(auth_config.clone()))
+            .wrap(middleware::Logger::default())
+            // Outermost, so that every error leaving the app shares one shape
+            .wrap(error_envelope())
             // Use the routes module to configure all application routes
             .configure(routes::configure)
             .configure(notification_controller::config)
-            .configure(faq_controller::config)
-            .configure(user_support_controller::config)
             .configure(claim_controller::config)
             .configure(kyc_controller::config)
     })
-    .bind("127.0.0.1:8080")?
+    .bind(settings.bind_address)?
     .run()
     .await
 }
+
+const USAGE: &str =
+    "Usage: inheritx-backend [migrate up | migrate status | migrate down <version>]";
+
+async fn migrate(pool: &Pool, args: &[String]) {
+    let result = match args {
+        [command] if command == "up" => migrations::up(pool).await.map(|applied| {
+            if applied.is_empty() {
+                println!("Schema is up to date");
+            }
+            for migration in applied {
+                println!("Applied {}", migration.name);
+            }
+        }),
+        [command] if command == "status" => migrations::status(pool).await.map(|statuses| {
+            for status in statuses {
+                let applied_at = status
+                    .applied_at
+                    .map(|at| at.to_rfc3339())
+                    .unwrap_or_default();
+                println!("{:<32} {:<9} {}", status.name, status.state, applied_at);
+            }
+        }),
+        [command, version] if command == "down" => {
+            let Ok(version) = version.parse() else {
+                eprintln!("Invalid version: {}\n{}", version, USAGE);
+                std::process::exit(2);
+            };
+            migrations::down_to(pool, version).await.map(|reverted| {
+                if reverted.is_empty() {
+                    println!("Nothing to revert");
+                }
+                for migration in reverted {
+                    println!("Reverted {}", migration.name);
+                }
+            })
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
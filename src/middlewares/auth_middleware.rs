@@ -0,0 +1,295 @@
+use std::future::{Future, Ready, ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::http::header;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, ResponseError};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use starknet_crypto::FieldElement;
+
+use crate::middlewares::error_middleware::AppError;
+use crate::models::auth_models::{Role, TokenClaims};
+
+/// Signing configuration for the HS256 bearer tokens issued at login, plus
+/// the settings for wallet-signature login.
+#[derive(Clone)]
+pub struct AuthConfig {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    pub token_ttl: Duration,
+    /// How long a wallet login nonce may be signed for.
+    pub nonce_ttl: Duration,
+    /// Account class Starknet wallets are expected to be deployed from.
+    /// Starknet login is disabled while this is unset.
+    pub starknet_account_class_hash: Option<FieldElement>,
+    /// Whether wallet-signature login is offered at all.
+    pub wallet_login: bool,
+}
+
+impl AuthConfig {
+    pub fn new(secret: &str, token_ttl: Duration) -> Self {
+        AuthConfig {
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            token_ttl,
+            nonce_ttl: Duration::minutes(5),
+            starknet_account_class_hash: None,
+            wallet_login: true,
+        }
+    }
+
+    pub fn with_starknet_account_class_hash(mut self, class_hash: FieldElement) -> Self {
+        self.starknet_account_class_hash = Some(class_hash);
+        self
+    }
+
+    pub fn issue_token(
+        &self,
+        user_id: i32,
+        roles: &[Role],
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let now = Utc::now();
+        let claims = TokenClaims {
+            sub: user_id.to_string(),
+            roles: roles.to_vec(),
+            iat: now.timestamp(),
+            exp: (now + self.token_ttl).timestamp(),
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+    }
+
+    pub fn verify_token(&self, token: &str) -> Result<AuthenticatedUser, String> {
+        let data = decode::<TokenClaims>(
+            token,
+            &self.decoding_key,
+            &Validation::new(Algorithm::HS256),
+        )
+        .map_err(|err| match err.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => "Token has expired".to_string(),
+            _ => "Invalid token".to_string(),
+        })?;
+
+        let user_id = data
+            .claims
+            .sub
+            .parse()
+            .map_err(|_| "Invalid token subject".to_string())?;
+
+        Ok(AuthenticatedUser {
+            user_id,
+            roles: data.claims.roles,
+        })
+    }
+}
+
+/// The caller identified by a valid bearer token. Taking this as a handler
+/// parameter makes the endpoint reject anonymous requests with 401.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: i32,
+    pub roles: Vec<Role>,
+}
+
+impl AuthenticatedUser {
+    /// Admins pass every role check.
+    pub fn has_any_role(&self, roles: &[Role]) -> bool {
+        self.roles
+            .iter()
+            .any(|role| *role == Role::Admin || roles.contains(role))
+    }
+
+    /// For checks that depend on the request body and so can't be expressed
+    /// with [`RequireRole`] on the route.
+    pub fn require_any_role(&self, roles: &[Role]) -> Result<(), AppError> {
+        if self.has_any_role(roles) {
+            Ok(())
+        } else {
+            Err(AppError::MissingRole(roles.to_vec()))
+        }
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user = req.extensions().get::<AuthenticatedUser>().cloned();
+        ready(
+            user.ok_or_else(|| {
+                AppError::Unauthorized("Authentication required".to_string()).into()
+            }),
+        )
+    }
+}
+
+/// Validates `Authorization: Bearer` tokens on every request.
+///
+/// Requests with a valid token get an [`AuthenticatedUser`] attached, requests
+/// with an invalid or expired token are rejected with 401, and requests
+/// without a token pass through so that public endpoints keep working.
+pub struct JwtAuth {
+    config: Rc<AuthConfig>,
+}
+
+impl JwtAuth {
+    pub fn new(config: AuthConfig) -> Self {
+        JwtAuth {
+            config: Rc::new(config),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for JwtAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = JwtAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(JwtAuthMiddleware {
+            service,
+            config: self.config.clone(),
+        }))
+    }
+}
+
+pub struct JwtAuthMiddleware<S> {
+    service: S,
+    config: Rc<AuthConfig>,
+}
+
+impl<S, B> Service<ServiceRequest> for JwtAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let token = match bearer_token(&req) {
+            Ok(token) => token,
+            Err(message) => return Box::pin(ready(Ok(unauthorized(req, message)))),
+        };
+
+        if let Some(token) = token {
+            match self.config.verify_token(&token) {
+                Ok(user) => {
+                    req.extensions_mut().insert(user);
+                }
+                Err(message) => return Box::pin(ready(Ok(unauthorized(req, &message)))),
+            }
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
+
+/// Restricts a resource or scope to callers holding at least one of the given
+/// roles, e.g.
+///
+/// ```ignore
+/// web::resource("/{id}/verify")
+///     .wrap(RequireRole::any_of(&[Role::KycReviewer]))
+///     .route(web::put().to(verify_kyc))
+/// ```
+///
+/// Relies on [`JwtAuth`] running first. Anonymous callers get 401, callers
+/// without a matching role get 403 naming the roles that were required.
+pub struct RequireRole {
+    roles: Rc<[Role]>,
+}
+
+impl RequireRole {
+    pub fn any_of(roles: &[Role]) -> Self {
+        RequireRole {
+            roles: Rc::from(roles),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireRoleMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireRoleMiddleware {
+            service,
+            roles: self.roles.clone(),
+        }))
+    }
+}
+
+pub struct RequireRoleMiddleware<S> {
+    service: S,
+    roles: Rc<[Role]>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireRoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let allowed = req
+            .extensions()
+            .get::<AuthenticatedUser>()
+            .map(|user| user.has_any_role(&self.roles));
+
+        match allowed {
+            None => Box::pin(ready(Ok(unauthorized(req, "Authentication required")))),
+            Some(false) => {
+                let response = AppError::MissingRole(self.roles.to_vec()).error_response();
+                Box::pin(ready(Ok(req.into_response(response).map_into_right_body())))
+            }
+            Some(true) => {
+                let fut = self.service.call(req);
+                Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+            }
+        }
+    }
+}
+
+fn bearer_token(req: &ServiceRequest) -> Result<Option<String>, &'static str> {
+    let Some(value) = req.headers().get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| "Malformed Authorization header")?;
+    match value.strip_prefix("Bearer ") {
+        Some(token) if !token.trim().is_empty() => Ok(Some(token.trim().to_string())),
+        _ => Err("Authorization header must use the Bearer scheme"),
+    }
+}
+
+fn unauthorized<B>(req: ServiceRequest, message: &str) -> ServiceResponse<EitherBody<B>> {
+    let response = AppError::Unauthorized(message.to_string()).error_response();
+    req.into_response(response).map_into_right_body()
+}
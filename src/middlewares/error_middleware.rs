@@ -0,0 +1,153 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{StatusCode, header};
+use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers};
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::json;
+
+use crate::models::auth_models::Role;
+use crate::repositories::kyc_repository::KycRepositoryError;
+
+/// Error type shared by all handlers. Every variant renders as
+///
+/// ```json
+/// {"error": "human readable message", "code": "machine_readable_code"}
+/// ```
+///
+/// Database and pool errors are logged and reported without their details.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Database unavailable")]
+    Pool(#[from] deadpool_postgres::PoolError),
+    #[error("Database error")]
+    Database(#[from] tokio_postgres::Error),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    PayloadTooLarge(String),
+    #[error("{0}")]
+    UnsupportedMediaType(String),
+    #[error("Missing required role: {}", role_names(.0).join(" or "))]
+    MissingRole(Vec<Role>),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Pool(_) => "database_unavailable",
+            AppError::Database(_) => "database_error",
+            AppError::NotFound(_) => "not_found",
+            AppError::Validation(_) => "validation_failed",
+            AppError::Conflict(_) => "conflict",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::MissingRole(_) => "missing_role",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+// A record that fails to decrypt is logged like any other internal error
+impl From<KycRepositoryError> for AppError {
+    fn from(err: KycRepositoryError) -> Self {
+        match err {
+            KycRepositoryError::Database(err) => AppError::Database(err),
+            err => AppError::Internal(err.to_string()),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Pool(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Forbidden(_) | AppError::MissingRole(_) => StatusCode::FORBIDDEN,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            AppError::Pool(err) => log::error!("Failed to get DB client: {}", err),
+            AppError::Database(err) => log::error!("Database error: {:?}", err),
+            AppError::Internal(message) => log::error!("Internal error: {}", message),
+            _ => {}
+        }
+
+        let mut body = json!({"error": self.to_string(), "code": self.code()});
+        if let AppError::MissingRole(roles) = self {
+            body["required_roles"] = json!(role_names(roles));
+        }
+        HttpResponse::build(self.status_code()).json(body)
+    }
+}
+
+fn role_names(roles: &[Role]) -> Vec<&'static str> {
+    roles.iter().map(Role::as_str).collect()
+}
+
+/// Rewrites error responses that did not come from an [`AppError`] (bad JSON
+/// bodies, unparsable path segments, unknown routes, ...) into the same
+/// envelope, so clients only have one error shape to handle.
+pub fn error_envelope<B: 'static>() -> ErrorHandlers<B> {
+    ErrorHandlers::new().default_handler(wrap_in_envelope)
+}
+
+fn wrap_in_envelope<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if is_json {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+
+    let status = res.status();
+    let message = match res.response().error() {
+        Some(err) if status.is_client_error() => err.to_string(),
+        _ => status
+            .canonical_reason()
+            .unwrap_or("Request failed")
+            .to_string(),
+    };
+    let body = json!({"error": message, "code": status_code_name(status)});
+
+    let (req, _) = res.into_parts();
+    let response = HttpResponse::build(status).json(body);
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, response).map_into_right_body(),
+    ))
+}
+
+fn status_code_name(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        status if status.is_client_error() => "bad_request",
+        _ => "internal_error",
+    }
+}
@@ -0,0 +1,2 @@
+pub mod auth_middleware;
+pub mod error_middleware;
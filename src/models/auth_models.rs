@@ -0,0 +1,196 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct User {
+    pub id: i32,
+    /// Users signing in with a wallet have no email until they add one.
+    pub email: Option<String>,
+    pub wallet_address: Option<String>,
+    pub roles: Vec<Role>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a user is allowed to do on the platform. Roles are stored as text in
+/// `users.roles` and copied into every token issued for the user.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Owner,
+    Beneficiary,
+    KycReviewer,
+    /// Works the claim review queue.
+    ClaimReviewer,
+    SupportAgent,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Beneficiary => "beneficiary",
+            Role::KycReviewer => "kyc_reviewer",
+            Role::ClaimReviewer => "claim_reviewer",
+            Role::SupportAgent => "support_agent",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owner" => Ok(Role::Owner),
+            "beneficiary" => Ok(Role::Beneficiary),
+            "kyc_reviewer" => Ok(Role::KycReviewer),
+            "claim_reviewer" => Ok(Role::ClaimReviewer),
+            "support_agent" => Ok(Role::SupportAgent),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("Unknown role: {}", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AssignRoles {
+    pub roles: Vec<Role>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegisterRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+}
+
+/// Claims carried by the bearer tokens this service issues.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenClaims {
+    pub sub: String,
+    pub roles: Vec<Role>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl RegisterRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.email.contains('@') {
+            return Err("A valid email address is required".to_string());
+        }
+        if self.password.len() < 8 {
+            return Err("Password must be at least 8 characters long".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Networks whose wallet signatures are accepted at login.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Chain {
+    Evm,
+    Starknet,
+}
+
+impl Chain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Chain::Evm => "evm",
+            Chain::Starknet => "starknet",
+        }
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Chain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "evm" => Ok(Chain::Evm),
+            "starknet" => Ok(Chain::Starknet),
+            other => Err(format!("Unsupported chain: {}", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NonceQuery {
+    pub wallet_address: String,
+    pub chain: Chain,
+}
+
+/// A single-use login challenge for one wallet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthNonce {
+    pub nonce: String,
+    pub wallet_address: String,
+    pub chain: Chain,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl AuthNonce {
+    /// The exact text the wallet is asked to sign. It is rebuilt from the
+    /// stored nonce on verify, so nothing the client sends back is trusted.
+    pub fn message(&self) -> String {
+        format!(
+            "InheritX wants you to sign in with your {} account:\n{}\n\nNonce: {}\nIssued At: {}\nExpiration Time: {}",
+            self.chain,
+            self.wallet_address,
+            self.nonce,
+            self.issued_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            self.expires_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NonceResponse {
+    pub nonce: String,
+    pub message: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WalletVerifyRequest {
+    pub wallet_address: String,
+    pub chain: Chain,
+    pub nonce: String,
+    /// EVM: a single 65 byte hex signature. Starknet: `[r, s]` as hex felts.
+    pub signature: Vec<String>,
+    /// Stark public key of the account, required on Starknet.
+    pub public_key: Option<String>,
+}
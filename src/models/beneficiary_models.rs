@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::money::Amount;
+use crate::models::plan_models::PlanAsset;
+use crate::models::vesting_models::ReleaseSchedule;
+
+/// Percentage shares are expressed in basis points, so a full allocation is
+/// 10_000 (100%).
+pub const FULL_SHARE_BPS: i32 = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Beneficiary {
+    pub id: i32,
+    pub plan_id: i32,
+    /// Platform account of the beneficiary, once they have one. Claims are
+    /// matched against this.
+    pub user_id: Option<i32>,
+    pub name: String,
+    pub wallet_address: String,
+    pub relationship: Option<String>,
+    pub email: Option<String>,
+    pub allocation: Allocation,
+    #[serde(default)]
+    pub release: ReleaseSchedule,
+    pub position: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Allocation {
+    /// A share of whatever is left once fixed allocations are paid out.
+    Percentage { share_bps: i32 },
+    /// A fixed amount of one asset.
+    Fixed { asset: String, amount: Amount },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewBeneficiary {
+    pub user_id: Option<i32>,
+    pub name: String,
+    pub wallet_address: String,
+    pub relationship: Option<String>,
+    pub email: Option<String>,
+    pub allocation: Allocation,
+    #[serde(default)]
+    pub release: ReleaseSchedule,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpdateBeneficiary {
+    pub user_id: Option<i32>,
+    pub name: Option<String>,
+    pub wallet_address: Option<String>,
+    pub relationship: Option<String>,
+    pub email: Option<String>,
+    pub allocation: Option<Allocation>,
+    pub release: Option<ReleaseSchedule>,
+}
+
+/// Links a beneficiary to the platform account they signed up with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkAccount {
+    pub user_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReorderBeneficiaries {
+    pub beneficiary_ids: Vec<i32>,
+}
+
+impl NewBeneficiary {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_details(&self.name, &self.wallet_address, self.email.as_deref())?;
+        self.release.validate()
+    }
+}
+
+impl UpdateBeneficiary {
+    /// Applies the update on top of `existing`, producing the beneficiary as it
+    /// would be stored.
+    pub fn apply(&self, existing: &Beneficiary) -> Result<NewBeneficiary, String> {
+        let updated = NewBeneficiary {
+            user_id: self.user_id.or(existing.user_id),
+            name: self.name.clone().unwrap_or_else(|| existing.name.clone()),
+            wallet_address: self
+                .wallet_address
+                .clone()
+                .unwrap_or_else(|| existing.wallet_address.clone()),
+            relationship: self
+                .relationship
+                .clone()
+                .or_else(|| existing.relationship.clone()),
+            email: self.email.clone().or_else(|| existing.email.clone()),
+            allocation: self
+                .allocation
+                .clone()
+                .unwrap_or_else(|| existing.allocation.clone()),
+            release: self
+                .release
+                .clone()
+                .unwrap_or_else(|| existing.release.clone()),
+        };
+        updated.validate()?;
+        Ok(updated)
+    }
+}
+
+fn validate_details(name: &str, wallet_address: &str, email: Option<&str>) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Beneficiary name must not be empty".to_string());
+    }
+    if wallet_address.trim().is_empty() {
+        return Err("Beneficiary wallet address must not be empty".to_string());
+    }
+    if let Some(email) = email
+        && !email.contains('@')
+    {
+        return Err(format!("Invalid contact email: {}", email));
+    }
+    Ok(())
+}
+
+/// Checks an allocation set against the plan's assets.
+///
+/// Percentage allocations must add up to exactly 100%, on every write, so a
+/// plan never holds a partial set. Shares are moved between beneficiaries by
+/// replacing the whole set.
+pub fn validate_allocations(
+    allocations: &[&Allocation],
+    assets: &[PlanAsset],
+) -> Result<(), String> {
+    let mut total_bps: i64 = 0;
+    let mut has_percentage = false;
+
+    for allocation in allocations {
+        match allocation {
+            Allocation::Percentage { share_bps } => {
+                if *share_bps <= 0 {
+                    return Err("Every percentage share must be positive".to_string());
+                }
+                has_percentage = true;
+                total_bps += *share_bps as i64;
+            }
+            Allocation::Fixed { amount, .. } => {
+                if !amount.is_positive() {
+                    return Err("Every fixed allocation must be positive".to_string());
+                }
+            }
+        }
+    }
+
+    if has_percentage && total_bps != FULL_SHARE_BPS as i64 {
+        return Err(format!(
+            "Percentage shares must sum to exactly 100%, got {}.{:02}%",
+            total_bps / 100,
+            total_bps % 100
+        ));
+    }
+
+    for allocation in allocations {
+        if let Allocation::Fixed { asset, .. } = allocation
+            && !assets.iter().any(|a| a.asset == *asset)
+        {
+            return Err(format!("Plan holds no {} to allocate", asset));
+        }
+    }
+
+    for asset in assets {
+        let fixed_total = allocations
+            .iter()
+            .filter_map(|allocation| match allocation {
+                Allocation::Fixed { asset: a, amount } if *a == asset.asset => Some(*amount),
+                _ => None,
+            })
+            .try_fold(Amount::ZERO, Amount::checked_add);
+        if fixed_total.is_none_or(|total| total > asset.amount) {
+            return Err(format!(
+                "Fixed allocations of {} exceed the {} held in the plan",
+                asset.asset, asset.amount
+            ));
+        }
+    }
+
+    Ok(())
+}
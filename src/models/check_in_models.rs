@@ -0,0 +1,144 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Intervals and grace periods are capped at a century, which keeps every
+/// escalation date computable.
+const MAX_PERIOD_SECS: i64 = 100 * 365 * 24 * 3600;
+
+/// The proof-of-life schedule of a plan. The owner is expected to check in
+/// every `interval_secs`; once they miss one, the scheduler escalates one step
+/// per `grace_secs` until the plan is triggered.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CheckInSchedule {
+    pub plan_id: i32,
+    pub interval_secs: i64,
+    pub grace_secs: i64,
+    pub last_check_in_at: DateTime<Utc>,
+    pub escalation: Escalation,
+    /// When the current escalation step was reached.
+    pub escalated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CheckInSchedule {
+    /// The step that is due next, and from when. `None` once the plan is
+    /// triggered, or if the due date can't be represented.
+    pub fn next_step(&self) -> Option<(Escalation, DateTime<Utc>)> {
+        let next = self.escalation.next()?;
+        let (from, secs) = match (self.escalation, self.escalated_at) {
+            (Escalation::None, _) | (_, None) => (self.last_check_in_at, self.interval_secs),
+            (_, Some(escalated_at)) => (escalated_at, self.grace_secs),
+        };
+        let due_at = from.checked_add_signed(Duration::try_seconds(secs)?)?;
+        Some((next, due_at))
+    }
+
+    /// The step the scheduler should take at `now`, if any. Only one step is
+    /// taken at a time, so the owner always gets a full grace window after each
+    /// notice, even if the scheduler was down for a while.
+    pub fn escalation_due(&self, now: DateTime<Utc>) -> Option<Escalation> {
+        self.next_step()
+            .filter(|(_, due_at)| now >= *due_at)
+            .map(|(step, _)| step)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CheckInStatus {
+    #[serde(flatten)]
+    pub schedule: CheckInSchedule,
+    pub next_escalation: Option<Escalation>,
+    pub next_escalation_at: Option<DateTime<Utc>>,
+}
+
+impl From<CheckInSchedule> for CheckInStatus {
+    fn from(schedule: CheckInSchedule) -> Self {
+        let next = schedule.next_step();
+        CheckInStatus {
+            schedule,
+            next_escalation: next.map(|(step, _)| step),
+            next_escalation_at: next.map(|(_, at)| at),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfigureCheckIn {
+    pub interval_secs: i64,
+    pub grace_secs: i64,
+}
+
+impl ConfigureCheckIn {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_secs <= 0 {
+            return Err("Check-in interval must be positive".to_string());
+        }
+        if self.grace_secs <= 0 {
+            return Err("Grace period must be positive".to_string());
+        }
+        if self.interval_secs > MAX_PERIOD_SECS || self.grace_secs > MAX_PERIOD_SECS {
+            return Err(
+                "Check-in intervals and grace periods may be at most 100 years".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Escalation {
+    /// The owner is checking in on time.
+    None,
+    Reminder,
+    Warning,
+    /// Last notice before the plan is triggered.
+    TriggerPending,
+    Triggered,
+}
+
+impl Escalation {
+    pub fn next(&self) -> Option<Escalation> {
+        match self {
+            Escalation::None => Some(Escalation::Reminder),
+            Escalation::Reminder => Some(Escalation::Warning),
+            Escalation::Warning => Some(Escalation::TriggerPending),
+            Escalation::TriggerPending => Some(Escalation::Triggered),
+            Escalation::Triggered => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Escalation::None => "none",
+            Escalation::Reminder => "reminder",
+            Escalation::Warning => "warning",
+            Escalation::TriggerPending => "trigger_pending",
+            Escalation::Triggered => "triggered",
+        }
+    }
+}
+
+impl Display for Escalation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Escalation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Escalation::None),
+            "reminder" => Ok(Escalation::Reminder),
+            "warning" => Ok(Escalation::Warning),
+            "trigger_pending" => Ok(Escalation::TriggerPending),
+            "triggered" => Ok(Escalation::Triggered),
+            _ => Err(format!("Unknown escalation: {}", s)),
+        }
+    }
+}
@@ -1,17 +1,31 @@
+use crate::models::money::Money;
 use bytes::BytesMut;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
 use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Claim {
     pub id: i32,
     pub user_id: i32,
-    pub amount: f64,
+    /// Plan the claim is made against. Claims filed before plans existed have none.
+    pub plan_id: Option<i32>,
+    /// What is being claimed.
+    #[serde(flatten)]
+    pub money: Money,
     pub status: ClaimStatus,
     pub description: String,
+    pub priority: ClaimPriority,
+    /// When a decision is due, going by the priority's SLA.
+    pub due_at: DateTime<Utc>,
+    /// Reviewer working the claim.
+    pub assignee_id: Option<i32>,
+    pub assigned_at: Option<DateTime<Utc>>,
+    /// When the claim was flagged for missing its deadline.
+    pub overdue_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -19,7 +33,9 @@ pub struct Claim {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateClaim {
     pub user_id: i32,
-    pub amount: f64,
+    pub plan_id: i32,
+    #[serde(flatten)]
+    pub money: Money,
     pub description: String,
 }
 
@@ -27,22 +43,229 @@ pub struct CreateClaim {
 pub struct UpdateClaim {
     pub status: Option<ClaimStatus>,
     pub description: Option<String>,
+    /// Why the status changed, recorded in the claim's history.
+    pub reason: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-#[serde(rename_all = "lowercase")]
+impl UpdateClaim {
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(status) = self.status else {
+            return Ok(());
+        };
+        let has_reason = self
+            .reason
+            .as_ref()
+            .is_some_and(|reason| !reason.trim().is_empty());
+        if status.needs_reason() && !has_reason {
+            return Err(format!("Moving a claim to {} needs a reason", status));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssignClaim {
+    /// `None` takes the claim away from its reviewer.
+    pub assignee_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrioritizeClaim {
+    pub priority: ClaimPriority,
+}
+
+/// One status change of a claim. The first entry records the filing and has
+/// no `from_status`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClaimStatusChange {
+    pub id: i64,
+    pub claim_id: i32,
+    pub from_status: Option<ClaimStatus>,
+    pub to_status: ClaimStatus,
+    pub actor_id: i32,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum ClaimStatus {
+    /// Filed, nobody has looked at it yet.
     Pending,
+    UnderReview,
+    /// Waiting on the claimant to answer the reviewer.
+    NeedsInformation,
     Approved,
     Rejected,
+    /// A decision was challenged and has to be looked at again.
+    Disputed,
+    /// Approved and paid out.
+    Paid,
+}
+
+impl ClaimStatus {
+    /// The review lifecycle. Claims are decided from review, decisions can be
+    /// disputed until the claim is paid, and a dispute is either settled
+    /// directly or sent back to review.
+    pub fn can_transition_to(&self, next: ClaimStatus) -> bool {
+        matches!(
+            (self, next),
+            (ClaimStatus::Pending, ClaimStatus::UnderReview)
+                | (ClaimStatus::Pending, ClaimStatus::Rejected)
+                | (ClaimStatus::UnderReview, ClaimStatus::NeedsInformation)
+                | (ClaimStatus::UnderReview, ClaimStatus::Approved)
+                | (ClaimStatus::UnderReview, ClaimStatus::Rejected)
+                | (ClaimStatus::NeedsInformation, ClaimStatus::UnderReview)
+                | (ClaimStatus::NeedsInformation, ClaimStatus::Rejected)
+                | (ClaimStatus::Approved, ClaimStatus::Paid)
+                | (ClaimStatus::Approved, ClaimStatus::Disputed)
+                | (ClaimStatus::Rejected, ClaimStatus::Disputed)
+                | (ClaimStatus::Disputed, ClaimStatus::UnderReview)
+                | (ClaimStatus::Disputed, ClaimStatus::Approved)
+                | (ClaimStatus::Disputed, ClaimStatus::Rejected)
+        )
+    }
+
+    /// The transition the claimant makes themselves: answering a request for
+    /// information. Disputes are filed separately, every other transition is a
+    /// reviewer's.
+    pub fn is_claimant_transition(&self, next: ClaimStatus) -> bool {
+        matches!(
+            (self, next),
+            (ClaimStatus::NeedsInformation, ClaimStatus::UnderReview)
+        )
+    }
+
+    /// Statuses the claimant has to be told the reason for.
+    pub fn needs_reason(&self) -> bool {
+        matches!(
+            self,
+            ClaimStatus::NeedsInformation | ClaimStatus::Rejected | ClaimStatus::Disputed
+        )
+    }
+
+    /// Still waiting on a decision, and so in someone's queue.
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            ClaimStatus::Pending
+                | ClaimStatus::UnderReview
+                | ClaimStatus::NeedsInformation
+                | ClaimStatus::Disputed
+        )
+    }
+
+    /// The claimant can add to their claim until review starts, and again
+    /// while the reviewer is waiting on them.
+    pub fn accepts_evidence(&self) -> bool {
+        matches!(self, ClaimStatus::Pending | ClaimStatus::NeedsInformation)
+    }
+
+    /// Evidence can only be removed before a reviewer has seen it.
+    pub fn allows_evidence_removal(&self) -> bool {
+        *self == ClaimStatus::Pending
+    }
+
+    /// New evidence can also be added to back a dispute, but nothing a
+    /// decision was based on can be removed.
+    pub fn accepts_new_evidence(&self) -> bool {
+        self.accepts_evidence() || *self == ClaimStatus::Disputed
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClaimStatus::Pending => "pending",
+            ClaimStatus::UnderReview => "under_review",
+            ClaimStatus::NeedsInformation => "needs_information",
+            ClaimStatus::Approved => "approved",
+            ClaimStatus::Rejected => "rejected",
+            ClaimStatus::Disputed => "disputed",
+            ClaimStatus::Paid => "paid",
+        }
+    }
 }
 
 impl Display for ClaimStatus {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ClaimStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ClaimStatus::Pending),
+            "under_review" => Ok(ClaimStatus::UnderReview),
+            "needs_information" => Ok(ClaimStatus::NeedsInformation),
+            "approved" => Ok(ClaimStatus::Approved),
+            "rejected" => Ok(ClaimStatus::Rejected),
+            "disputed" => Ok(ClaimStatus::Disputed),
+            "paid" => Ok(ClaimStatus::Paid),
+            _ => Err(format!("invalid claim status: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaimPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl ClaimPriority {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            ClaimStatus::Pending => write!(f, "pending"),
-            ClaimStatus::Approved => write!(f, "approved"),
-            ClaimStatus::Rejected => write!(f, "rejected"),
+            ClaimPriority::Low => "low",
+            ClaimPriority::Normal => "normal",
+            ClaimPriority::High => "high",
+            ClaimPriority::Urgent => "urgent",
+        }
+    }
+}
+
+impl Display for ClaimPriority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ClaimPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(ClaimPriority::Low),
+            "normal" => Ok(ClaimPriority::Normal),
+            "high" => Ok(ClaimPriority::High),
+            "urgent" => Ok(ClaimPriority::Urgent),
+            _ => Err(format!("Unknown claim priority: {}", s)),
+        }
+    }
+}
+
+/// How many hours reviewers have to decide a claim of each priority,
+/// counted from when it was filed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClaimSla {
+    pub low_hours: i32,
+    pub normal_hours: i32,
+    pub high_hours: i32,
+    pub urgent_hours: i32,
+}
+
+impl ClaimSla {
+    pub fn hours(&self, priority: ClaimPriority) -> i32 {
+        match priority {
+            ClaimPriority::Low => self.low_hours,
+            ClaimPriority::Normal => self.normal_hours,
+            ClaimPriority::High => self.high_hours,
+            ClaimPriority::Urgent => self.urgent_hours,
         }
     }
 }
@@ -53,12 +276,7 @@ impl ToSql for ClaimStatus {
         ty: &Type,
         out: &mut BytesMut,
     ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
-        let s = match self {
-            ClaimStatus::Pending => "pending",
-            ClaimStatus::Approved => "approved",
-            ClaimStatus::Rejected => "rejected",
-        };
-        s.to_sql(ty, out)
+        self.as_str().to_sql(ty, out)
     }
 
     fn accepts(ty: &Type) -> bool {
@@ -77,12 +295,7 @@ impl ToSql for ClaimStatus {
 impl<'a> FromSql<'a> for ClaimStatus {
     fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<ClaimStatus, Box<dyn Error + Sync + Send>> {
         let s = <&str as FromSql>::from_sql(ty, raw)?;
-        match s {
-            "pending" => Ok(ClaimStatus::Pending),
-            "approved" => Ok(ClaimStatus::Approved),
-            "rejected" => Ok(ClaimStatus::Rejected),
-            _ => Err(format!("invalid claim status: {}", s).into()),
-        }
+        Ok(s.parse()?)
     }
 
     fn accepts(ty: &Type) -> bool {
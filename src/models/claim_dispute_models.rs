@@ -0,0 +1,168 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::models::claim::ClaimStatus;
+
+/// A claim decision someone has asked to have looked at again.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClaimDispute {
+    pub id: i32,
+    pub claim_id: i32,
+    pub kind: DisputeKind,
+    /// The decision being disputed, which the claim returns to if it is upheld.
+    pub contested_status: ClaimStatus,
+    /// Reviewer who made that decision, and so cannot settle the dispute.
+    pub decided_by: Option<i32>,
+    pub filed_by: i32,
+    pub reason: String,
+    /// `None` while the dispute is open.
+    pub outcome: Option<DisputeOutcome>,
+    pub resolved_by: Option<i32>,
+    pub resolution: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewClaimDispute {
+    pub kind: DisputeKind,
+    pub contested_status: ClaimStatus,
+    pub decided_by: Option<i32>,
+    pub filed_by: i32,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DisputeKind {
+    /// The claimant appealing a rejection.
+    Appeal,
+    /// A co-beneficiary challenging an approval before it is paid.
+    Challenge,
+}
+
+impl DisputeKind {
+    /// What can be disputed from each status: rejections are appealed,
+    /// approvals challenged. Nothing else is a decision.
+    pub fn for_decision(status: ClaimStatus) -> Option<DisputeKind> {
+        match status {
+            ClaimStatus::Rejected => Some(DisputeKind::Appeal),
+            ClaimStatus::Approved => Some(DisputeKind::Challenge),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisputeKind::Appeal => "appeal",
+            DisputeKind::Challenge => "challenge",
+        }
+    }
+}
+
+impl Display for DisputeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for DisputeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "appeal" => Ok(DisputeKind::Appeal),
+            "challenge" => Ok(DisputeKind::Challenge),
+            _ => Err(format!("Unknown dispute kind: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DisputeOutcome {
+    /// The decision stands and the claim returns to it.
+    Upheld,
+    /// The decision is set aside and the claim goes back to review.
+    Reopened,
+}
+
+impl DisputeOutcome {
+    /// Where the claim goes from disputed.
+    pub fn next_status(&self, contested: ClaimStatus) -> ClaimStatus {
+        match self {
+            DisputeOutcome::Upheld => contested,
+            DisputeOutcome::Reopened => ClaimStatus::UnderReview,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisputeOutcome::Upheld => "upheld",
+            DisputeOutcome::Reopened => "reopened",
+        }
+    }
+}
+
+impl Display for DisputeOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for DisputeOutcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upheld" => Ok(DisputeOutcome::Upheld),
+            "reopened" => Ok(DisputeOutcome::Reopened),
+            _ => Err(format!("Unknown dispute outcome: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileDispute {
+    pub reason: String,
+}
+
+impl FileDispute {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reason.trim().is_empty() {
+            return Err("A dispute needs a reason".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResolveDispute {
+    pub outcome: DisputeOutcome,
+    /// Why, recorded on the dispute and in the claim's history.
+    pub reason: String,
+}
+
+impl ResolveDispute {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reason.trim().is_empty() {
+            return Err("Settling a dispute needs a reason".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How long after a rejection the claimant can appeal it, from
+/// `CLAIMS_APPEAL_WINDOW_DAYS`.
+#[derive(Debug, Clone, Copy)]
+pub struct DisputeLimits {
+    pub appeal_window_days: i64,
+}
+
+impl DisputeLimits {
+    pub fn appeal_closes_at(&self, rejected_at: DateTime<Utc>) -> DateTime<Utc> {
+        rejected_at + Duration::days(self.appeal_window_days)
+    }
+}
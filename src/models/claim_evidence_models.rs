@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A document attached to a claim. The file itself is kept in object storage.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClaimEvidence {
+    pub id: i32,
+    pub claim_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Hex-encoded SHA-256 of the file.
+    pub sha256: String,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub uploaded_by: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewClaimEvidence {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub storage_key: String,
+    pub uploaded_by: i32,
+}
+
+/// How large an evidence file may be, from `CLAIMS_EVIDENCE_MAX_BYTES`.
+#[derive(Debug, Clone, Copy)]
+pub struct EvidenceLimits {
+    pub max_bytes: usize,
+}
+
+/// Evidence types accepted, with the bytes their files start with.
+const ACCEPTED_TYPES: [(&str, &[u8]); 3] = [
+    ("application/pdf", b"%PDF-"),
+    ("image/png", b"\x89PNG\r\n\x1a\n"),
+    ("image/jpeg", b"\xff\xd8\xff"),
+];
+
+/// Works out the MIME type from the file's contents rather than trusting the
+/// uploader. Returns `None` for anything but PDFs, PNGs and JPEGs.
+pub fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    ACCEPTED_TYPES
+        .iter()
+        .find(|(_, magic)| bytes.starts_with(magic))
+        .map(|(content_type, _)| *content_type)
+}
+
+/// The name the file is offered back under: the last path component of what
+/// the uploader sent, without control characters or quotes, and never empty.
+pub fn clean_file_name(name: Option<&str>) -> String {
+    let name: String = name
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(255)
+        .collect();
+    let name = name.trim();
+
+    if name.is_empty() || name == "." || name == ".." {
+        "evidence".to_string()
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::beneficiary_models::{Allocation, FULL_SHARE_BPS, validate_allocations};
+use crate::models::money::Amount;
+use crate::models::plan_models::Plan;
+use crate::models::withdrawal_history_models::WithdrawalRecord;
+
+/// The outcome of executing a triggered plan.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlanExecution {
+    pub id: i64,
+    pub plan_id: i32,
+    /// The user who ran the execution.
+    pub executed_by: i32,
+    pub executed_at: DateTime<Utc>,
+    pub payouts: Vec<WithdrawalRecord>,
+}
+
+/// What one beneficiary is entitled to of one asset.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Payout {
+    pub beneficiary_id: i32,
+    /// The beneficiary's account. `None` while they have none, which leaves
+    /// the payout unclaimed.
+    pub user_id: Option<i32>,
+    pub wallet_address: String,
+    pub asset: String,
+    pub amount: Amount,
+}
+
+/// Works out every beneficiary's entitlement, in beneficiary order per asset.
+///
+/// Fixed allocations are paid first. What is left of each asset is split by
+/// percentage share, rounding every share down to the smallest unit. The units
+/// lost to rounding go one each to the beneficiaries whose shares were
+/// rounded down the most, ties going to the earlier beneficiary, so the whole
+/// remainder is paid out and the result depends only on the plan. A plan
+/// without percentage beneficiaries keeps what the fixed allocations leave.
+/// Entitlements of zero are left out.
+pub fn compute_payouts(plan: &Plan) -> Result<Vec<Payout>, String> {
+    let allocations: Vec<&Allocation> = plan.beneficiaries.iter().map(|b| &b.allocation).collect();
+    validate_allocations(&allocations, &plan.assets)?;
+
+    let out_of_range = || "Payouts of the plan are out of range".to_string();
+    let mut payouts = Vec::new();
+    for asset in &plan.assets {
+        let mut remaining = asset.amount;
+        let mut shares = Vec::new();
+        let mut asset_payouts = Vec::new();
+
+        for beneficiary in &plan.beneficiaries {
+            match &beneficiary.allocation {
+                Allocation::Fixed {
+                    asset: fixed_asset,
+                    amount,
+                } if *fixed_asset == asset.asset => {
+                    remaining = remaining.checked_sub(*amount).ok_or_else(out_of_range)?;
+                    asset_payouts.push((beneficiary, *amount));
+                }
+                Allocation::Percentage { share_bps } => shares.push((beneficiary, *share_bps)),
+                Allocation::Fixed { .. } => {}
+            }
+        }
+
+        if !shares.is_empty() {
+            // (index into shares, amount rounded down, fraction lost)
+            let mut split: Vec<(usize, Amount, i128)> = shares
+                .iter()
+                .enumerate()
+                .map(|(index, (_, share_bps))| {
+                    let (amount, fraction) =
+                        remaining.mul_div(*share_bps as i128, FULL_SHARE_BPS as i128);
+                    (index, amount, fraction)
+                })
+                .collect();
+
+            let paid = split
+                .iter()
+                .try_fold(Amount::ZERO, |paid, (_, amount, _)| {
+                    paid.checked_add(*amount)
+                })
+                .ok_or_else(out_of_range)?;
+            let dust = remaining.checked_sub(paid).ok_or_else(out_of_range)?;
+            // Stable, so equal fractions keep beneficiary order
+            split.sort_by_key(|(_, _, fraction)| std::cmp::Reverse(*fraction));
+            // Less than one unit per share is lost
+            for entry in split.iter_mut().take(i128::from(dust) as usize) {
+                entry.1 = entry
+                    .1
+                    .checked_add(Amount::from(1))
+                    .ok_or_else(out_of_range)?;
+            }
+            split.sort_by_key(|(index, _, _)| *index);
+
+            asset_payouts.extend(
+                split
+                    .into_iter()
+                    .map(|(index, amount, _)| (shares[index].0, amount)),
+            );
+            asset_payouts.sort_by_key(|(beneficiary, _)| (beneficiary.position, beneficiary.id));
+        }
+
+        payouts.extend(
+            asset_payouts
+                .into_iter()
+                .filter(|(_, amount)| amount.is_positive())
+                .map(|(beneficiary, amount)| Payout {
+                    beneficiary_id: beneficiary.id,
+                    user_id: beneficiary.user_id,
+                    wallet_address: beneficiary.wallet_address.clone(),
+                    asset: asset.asset.clone(),
+                    amount,
+                }),
+        );
+    }
+
+    Ok(payouts)
+}
+
+/// The part of each entitlement that its release schedule frees on execution
+/// at `executed_at`, which is what the executor pays out. The rest is
+/// requested as it unlocks. Entitlements with nothing released yet are left
+/// out.
+pub fn released_on_execution(
+    plan: &Plan,
+    entitlements: Vec<Payout>,
+    executed_at: DateTime<Utc>,
+) -> Vec<Payout> {
+    entitlements
+        .into_iter()
+        .filter_map(|mut payout| {
+            let release = plan
+                .beneficiaries
+                .iter()
+                .find(|beneficiary| beneficiary.id == payout.beneficiary_id)
+                .map(|beneficiary| &beneficiary.release)?;
+            payout.amount = release.unlocked(payout.amount, executed_at, executed_at);
+            payout.amount.is_positive().then_some(payout)
+        })
+        .collect()
+}
@@ -1,10 +1,29 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct FAQ {
+pub struct Faq {
     pub id: i32,
     pub question: String,
     pub answer: String,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: chrono::NaiveDateTime,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FaqRequest {
+    pub question: String,
+    pub answer: String,
+}
+
+impl FaqRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.question.trim().is_empty() {
+            return Err("Question must not be empty".to_string());
+        }
+        if self.answer.trim().is_empty() {
+            return Err("Answer must not be empty".to_string());
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,223 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Attestation windows are capped at a century, which keeps every round's
+/// expiry computable.
+const MAX_WINDOW_SECS: i64 = 100 * 365 * 24 * 3600;
+
+/// A trusted contact who can attest that the plan owner has died or become
+/// incapacitated. Guardians attest from their own account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Guardian {
+    pub id: i32,
+    pub plan_id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewGuardian {
+    pub user_id: i32,
+    pub name: String,
+    pub email: Option<String>,
+}
+
+impl NewGuardian {
+    pub fn validate(&self, owner_id: i32) -> Result<(), String> {
+        if self.user_id == owner_id {
+            return Err("Owners cannot be guardians of their own plan".to_string());
+        }
+        if self.name.trim().is_empty() {
+            return Err("Guardian name must not be empty".to_string());
+        }
+        if let Some(email) = &self.email
+            && !email.contains('@')
+        {
+            return Err(format!("Invalid contact email: {}", email));
+        }
+        Ok(())
+    }
+}
+
+/// What may move an active plan to triggered.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerPolicy {
+    /// Missed check-ins only.
+    #[default]
+    Inactivity,
+    /// A quorum of guardians only. Missed check-ins still send reminders but
+    /// stop short of triggering the plan.
+    Guardians,
+    /// Whichever comes first.
+    Either,
+}
+
+impl TriggerPolicy {
+    pub fn allows_guardians(&self) -> bool {
+        matches!(self, TriggerPolicy::Guardians | TriggerPolicy::Either)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerPolicy::Inactivity => "inactivity",
+            TriggerPolicy::Guardians => "guardians",
+            TriggerPolicy::Either => "either",
+        }
+    }
+}
+
+impl Display for TriggerPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TriggerPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inactivity" => Ok(TriggerPolicy::Inactivity),
+            "guardians" => Ok(TriggerPolicy::Guardians),
+            "either" => Ok(TriggerPolicy::Either),
+            _ => Err(format!("Unknown trigger policy: {}", s)),
+        }
+    }
+}
+
+/// How a plan is triggered. `quorum` guardians must attest within
+/// `window_secs` of the first attestation of a round.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GuardianPolicy {
+    pub plan_id: i32,
+    pub policy: TriggerPolicy,
+    pub quorum: i32,
+    pub window_secs: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GuardianPolicy {
+    /// When a round opened at `opened_at` runs out, `None` if that can't be
+    /// represented.
+    pub fn round_expires_at(&self, opened_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        opened_at.checked_add_signed(Duration::try_seconds(self.window_secs)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfigureGuardianPolicy {
+    pub policy: TriggerPolicy,
+    pub quorum: i32,
+    pub window_secs: i64,
+}
+
+impl ConfigureGuardianPolicy {
+    /// `guardians` is how many guardians the plan has.
+    pub fn validate(&self, guardians: usize) -> Result<(), String> {
+        if self.quorum <= 0 {
+            return Err("Quorum must be at least one guardian".to_string());
+        }
+        if self.window_secs <= 0 {
+            return Err("Attestation window must be positive".to_string());
+        }
+        if self.window_secs > MAX_WINDOW_SECS {
+            return Err("Attestation window may be at most 100 years".to_string());
+        }
+        if self.policy.allows_guardians() && self.quorum as usize > guardians {
+            return Err(format!(
+                "A quorum of {} needs at least as many guardians, the plan has {}",
+                self.quorum, guardians
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundStatus {
+    /// Collecting attestations.
+    Open,
+    /// A quorum attested and the plan was triggered.
+    Confirmed,
+    /// The owner checked in.
+    Cancelled,
+    /// The window ran out before a quorum attested.
+    Expired,
+}
+
+impl RoundStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundStatus::Open => "open",
+            RoundStatus::Confirmed => "confirmed",
+            RoundStatus::Cancelled => "cancelled",
+            RoundStatus::Expired => "expired",
+        }
+    }
+}
+
+impl Display for RoundStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for RoundStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(RoundStatus::Open),
+            "confirmed" => Ok(RoundStatus::Confirmed),
+            "cancelled" => Ok(RoundStatus::Cancelled),
+            "expired" => Ok(RoundStatus::Expired),
+            _ => Err(format!("Unknown round status: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attestation {
+    pub guardian_id: i32,
+    pub attested_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GuardianRound {
+    pub id: i64,
+    pub plan_id: i32,
+    pub status: RoundStatus,
+    pub opened_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub attestations: Vec<Attestation>,
+}
+
+impl GuardianRound {
+    /// Attestations that have not been revoked.
+    pub fn standing(&self) -> usize {
+        self.attestations
+            .iter()
+            .filter(|attestation| attestation.revoked_at.is_none())
+            .count()
+    }
+
+    /// The round as it stands at `now`. Rounds are only marked expired when
+    /// the plan is next touched, so an open round past its window is reported
+    /// as expired here.
+    pub fn at(mut self, now: DateTime<Utc>) -> GuardianRound {
+        if self.status == RoundStatus::Open && now >= self.expires_at {
+            self.status = RoundStatus::Expired;
+            self.closed_at = Some(self.expires_at);
+        }
+        self
+    }
+}
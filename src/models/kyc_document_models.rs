@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::io::Cursor;
+use std::str::FromStr;
+
+/// An identity document image attached to a KYC record. The image itself is
+/// kept in object storage, with its metadata stripped.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KycDocument {
+    pub id: i32,
+    pub kyc_id: i32,
+    pub kind: KycDocumentKind,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub width: i32,
+    pub height: i32,
+    /// Hex-encoded SHA-256 of the stored image, checked on every download.
+    pub sha256: String,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub uploaded_by: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewKycDocument {
+    pub kind: KycDocumentKind,
+    pub content_type: &'static str,
+    pub size_bytes: i64,
+    pub width: i32,
+    pub height: i32,
+    pub sha256: String,
+    pub storage_key: String,
+    pub uploaded_by: i32,
+}
+
+/// Which image of the submission a document is. A record holds at most one
+/// of each, uploading another replaces it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KycDocumentKind {
+    IdFront,
+    IdBack,
+    Selfie,
+}
+
+impl KycDocumentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycDocumentKind::IdFront => "id_front",
+            KycDocumentKind::IdBack => "id_back",
+            KycDocumentKind::Selfie => "selfie",
+        }
+    }
+}
+
+impl Display for KycDocumentKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for KycDocumentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id_front" => Ok(KycDocumentKind::IdFront),
+            "id_back" => Ok(KycDocumentKind::IdBack),
+            "selfie" => Ok(KycDocumentKind::Selfie),
+            _ => Err(format!("Unknown KYC document kind: {}", s)),
+        }
+    }
+}
+
+/// What a KYC image has to look like, from the `KYC_DOCUMENT_*` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KycDocumentLimits {
+    pub max_bytes: usize,
+    /// Shortest side an image may have, in pixels, for it to be legible.
+    pub min_side_px: u32,
+    /// Longest side an image may have, in pixels. Larger images are refused
+    /// before they are decoded.
+    pub max_side_px: u32,
+}
+
+/// An uploaded image after it was decoded and written out again.
+#[derive(Debug, Clone)]
+pub struct CleanImage {
+    pub bytes: Vec<u8>,
+    pub content_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ImageRejection {
+    #[error("KYC documents must be PNG or JPEG images")]
+    UnsupportedType,
+    #[error("The image could not be read: {0}")]
+    Unreadable(String),
+    #[error("The image is {width}x{height} pixels, each side has to be at least {min}")]
+    TooSmall { width: u32, height: u32, min: u32 },
+    #[error("Images can be at most {max} pixels on each side")]
+    TooLarge { max: u32 },
+}
+
+/// Image types accepted, with the bytes their files start with.
+const IMAGE_TYPES: [(ImageFormat, &str, &[u8]); 2] = [
+    (ImageFormat::Png, "image/png", b"\x89PNG\r\n\x1a\n"),
+    (ImageFormat::Jpeg, "image/jpeg", b"\xff\xd8\xff"),
+];
+
+/// JPEG quality the cleaned images are written at.
+const JPEG_QUALITY: u8 = 90;
+
+/// Checks that the upload really is a PNG or JPEG of a usable size, and
+/// writes its pixels out again. Only the pixels survive, so EXIF (including
+/// GPS), XMP and text chunks are all dropped. The EXIF orientation is applied
+/// first, so the image still shows the right way up.
+pub fn clean_image(bytes: &[u8], limits: &KycDocumentLimits) -> Result<CleanImage, ImageRejection> {
+    let (format, content_type) = IMAGE_TYPES
+        .iter()
+        .find(|(_, _, magic)| bytes.starts_with(magic))
+        .map(|(format, content_type, _)| (*format, *content_type))
+        .ok_or(ImageRejection::UnsupportedType)?;
+
+    let mut decoder_limits = image::Limits::default();
+    decoder_limits.max_image_width = Some(limits.max_side_px);
+    decoder_limits.max_image_height = Some(limits.max_side_px);
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(decoder_limits);
+
+    let rejected = |err: ImageError| match err {
+        ImageError::Limits(_) => ImageRejection::TooLarge {
+            max: limits.max_side_px,
+        },
+        err => ImageRejection::Unreadable(err.to_string()),
+    };
+    let mut decoder = reader.into_decoder().map_err(rejected)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).map_err(rejected)?;
+    image.apply_orientation(orientation);
+
+    let (width, height) = (image.width(), image.height());
+    if width.min(height) < limits.min_side_px {
+        return Err(ImageRejection::TooSmall {
+            width,
+            height,
+            min: limits.min_side_px,
+        });
+    }
+
+    let mut out = Cursor::new(Vec::new());
+    let written = match format {
+        ImageFormat::Jpeg => {
+            JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY).encode_image(&image.to_rgb8())
+        }
+        _ => image.write_to(&mut out, format),
+    };
+    written.map_err(|err| ImageRejection::Unreadable(err.to_string()))?;
+
+    Ok(CleanImage {
+        bytes: out.into_inner(),
+        content_type,
+        width,
+        height,
+    })
+}
@@ -1,5 +1,12 @@
+use bytes::BytesMut;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+use crate::models::notification::CreateNotification;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct KycRecord {
@@ -10,9 +17,16 @@ pub struct KycRecord {
     pub id_type: String,
     pub id_number: String,
     pub address: String,
-    pub verification_status: String,
+    pub verification_status: KycStatus,
+    /// Set exactly when the record is rejected.
+    pub rejection_reason: Option<KycRejectionReason>,
+    /// What the reviewer added to the catalogue reason.
+    pub rejection_note: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// Whether this is the submission the user's status comes from, rather
+    /// than one it replaced.
+    pub is_current: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,6 +39,39 @@ pub struct CreateKycRequest {
     pub address: String,
 }
 
+impl CreateKycRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if normalize_id_number(&self.id_type, &self.id_number).is_empty() {
+            return Err("id_number must contain letters or digits".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The form an ID type is compared in, so `Passport` and `national-id`
+/// match `passport` and `national_id`.
+pub fn normalize_id_type(id_type: &str) -> String {
+    id_type.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+/// The form an ID number is compared in, so that spacing, punctuation and
+/// case do not hide a duplicate. Social security and tax numbers are only
+/// digits, other documents can carry letters too.
+pub fn normalize_id_number(id_type: &str, id_number: &str) -> String {
+    let digits_only = matches!(normalize_id_type(id_type).as_str(), "ssn" | "tax_id");
+    id_number
+        .chars()
+        .filter(|c| {
+            if digits_only {
+                c.is_ascii_digit()
+            } else {
+                c.is_alphanumeric()
+            }
+        })
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KycRecordResponse {
     pub id: i32,
@@ -34,15 +81,109 @@ pub struct KycRecordResponse {
     pub id_type: String,
     pub id_number: String,
     pub address: String,
-    pub verification_status: String,
+    pub verification_status: KycStatus,
+    /// What to fix before submitting again.
+    pub rejection_reason: Option<KycRejectionInfo>,
+    pub rejection_note: Option<String>,
     pub created_at: String,
     pub updated_at: Option<String>,
+    pub is_current: bool,
+}
+
+impl From<KycRecord> for KycRecordResponse {
+    fn from(kyc: KycRecord) -> Self {
+        KycRecordResponse {
+            id: kyc.id,
+            user_id: kyc.user_id,
+            full_name: kyc.full_name,
+            date_of_birth: kyc.date_of_birth,
+            id_type: kyc.id_type,
+            id_number: kyc.id_number,
+            address: kyc.address,
+            verification_status: kyc.verification_status,
+            rejection_reason: kyc.rejection_reason.map(KycRejectionInfo::from),
+            rejection_note: kyc.rejection_note,
+            created_at: kyc.created_at.format("%d-%m-%Y").to_string(),
+            updated_at: kyc.updated_at.map(|dt| dt.format("%d-%m-%Y").to_string()),
+            is_current: kyc.is_current,
+        }
+    }
+}
+
+/// A replaced submission, without the personal details it carried.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KycSubmissionSummary {
+    pub id: i32,
+    pub verification_status: KycStatus,
+    pub rejection_reason: Option<KycRejectionInfo>,
+    pub rejection_note: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+}
+
+impl From<KycRecord> for KycSubmissionSummary {
+    fn from(kyc: KycRecord) -> Self {
+        KycSubmissionSummary {
+            id: kyc.id,
+            verification_status: kyc.verification_status,
+            rejection_reason: kyc.rejection_reason.map(KycRejectionInfo::from),
+            rejection_note: kyc.rejection_note,
+            created_at: kyc.created_at.format("%d-%m-%Y").to_string(),
+            updated_at: kyc.updated_at.map(|dt| dt.format("%d-%m-%Y").to_string()),
+        }
+    }
+}
+
+/// Where a user's KYC stands, and the submissions that came before.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KycSubmissionsResponse {
+    pub user_id: i32,
+    /// Of the current submission, `not_started` without one.
+    pub verification_status: KycStatus,
+    pub current: Option<KycRecordResponse>,
+    /// Replaced submissions, newest first.
+    pub history: Vec<KycSubmissionSummary>,
+}
+
+impl KycSubmissionsResponse {
+    pub fn new(user_id: i32, submissions: Vec<KycRecord>) -> Self {
+        let (current, history): (Vec<KycRecord>, Vec<KycRecord>) =
+            submissions.into_iter().partition(|kyc| kyc.is_current);
+        let current = current.into_iter().next().map(KycRecordResponse::from);
+        KycSubmissionsResponse {
+            user_id,
+            verification_status: current
+                .as_ref()
+                .map_or(KycStatus::NotStarted, |kyc| kyc.verification_status),
+            current,
+            history: history
+                .into_iter()
+                .map(KycSubmissionSummary::from)
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KycVerificationRequest {
     pub id: i32,
-    pub verification_status: String,
+    pub verification_status: KycStatus,
+    /// Required when rejecting, and only then.
+    pub rejection_reason: Option<KycRejectionReason>,
+    pub rejection_note: Option<String>,
+}
+
+impl KycVerificationRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        let rejecting = self.verification_status == KycStatus::Rejected;
+        if rejecting && self.rejection_reason.is_none() {
+            return Err("Rejecting a KYC record needs a rejection_reason".to_string());
+        }
+        if !rejecting && (self.rejection_reason.is_some() || self.rejection_note.is_some()) {
+            return Err("Only rejections carry a rejection reason".to_string());
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,3 +195,278 @@ pub struct KycQueryById {
 pub struct KycQueryByUserId {
     pub user_id: i32,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KycFraudSignalQuery {
+    /// Only the signals raised on this record.
+    pub kyc_id: Option<i32>,
+}
+
+/// Something about a KYC record a reviewer should look at before verifying
+/// it. Applicants are never shown these.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KycFraudSignal {
+    pub id: i32,
+    pub kyc_id: i32,
+    pub kind: KycFraudSignalKind,
+    /// The other record involved, if it still exists.
+    pub related_kyc_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KycFraudSignalKind {
+    /// Another user submitted the same identity document.
+    DuplicateIdentity,
+}
+
+impl KycFraudSignalKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycFraudSignalKind::DuplicateIdentity => "duplicate_identity",
+        }
+    }
+}
+
+impl FromStr for KycFraudSignalKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "duplicate_identity" => Ok(KycFraudSignalKind::DuplicateIdentity),
+            _ => Err(format!("Unknown KYC fraud signal: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KycStatus {
+    /// Nothing submitted yet. Users without a record are in this state.
+    NotStarted,
+    /// Submitted, waiting for a reviewer.
+    Pending,
+    InReview,
+    Verified,
+    /// Turned down for one of the catalogue reasons, can be submitted again.
+    Rejected,
+    /// Was verified, but the documents are no longer valid.
+    Expired,
+    /// Verification withdrawn while something is looked into.
+    Suspended,
+}
+
+impl KycStatus {
+    /// The verification lifecycle. Records are verified from review, and
+    /// rejected or expired ones are replaced by a new submission.
+    pub fn can_transition_to(&self, next: KycStatus) -> bool {
+        matches!(
+            (self, next),
+            (KycStatus::NotStarted, KycStatus::Pending)
+                | (KycStatus::Pending, KycStatus::InReview)
+                | (KycStatus::Pending, KycStatus::Rejected)
+                | (KycStatus::InReview, KycStatus::Verified)
+                | (KycStatus::InReview, KycStatus::Rejected)
+                | (KycStatus::Verified, KycStatus::Expired)
+                | (KycStatus::Verified, KycStatus::Suspended)
+                | (KycStatus::Suspended, KycStatus::Verified)
+                | (KycStatus::Suspended, KycStatus::Rejected)
+                | (KycStatus::Rejected, KycStatus::Pending)
+                | (KycStatus::Expired, KycStatus::Pending)
+        )
+    }
+
+    /// Moving to pending is a submission by the user, every other transition
+    /// is a reviewer's.
+    pub fn is_reviewer_decision(&self) -> bool {
+        !matches!(self, KycStatus::NotStarted | KycStatus::Pending)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycStatus::NotStarted => "not_started",
+            KycStatus::Pending => "pending",
+            KycStatus::InReview => "in_review",
+            KycStatus::Verified => "verified",
+            KycStatus::Rejected => "rejected",
+            KycStatus::Expired => "expired",
+            KycStatus::Suspended => "suspended",
+        }
+    }
+}
+
+impl Display for KycStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for KycStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "not_started" => Ok(KycStatus::NotStarted),
+            "pending" => Ok(KycStatus::Pending),
+            "in_review" => Ok(KycStatus::InReview),
+            "verified" => Ok(KycStatus::Verified),
+            "rejected" => Ok(KycStatus::Rejected),
+            "expired" => Ok(KycStatus::Expired),
+            "suspended" => Ok(KycStatus::Suspended),
+            _ => Err(format!("invalid KYC status: {}", s)),
+        }
+    }
+}
+
+impl ToSql for KycStatus {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.as_str().to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "kyc_status"
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.to_sql(ty, out)
+    }
+}
+
+impl<'a> FromSql<'a> for KycStatus {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<KycStatus, Box<dyn Error + Sync + Send>> {
+        let s = <&str as FromSql>::from_sql(ty, raw)?;
+        Ok(s.parse()?)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "kyc_status"
+    }
+}
+
+/// Why a KYC record was rejected. Users are shown the matching message, so
+/// they know what to fix before submitting again.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KycRejectionReason {
+    DocumentUnreadable,
+    DocumentExpired,
+    UnsupportedDocument,
+    NameMismatch,
+    DateOfBirthMismatch,
+    IdNumberInvalid,
+    AddressUnverifiable,
+    SelfieMismatch,
+    Other,
+}
+
+impl KycRejectionReason {
+    /// The whole catalogue, in the order it is listed to users.
+    pub const ALL: [KycRejectionReason; 9] = [
+        KycRejectionReason::DocumentUnreadable,
+        KycRejectionReason::DocumentExpired,
+        KycRejectionReason::UnsupportedDocument,
+        KycRejectionReason::NameMismatch,
+        KycRejectionReason::DateOfBirthMismatch,
+        KycRejectionReason::IdNumberInvalid,
+        KycRejectionReason::AddressUnverifiable,
+        KycRejectionReason::SelfieMismatch,
+        KycRejectionReason::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycRejectionReason::DocumentUnreadable => "document_unreadable",
+            KycRejectionReason::DocumentExpired => "document_expired",
+            KycRejectionReason::UnsupportedDocument => "unsupported_document",
+            KycRejectionReason::NameMismatch => "name_mismatch",
+            KycRejectionReason::DateOfBirthMismatch => "date_of_birth_mismatch",
+            KycRejectionReason::IdNumberInvalid => "id_number_invalid",
+            KycRejectionReason::AddressUnverifiable => "address_unverifiable",
+            KycRejectionReason::SelfieMismatch => "selfie_mismatch",
+            KycRejectionReason::Other => "other",
+        }
+    }
+
+    /// What the user has to do about it.
+    pub fn message(&self) -> &'static str {
+        match self {
+            KycRejectionReason::DocumentUnreadable => {
+                "The document could not be read. Upload a sharp, well-lit image showing all four corners."
+            }
+            KycRejectionReason::DocumentExpired => {
+                "The document has expired. Submit an identity document that is currently valid."
+            }
+            KycRejectionReason::UnsupportedDocument => {
+                "This type of document is not accepted. Use a passport, national ID card or driving licence."
+            }
+            KycRejectionReason::NameMismatch => {
+                "The name does not match the document. Enter your name exactly as it is printed."
+            }
+            KycRejectionReason::DateOfBirthMismatch => {
+                "The date of birth does not match the document. Check it and submit again."
+            }
+            KycRejectionReason::IdNumberInvalid => {
+                "The ID number is not valid for this document type. Check it and submit again."
+            }
+            KycRejectionReason::AddressUnverifiable => {
+                "The address could not be verified. Enter your full residential address."
+            }
+            KycRejectionReason::SelfieMismatch => {
+                "The selfie does not match the document photo. Take a new selfie in good light."
+            }
+            KycRejectionReason::Other => {
+                "The submission could not be verified. See the reviewer's note for details."
+            }
+        }
+    }
+    /// Tells the user their submission was turned down, and what to fix.
+    pub fn notification(&self, user_id: i32) -> CreateNotification {
+        CreateNotification {
+            user_id: Some(user_id),
+            title: "KYC verification rejected".to_string(),
+            body: self.message().to_string(),
+        }
+    }
+}
+
+impl Display for KycRejectionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for KycRejectionReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KycRejectionReason::ALL
+            .into_iter()
+            .find(|reason| reason.as_str() == s)
+            .ok_or_else(|| format!("Unknown KYC rejection reason: {}", s))
+    }
+}
+
+/// A catalogue entry, as shown to users.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct KycRejectionInfo {
+    pub code: KycRejectionReason,
+    pub message: String,
+}
+
+impl From<KycRejectionReason> for KycRejectionInfo {
+    fn from(reason: KycRejectionReason) -> Self {
+        KycRejectionInfo {
+            code: reason,
+            message: reason.message().to_string(),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::models::kyc_models::{KycRejectionReason, KycStatus};
+use crate::utils::webhook_signature::WebhookSecret;
+
+/// A KYC submission handed to an outside verification provider, and what
+/// the provider made of it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KycProviderCheck {
+    pub id: i32,
+    pub kyc_id: i32,
+    /// Name of the provider the check was made with.
+    pub provider: String,
+    /// The provider's own id for the check.
+    pub reference: String,
+    pub status: KycCheckStatus,
+    /// Set exactly when the provider rejected the submission.
+    pub rejection_reason: Option<KycRejectionReason>,
+    pub rejection_note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KycCheckStatus {
+    /// Waiting for the provider's result.
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl KycCheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycCheckStatus::Pending => "pending",
+            KycCheckStatus::Approved => "approved",
+            KycCheckStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl Display for KycCheckStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for KycCheckStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(KycCheckStatus::Pending),
+            "approved" => Ok(KycCheckStatus::Approved),
+            "rejected" => Ok(KycCheckStatus::Rejected),
+            _ => Err(format!("Unknown KYC check status: {}", s)),
+        }
+    }
+}
+
+/// What a provider decided about a submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KycDecision {
+    Approved,
+    Rejected {
+        reason: KycRejectionReason,
+        note: Option<String>,
+    },
+}
+
+impl KycDecision {
+    /// Where the check ends up.
+    pub fn check_status(&self) -> KycCheckStatus {
+        match self {
+            KycDecision::Approved => KycCheckStatus::Approved,
+            KycDecision::Rejected { .. } => KycCheckStatus::Rejected,
+        }
+    }
+
+    /// Where the KYC record ends up.
+    pub fn kyc_status(&self) -> KycStatus {
+        match self {
+            KycDecision::Approved => KycStatus::Verified,
+            KycDecision::Rejected { .. } => KycStatus::Rejected,
+        }
+    }
+}
+
+/// Body of a webhook call from the provider: a result is ready for the
+/// check under `reference`. The result itself is fetched from the provider.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct KycWebhookEvent {
+    /// Unique per call, so that a call sent twice is acted on once.
+    pub event_id: String,
+    pub reference: String,
+}
+
+impl KycWebhookEvent {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.event_id.trim().is_empty() || self.event_id.len() > 255 {
+            return Err("event_id must be 1 to 255 characters".to_string());
+        }
+        if self.reference.trim().is_empty() {
+            return Err("reference is required".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What became of a webhook call.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KycWebhookOutcome {
+    /// The decision was applied.
+    Processed,
+    /// The call was already acted on.
+    Duplicate,
+    /// The provider has no result yet.
+    Pending,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KycWebhookResponse {
+    pub outcome: KycWebhookOutcome,
+    pub check: KycProviderCheck,
+}
+
+/// How webhook calls from the KYC provider are checked, from the
+/// `KYC_PROVIDER_WEBHOOK_*` settings.
+#[derive(Debug, Clone)]
+pub struct KycWebhookConfig {
+    pub secret: WebhookSecret,
+    /// Calls signed longer ago than this, or this far ahead, are refused.
+    pub tolerance: chrono::Duration,
+}
@@ -1,6 +1,19 @@
 pub mod activity_models;
+pub mod auth_models;
+pub mod beneficiary_models;
+pub mod check_in_models;
 pub mod claim;
+pub mod claim_dispute_models;
+pub mod claim_evidence_models;
+pub mod execution_models;
 pub mod faq;
+pub mod guardian_models;
+pub mod kyc_document_models;
 pub mod kyc_models;
+pub mod kyc_provider_models;
+pub mod money;
 pub mod notification;
+pub mod plan_models;
+pub mod support_models;
+pub mod vesting_models;
 pub mod withdrawal_history_models;
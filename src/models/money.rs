@@ -0,0 +1,249 @@
+use bytes::{BufMut, BytesMut};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+/// A quantity of an asset counted in its smallest unit (wei for ETH), so it
+/// is always a whole number. Exact, and bounded to what the NUMERIC(38, 0)
+/// columns it is stored in can hold.
+///
+/// In JSON it is a string of digits, as most clients can't hold integers
+/// that large exactly. Integers are accepted on input too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Amount(i128);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    /// The largest amount with 38 digits.
+    pub const MAX: Amount = Amount(10_i128.pow(38) - 1);
+
+    /// `None` if `value` has more than 38 digits.
+    pub fn new(value: i128) -> Option<Amount> {
+        (value.unsigned_abs() <= Amount::MAX.0 as u128).then_some(Amount(value))
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).and_then(Amount::new)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).and_then(Amount::new)
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    /// `self * part / whole` rounded down, and what was lost to rounding in
+    /// `whole`ths of a unit. Taking a non-negative amount and
+    /// `0 <= part <= whole`, so that it can't overflow even at 38 digits.
+    pub fn mul_div(self, part: i128, whole: i128) -> (Amount, i128) {
+        let rest = self.0 % whole * part;
+        (Amount(self.0 / whole * part + rest / whole), rest % whole)
+    }
+}
+
+impl From<i64> for Amount {
+    fn from(value: i64) -> Amount {
+        Amount(value as i128)
+    }
+}
+
+impl From<Amount> for i128 {
+    fn from(amount: Amount) -> i128 {
+        amount.0
+    }
+}
+
+impl Display for Amount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!(
+                "Invalid amount {:?}, expected a whole number of the asset's smallest unit",
+                s
+            ));
+        }
+        s.parse()
+            .ok()
+            .and_then(Amount::new)
+            .ok_or_else(|| format!("Amount {} is out of range", s))
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+struct AmountVisitor;
+
+impl Visitor<'_> for AmountVisitor {
+    type Value = Amount;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "a whole number of the asset's smallest unit")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Amount, E> {
+        Ok(Amount::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Amount, E> {
+        Ok(Amount(value as i128))
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<Amount, E> {
+        Amount::new(value).ok_or_else(|| E::custom(format!("Amount {} is out of range", value)))
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Amount, E> {
+        i128::try_from(value)
+            .ok()
+            .and_then(Amount::new)
+            .ok_or_else(|| E::custom(format!("Amount {} is out of range", value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Amount, E> {
+        value.parse().map_err(E::custom)
+    }
+}
+
+// NUMERIC travels as base 10000 digits, most significant first:
+// ndigits, weight (power of 10000 of the first digit), sign, display scale.
+const NUMERIC_POSITIVE: u16 = 0x0000;
+const NUMERIC_NEGATIVE: u16 = 0x4000;
+
+impl ToSql for Amount {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        let mut digits = Vec::new();
+        let mut rest = self.0.unsigned_abs();
+        while rest > 0 {
+            digits.push((rest % 10_000) as i16);
+            rest /= 10_000;
+        }
+        let weight = digits.len() as i16 - 1;
+        // Trailing zero digits are implied by the weight
+        let zeros = digits.iter().take_while(|digit| **digit == 0).count();
+        digits.drain(..zeros);
+        digits.reverse();
+
+        out.put_i16(digits.len() as i16);
+        out.put_i16(weight.max(0));
+        out.put_u16(if self.0 < 0 {
+            NUMERIC_NEGATIVE
+        } else {
+            NUMERIC_POSITIVE
+        });
+        out.put_i16(0);
+        for digit in digits {
+            out.put_i16(digit);
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        if !<Amount as ToSql>::accepts(ty) {
+            return Err(format!("cannot store an amount in a {} column", ty).into());
+        }
+        self.to_sql(ty, out)
+    }
+}
+
+impl<'a> FromSql<'a> for Amount {
+    fn from_sql(_ty: &Type, mut raw: &'a [u8]) -> Result<Amount, Box<dyn Error + Sync + Send>> {
+        let ndigits = read_i16(&mut raw)?;
+        let weight = read_i16(&mut raw)?;
+        let sign = read_i16(&mut raw)? as u16;
+        let _scale = read_i16(&mut raw)?;
+        if sign != NUMERIC_POSITIVE && sign != NUMERIC_NEGATIVE {
+            return Err("amount is NaN or infinite".into());
+        }
+
+        let overflow = || -> Box<dyn Error + Sync + Send> { "amount is out of range".into() };
+        let mut value: i128 = 0;
+        for position in 0..ndigits {
+            let digit = read_i16(&mut raw)?;
+            if weight - position < 0 {
+                if digit != 0 {
+                    return Err("amount is not a whole number".into());
+                }
+                continue;
+            }
+            value = value
+                .checked_mul(10_000)
+                .and_then(|value| value.checked_add(digit as i128))
+                .ok_or_else(overflow)?;
+        }
+        for _ in 0..(weight as i32 + 1 - ndigits as i32).max(0) {
+            value = value.checked_mul(10_000).ok_or_else(overflow)?;
+        }
+
+        if sign == NUMERIC_NEGATIVE {
+            value = -value;
+        }
+        Amount::new(value).ok_or_else(overflow)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+}
+
+fn read_i16(raw: &mut &[u8]) -> Result<i16, Box<dyn Error + Sync + Send>> {
+    let Some((bytes, rest)) = raw.split_first_chunk::<2>() else {
+        return Err("truncated numeric value".into());
+    };
+    *raw = rest;
+    Ok(i16::from_be_bytes(*bytes))
+}
+
+/// An amount of a named asset. Flattened into the enclosing JSON object, so
+/// records carry `"asset"` and `"amount"` side by side.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Money {
+    pub amount: Amount,
+    pub asset: String,
+}
+
+impl Money {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.asset.trim().is_empty() {
+            return Err("Asset identifier must not be empty".to_string());
+        }
+        if !self.amount.is_positive() {
+            return Err("Amount must be positive".to_string());
+        }
+        Ok(())
+    }
+}
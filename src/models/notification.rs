@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Notification {
     pub id: i32,
+    /// Recipient, or `None` for announcements to everyone.
+    pub user_id: Option<i32>,
     pub title: String,
     pub body: String,
     pub is_read: bool,
@@ -13,6 +15,8 @@ pub struct Notification {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateNotification {
+    #[serde(default)]
+    pub user_id: Option<i32>,
     pub title: String,
     pub body: String,
 }
@@ -0,0 +1,196 @@
+use bytes::BytesMut;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+use crate::models::beneficiary_models::{Beneficiary, NewBeneficiary, validate_allocations};
+use crate::models::money::Amount;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Plan {
+    pub id: i32,
+    pub owner_id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub assets: Vec<PlanAsset>,
+    pub beneficiaries: Vec<Beneficiary>,
+    pub status: PlanStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlanAsset {
+    pub asset: String,
+    pub amount: Amount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreatePlan {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<PlanAsset>,
+    #[serde(default)]
+    pub beneficiaries: Vec<NewBeneficiary>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpdatePlan {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub assets: Option<Vec<PlanAsset>>,
+    pub beneficiaries: Option<Vec<NewBeneficiary>>,
+}
+
+impl CreatePlan {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Plan name must not be empty".to_string());
+        }
+        validate_assets(&self.assets)?;
+        validate_beneficiaries(&self.beneficiaries, &self.assets)
+    }
+}
+
+impl UpdatePlan {
+    /// Validates the update against the plan as currently stored, since a
+    /// change of assets can invalidate existing fixed allocations.
+    pub fn validate(&self, current: &Plan) -> Result<(), String> {
+        if let Some(name) = &self.name
+            && name.trim().is_empty()
+        {
+            return Err("Plan name must not be empty".to_string());
+        }
+        if let Some(assets) = &self.assets {
+            validate_assets(assets)?;
+        }
+        let assets = self.assets.as_deref().unwrap_or(&current.assets);
+        match &self.beneficiaries {
+            Some(beneficiaries) => validate_beneficiaries(beneficiaries, assets),
+            None => {
+                let allocations: Vec<_> = current
+                    .beneficiaries
+                    .iter()
+                    .map(|b| &b.allocation)
+                    .collect();
+                validate_allocations(&allocations, assets)
+            }
+        }
+    }
+}
+
+fn validate_assets(assets: &[PlanAsset]) -> Result<(), String> {
+    for asset in assets {
+        if asset.asset.trim().is_empty() {
+            return Err("Asset identifier must not be empty".to_string());
+        }
+        if asset.amount < Amount::ZERO {
+            return Err(format!(
+                "Amount for asset {} must not be negative",
+                asset.asset
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_beneficiaries(
+    beneficiaries: &[NewBeneficiary],
+    assets: &[PlanAsset],
+) -> Result<(), String> {
+    for beneficiary in beneficiaries {
+        beneficiary.validate()?;
+    }
+    let allocations: Vec<_> = beneficiaries.iter().map(|b| &b.allocation).collect();
+    validate_allocations(&allocations, assets)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanStatus {
+    Draft,
+    Active,
+    Triggered,
+    Executed,
+    Cancelled,
+}
+
+impl PlanStatus {
+    /// The plan lifecycle. A triggered plan may still fall back to active if the
+    /// owner turns out to be alive before execution starts.
+    pub fn can_transition_to(&self, next: PlanStatus) -> bool {
+        matches!(
+            (self, next),
+            (PlanStatus::Draft, PlanStatus::Active)
+                | (PlanStatus::Draft, PlanStatus::Cancelled)
+                | (PlanStatus::Active, PlanStatus::Triggered)
+                | (PlanStatus::Active, PlanStatus::Cancelled)
+                | (PlanStatus::Triggered, PlanStatus::Active)
+                | (PlanStatus::Triggered, PlanStatus::Executed)
+        )
+    }
+
+    /// Plans can only be edited before anything has been set in motion.
+    pub fn is_editable(&self) -> bool {
+        matches!(self, PlanStatus::Draft | PlanStatus::Active)
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            PlanStatus::Draft => "draft",
+            PlanStatus::Active => "active",
+            PlanStatus::Triggered => "triggered",
+            PlanStatus::Executed => "executed",
+            PlanStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl Display for PlanStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl ToSql for PlanStatus {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.as_str().to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "plan_status"
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.to_sql(ty, out)
+    }
+}
+
+impl<'a> FromSql<'a> for PlanStatus {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<PlanStatus, Box<dyn Error + Sync + Send>> {
+        let s = <&str as FromSql>::from_sql(ty, raw)?;
+        match s {
+            "draft" => Ok(PlanStatus::Draft),
+            "active" => Ok(PlanStatus::Active),
+            "triggered" => Ok(PlanStatus::Triggered),
+            "executed" => Ok(PlanStatus::Executed),
+            "cancelled" => Ok(PlanStatus::Cancelled),
+            _ => Err(format!("invalid plan status: {}", s).into()),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "plan_status"
+    }
+}
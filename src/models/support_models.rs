@@ -0,0 +1,251 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SupportTicket {
+    pub id: i32,
+    pub user_id: i32,
+    pub subject: String,
+    pub description: String,
+    pub category: TicketCategory,
+    pub priority: TicketPriority,
+    pub status: TicketStatus,
+    /// Support agent the ticket is assigned to.
+    pub assignee_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A ticket together with its conversation, oldest reply first.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SupportTicketThread {
+    #[serde(flatten)]
+    pub ticket: SupportTicket,
+    pub replies: Vec<TicketReply>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TicketReply {
+    pub id: i32,
+    pub ticket_id: i32,
+    pub author_id: i32,
+    pub author_role: ReplyAuthor,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateTicket {
+    pub subject: String,
+    pub description: String,
+    pub category: TicketCategory,
+    #[serde(default)]
+    pub priority: TicketPriority,
+}
+
+impl CreateTicket {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.subject.trim().is_empty() {
+            return Err("Subject must not be empty".to_string());
+        }
+        if self.subject.len() > 255 {
+            return Err("Subject must be at most 255 characters".to_string());
+        }
+        if self.description.trim().is_empty() {
+            return Err("Description must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Triage by a support agent. Fields left out are unchanged.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateTicket {
+    pub status: Option<TicketStatus>,
+    pub priority: Option<TicketPriority>,
+    pub assignee_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateReply {
+    pub body: String,
+}
+
+impl CreateReply {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.body.trim().is_empty() {
+            return Err("Reply must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TicketFilter {
+    pub status: Option<TicketStatus>,
+    pub assignee_id: Option<i32>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SupportTicketsResponse {
+    pub tickets: Vec<SupportTicket>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketStatus {
+    /// Waiting on support.
+    Open,
+    /// Waiting on the user.
+    Pending,
+    Resolved,
+    Closed,
+}
+
+impl TicketStatus {
+    /// Closed tickets are final, anything else can still move. A user reply
+    /// reopens a resolved ticket.
+    pub fn can_transition_to(&self, next: TicketStatus) -> bool {
+        *self != TicketStatus::Closed && *self != next
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TicketStatus::Open => "open",
+            TicketStatus::Pending => "pending",
+            TicketStatus::Resolved => "resolved",
+            TicketStatus::Closed => "closed",
+        }
+    }
+}
+
+impl Display for TicketStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TicketStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(TicketStatus::Open),
+            "pending" => Ok(TicketStatus::Pending),
+            "resolved" => Ok(TicketStatus::Resolved),
+            "closed" => Ok(TicketStatus::Closed),
+            _ => Err(format!("Unknown ticket status: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketCategory {
+    Account,
+    Plan,
+    Kyc,
+    Withdrawal,
+    Technical,
+    Other,
+}
+
+impl TicketCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TicketCategory::Account => "account",
+            TicketCategory::Plan => "plan",
+            TicketCategory::Kyc => "kyc",
+            TicketCategory::Withdrawal => "withdrawal",
+            TicketCategory::Technical => "technical",
+            TicketCategory::Other => "other",
+        }
+    }
+}
+
+impl FromStr for TicketCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "account" => Ok(TicketCategory::Account),
+            "plan" => Ok(TicketCategory::Plan),
+            "kyc" => Ok(TicketCategory::Kyc),
+            "withdrawal" => Ok(TicketCategory::Withdrawal),
+            "technical" => Ok(TicketCategory::Technical),
+            "other" => Ok(TicketCategory::Other),
+            _ => Err(format!("Unknown ticket category: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl TicketPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TicketPriority::Low => "low",
+            TicketPriority::Normal => "normal",
+            TicketPriority::High => "high",
+            TicketPriority::Urgent => "urgent",
+        }
+    }
+}
+
+impl FromStr for TicketPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(TicketPriority::Low),
+            "normal" => Ok(TicketPriority::Normal),
+            "high" => Ok(TicketPriority::High),
+            "urgent" => Ok(TicketPriority::Urgent),
+            _ => Err(format!("Unknown ticket priority: {}", s)),
+        }
+    }
+}
+
+/// Which side of the conversation a reply came from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyAuthor {
+    User,
+    Agent,
+}
+
+impl ReplyAuthor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplyAuthor::User => "user",
+            ReplyAuthor::Agent => "agent",
+        }
+    }
+}
+
+impl FromStr for ReplyAuthor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(ReplyAuthor::User),
+            "agent" => Ok(ReplyAuthor::Agent),
+            _ => Err(format!("Unknown reply author: {}", s)),
+        }
+    }
+}
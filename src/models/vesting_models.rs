@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::execution_models::compute_payouts;
+use crate::models::money::Amount;
+use crate::models::plan_models::Plan;
+
+/// Releases are capped at a century, which keeps every date computable.
+const MAX_RELEASE_DAYS: u32 = 36_500;
+
+/// How a beneficiary's entitlement is released once the plan is executed.
+/// Nothing is released before execution, which is when every schedule starts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReleaseSchedule {
+    /// Everything at once.
+    #[default]
+    LumpSum,
+    /// `count` equal tranches, the first on execution and then one every
+    /// `interval_days`.
+    Tranches { count: u32, interval_days: u32 },
+    /// Everything once the beneficiary turns `age`.
+    AtAge { birth_date: NaiveDate, age: u32 },
+    /// Nothing for `cliff_days`, then linearly over `duration_days` counted
+    /// from execution, so the part accrued during the cliff is released at
+    /// once when it ends.
+    CliffLinear { cliff_days: u32, duration_days: u32 },
+}
+
+impl ReleaseSchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ReleaseSchedule::LumpSum => Ok(()),
+            ReleaseSchedule::Tranches {
+                count,
+                interval_days,
+            } => {
+                if *count == 0 {
+                    return Err("A release needs at least one tranche".to_string());
+                }
+                if *count > 1 && *interval_days == 0 {
+                    return Err("Tranches need an interval of at least one day".to_string());
+                }
+                if (*count as u64 - 1) * *interval_days as u64 > MAX_RELEASE_DAYS as u64 {
+                    return Err("A release may span at most 100 years".to_string());
+                }
+                Ok(())
+            }
+            ReleaseSchedule::AtAge { age, .. } => {
+                if *age > 150 {
+                    return Err(format!("Release age {} is not realistic", age));
+                }
+                Ok(())
+            }
+            ReleaseSchedule::CliffLinear {
+                cliff_days,
+                duration_days,
+            } => {
+                if *duration_days == 0 {
+                    return Err("A linear release needs a duration of at least one day".to_string());
+                }
+                if cliff_days > duration_days {
+                    return Err("The cliff must not be longer than the release".to_string());
+                }
+                if *duration_days > MAX_RELEASE_DAYS {
+                    return Err("A release may span at most 100 years".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// How much of `total` is released by `as_of`, for a plan executed at
+    /// `start`. Partial releases round down, so the last unit is only released
+    /// with the final tranche or at the end of a linear release.
+    pub fn unlocked(&self, total: Amount, start: DateTime<Utc>, as_of: DateTime<Utc>) -> Amount {
+        if as_of < start {
+            return Amount::ZERO;
+        }
+        match self {
+            ReleaseSchedule::LumpSum => total,
+            ReleaseSchedule::Tranches {
+                count,
+                interval_days,
+            } => {
+                let elapsed = (as_of - start).num_seconds() as i128;
+                let interval = days(*interval_days).num_seconds() as i128;
+                let released = match interval {
+                    0 => *count as i128,
+                    _ => (1 + elapsed / interval).min(*count as i128),
+                };
+                total.mul_div(released, *count as i128).0
+            }
+            ReleaseSchedule::AtAge { .. } => match self.fully_released_at(start) {
+                Some(at) if as_of >= at => total,
+                _ => Amount::ZERO,
+            },
+            ReleaseSchedule::CliffLinear {
+                cliff_days,
+                duration_days,
+            } => {
+                let elapsed = (as_of - start).num_seconds() as i128;
+                let cliff = days(*cliff_days).num_seconds() as i128;
+                let duration = days(*duration_days).num_seconds() as i128;
+                if elapsed < cliff {
+                    Amount::ZERO
+                } else if elapsed >= duration {
+                    total
+                } else {
+                    total.mul_div(elapsed, duration).0
+                }
+            }
+        }
+    }
+
+    /// When the whole entitlement is released, for a plan executed at `start`.
+    /// `None` only if the release date can't be represented.
+    pub fn fully_released_at(&self, start: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            ReleaseSchedule::LumpSum => Some(start),
+            ReleaseSchedule::Tranches {
+                count,
+                interval_days,
+            } => Some(start + days(*interval_days) * (count.saturating_sub(1) as i32)),
+            ReleaseSchedule::AtAge { birth_date, age } => {
+                let birthday = birth_date
+                    .checked_add_months(Months::new(age.saturating_mul(12)))?
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+                Some(birthday.max(start))
+            }
+            ReleaseSchedule::CliffLinear { duration_days, .. } => {
+                Some(start + days(*duration_days))
+            }
+        }
+    }
+}
+
+fn days(days: u32) -> Duration {
+    Duration::days(days as i64)
+}
+
+/// Where a beneficiary stands with one asset.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AssetRelease {
+    pub asset: String,
+    /// Everything the beneficiary is entitled to.
+    pub entitled: Amount,
+    /// The part of it released so far.
+    pub unlocked: Amount,
+    /// Already paid out or on its way, failed withdrawals excluded.
+    pub withdrawn: Amount,
+    /// What can still be requested now.
+    pub available: Amount,
+    pub fully_released_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReleaseStatus {
+    pub beneficiary_id: i32,
+    pub schedule: ReleaseSchedule,
+    pub as_of: DateTime<Utc>,
+    /// When the plan was executed and the schedule started, if it has been.
+    pub started_at: Option<DateTime<Utc>>,
+    pub assets: Vec<AssetRelease>,
+}
+
+/// Works out what a beneficiary can withdraw of each asset at `as_of`.
+/// `started_at` is when the plan was executed and `withdrawn` what has been
+/// requested per asset so far.
+pub fn release_status(
+    plan: &Plan,
+    beneficiary_id: i32,
+    started_at: Option<DateTime<Utc>>,
+    withdrawn: &HashMap<String, Amount>,
+    as_of: DateTime<Utc>,
+) -> Result<ReleaseStatus, String> {
+    let beneficiary = plan
+        .beneficiaries
+        .iter()
+        .find(|beneficiary| beneficiary.id == beneficiary_id)
+        .ok_or_else(|| "Beneficiary is not part of this plan".to_string())?;
+
+    let assets = compute_payouts(plan)?
+        .into_iter()
+        .filter(|payout| payout.beneficiary_id == beneficiary_id)
+        .map(|payout| {
+            let unlocked = match started_at {
+                Some(start) => beneficiary.release.unlocked(payout.amount, start, as_of),
+                None => Amount::ZERO,
+            };
+            let withdrawn = withdrawn.get(&payout.asset).copied().unwrap_or_default();
+            AssetRelease {
+                entitled: payout.amount,
+                unlocked,
+                withdrawn,
+                available: unlocked
+                    .checked_sub(withdrawn)
+                    .map_or(Amount::ZERO, |available| available.max(Amount::ZERO)),
+                fully_released_at: started_at
+                    .and_then(|start| beneficiary.release.fully_released_at(start)),
+                asset: payout.asset,
+            }
+        })
+        .collect();
+
+    Ok(ReleaseStatus {
+        beneficiary_id,
+        schedule: beneficiary.release.clone(),
+        as_of,
+        started_at,
+        assets,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReleaseQuery {
+    /// Defaults to now.
+    pub as_of: Option<DateTime<Utc>>,
+}
@@ -1,24 +1,73 @@
+use crate::models::money::Money;
+use bytes::BytesMut;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
 
+/// A payout of one plan asset to a beneficiary's wallet.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WithdrawalRecord {
     pub id: i64,
-    pub plan_id: String,
-    // pub date: DateTime<Utc>,
+    /// The user who requested the withdrawal. Payouts scheduled by a plan
+    /// execution belong to the beneficiary, and to nobody while the
+    /// beneficiary has no account.
+    pub user_id: Option<i32>,
+    pub plan_id: i32,
+    /// `None` once the beneficiary has been removed from the plan.
+    pub beneficiary_id: Option<i32>,
+    /// Destination wallet address.
     pub wallet_id: String,
-    pub amount: i64,
-    pub payer_name: String,
+    #[serde(flatten)]
+    pub money: Money,
+    pub status: WithdrawalStatus,
+    pub tx_hash: Option<String>,
+    pub failure_reason: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// The plan execution that scheduled this payout, if it wasn't requested
+    /// by hand.
+    pub execution_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWithdrawalRequest {
+    pub plan_id: i32,
+    /// Paid to the wallet the plan lists for this beneficiary.
+    pub beneficiary_id: i32,
+    #[serde(flatten)]
+    pub money: Money,
+}
+
+impl CreateWithdrawalRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        self.money.validate()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateWithdrawalRecordRequest {
-    pub user_id: String,
-    pub activity_type: String,
-    pub details: String,
-    pub action_type: String,
-    pub action_link: Option<String>,
+pub struct UpdateWithdrawalStatus {
+    pub status: WithdrawalStatus,
+    /// Required when moving to `broadcast`.
+    pub tx_hash: Option<String>,
+    /// Required when moving to `failed`.
+    pub failure_reason: Option<String>,
+}
+
+impl UpdateWithdrawalStatus {
+    pub fn validate(&self) -> Result<(), String> {
+        let filled = |value: &Option<String>| value.as_ref().is_some_and(|v| !v.trim().is_empty());
+        match self.status {
+            WithdrawalStatus::Broadcast if !filled(&self.tx_hash) => {
+                Err("A broadcast withdrawal needs a transaction hash".to_string())
+            }
+            WithdrawalStatus::Failed if !filled(&self.failure_reason) => {
+                Err("A failed withdrawal needs a failure reason".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,13 +78,33 @@ pub struct SingleWithdrawalRecordRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WithdrawalRecordResponse {
     pub id: i64,
-    pub plan_id: String,
+    pub user_id: Option<i32>,
+    pub plan_id: i32,
+    pub beneficiary_id: Option<i32>,
     pub wallet_id: String,
-    pub amount: i64,
-    pub payer_name: String,
+    #[serde(flatten)]
+    pub money: Money,
+    pub status: WithdrawalStatus,
+    pub tx_hash: Option<String>,
     pub created_at: String, // Formatted as dd-mm-yyyy
 }
 
+impl From<WithdrawalRecord> for WithdrawalRecordResponse {
+    fn from(withdrawal: WithdrawalRecord) -> Self {
+        WithdrawalRecordResponse {
+            id: withdrawal.id,
+            user_id: withdrawal.user_id,
+            plan_id: withdrawal.plan_id,
+            beneficiary_id: withdrawal.beneficiary_id,
+            wallet_id: withdrawal.wallet_id,
+            money: withdrawal.money,
+            status: withdrawal.status,
+            tx_hash: withdrawal.tx_hash,
+            created_at: withdrawal.created_at.format("%d-%m-%Y").to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WithdrawalRecordsResponse {
     pub records: Vec<WithdrawalRecordResponse>,
@@ -43,3 +112,87 @@ pub struct WithdrawalRecordsResponse {
     pub page: i64,
     pub page_size: i64,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WithdrawalStatus {
+    Requested,
+    Approved,
+    Broadcast,
+    Confirmed,
+    Failed,
+}
+
+impl WithdrawalStatus {
+    /// The payout lifecycle. Anything not yet confirmed on chain can fail.
+    pub fn can_transition_to(&self, next: WithdrawalStatus) -> bool {
+        matches!(
+            (self, next),
+            (WithdrawalStatus::Requested, WithdrawalStatus::Approved)
+                | (WithdrawalStatus::Approved, WithdrawalStatus::Broadcast)
+                | (WithdrawalStatus::Broadcast, WithdrawalStatus::Confirmed)
+                | (WithdrawalStatus::Requested, WithdrawalStatus::Failed)
+                | (WithdrawalStatus::Approved, WithdrawalStatus::Failed)
+                | (WithdrawalStatus::Broadcast, WithdrawalStatus::Failed)
+        )
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            WithdrawalStatus::Requested => "requested",
+            WithdrawalStatus::Approved => "approved",
+            WithdrawalStatus::Broadcast => "broadcast",
+            WithdrawalStatus::Confirmed => "confirmed",
+            WithdrawalStatus::Failed => "failed",
+        }
+    }
+}
+
+impl Display for WithdrawalStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl ToSql for WithdrawalStatus {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.as_str().to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "withdrawal_status"
+    }
+
+    fn to_sql_checked(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.to_sql(ty, out)
+    }
+}
+
+impl<'a> FromSql<'a> for WithdrawalStatus {
+    fn from_sql(
+        ty: &Type,
+        raw: &'a [u8],
+    ) -> Result<WithdrawalStatus, Box<dyn Error + Sync + Send>> {
+        let s = <&str as FromSql>::from_sql(ty, raw)?;
+        match s {
+            "requested" => Ok(WithdrawalStatus::Requested),
+            "approved" => Ok(WithdrawalStatus::Approved),
+            "broadcast" => Ok(WithdrawalStatus::Broadcast),
+            "confirmed" => Ok(WithdrawalStatus::Confirmed),
+            "failed" => Ok(WithdrawalStatus::Failed),
+            _ => Err(format!("invalid withdrawal status: {}", s).into()),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "withdrawal_status"
+    }
+}
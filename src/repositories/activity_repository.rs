@@ -1,9 +1,9 @@
 use crate::models::activity_models::{CreateUserActivityRequest, UserActivity};
-use deadpool_postgres::Client;
+use deadpool_postgres::{Client, GenericClient};
 use tokio_postgres::Error;
 
-pub async fn create_activity(
-    client: &Client,
+pub async fn create_activity<C: GenericClient>(
+    client: &C,
     create_activity_request: &CreateUserActivityRequest,
 ) -> Result<UserActivity, Error> {
     let statement = client
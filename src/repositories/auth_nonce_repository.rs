@@ -0,0 +1,74 @@
+use crate::models::auth_models::AuthNonce;
+use deadpool_postgres::Client;
+use tokio_postgres::{Error, Row};
+
+pub async fn create(client: &Client, nonce: &AuthNonce) -> Result<(), Error> {
+    // Expired challenges for this wallet are no longer useful to anyone
+    client
+        .execute(
+            "DELETE FROM auth_nonces WHERE wallet_address = $1 AND expires_at < NOW()",
+            &[&nonce.wallet_address],
+        )
+        .await?;
+
+    let stmt = client
+        .prepare(
+            "INSERT INTO auth_nonces (nonce, wallet_address, chain, issued_at, expires_at)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .await?;
+
+    client
+        .execute(
+            &stmt,
+            &[
+                &nonce.nonce,
+                &nonce.wallet_address,
+                &nonce.chain.as_str(),
+                &nonce.issued_at,
+                &nonce.expires_at,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get(client: &Client, nonce: &str) -> Result<Option<AuthNonce>, Error> {
+    let stmt = client
+        .prepare(
+            "SELECT nonce, wallet_address, chain, issued_at, expires_at, used_at
+             FROM auth_nonces
+             WHERE nonce = $1",
+        )
+        .await?;
+
+    let row = client.query_opt(&stmt, &[&nonce]).await?;
+
+    Ok(row.as_ref().map(row_to_nonce))
+}
+
+/// Marks the nonce as used. Returns `false` if it was already used or has
+/// expired in the meantime, so two concurrent verifies can't both succeed.
+pub async fn consume(client: &Client, nonce: &str) -> Result<bool, Error> {
+    let stmt = client
+        .prepare(
+            "UPDATE auth_nonces SET used_at = NOW()
+             WHERE nonce = $1 AND used_at IS NULL AND expires_at > NOW()",
+        )
+        .await?;
+
+    Ok(client.execute(&stmt, &[&nonce]).await? == 1)
+}
+
+fn row_to_nonce(row: &Row) -> AuthNonce {
+    let chain: &str = row.get(2);
+    AuthNonce {
+        nonce: row.get(0),
+        wallet_address: row.get(1),
+        chain: chain.parse().expect("chain is validated on insert"),
+        issued_at: row.get(3),
+        expires_at: row.get(4),
+        used_at: row.get(5),
+    }
+}
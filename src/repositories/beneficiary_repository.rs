@@ -0,0 +1,262 @@
+use crate::models::beneficiary_models::{Allocation, Beneficiary, NewBeneficiary};
+use crate::models::money::Amount;
+use crate::models::vesting_models::ReleaseSchedule;
+use crate::repositories::withdrawal_history_repository;
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::types::Json;
+use tokio_postgres::{Error, Row};
+
+const BENEFICIARY_COLUMNS: &str = "id, plan_id, user_id, name, wallet_address, relationship, email, allocation_type, share_bps, fixed_asset, fixed_amount, position, release_schedule";
+
+/// Beneficiaries of several plans at once, in payout order.
+pub async fn get_by_plans<C: GenericClient>(
+    client: &C,
+    plan_ids: &[i32],
+) -> Result<Vec<Beneficiary>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM beneficiaries WHERE plan_id = ANY($1) ORDER BY plan_id, position, id",
+                BENEFICIARY_COLUMNS
+            ),
+            &[&plan_ids],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_beneficiary).collect())
+}
+
+pub async fn create(
+    client: &Client,
+    plan_id: i32,
+    beneficiary: &NewBeneficiary,
+) -> Result<Beneficiary, Error> {
+    let position: i32 = client
+        .query_one(
+            "SELECT COALESCE(MAX(position) + 1, 0) FROM beneficiaries WHERE plan_id = $1",
+            &[&plan_id],
+        )
+        .await?
+        .get(0);
+
+    insert(client, plan_id, beneficiary, position).await
+}
+
+/// Inserts a full beneficiary list, keeping the order it was given in.
+pub async fn insert_all<C: GenericClient>(
+    client: &C,
+    plan_id: i32,
+    beneficiaries: &[NewBeneficiary],
+) -> Result<Vec<Beneficiary>, Error> {
+    let mut inserted = Vec::with_capacity(beneficiaries.len());
+    for (position, beneficiary) in beneficiaries.iter().enumerate() {
+        inserted.push(insert(client, plan_id, beneficiary, position as i32).await?);
+    }
+    Ok(inserted)
+}
+
+pub async fn replace_all(
+    client: &mut Client,
+    plan_id: i32,
+    beneficiaries: &[NewBeneficiary],
+) -> Result<Vec<Beneficiary>, Error> {
+    let transaction = client.transaction().await?;
+
+    transaction
+        .execute("DELETE FROM beneficiaries WHERE plan_id = $1", &[&plan_id])
+        .await?;
+    let inserted = insert_all(&transaction, plan_id, beneficiaries).await?;
+
+    transaction.commit().await?;
+
+    Ok(inserted)
+}
+
+pub async fn update(
+    client: &Client,
+    id: i32,
+    beneficiary: &NewBeneficiary,
+) -> Result<Beneficiary, Error> {
+    let (allocation_type, share_bps, fixed_asset, fixed_amount) =
+        allocation_columns(&beneficiary.allocation);
+
+    let stmt = client
+        .prepare(&format!(
+            "UPDATE beneficiaries
+             SET user_id = $1, name = $2, wallet_address = $3, relationship = $4, email = $5,
+                 allocation_type = $6, share_bps = $7, fixed_asset = $8, fixed_amount = $9,
+                 release_schedule = $10
+             WHERE id = $11
+             RETURNING {}",
+            BENEFICIARY_COLUMNS
+        ))
+        .await?;
+
+    let row = client
+        .query_one(
+            &stmt,
+            &[
+                &beneficiary.user_id,
+                &beneficiary.name,
+                &beneficiary.wallet_address,
+                &beneficiary.relationship,
+                &beneficiary.email,
+                &allocation_type,
+                &share_bps,
+                &fixed_asset,
+                &fixed_amount,
+                &Json(&beneficiary.release),
+                &id,
+            ],
+        )
+        .await?;
+
+    Ok(row_to_beneficiary(&row))
+}
+
+/// Links a beneficiary to `user_id` and hands them the payouts left unclaimed
+/// while they had no account. Returns the beneficiary and how many payouts
+/// were claimed, or `None` if they are already linked to another account.
+pub async fn link_account(
+    client: &mut Client,
+    id: i32,
+    user_id: i32,
+) -> Result<Option<(Beneficiary, u64)>, Error> {
+    let transaction = client.transaction().await?;
+
+    let row = transaction
+        .query_opt(
+            &format!(
+                "UPDATE beneficiaries SET user_id = $1
+                 WHERE id = $2 AND (user_id IS NULL OR user_id = $1)
+                 RETURNING {}",
+                BENEFICIARY_COLUMNS
+            ),
+            &[&user_id, &id],
+        )
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let claimed = withdrawal_history_repository::claim_payouts(&transaction, id, user_id).await?;
+
+    transaction.commit().await?;
+
+    Ok(Some((row_to_beneficiary(&row), claimed)))
+}
+
+pub async fn delete(client: &Client, id: i32) -> Result<(), Error> {
+    let stmt = client
+        .prepare("DELETE FROM beneficiaries WHERE id = $1")
+        .await?;
+    client.execute(&stmt, &[&id]).await?;
+    Ok(())
+}
+
+/// Rewrites positions so that `ordered_ids` is the new payout order. The
+/// caller is expected to pass every beneficiary of the plan exactly once.
+pub async fn reorder(
+    client: &mut Client,
+    plan_id: i32,
+    ordered_ids: &[i32],
+) -> Result<Vec<Beneficiary>, Error> {
+    let transaction = client.transaction().await?;
+
+    let stmt = transaction
+        .prepare("UPDATE beneficiaries SET position = $1 WHERE plan_id = $2 AND id = $3")
+        .await?;
+    for (position, id) in ordered_ids.iter().enumerate() {
+        transaction
+            .execute(&stmt, &[&(position as i32), &plan_id, id])
+            .await?;
+    }
+    let reordered = get_by_plans(&transaction, &[plan_id]).await?;
+
+    transaction.commit().await?;
+
+    Ok(reordered)
+}
+
+pub async fn is_beneficiary(client: &Client, plan_id: i32, user_id: i32) -> Result<bool, Error> {
+    let stmt = client
+        .prepare("SELECT EXISTS(SELECT 1 FROM beneficiaries WHERE plan_id = $1 AND user_id = $2)")
+        .await?;
+
+    let row = client.query_one(&stmt, &[&plan_id, &user_id]).await?;
+
+    Ok(row.get(0))
+}
+
+async fn insert<C: GenericClient>(
+    client: &C,
+    plan_id: i32,
+    beneficiary: &NewBeneficiary,
+    position: i32,
+) -> Result<Beneficiary, Error> {
+    let (allocation_type, share_bps, fixed_asset, fixed_amount) =
+        allocation_columns(&beneficiary.allocation);
+
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO beneficiaries (plan_id, user_id, name, wallet_address, relationship, email,
+                    allocation_type, share_bps, fixed_asset, fixed_amount, position, release_schedule)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                 RETURNING {}",
+                BENEFICIARY_COLUMNS
+            ),
+            &[
+                &plan_id,
+                &beneficiary.user_id,
+                &beneficiary.name,
+                &beneficiary.wallet_address,
+                &beneficiary.relationship,
+                &beneficiary.email,
+                &allocation_type,
+                &share_bps,
+                &fixed_asset,
+                &fixed_amount,
+                &position,
+                &Json(&beneficiary.release),
+            ],
+        )
+        .await?;
+
+    Ok(row_to_beneficiary(&row))
+}
+
+fn allocation_columns(
+    allocation: &Allocation,
+) -> (&'static str, Option<i32>, Option<&str>, Option<Amount>) {
+    match allocation {
+        Allocation::Percentage { share_bps } => ("percentage", Some(*share_bps), None, None),
+        Allocation::Fixed { asset, amount } => ("fixed", None, Some(asset.as_str()), Some(*amount)),
+    }
+}
+
+fn row_to_beneficiary(row: &Row) -> Beneficiary {
+    let release: Json<ReleaseSchedule> = row.get(12);
+    let allocation_type: &str = row.get(7);
+    let allocation = match allocation_type {
+        "fixed" => Allocation::Fixed {
+            asset: row.get(9),
+            amount: row.get(10),
+        },
+        _ => Allocation::Percentage {
+            share_bps: row.get(8),
+        },
+    };
+
+    Beneficiary {
+        id: row.get(0),
+        plan_id: row.get(1),
+        user_id: row.get(2),
+        name: row.get(3),
+        wallet_address: row.get(4),
+        relationship: row.get(5),
+        email: row.get(6),
+        allocation,
+        release: release.0,
+        position: row.get(11),
+    }
+}
@@ -0,0 +1,177 @@
+use crate::models::check_in_models::{CheckInSchedule, ConfigureCheckIn, Escalation};
+use crate::models::plan_models::PlanStatus;
+use crate::repositories::{guardian_repository, plan_repository};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
+
+const CHECK_IN_COLUMNS: &str = "plan_id, interval_secs, grace_secs, last_check_in_at, \
+     escalation, escalated_at, created_at, updated_at";
+
+pub async fn get_by_plan(client: &Client, plan_id: i32) -> Result<Option<CheckInSchedule>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM plan_check_ins WHERE plan_id = $1",
+                CHECK_IN_COLUMNS
+            ),
+            &[&plan_id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_schedule))
+}
+
+/// Creates or changes the schedule of a plan. A new schedule counts as a
+/// check-in at `now`; changing an existing one keeps its progress.
+pub async fn configure(
+    client: &Client,
+    plan_id: i32,
+    schedule: &ConfigureCheckIn,
+    now: DateTime<Utc>,
+) -> Result<CheckInSchedule, Error> {
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO plan_check_ins (plan_id, interval_secs, grace_secs, last_check_in_at)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (plan_id) DO UPDATE
+                 SET interval_secs = EXCLUDED.interval_secs,
+                     grace_secs = EXCLUDED.grace_secs,
+                     updated_at = NOW()
+                 RETURNING {}",
+                CHECK_IN_COLUMNS
+            ),
+            &[
+                &plan_id,
+                &schedule.interval_secs,
+                &schedule.grace_secs,
+                &now,
+            ],
+        )
+        .await?;
+
+    Ok(row_to_schedule(&row))
+}
+
+/// Resets the escalation ladder and cancels any guardian round in flight. A
+/// plan that the scheduler triggered goes back to active, since the owner has
+/// just shown they are alive.
+pub async fn record_check_in(
+    client: &mut Client,
+    plan_id: i32,
+    now: DateTime<Utc>,
+) -> Result<Option<CheckInSchedule>, Error> {
+    let transaction = client.transaction().await?;
+
+    let previous = transaction
+        .query_opt(
+            "SELECT escalation FROM plan_check_ins WHERE plan_id = $1 FOR UPDATE",
+            &[&plan_id],
+        )
+        .await?;
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+    let previous: &str = previous.get(0);
+
+    let row = transaction
+        .query_one(
+            &format!(
+                "UPDATE plan_check_ins
+                 SET last_check_in_at = $1, escalation = 'none', escalated_at = NULL,
+                     updated_at = NOW()
+                 WHERE plan_id = $2
+                 RETURNING {}",
+                CHECK_IN_COLUMNS
+            ),
+            &[&now, &plan_id],
+        )
+        .await?;
+
+    guardian_repository::cancel_open_round(&transaction, plan_id, now).await?;
+
+    if previous == Escalation::Triggered.as_str() {
+        plan_repository::transition_status(
+            &transaction,
+            plan_id,
+            PlanStatus::Triggered,
+            PlanStatus::Active,
+        )
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(Some(row_to_schedule(&row)))
+}
+
+/// Schedules of active plans that have not been triggered yet, each paired
+/// with the plan owner. Plans that only guardians may trigger drop out once
+/// their last notice has gone out.
+pub async fn get_watched(client: &Client) -> Result<Vec<(i32, CheckInSchedule)>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {}, owner_id FROM plan_check_ins
+                 JOIN (SELECT id AS plan_id, owner_id FROM plans WHERE status = 'active') active
+                     USING (plan_id)
+                 LEFT JOIN (SELECT plan_id, policy FROM plan_trigger_policies) policies
+                     USING (plan_id)
+                 WHERE escalation <> 'triggered'
+                     AND NOT (escalation = 'trigger_pending'
+                         AND COALESCE(policy, 'inactivity') = 'guardians')
+                 ORDER BY plan_id",
+                CHECK_IN_COLUMNS
+            ),
+            &[],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| (row.get(8), row_to_schedule(row)))
+        .collect())
+}
+
+/// Moves a schedule one step up the ladder, provided it is still at `current`.
+/// Returns `None` when it has changed in the meantime, for example because the
+/// owner checked in or another instance escalated it first.
+pub async fn escalate<C: GenericClient>(
+    client: &C,
+    plan_id: i32,
+    current: Escalation,
+    next: Escalation,
+    now: DateTime<Utc>,
+) -> Result<Option<CheckInSchedule>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE plan_check_ins
+                 SET escalation = $1, escalated_at = $2, updated_at = NOW()
+                 WHERE plan_id = $3 AND escalation = $4
+                 RETURNING {}",
+                CHECK_IN_COLUMNS
+            ),
+            &[&next.as_str(), &now, &plan_id, &current.as_str()],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_schedule))
+}
+
+fn row_to_schedule(row: &Row) -> CheckInSchedule {
+    let escalation: &str = row.get(4);
+    CheckInSchedule {
+        plan_id: row.get(0),
+        interval_secs: row.get(1),
+        grace_secs: row.get(2),
+        last_check_in_at: row.get(3),
+        escalation: escalation
+            .parse()
+            .expect("escalation is checked by the table"),
+        escalated_at: row.get(5),
+        created_at: row.get(6),
+        updated_at: row.get(7),
+    }
+}
@@ -0,0 +1,170 @@
+use crate::models::claim::{Claim, ClaimStatus};
+use crate::models::claim_dispute_models::{ClaimDispute, DisputeOutcome, NewClaimDispute};
+use crate::repositories::claim_repository;
+use deadpool_postgres::Client;
+use tokio_postgres::{Error, Row};
+
+const DISPUTE_COLUMNS: &str = "id, claim_id, kind, contested_status, decided_by, filed_by, \
+     reason, outcome, resolved_by, resolution, created_at, resolved_at";
+
+/// Every dispute of the claim, oldest first.
+pub async fn get_by_claim(client: &Client, claim_id: i32) -> Result<Vec<ClaimDispute>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM claim_disputes WHERE claim_id = $1 ORDER BY id",
+                DISPUTE_COLUMNS
+            ),
+            &[&claim_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_dispute).collect())
+}
+
+pub async fn get_by_id(
+    client: &Client,
+    claim_id: i32,
+    id: i32,
+) -> Result<Option<ClaimDispute>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM claim_disputes WHERE claim_id = $1 AND id = $2",
+                DISPUTE_COLUMNS
+            ),
+            &[&claim_id, &id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_dispute))
+}
+
+pub async fn get_open(client: &Client, claim_id: i32) -> Result<Option<ClaimDispute>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM claim_disputes WHERE claim_id = $1 AND outcome IS NULL",
+                DISPUTE_COLUMNS
+            ),
+            &[&claim_id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_dispute))
+}
+
+/// Opens a dispute of the claim's decision, moves the claim to disputed and
+/// puts it back in the review queue, due a decision in `due_in_hours`.
+/// Returns `None` if the claim is no longer in the contested status.
+pub async fn file(
+    client: &mut Client,
+    claim_id: i32,
+    dispute: &NewClaimDispute,
+    due_in_hours: i32,
+) -> Result<Option<(ClaimDispute, Claim)>, Error> {
+    let transaction = client.transaction().await?;
+
+    let moved = claim_repository::move_status(
+        &transaction,
+        claim_id,
+        dispute.contested_status,
+        ClaimStatus::Disputed,
+        dispute.filed_by,
+        Some(&dispute.reason),
+    )
+    .await?;
+    if moved.is_none() {
+        return Ok(None);
+    }
+
+    let row = transaction
+        .query_one(
+            &format!(
+                "INSERT INTO claim_disputes
+                     (claim_id, kind, contested_status, decided_by, filed_by, reason)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 RETURNING {}",
+                DISPUTE_COLUMNS
+            ),
+            &[
+                &claim_id,
+                &dispute.kind.as_str(),
+                &dispute.contested_status,
+                &dispute.decided_by,
+                &dispute.filed_by,
+                &dispute.reason,
+            ],
+        )
+        .await?;
+    let claim = claim_repository::requeue(&transaction, claim_id, due_in_hours)
+        .await?
+        .expect("claim was just updated");
+
+    transaction.commit().await?;
+
+    Ok(Some((row_to_dispute(&row), claim)))
+}
+
+/// Settles an open dispute and moves the claim on to match the outcome.
+/// Returns `None` if the dispute was settled in the meantime.
+pub async fn resolve(
+    client: &mut Client,
+    dispute: &ClaimDispute,
+    outcome: DisputeOutcome,
+    resolved_by: i32,
+    resolution: &str,
+) -> Result<Option<(ClaimDispute, Claim)>, Error> {
+    let transaction = client.transaction().await?;
+
+    let row = transaction
+        .query_opt(
+            &format!(
+                "UPDATE claim_disputes
+                 SET outcome = $1, resolved_by = $2, resolution = $3, resolved_at = NOW()
+                 WHERE id = $4 AND outcome IS NULL
+                 RETURNING {}",
+                DISPUTE_COLUMNS
+            ),
+            &[&outcome.as_str(), &resolved_by, &resolution, &dispute.id],
+        )
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let claim = claim_repository::move_status(
+        &transaction,
+        dispute.claim_id,
+        ClaimStatus::Disputed,
+        outcome.next_status(dispute.contested_status),
+        resolved_by,
+        Some(resolution),
+    )
+    .await?;
+    let Some(claim) = claim else {
+        return Ok(None);
+    };
+
+    transaction.commit().await?;
+
+    Ok(Some((row_to_dispute(&row), claim)))
+}
+
+fn row_to_dispute(row: &Row) -> ClaimDispute {
+    let kind: &str = row.get(2);
+    let outcome: Option<&str> = row.get(7);
+    ClaimDispute {
+        id: row.get(0),
+        claim_id: row.get(1),
+        kind: kind.parse().expect("kind is checked by the table"),
+        contested_status: row.get(3),
+        decided_by: row.get(4),
+        filed_by: row.get(5),
+        reason: row.get(6),
+        outcome: outcome.map(|outcome| outcome.parse().expect("outcome is checked by the table")),
+        resolved_by: row.get(8),
+        resolution: row.get(9),
+        created_at: row.get(10),
+        resolved_at: row.get(11),
+    }
+}
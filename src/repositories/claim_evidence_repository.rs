@@ -0,0 +1,151 @@
+use crate::models::claim::ClaimStatus;
+use crate::models::claim_evidence_models::{ClaimEvidence, NewClaimEvidence};
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
+
+const EVIDENCE_COLUMNS: &str = "id, claim_id, file_name, content_type, size_bytes, sha256, storage_key, uploaded_by, created_at";
+
+/// Who filed the claim and where it stands, which is all evidence access
+/// depends on.
+pub async fn get_claim_state(
+    client: &Client,
+    claim_id: i32,
+) -> Result<Option<(i32, ClaimStatus)>, Error> {
+    let row = client
+        .query_opt(
+            "SELECT user_id, status FROM claims WHERE id = $1",
+            &[&claim_id],
+        )
+        .await?;
+
+    Ok(row.map(|row| (row.get(0), row.get(1))))
+}
+
+pub async fn get_by_claim(client: &Client, claim_id: i32) -> Result<Vec<ClaimEvidence>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM claim_evidence WHERE claim_id = $1 ORDER BY id",
+                EVIDENCE_COLUMNS
+            ),
+            &[&claim_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_evidence).collect())
+}
+
+pub async fn get_by_id(
+    client: &Client,
+    claim_id: i32,
+    id: i32,
+) -> Result<Option<ClaimEvidence>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM claim_evidence WHERE claim_id = $1 AND id = $2",
+                EVIDENCE_COLUMNS
+            ),
+            &[&claim_id, &id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_evidence))
+}
+
+/// Records an uploaded file against a claim. Returns `None` if the claim has
+/// moved to a status that does not accept new evidence.
+pub async fn create(
+    client: &mut Client,
+    claim_id: i32,
+    evidence: &NewClaimEvidence,
+) -> Result<Option<ClaimEvidence>, Error> {
+    let transaction = client.transaction().await?;
+
+    if !lock_claim(&transaction, claim_id, ClaimStatus::accepts_new_evidence).await? {
+        return Ok(None);
+    }
+    let row = transaction
+        .query_one(
+            &format!(
+                "INSERT INTO claim_evidence
+                     (claim_id, file_name, content_type, size_bytes, sha256, storage_key, uploaded_by)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 RETURNING {}",
+                EVIDENCE_COLUMNS
+            ),
+            &[
+                &claim_id,
+                &evidence.file_name,
+                &evidence.content_type,
+                &evidence.size_bytes,
+                &evidence.sha256,
+                &evidence.storage_key,
+                &evidence.uploaded_by,
+            ],
+        )
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(Some(row_to_evidence(&row)))
+}
+
+/// Removes a file from a pending claim and returns it, so that its object can
+/// be deleted too. Returns `None` if the claim is no longer pending or the
+/// file is already gone.
+pub async fn delete(
+    client: &mut Client,
+    claim_id: i32,
+    id: i32,
+) -> Result<Option<ClaimEvidence>, Error> {
+    let transaction = client.transaction().await?;
+
+    if !lock_claim(&transaction, claim_id, ClaimStatus::allows_evidence_removal).await? {
+        return Ok(None);
+    }
+    let row = transaction
+        .query_opt(
+            &format!(
+                "DELETE FROM claim_evidence WHERE claim_id = $1 AND id = $2 RETURNING {}",
+                EVIDENCE_COLUMNS
+            ),
+            &[&claim_id, &id],
+        )
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(row.as_ref().map(row_to_evidence))
+}
+
+/// Locks the claim row, so its status cannot change until the transaction
+/// ends, and reports whether its status still passes `allowed`.
+async fn lock_claim<C: GenericClient>(
+    client: &C,
+    claim_id: i32,
+    allowed: fn(&ClaimStatus) -> bool,
+) -> Result<bool, Error> {
+    let row = client
+        .query_opt(
+            "SELECT status FROM claims WHERE id = $1 FOR UPDATE",
+            &[&claim_id],
+        )
+        .await?;
+
+    Ok(row.is_some_and(|row| allowed(&row.get::<_, ClaimStatus>(0))))
+}
+
+fn row_to_evidence(row: &Row) -> ClaimEvidence {
+    ClaimEvidence {
+        id: row.get(0),
+        claim_id: row.get(1),
+        file_name: row.get(2),
+        content_type: row.get(3),
+        size_bytes: row.get(4),
+        sha256: row.get(5),
+        storage_key: row.get(6),
+        uploaded_by: row.get(7),
+        created_at: row.get(8),
+    }
+}
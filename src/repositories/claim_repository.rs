@@ -1,85 +1,67 @@
-use crate::models::claim::{Claim, ClaimStatus, CreateClaim, UpdateClaim};
-use deadpool_postgres::Client;
-use tokio_postgres::Error;
+use crate::models::claim::{
+    Claim, ClaimPriority, ClaimSla, ClaimStatus, ClaimStatusChange, CreateClaim,
+};
+use crate::models::money::Money;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
+
+const CLAIM_COLUMNS: &str = "id, user_id, amount, status, description, created_at, updated_at, \
+     plan_id, asset, priority, due_at, assignee_id, assigned_at, overdue_at";
+
+// The statuses of `ClaimStatus::is_open`
+const OPEN: &str = "status IN ('pending', 'under_review', 'needs_information', 'disputed')";
+
+const HISTORY_COLUMNS: &str = "id, claim_id, from_status, to_status, actor_id, reason, created_at";
 
 pub async fn get_all(client: &Client) -> Result<Vec<Claim>, Error> {
-    let stmt = client
-        .prepare(
-            "
-        SELECT id, user_id, amount, status, description, created_at, updated_at 
-        FROM claims
-    ",
+    let rows = client
+        .query(
+            &format!("SELECT {} FROM claims ORDER BY id", CLAIM_COLUMNS),
+            &[],
         )
         .await?;
 
-    let rows = client.query(&stmt, &[]).await?;
+    Ok(rows.iter().map(row_to_claim).collect())
+}
 
-    Ok(rows
-        .iter()
-        .map(|row| Claim {
-            id: row.get(0),
-            user_id: row.get(1),
-            amount: row.get(2),
-            status: row.get(3),
-            description: row.get(4),
-            created_at: row.get(5),
-            updated_at: row.get(6),
-        })
-        .collect())
+pub async fn get_by_id(client: &Client, id: i32) -> Result<Option<Claim>, Error> {
+    let row = client
+        .query_opt(
+            &format!("SELECT {} FROM claims WHERE id = $1", CLAIM_COLUMNS),
+            &[&id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_claim))
 }
 
 pub async fn get_by_user_id(client: &Client, user_id: i32) -> Result<Vec<Claim>, Error> {
-    let stmt = client
-        .prepare(
-            "
-        SELECT id, user_id, amount, status, description, created_at, updated_at 
-        FROM claims 
-        WHERE user_id = $1
-    ",
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM claims WHERE user_id = $1 ORDER BY id",
+                CLAIM_COLUMNS
+            ),
+            &[&user_id],
         )
         .await?;
 
-    let rows = client.query(&stmt, &[&user_id]).await?;
-
-    Ok(rows
-        .iter()
-        .map(|row| Claim {
-            id: row.get(0),
-            user_id: row.get(1),
-            amount: row.get(2),
-            status: row.get(3),
-            description: row.get(4),
-            created_at: row.get(5),
-            updated_at: row.get(6),
-        })
-        .collect())
+    Ok(rows.iter().map(row_to_claim).collect())
 }
 
 pub async fn get_by_status(client: &Client, status: ClaimStatus) -> Result<Vec<Claim>, Error> {
-    let stmt = client
-        .prepare(
-            "
-        SELECT id, user_id, amount, status, description, created_at, updated_at 
-        FROM claims 
-        WHERE status = $1
-    ",
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM claims WHERE status = $1 ORDER BY id",
+                CLAIM_COLUMNS
+            ),
+            &[&status],
         )
         .await?;
 
-    let rows = client.query(&stmt, &[&status]).await?;
-
-    Ok(rows
-        .iter()
-        .map(|row| Claim {
-            id: row.get(0),
-            user_id: row.get(1),
-            amount: row.get(2),
-            status: row.get(3),
-            description: row.get(4),
-            created_at: row.get(5),
-            updated_at: row.get(6),
-        })
-        .collect())
+    Ok(rows.iter().map(row_to_claim).collect())
 }
 
 pub async fn get_by_user_and_status(
@@ -87,92 +69,402 @@ pub async fn get_by_user_and_status(
     user_id: i32,
     status: ClaimStatus,
 ) -> Result<Vec<Claim>, Error> {
-    let stmt = client
-        .prepare(
-            "
-        SELECT id, user_id, amount, status, description, created_at, updated_at 
-        FROM claims 
-        WHERE user_id = $1 AND status = $2
-    ",
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM claims WHERE user_id = $1 AND status = $2 ORDER BY id",
+                CLAIM_COLUMNS
+            ),
+            &[&user_id, &status],
         )
         .await?;
 
-    let rows = client.query(&stmt, &[&user_id, &status]).await?;
-
-    Ok(rows
-        .iter()
-        .map(|row| Claim {
-            id: row.get(0),
-            user_id: row.get(1),
-            amount: row.get(2),
-            status: row.get(3),
-            description: row.get(4),
-            created_at: row.get(5),
-            updated_at: row.get(6),
-        })
-        .collect())
+    Ok(rows.iter().map(row_to_claim).collect())
 }
 
-pub async fn create(client: &Client, claim: &CreateClaim) -> Result<Claim, Error> {
-    let stmt = client
-        .prepare(
-            "
-        INSERT INTO claims (user_id, amount, status, description) 
-        VALUES ($1, $2, $3, $4) 
-        RETURNING id, user_id, amount, status, description, created_at, updated_at
-    ",
-        )
-        .await?;
+/// Files a claim as pending at normal priority and opens its history with
+/// the filing.
+pub async fn create(
+    client: &mut Client,
+    claim: &CreateClaim,
+    sla: &ClaimSla,
+) -> Result<Claim, Error> {
+    let priority = ClaimPriority::default();
+    let transaction = client.transaction().await?;
 
-    let row = client
+    let row = transaction
         .query_one(
-            &stmt,
+            &format!(
+                "INSERT INTO claims
+                     (user_id, amount, asset, status, description, plan_id, priority, due_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, NOW() + make_interval(hours => $8))
+                 RETURNING {}",
+                CLAIM_COLUMNS
+            ),
             &[
                 &claim.user_id,
-                &claim.amount,
+                &claim.money.amount,
+                &claim.money.asset,
                 &ClaimStatus::Pending,
                 &claim.description,
+                &claim.plan_id,
+                &priority.as_str(),
+                &sla.hours(priority),
             ],
         )
         .await?;
+    let created = row_to_claim(&row);
+    record_change(
+        &transaction,
+        created.id,
+        None,
+        created.status,
+        created.user_id,
+        None,
+    )
+    .await?;
 
-    Ok(Claim {
-        id: row.get(0),
-        user_id: row.get(1),
-        amount: row.get(2),
-        status: row.get(3),
-        description: row.get(4),
-        created_at: row.get(5),
-        updated_at: row.get(6),
-    })
+    transaction.commit().await?;
+
+    Ok(created)
 }
 
-pub async fn update(client: &Client, id: i32, claim: &UpdateClaim) -> Result<Claim, Error> {
-    let stmt = client
-        .prepare(
-            "
-        UPDATE claims 
-        SET 
-            status = COALESCE($1, status),
-            description = COALESCE($2, description),
-            updated_at = NOW()
-        WHERE id = $3
-        RETURNING id, user_id, amount, status, description, created_at, updated_at
-    ",
+/// Returns `None` if the claim is no longer in `current`.
+pub async fn update_description(
+    client: &Client,
+    id: i32,
+    current: ClaimStatus,
+    description: &str,
+) -> Result<Option<Claim>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE claims SET description = $1, updated_at = NOW()
+                 WHERE id = $2 AND status = $3
+                 RETURNING {}",
+                CLAIM_COLUMNS
+            ),
+            &[&description, &id, &current],
         )
         .await?;
 
+    Ok(row.as_ref().map(row_to_claim))
+}
+
+/// Moves a claim on from `current` and records who did it and why. Returns
+/// `None` if it is no longer in that status, so concurrent updates can't skip
+/// a step.
+pub async fn transition(
+    client: &mut Client,
+    id: i32,
+    current: ClaimStatus,
+    next: ClaimStatus,
+    actor_id: i32,
+    reason: Option<&str>,
+) -> Result<Option<Claim>, Error> {
+    let transaction = client.transaction().await?;
+
+    let claim = move_status(&transaction, id, current, next, actor_id, reason).await?;
+    if claim.is_some() {
+        transaction.commit().await?;
+    }
+
+    Ok(claim)
+}
+
+/// [`transition`] as part of a larger transaction.
+pub async fn move_status<C: GenericClient>(
+    client: &C,
+    id: i32,
+    current: ClaimStatus,
+    next: ClaimStatus,
+    actor_id: i32,
+    reason: Option<&str>,
+) -> Result<Option<Claim>, Error> {
     let row = client
-        .query_one(&stmt, &[&claim.status, &claim.description, &id])
+        .query_opt(
+            &format!(
+                "UPDATE claims SET status = $1, updated_at = NOW()
+                 WHERE id = $2 AND status = $3
+                 RETURNING {}",
+                CLAIM_COLUMNS
+            ),
+            &[&next, &id, &current],
+        )
         .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    record_change(client, id, Some(current), next, actor_id, reason).await?;
+
+    Ok(Some(row_to_claim(&row)))
+}
+
+/// Hands the unassigned claim with the nearest deadline to `reviewer_id`:
+/// a pending one, whose review starts, or a disputed one. Claims other
+/// reviewers are taking at the same moment are skipped rather than waited
+/// for, so two reviewers never get the same claim. Reviewers are never given
+/// their own claims, nor disputes of their own decisions.
+pub async fn take_next(client: &mut Client, reviewer_id: i32) -> Result<Option<Claim>, Error> {
+    let transaction = client.transaction().await?;
 
-    Ok(Claim {
+    let next = transaction
+        .query_opt(
+            "SELECT id, status FROM claims
+             WHERE status IN ('pending', 'disputed')
+               AND assignee_id IS NULL
+               AND user_id <> $1
+               AND NOT EXISTS (
+                   SELECT 1 FROM claim_disputes
+                   WHERE claim_disputes.claim_id = claims.id
+                     AND claim_disputes.outcome IS NULL
+                     AND $1 IN (claim_disputes.decided_by, claim_disputes.filed_by)
+               )
+             ORDER BY due_at, id
+             LIMIT 1
+             FOR UPDATE SKIP LOCKED",
+            &[&reviewer_id],
+        )
+        .await?;
+    let Some(next) = next else {
+        return Ok(None);
+    };
+    let id: i32 = next.get(0);
+    let status: ClaimStatus = next.get(1);
+    let started = if status == ClaimStatus::Pending {
+        ClaimStatus::UnderReview
+    } else {
+        status
+    };
+
+    let row = transaction
+        .query_one(
+            &format!(
+                "UPDATE claims
+                 SET status = $1, assignee_id = $2, assigned_at = NOW(), updated_at = NOW()
+                 WHERE id = $3
+                 RETURNING {}",
+                CLAIM_COLUMNS
+            ),
+            &[&started, &reviewer_id, &id],
+        )
+        .await?;
+    if started != status {
+        record_change(&transaction, id, Some(status), started, reviewer_id, None).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(Some(row_to_claim(&row)))
+}
+
+/// Puts a claim back in the queue, unassigned and due a decision in
+/// `due_in_hours`.
+pub async fn requeue<C: GenericClient>(
+    client: &C,
+    id: i32,
+    due_in_hours: i32,
+) -> Result<Option<Claim>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE claims
+                 SET assignee_id = NULL,
+                     assigned_at = NULL,
+                     overdue_at = NULL,
+                     due_at = NOW() + make_interval(hours => $1),
+                     updated_at = NOW()
+                 WHERE id = $2
+                 RETURNING {}",
+                CLAIM_COLUMNS
+            ),
+            &[&due_in_hours, &id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_claim))
+}
+
+/// Open claims assigned to the reviewer, nearest deadline first.
+pub async fn get_assigned(client: &Client, assignee_id: i32) -> Result<Vec<Claim>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM claims
+                 WHERE assignee_id = $1 AND {}
+                 ORDER BY due_at, id",
+                CLAIM_COLUMNS, OPEN
+            ),
+            &[&assignee_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_claim).collect())
+}
+
+/// Gives an open claim to another reviewer, or to none. Returns `None` if
+/// there is no such open claim.
+pub async fn assign(
+    client: &Client,
+    id: i32,
+    assignee_id: Option<i32>,
+) -> Result<Option<Claim>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE claims
+                 SET assignee_id = $1,
+                     assigned_at = CASE WHEN $1::INTEGER IS NULL THEN NULL ELSE NOW() END,
+                     updated_at = NOW()
+                 WHERE id = $2 AND {}
+                 RETURNING {}",
+                OPEN, CLAIM_COLUMNS
+            ),
+            &[&assignee_id, &id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_claim))
+}
+
+/// Changes an open claim's priority and moves its deadline to match. A claim
+/// flagged overdue is cleared if the new deadline is still ahead.
+pub async fn prioritize(
+    client: &Client,
+    id: i32,
+    priority: ClaimPriority,
+    sla: &ClaimSla,
+) -> Result<Option<Claim>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE claims
+                 SET priority = $1,
+                     due_at = created_at + make_interval(hours => $2),
+                     overdue_at = CASE
+                         WHEN created_at + make_interval(hours => $2) > NOW() THEN NULL
+                         ELSE overdue_at
+                     END,
+                     updated_at = NOW()
+                 WHERE id = $3 AND {}
+                 RETURNING {}",
+                OPEN, CLAIM_COLUMNS
+            ),
+            &[&priority.as_str(), &sla.hours(priority), &id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_claim))
+}
+
+/// Flags the open claims whose deadline has passed by `now` and returns them.
+/// Each claim is only flagged once.
+pub async fn flag_overdue<C: GenericClient>(
+    client: &C,
+    now: DateTime<Utc>,
+) -> Result<Vec<Claim>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "UPDATE claims SET overdue_at = $1
+                 WHERE {} AND overdue_at IS NULL AND due_at <= $1
+                 RETURNING {}",
+                OPEN, CLAIM_COLUMNS
+            ),
+            &[&now],
+        )
+        .await?;
+
+    let mut claims: Vec<Claim> = rows.iter().map(row_to_claim).collect();
+    claims.sort_by_key(|claim| claim.id);
+    Ok(claims)
+}
+
+/// The most recent time the claim was moved to `status`.
+pub async fn last_change_to(
+    client: &Client,
+    claim_id: i32,
+    status: ClaimStatus,
+) -> Result<Option<ClaimStatusChange>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM claim_status_history
+                 WHERE claim_id = $1 AND to_status = $2
+                 ORDER BY id DESC
+                 LIMIT 1",
+                HISTORY_COLUMNS
+            ),
+            &[&claim_id, &status],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_change))
+}
+
+/// Every status the claim has been in, oldest first.
+pub async fn get_history(client: &Client, claim_id: i32) -> Result<Vec<ClaimStatusChange>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM claim_status_history WHERE claim_id = $1 ORDER BY id",
+                HISTORY_COLUMNS
+            ),
+            &[&claim_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_change).collect())
+}
+
+async fn record_change<C: GenericClient>(
+    client: &C,
+    claim_id: i32,
+    from: Option<ClaimStatus>,
+    to: ClaimStatus,
+    actor_id: i32,
+    reason: Option<&str>,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "INSERT INTO claim_status_history (claim_id, from_status, to_status, actor_id, reason)
+             VALUES ($1, $2, $3, $4, $5)",
+            &[&claim_id, &from, &to, &actor_id, &reason],
+        )
+        .await?;
+    Ok(())
+}
+
+fn row_to_claim(row: &Row) -> Claim {
+    let priority: &str = row.get(9);
+    Claim {
         id: row.get(0),
         user_id: row.get(1),
-        amount: row.get(2),
+        plan_id: row.get(7),
+        money: Money {
+            amount: row.get(2),
+            asset: row.get(8),
+        },
         status: row.get(3),
         description: row.get(4),
+        priority: priority.parse().expect("priority is checked by the table"),
+        due_at: row.get(10),
+        assignee_id: row.get(11),
+        assigned_at: row.get(12),
+        overdue_at: row.get(13),
         created_at: row.get(5),
         updated_at: row.get(6),
-    })
+    }
+}
+
+fn row_to_change(row: &Row) -> ClaimStatusChange {
+    ClaimStatusChange {
+        id: row.get(0),
+        claim_id: row.get(1),
+        from_status: row.get(2),
+        to_status: row.get(3),
+        actor_id: row.get(4),
+        reason: row.get(5),
+        created_at: row.get(6),
+    }
 }
@@ -0,0 +1,125 @@
+use crate::models::activity_models::CreateUserActivityRequest;
+use crate::models::execution_models::{Payout, PlanExecution};
+use crate::models::plan_models::{Plan, PlanStatus};
+use crate::repositories::{activity_repository, plan_repository, withdrawal_history_repository};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Client;
+use tokio_postgres::Error;
+
+pub async fn get_by_plan(client: &Client, plan_id: i32) -> Result<Option<PlanExecution>, Error> {
+    let row = client
+        .query_opt(
+            "SELECT id, plan_id, executed_by, executed_at FROM plan_executions WHERE plan_id = $1",
+            &[&plan_id],
+        )
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let id: i64 = row.get(0);
+    Ok(Some(PlanExecution {
+        id,
+        plan_id: row.get(1),
+        executed_by: row.get(2),
+        executed_at: row.get(3),
+        payouts: withdrawal_history_repository::get_by_execution(client, id).await?,
+    }))
+}
+
+/// When the plan was executed, which is when release schedules start.
+pub async fn executed_at(client: &Client, plan_id: i32) -> Result<Option<DateTime<Utc>>, Error> {
+    let row = client
+        .query_opt(
+            "SELECT executed_at FROM plan_executions WHERE plan_id = $1",
+            &[&plan_id],
+        )
+        .await?;
+
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// Executes a triggered plan: records the execution and its payouts, logs
+/// each step to the activity feed and marks the plan executed, all in one
+/// transaction.
+///
+/// Returns `None` without changing anything if the plan is no longer
+/// triggered, which includes it having been executed already, so running the
+/// executor twice never pays out twice.
+pub async fn record(
+    client: &mut Client,
+    plan: &Plan,
+    executed_by: i32,
+    executed_at: DateTime<Utc>,
+    payouts: &[Payout],
+) -> Result<Option<PlanExecution>, Error> {
+    let transaction = client.transaction().await?;
+
+    let moved = plan_repository::transition_status(
+        &transaction,
+        plan.id,
+        PlanStatus::Triggered,
+        PlanStatus::Executed,
+    )
+    .await?;
+    if !moved {
+        return Ok(None);
+    }
+
+    let row = transaction
+        .query_one(
+            "INSERT INTO plan_executions (plan_id, executed_by, executed_at)
+             VALUES ($1, $2, $3)
+             RETURNING id",
+            &[&plan.id, &executed_by, &executed_at],
+        )
+        .await?;
+    let id: i64 = row.get(0);
+
+    let link = Some(format!("/plans/{}/execution", plan.id));
+    let mut records = Vec::with_capacity(payouts.len());
+    for payout in payouts {
+        let record =
+            withdrawal_history_repository::record_payout(&transaction, id, plan.id, payout).await?;
+
+        // Unclaimed payouts only show up in the owner's summary below
+        if let Some(user_id) = payout.user_id {
+            let activity = CreateUserActivityRequest {
+                user_id: user_id.to_string(),
+                activity_type: "plan_execution".to_string(),
+                details: format!(
+                    "Payout of {} {} to {} scheduled from plan {}",
+                    payout.amount, payout.asset, payout.wallet_address, plan.name
+                ),
+                action_type: "payout_scheduled".to_string(),
+                action_link: link.clone(),
+            };
+            activity_repository::create_activity(&transaction, &activity).await?;
+        }
+
+        records.push(record);
+    }
+
+    let activity = CreateUserActivityRequest {
+        user_id: plan.owner_id.to_string(),
+        activity_type: "plan_execution".to_string(),
+        details: format!(
+            "Plan {} was executed with {} payouts",
+            plan.name,
+            payouts.len()
+        ),
+        action_type: "executed".to_string(),
+        action_link: link,
+    };
+    activity_repository::create_activity(&transaction, &activity).await?;
+
+    transaction.commit().await?;
+
+    Ok(Some(PlanExecution {
+        id,
+        plan_id: plan.id,
+        executed_by,
+        executed_at,
+        payouts: records,
+    }))
+}
@@ -0,0 +1,57 @@
+use crate::models::faq::{Faq, FaqRequest};
+use deadpool_postgres::Client;
+use tokio_postgres::{Error, Row};
+
+pub async fn get_all(client: &Client) -> Result<Vec<Faq>, Error> {
+    let rows = client
+        .query(
+            "SELECT id, question, answer, created_at, updated_at FROM faqs ORDER BY id",
+            &[],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_faq).collect())
+}
+
+pub async fn create(client: &Client, faq: &FaqRequest) -> Result<Faq, Error> {
+    let row = client
+        .query_one(
+            "INSERT INTO faqs (question, answer) VALUES ($1, $2)
+             RETURNING id, question, answer, created_at, updated_at",
+            &[&faq.question, &faq.answer],
+        )
+        .await?;
+
+    Ok(row_to_faq(&row))
+}
+
+pub async fn update(client: &Client, id: i32, faq: &FaqRequest) -> Result<Option<Faq>, Error> {
+    let row = client
+        .query_opt(
+            "UPDATE faqs SET question = $1, answer = $2, updated_at = NOW() WHERE id = $3
+             RETURNING id, question, answer, created_at, updated_at",
+            &[&faq.question, &faq.answer, &id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_faq))
+}
+
+/// Returns whether a FAQ was actually deleted.
+pub async fn delete(client: &Client, id: i32) -> Result<bool, Error> {
+    let deleted = client
+        .execute("DELETE FROM faqs WHERE id = $1", &[&id])
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+fn row_to_faq(row: &Row) -> Faq {
+    Faq {
+        id: row.get("id"),
+        question: row.get("question"),
+        answer: row.get("answer"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
@@ -0,0 +1,370 @@
+use crate::models::guardian_models::{
+    Attestation, ConfigureGuardianPolicy, Guardian, GuardianPolicy, GuardianRound, NewGuardian,
+    RoundStatus,
+};
+use crate::models::notification::CreateNotification;
+use crate::models::plan_models::{Plan, PlanStatus};
+use crate::repositories::{notification_repository, plan_repository};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
+
+const GUARDIAN_COLUMNS: &str = "id, plan_id, user_id, name, email, created_at";
+
+const POLICY_COLUMNS: &str = "plan_id, policy, quorum, window_secs, created_at, updated_at";
+
+const ROUND_COLUMNS: &str = "id, plan_id, status, opened_at, expires_at, closed_at";
+
+pub async fn get_by_plan(client: &Client, plan_id: i32) -> Result<Vec<Guardian>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM plan_guardians WHERE plan_id = $1 ORDER BY id",
+                GUARDIAN_COLUMNS
+            ),
+            &[&plan_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_guardian).collect())
+}
+
+/// The guardian entry of `user_id` on a plan, if they are one.
+pub async fn get_by_user(
+    client: &Client,
+    plan_id: i32,
+    user_id: i32,
+) -> Result<Option<Guardian>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM plan_guardians WHERE plan_id = $1 AND user_id = $2",
+                GUARDIAN_COLUMNS
+            ),
+            &[&plan_id, &user_id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_guardian))
+}
+
+/// Returns `None` if the user already guards this plan.
+pub async fn create(
+    client: &Client,
+    plan_id: i32,
+    guardian: &NewGuardian,
+) -> Result<Option<Guardian>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "INSERT INTO plan_guardians (plan_id, user_id, name, email)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (plan_id, user_id) DO NOTHING
+                 RETURNING {}",
+                GUARDIAN_COLUMNS
+            ),
+            &[&plan_id, &guardian.user_id, &guardian.name, &guardian.email],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_guardian))
+}
+
+pub async fn delete(client: &Client, plan_id: i32, id: i32) -> Result<bool, Error> {
+    let deleted = client
+        .execute(
+            "DELETE FROM plan_guardians WHERE plan_id = $1 AND id = $2",
+            &[&plan_id, &id],
+        )
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+pub async fn get_policy(client: &Client, plan_id: i32) -> Result<Option<GuardianPolicy>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM plan_trigger_policies WHERE plan_id = $1",
+                POLICY_COLUMNS
+            ),
+            &[&plan_id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_policy))
+}
+
+pub async fn configure_policy(
+    client: &Client,
+    plan_id: i32,
+    policy: &ConfigureGuardianPolicy,
+) -> Result<GuardianPolicy, Error> {
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO plan_trigger_policies (plan_id, policy, quorum, window_secs)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (plan_id) DO UPDATE
+                 SET policy = EXCLUDED.policy,
+                     quorum = EXCLUDED.quorum,
+                     window_secs = EXCLUDED.window_secs,
+                     updated_at = NOW()
+                 RETURNING {}",
+                POLICY_COLUMNS
+            ),
+            &[
+                &plan_id,
+                &policy.policy.as_str(),
+                &policy.quorum,
+                &policy.window_secs,
+            ],
+        )
+        .await?;
+
+    Ok(row_to_policy(&row))
+}
+
+/// The plan's most recent round with its attestations, whatever its status.
+pub async fn get_latest_round(
+    client: &Client,
+    plan_id: i32,
+) -> Result<Option<GuardianRound>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM guardian_rounds WHERE plan_id = $1 ORDER BY id DESC LIMIT 1",
+                ROUND_COLUMNS
+            ),
+            &[&plan_id],
+        )
+        .await?;
+
+    match row {
+        Some(row) => Ok(Some(load_round(client, &row).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Records a guardian's attestation in the plan's open round, opening one
+/// that runs until `expires_at` if there is none or the last one ran out of
+/// time. Once a quorum stands the round is confirmed and the plan moves from
+/// active to triggered. The owner is notified when a round opens and when it
+/// is confirmed, all in one transaction.
+pub async fn attest(
+    client: &mut Client,
+    plan: &Plan,
+    policy: &GuardianPolicy,
+    guardian: &Guardian,
+    now: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Result<GuardianRound, Error> {
+    let transaction = client.transaction().await?;
+
+    // Attestations on one plan are taken one at a time
+    transaction
+        .query_one("SELECT 1 FROM plans WHERE id = $1 FOR UPDATE", &[&plan.id])
+        .await?;
+    expire_rounds(&transaction, plan.id, now).await?;
+
+    let open = transaction
+        .query_opt(
+            &format!(
+                "SELECT {} FROM guardian_rounds WHERE plan_id = $1 AND status = 'open'",
+                ROUND_COLUMNS
+            ),
+            &[&plan.id],
+        )
+        .await?;
+    let opened = open.is_none();
+    let round = match open {
+        Some(row) => row,
+        None => {
+            transaction
+                .query_one(
+                    &format!(
+                        "INSERT INTO guardian_rounds (plan_id, opened_at, expires_at)
+                         VALUES ($1, $2, $3)
+                         RETURNING {}",
+                        ROUND_COLUMNS
+                    ),
+                    &[&plan.id, &now, &expires_at],
+                )
+                .await?
+        }
+    };
+    let round_id: i64 = round.get(0);
+
+    transaction
+        .execute(
+            "INSERT INTO guardian_attestations (round_id, guardian_id, attested_at)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (round_id, guardian_id) DO UPDATE
+             SET attested_at = EXCLUDED.attested_at, revoked_at = NULL",
+            &[&round_id, &guardian.id, &now],
+        )
+        .await?;
+
+    let mut round = load_round(&transaction, &round).await?;
+
+    if opened {
+        let notification = CreateNotification {
+            user_id: Some(plan.owner_id),
+            title: "Guardian attestation".to_string(),
+            body: format!(
+                "{} reported that you can no longer manage plan {}. Check in before {} to stop the plan from being triggered.",
+                guardian.name, plan.name, round.expires_at
+            ),
+        };
+        notification_repository::create(&transaction, &notification).await?;
+    }
+
+    if round.standing() >= policy.quorum as usize
+        && plan_repository::transition_status(
+            &transaction,
+            plan.id,
+            PlanStatus::Active,
+            PlanStatus::Triggered,
+        )
+        .await?
+    {
+        transaction
+            .execute(
+                "UPDATE guardian_rounds SET status = 'confirmed', closed_at = $1 WHERE id = $2",
+                &[&now, &round_id],
+            )
+            .await?;
+        round.status = RoundStatus::Confirmed;
+        round.closed_at = Some(now);
+
+        let notification = CreateNotification {
+            user_id: Some(plan.owner_id),
+            title: "Plan triggered".to_string(),
+            body: format!(
+                "{} of your guardians confirmed that you can no longer manage plan {}, so it was triggered.",
+                round.standing(),
+                plan.name
+            ),
+        };
+        notification_repository::create(&transaction, &notification).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(round)
+}
+
+/// Withdraws a guardian's attestation from the plan's open round. Returns
+/// `None` if they have no standing attestation in a round that is still open.
+pub async fn revoke(
+    client: &Client,
+    plan_id: i32,
+    guardian_id: i32,
+    now: DateTime<Utc>,
+) -> Result<Option<GuardianRound>, Error> {
+    let row = client
+        .query_opt(
+            "UPDATE guardian_attestations a SET revoked_at = $1
+             FROM guardian_rounds r
+             WHERE a.round_id = r.id AND r.plan_id = $2 AND a.guardian_id = $3
+                 AND r.status = 'open' AND r.expires_at > $1 AND a.revoked_at IS NULL
+             RETURNING r.id, r.plan_id, r.status, r.opened_at, r.expires_at, r.closed_at",
+            &[&now, &plan_id, &guardian_id],
+        )
+        .await?;
+
+    match row {
+        Some(row) => Ok(Some(load_round(client, &row).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Closes the plan's open round because the owner checked in. Returns whether
+/// there was a round in flight.
+pub async fn cancel_open_round<C: GenericClient>(
+    client: &C,
+    plan_id: i32,
+    now: DateTime<Utc>,
+) -> Result<bool, Error> {
+    expire_rounds(client, plan_id, now).await?;
+    let cancelled = client
+        .execute(
+            "UPDATE guardian_rounds SET status = 'cancelled', closed_at = $1
+             WHERE plan_id = $2 AND status = 'open'",
+            &[&now, &plan_id],
+        )
+        .await?;
+
+    Ok(cancelled > 0)
+}
+
+/// Marks open rounds whose window has run out as expired.
+async fn expire_rounds<C: GenericClient>(
+    client: &C,
+    plan_id: i32,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "UPDATE guardian_rounds SET status = 'expired', closed_at = expires_at
+             WHERE plan_id = $1 AND status = 'open' AND expires_at <= $2",
+            &[&plan_id, &now],
+        )
+        .await?;
+    Ok(())
+}
+
+async fn load_round<C: GenericClient>(client: &C, row: &Row) -> Result<GuardianRound, Error> {
+    let id: i64 = row.get(0);
+    let attestations = client
+        .query(
+            "SELECT guardian_id, attested_at, revoked_at FROM guardian_attestations
+             WHERE round_id = $1
+             ORDER BY attested_at, guardian_id",
+            &[&id],
+        )
+        .await?
+        .iter()
+        .map(|row| Attestation {
+            guardian_id: row.get(0),
+            attested_at: row.get(1),
+            revoked_at: row.get(2),
+        })
+        .collect();
+
+    let status: &str = row.get(2);
+    Ok(GuardianRound {
+        id,
+        plan_id: row.get(1),
+        status: status
+            .parse()
+            .expect("round status is checked by the table"),
+        opened_at: row.get(3),
+        expires_at: row.get(4),
+        closed_at: row.get(5),
+        attestations,
+    })
+}
+
+fn row_to_guardian(row: &Row) -> Guardian {
+    Guardian {
+        id: row.get(0),
+        plan_id: row.get(1),
+        user_id: row.get(2),
+        name: row.get(3),
+        email: row.get(4),
+        created_at: row.get(5),
+    }
+}
+
+fn row_to_policy(row: &Row) -> GuardianPolicy {
+    let policy: &str = row.get(1);
+    GuardianPolicy {
+        plan_id: row.get(0),
+        policy: policy.parse().expect("policy is checked by the table"),
+        quorum: row.get(2),
+        window_secs: row.get(3),
+        created_at: row.get(4),
+        updated_at: row.get(5),
+    }
+}
@@ -0,0 +1,117 @@
+use crate::models::kyc_document_models::{KycDocument, KycDocumentKind, NewKycDocument};
+use crate::models::kyc_models::KycStatus;
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
+
+const DOCUMENT_COLUMNS: &str = "id, kyc_id, kind, content_type, size_bytes, width, height, sha256, storage_key, uploaded_by, created_at";
+
+pub async fn get_by_kyc(client: &Client, kyc_id: i32) -> Result<Vec<KycDocument>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM kyc_documents WHERE kyc_id = $1 ORDER BY id",
+                DOCUMENT_COLUMNS
+            ),
+            &[&kyc_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_document).collect())
+}
+
+pub async fn get_by_kind(
+    client: &Client,
+    kyc_id: i32,
+    kind: KycDocumentKind,
+) -> Result<Option<KycDocument>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM kyc_documents WHERE kyc_id = $1 AND kind = $2",
+                DOCUMENT_COLUMNS
+            ),
+            &[&kyc_id, &kind.as_str()],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_document))
+}
+
+/// Records an uploaded image against a KYC record, replacing any earlier one
+/// of the same kind. Returns the new document and the storage key of the one
+/// it replaced, or `None` if the record is no longer waiting for review.
+pub async fn save(
+    client: &mut Client,
+    kyc_id: i32,
+    document: &NewKycDocument,
+) -> Result<Option<(KycDocument, Option<String>)>, Error> {
+    let transaction = client.transaction().await?;
+
+    if !lock_pending_record(&transaction, kyc_id).await? {
+        return Ok(None);
+    }
+    let replaced = transaction
+        .query_opt(
+            "DELETE FROM kyc_documents WHERE kyc_id = $1 AND kind = $2 RETURNING storage_key",
+            &[&kyc_id, &document.kind.as_str()],
+        )
+        .await?
+        .map(|row| row.get(0));
+    let row = transaction
+        .query_one(
+            &format!(
+                "INSERT INTO kyc_documents
+                     (kyc_id, kind, content_type, size_bytes, width, height, sha256, storage_key, uploaded_by)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 RETURNING {}",
+                DOCUMENT_COLUMNS
+            ),
+            &[
+                &kyc_id,
+                &document.kind.as_str(),
+                &document.content_type,
+                &document.size_bytes,
+                &document.width,
+                &document.height,
+                &document.sha256,
+                &document.storage_key,
+                &document.uploaded_by,
+            ],
+        )
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(Some((row_to_document(&row), replaced)))
+}
+
+/// Locks the KYC record, so it cannot go into review until the transaction
+/// ends, and reports whether it still takes documents.
+async fn lock_pending_record<C: GenericClient>(client: &C, kyc_id: i32) -> Result<bool, Error> {
+    let row = client
+        .query_opt(
+            "SELECT verification_status FROM kyc_records WHERE id = $1 FOR UPDATE",
+            &[&kyc_id],
+        )
+        .await?;
+
+    Ok(row.is_some_and(|row| row.get::<_, KycStatus>(0) == KycStatus::Pending))
+}
+
+fn row_to_document(row: &Row) -> KycDocument {
+    let kind: String = row.get(2);
+
+    KycDocument {
+        id: row.get(0),
+        kyc_id: row.get(1),
+        kind: kind.parse().expect("kind is checked by the table"),
+        content_type: row.get(3),
+        size_bytes: row.get(4),
+        width: row.get(5),
+        height: row.get(6),
+        sha256: row.get(7),
+        storage_key: row.get(8),
+        uploaded_by: row.get(9),
+        created_at: row.get(10),
+    }
+}
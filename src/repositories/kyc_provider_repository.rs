@@ -0,0 +1,178 @@
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
+
+use crate::models::kyc_models::KycStatus;
+use crate::models::kyc_provider_models::{KycDecision, KycProviderCheck};
+use crate::repositories::notification_repository;
+
+const CHECK_COLUMNS: &str = "id, kyc_id, provider, reference, status, rejection_reason, rejection_note, created_at, completed_at";
+
+/// Records that a submission was handed to `provider` under `reference`,
+/// moving it from pending into review. Returns `None` if the record is no
+/// longer the user's current pending submission.
+pub async fn start_check(
+    client: &mut Client,
+    kyc_id: i32,
+    provider: &str,
+    reference: &str,
+) -> Result<Option<KycProviderCheck>, Error> {
+    let transaction = client.transaction().await?;
+
+    let moved = transaction
+        .execute(
+            "UPDATE kyc_records SET verification_status = $1, updated_at = NOW()
+            WHERE id = $2 AND verification_status = $3 AND is_current",
+            &[&KycStatus::InReview, &kyc_id, &KycStatus::Pending],
+        )
+        .await?;
+    if moved == 0 {
+        return Ok(None);
+    }
+
+    let row = transaction
+        .query_one(
+            &format!(
+                "INSERT INTO kyc_provider_checks (kyc_id, provider, reference)
+                VALUES ($1, $2, $3)
+                RETURNING {}",
+                CHECK_COLUMNS
+            ),
+            &[&kyc_id, &provider, &reference],
+        )
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(Some(row_to_check(&row)))
+}
+
+pub async fn get_by_reference(
+    client: &Client,
+    provider: &str,
+    reference: &str,
+) -> Result<Option<KycProviderCheck>, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM kyc_provider_checks WHERE provider = $1 AND reference = $2",
+                CHECK_COLUMNS
+            ),
+            &[&provider, &reference],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_check))
+}
+
+pub async fn is_event_processed(client: &Client, event_id: &str) -> Result<bool, Error> {
+    let row = client
+        .query_opt(
+            "SELECT 1 FROM kyc_webhook_events WHERE event_id = $1",
+            &[&event_id],
+        )
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// Applies the provider's decision on a check, as delivered by webhook
+/// event `event_id`: the event is recorded, the check completed, and the
+/// record moved out of review, telling the user about a rejection. A record
+/// a reviewer decided on in the meantime is left as it is. Returns `None` if
+/// the event, or another one for the same check, was already processed.
+pub async fn complete_check(
+    client: &mut Client,
+    check_id: i32,
+    event_id: &str,
+    decision: &KycDecision,
+) -> Result<Option<KycProviderCheck>, Error> {
+    let transaction = client.transaction().await?;
+
+    let recorded = transaction
+        .execute(
+            "INSERT INTO kyc_webhook_events (event_id, check_id) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+            &[&event_id, &check_id],
+        )
+        .await?;
+    if recorded == 0 {
+        return Ok(None);
+    }
+
+    let (reason, note) = match decision {
+        KycDecision::Approved => (None, None),
+        KycDecision::Rejected { reason, note } => (Some(*reason), note.as_deref()),
+    };
+    let Some(row) = transaction
+        .query_opt(
+            &format!(
+                "UPDATE kyc_provider_checks
+                SET status = $1, rejection_reason = $2, rejection_note = $3, completed_at = NOW()
+                WHERE id = $4 AND status = 'pending'
+                RETURNING {}",
+                CHECK_COLUMNS
+            ),
+            &[
+                &decision.check_status().as_str(),
+                &reason.map(|reason| reason.as_str()),
+                &note,
+                &check_id,
+            ],
+        )
+        .await?
+    else {
+        return Ok(None);
+    };
+    let check = row_to_check(&row);
+
+    let user_id: Option<i32> = transaction
+        .query_opt(
+            "UPDATE kyc_records
+            SET verification_status = $1, rejection_reason = $2, rejection_note = $3, updated_at = NOW()
+            WHERE id = $4 AND verification_status = $5
+            RETURNING user_id",
+            &[
+                &decision.kyc_status(),
+                &reason.map(|reason| reason.as_str()),
+                &note,
+                &check.kyc_id,
+                &KycStatus::InReview,
+            ],
+        )
+        .await?
+        .map(|row| row.get(0));
+    match (user_id, reason) {
+        (Some(user_id), Some(reason)) => {
+            notification_repository::create(&transaction, &reason.notification(user_id)).await?;
+        }
+        (Some(_), None) => {}
+        (None, _) => log::warn!(
+            "KYC record {} left review before provider check {} came back",
+            check.kyc_id,
+            check.id
+        ),
+    }
+
+    transaction.commit().await?;
+
+    Ok(Some(check))
+}
+
+fn row_to_check(row: &Row) -> KycProviderCheck {
+    let status: String = row.get(4);
+    KycProviderCheck {
+        id: row.get(0),
+        kyc_id: row.get(1),
+        provider: row.get(2),
+        reference: row.get(3),
+        status: status.parse().expect("status is checked by the table"),
+        rejection_reason: row.get::<_, Option<String>>(5).map(|reason| {
+            reason
+                .parse()
+                .expect("rejection reason is checked by the table")
+        }),
+        rejection_note: row.get(6),
+        created_at: row.get(7),
+        completed_at: row.get(8),
+    }
+}
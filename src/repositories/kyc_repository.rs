@@ -1,150 +1,454 @@
 use chrono::Utc;
 use deadpool_postgres::Client;
+use tokio_postgres::Row;
 use tokio_postgres::error::Error;
 
-use crate::models::kyc_models::{CreateKycRequest, KycRecord};
+use crate::models::kyc_models::{
+    CreateKycRequest, KycFraudSignal, KycFraudSignalKind, KycRecord, KycRejectionReason, KycStatus,
+    normalize_id_number, normalize_id_type,
+};
+use crate::utils::blind_index::BlindIndexKey;
+use crate::utils::envelope::{EnvelopeError, Keyring};
 
-pub async fn create_kyc(client: &Client, kyc_request: &CreateKycRequest) -> Result<KycRecord, Error> {
-    let statement = client
-        .prepare(
-            "INSERT INTO kyc_records (user_id, full_name, date_of_birth, id_type, id_number, address, verification_status, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, user_id, full_name, date_of_birth, id_type, id_number, address, verification_status, created_at, updated_at",
+// The personal details come last: either the plaintext of a record written
+// before encryption, or the wrapped data key and the sealed fields.
+const KYC_COLUMNS: &str = "id, user_id, id_type, verification_status, rejection_reason, rejection_note, created_at, updated_at, is_current, full_name, date_of_birth, id_number, address, master_key_id, data_key, encrypted_full_name, encrypted_date_of_birth, encrypted_id_number, encrypted_address";
+
+/// Names the sealed fields are bound to, in `KYC_COLUMNS` order.
+const SEALED_FIELDS: [&str; 4] = ["full_name", "date_of_birth", "id_number", "address"];
+
+/// A KYC record can also fail to decrypt, on top of the database failing.
+#[derive(Debug, thiserror::Error)]
+pub enum KycRepositoryError {
+    #[error("Database error: {0}")]
+    Database(#[from] Error),
+    #[error("KYC record {id} could not be decrypted: {source}")]
+    Decryption { id: i32, source: EnvelopeError },
+}
+
+/// Stores a new submission with its personal details encrypted, and makes
+/// it the user's current one in place of `replaces`, which is kept. Returns
+/// `None` if the current submission is no longer `replaces`. If other users
+/// already submitted the same identity document, each of their records and
+/// the new one get a duplicate identity fraud signal, but the submission
+/// itself goes ahead.
+pub async fn create_kyc(
+    client: &mut Client,
+    keyring: &Keyring,
+    blind_index: &BlindIndexKey,
+    kyc_request: &CreateKycRequest,
+    replaces: Option<i32>,
+) -> Result<Option<KycRecord>, KycRepositoryError> {
+    let transaction = client.transaction().await?;
+
+    let replaced: Option<i32> = transaction
+        .query_opt(
+            "UPDATE kyc_records SET is_current = FALSE
+            WHERE user_id = $1 AND is_current
+            RETURNING id",
+            &[&kyc_request.user_id],
+        )
+        .await?
+        .map(|row| row.get(0));
+    if replaced != replaces {
+        return Ok(None);
+    }
+
+    // Submissions of the same document wait for each other, so two at once
+    // still find each other
+    let id_number_index =
+        id_number_index(blind_index, &kyc_request.id_type, &kyc_request.id_number);
+    let lock = i64::from_be_bytes(
+        id_number_index[..8]
+            .try_into()
+            .expect("indexes are 32 bytes"),
+    );
+    transaction
+        .execute("SELECT pg_advisory_xact_lock($1)", &[&lock])
+        .await?;
+    let duplicates: Vec<i32> = transaction
+        .query(
+            "SELECT id FROM kyc_records WHERE id_number_index = $1 AND user_id <> $2",
+            &[&id_number_index, &kyc_request.user_id],
         )
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let statement = transaction
+        .prepare(&format!(
+            "INSERT INTO kyc_records (user_id, id_type, verification_status, created_at, master_key_id, data_key,
+                encrypted_full_name, encrypted_date_of_birth, encrypted_id_number, encrypted_address, id_number_index,
+                is_current)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, TRUE)
+            ON CONFLICT (user_id) WHERE is_current DO NOTHING
+            RETURNING {}",
+            KYC_COLUMNS
+        ))
         .await?;
 
-    // Default verification status is "pending"
-    let verification_status = "pending".to_string();
+    // A new submission waits for a reviewer
+    let verification_status = KycStatus::Pending;
     let created_at = Utc::now();
+    let (data_key, wrapped_key) = keyring.new_data_key();
+    let sealed: Vec<Vec<u8>> = SEALED_FIELDS
+        .iter()
+        .zip([
+            &kyc_request.full_name,
+            &kyc_request.date_of_birth,
+            &kyc_request.id_number,
+            &kyc_request.address,
+        ])
+        .map(|(field, value)| data_key.seal(field, value))
+        .collect();
 
-    let row = client
-        .query_one(
+    // A first submission made at the same time wins
+    let Some(row) = transaction
+        .query_opt(
             &statement,
             &[
                 &kyc_request.user_id,
-                &kyc_request.full_name,
-                &kyc_request.date_of_birth,
                 &kyc_request.id_type,
-                &kyc_request.id_number,
-                &kyc_request.address,
                 &verification_status,
                 &created_at,
+                &keyring.current_key_id(),
+                &wrapped_key,
+                &sealed[0],
+                &sealed[1],
+                &sealed[2],
+                &sealed[3],
+                &id_number_index,
             ],
         )
-        .await?;
+        .await?
+    else {
+        return Ok(None);
+    };
+    let kyc = row_to_kyc(&row, keyring)?;
 
-    Ok(KycRecord {
-        id: row.get(0),
-        user_id: row.get(1),
-        full_name: row.get(2),
-        date_of_birth: row.get(3),
-        id_type: row.get(4),
-        id_number: row.get(5),
-        address: row.get(6),
-        verification_status: row.get(7),
-        created_at: row.get(8),
-        updated_at: row.get(9),
-    })
+    for duplicate in &duplicates {
+        transaction
+            .execute(
+                "INSERT INTO kyc_fraud_signals (kyc_id, kind, related_kyc_id)
+                VALUES ($1, $2, $3), ($3, $2, $1)
+                ON CONFLICT DO NOTHING",
+                &[
+                    &kyc.id,
+                    &KycFraudSignalKind::DuplicateIdentity.as_str(),
+                    duplicate,
+                ],
+            )
+            .await?;
+    }
+
+    transaction.commit().await?;
+
+    if !duplicates.is_empty() {
+        log::warn!(
+            "KYC record {} shares its identity document with records {:?}",
+            kyc.id,
+            duplicates
+        );
+    }
+
+    Ok(Some(kyc))
 }
 
+/// Moves the record on from `current`, setting or clearing the rejection
+/// reason with it. Returns `None` if the record is gone or no longer in
+/// `current`.
 pub async fn update_kyc_verification_status(
     client: &Client,
+    keyring: &Keyring,
     kyc_id: i32,
-    verification_status: &str,
-) -> Result<KycRecord, Error> {
+    current: KycStatus,
+    next: KycStatus,
+    rejection_reason: Option<KycRejectionReason>,
+    rejection_note: Option<&str>,
+) -> Result<Option<KycRecord>, KycRepositoryError> {
     let statement = client
-        .prepare(
+        .prepare(&format!(
             "UPDATE kyc_records
-            SET verification_status = $1, updated_at = $2
-            WHERE id = $3
-            RETURNING id, user_id, full_name, date_of_birth, id_type, id_number, address, verification_status, created_at, updated_at",
-        )
+            SET verification_status = $1, rejection_reason = $2, rejection_note = $3, updated_at = $4
+            WHERE id = $5 AND verification_status = $6
+            RETURNING {}",
+            KYC_COLUMNS
+        ))
         .await?;
 
     let updated_at = Utc::now();
+    let rejection_reason = rejection_reason.map(|reason| reason.as_str());
 
     let row = client
-        .query_one(&statement, &[&verification_status, &updated_at, &kyc_id])
+        .query_opt(
+            &statement,
+            &[
+                &next,
+                &rejection_reason,
+                &rejection_note,
+                &updated_at,
+                &kyc_id,
+                &current,
+            ],
+        )
         .await?;
 
-    Ok(KycRecord {
-        id: row.get(0),
-        user_id: row.get(1),
-        full_name: row.get(2),
-        date_of_birth: row.get(3),
-        id_type: row.get(4),
-        id_number: row.get(5),
-        address: row.get(6),
-        verification_status: row.get(7),
-        created_at: row.get(8),
-        updated_at: row.get(9),
-    })
+    row.map(|row| row_to_kyc(&row, keyring)).transpose()
 }
 
-pub async fn get_kyc_by_id(client: &Client, kyc_id: i32) -> Result<KycRecord, Error> {
+pub async fn get_kyc_by_id(
+    client: &Client,
+    keyring: &Keyring,
+    kyc_id: i32,
+) -> Result<Option<KycRecord>, KycRepositoryError> {
     let statement = client
-        .prepare(
-            "SELECT id, user_id, full_name, date_of_birth, id_type, id_number, address, verification_status, created_at, updated_at
+        .prepare(&format!(
+            "SELECT {}
             FROM kyc_records
             WHERE id = $1",
-        )
+            KYC_COLUMNS
+        ))
         .await?;
 
-    let row = client.query_one(&statement, &[&kyc_id]).await?;
+    let row = client.query_opt(&statement, &[&kyc_id]).await?;
 
-    Ok(KycRecord {
-        id: row.get(0),
-        user_id: row.get(1),
-        full_name: row.get(2),
-        date_of_birth: row.get(3),
-        id_type: row.get(4),
-        id_number: row.get(5),
-        address: row.get(6),
-        verification_status: row.get(7),
-        created_at: row.get(8),
-        updated_at: row.get(9),
-    })
+    row.map(|row| row_to_kyc(&row, keyring)).transpose()
 }
 
-pub async fn get_kyc_by_user_id(client: &Client, user_id: i32) -> Result<KycRecord, Error> {
+/// The user's current submission.
+pub async fn get_kyc_by_user_id(
+    client: &Client,
+    keyring: &Keyring,
+    user_id: i32,
+) -> Result<Option<KycRecord>, KycRepositoryError> {
     let statement = client
-        .prepare(
-            "SELECT id, user_id, full_name, date_of_birth, id_type, id_number, address, verification_status, created_at, updated_at
+        .prepare(&format!(
+            "SELECT {}
             FROM kyc_records
-            WHERE user_id = $1",
-        )
+            WHERE user_id = $1 AND is_current",
+            KYC_COLUMNS
+        ))
         .await?;
 
-    let row = client.query_one(&statement, &[&user_id]).await?;
+    let row = client.query_opt(&statement, &[&user_id]).await?;
 
-    Ok(KycRecord {
-        id: row.get(0),
-        user_id: row.get(1),
-        full_name: row.get(2),
-        date_of_birth: row.get(3),
-        id_type: row.get(4),
-        id_number: row.get(5),
-        address: row.get(6),
-        verification_status: row.get(7),
-        created_at: row.get(8),
-        updated_at: row.get(9),
-    })
+    row.map(|row| row_to_kyc(&row, keyring)).transpose()
+}
+
+/// Every submission of the user, the current one and those it replaced,
+/// newest first.
+pub async fn get_kyc_submissions(
+    client: &Client,
+    keyring: &Keyring,
+    user_id: i32,
+) -> Result<Vec<KycRecord>, KycRepositoryError> {
+    let statement = client
+        .prepare(&format!(
+            "SELECT {}
+            FROM kyc_records
+            WHERE user_id = $1
+            ORDER BY id DESC",
+            KYC_COLUMNS
+        ))
+        .await?;
+
+    let rows = client.query(&statement, &[&user_id]).await?;
+
+    rows.iter().map(|row| row_to_kyc(row, keyring)).collect()
 }
 
+/// Only the current submission counts, so a verification that was since
+/// replaced does not.
 pub async fn is_kyc_verified(client: &Client, user_id: &i32) -> Result<bool, Error> {
     let statement = client
         .prepare(
             "SELECT verification_status 
             FROM kyc_records 
-            WHERE user_id = $1",
+            WHERE user_id = $1 AND is_current",
         )
         .await?;
 
     match client.query_opt(&statement, &[user_id]).await? {
         Some(row) => {
-            let status: String = row.get(0);
-            Ok(status == "verified")
+            let status: KycStatus = row.get(0);
+            Ok(status == KycStatus::Verified)
         }
         None => Ok(false), // User has no KYC record, so not verified
     }
-} 
\ No newline at end of file
+}
+
+/// Fraud signals for reviewers, newest first, optionally only those
+/// raised on one record.
+pub async fn get_fraud_signals(
+    client: &Client,
+    kyc_id: Option<i32>,
+) -> Result<Vec<KycFraudSignal>, Error> {
+    let rows = client
+        .query(
+            "SELECT id, kyc_id, kind, related_kyc_id, created_at
+            FROM kyc_fraud_signals
+            WHERE $1::INTEGER IS NULL OR kyc_id = $1
+            ORDER BY id DESC",
+            &[&kyc_id],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let kind: String = row.get(2);
+            KycFraudSignal {
+                id: row.get(0),
+                kyc_id: row.get(1),
+                kind: kind.parse().expect("kind is checked by the table"),
+                related_kyc_id: row.get(3),
+                created_at: row.get(4),
+            }
+        })
+        .collect())
+}
+
+/// Moves up to `limit` records onto the current master key: records still
+/// in plaintext get a data key and have their fields sealed, and the data
+/// keys of records under an older master key are wrapped again. Records
+/// without an ID number index get one on the way. Records under a key that
+/// is no longer configured are left alone. Returns how many records were
+/// changed.
+pub async fn reencrypt_batch(
+    client: &mut Client,
+    keyring: &Keyring,
+    blind_index: &BlindIndexKey,
+    limit: i64,
+) -> Result<usize, KycRepositoryError> {
+    let transaction = client.transaction().await?;
+
+    let current = keyring.current_key_id();
+    let older: Vec<String> = keyring.key_ids().split_off(1);
+    let rows = transaction
+        .query(
+            "SELECT id, master_key_id, data_key, id_type, full_name, date_of_birth, id_number, address,
+                encrypted_id_number, id_number_index IS NULL
+            FROM kyc_records
+            WHERE master_key_id IS NULL
+                OR master_key_id = ANY($1)
+                OR (master_key_id = $2 AND id_number_index IS NULL)
+            ORDER BY id
+            LIMIT $3
+            FOR UPDATE SKIP LOCKED",
+            &[&older, &current, &limit],
+        )
+        .await?;
+
+    for row in &rows {
+        let id: i32 = row.get(0);
+        let id_type: &str = row.get(3);
+        match row.get::<_, Option<String>>(1) {
+            Some(master_key_id) => {
+                let decryption = |source| KycRepositoryError::Decryption { id, source };
+                let wrapped_key: Vec<u8> = row.get(2);
+                let index = if row.get(9) {
+                    let data_key = keyring
+                        .unwrap_data_key(&master_key_id, &wrapped_key)
+                        .map_err(decryption)?;
+                    let id_number = data_key.open("id_number", row.get(8)).map_err(decryption)?;
+                    Some(id_number_index(blind_index, id_type, &id_number))
+                } else {
+                    None
+                };
+                let wrapped_key = keyring
+                    .rewrap(&master_key_id, &wrapped_key)
+                    .map_err(decryption)?;
+                transaction
+                    .execute(
+                        "UPDATE kyc_records
+                        SET master_key_id = $1, data_key = $2, id_number_index = COALESCE($3, id_number_index)
+                        WHERE id = $4",
+                        &[&current, &wrapped_key, &index, &id],
+                    )
+                    .await?;
+            }
+            None => {
+                let (data_key, wrapped_key) = keyring.new_data_key();
+                let sealed: Vec<Vec<u8>> = SEALED_FIELDS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| data_key.seal(field, row.get::<_, &str>(i + 4)))
+                    .collect();
+                let index = id_number_index(blind_index, id_type, row.get(6));
+                transaction
+                    .execute(
+                        "UPDATE kyc_records
+                        SET master_key_id = $1, data_key = $2,
+                            encrypted_full_name = $3, encrypted_date_of_birth = $4,
+                            encrypted_id_number = $5, encrypted_address = $6,
+                            full_name = NULL, date_of_birth = NULL, id_number = NULL, address = NULL,
+                            id_number_index = $7
+                        WHERE id = $8",
+                        &[
+                            &current,
+                            &wrapped_key,
+                            &sealed[0],
+                            &sealed[1],
+                            &sealed[2],
+                            &sealed[3],
+                            &index,
+                            &id,
+                        ],
+                    )
+                    .await?;
+            }
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(rows.len())
+}
+
+fn id_number_index(blind_index: &BlindIndexKey, id_type: &str, id_number: &str) -> Vec<u8> {
+    blind_index.index(
+        &normalize_id_type(id_type),
+        &normalize_id_number(id_type, id_number),
+    )
+}
+
+fn row_to_kyc(row: &Row, keyring: &Keyring) -> Result<KycRecord, KycRepositoryError> {
+    let id: i32 = row.get(0);
+    let [full_name, date_of_birth, id_number, address] = match row.get::<_, Option<String>>(13) {
+        None => [row.get(9), row.get(10), row.get(11), row.get(12)],
+        Some(master_key_id) => {
+            let open = || {
+                let data_key = keyring.unwrap_data_key(&master_key_id, row.get(14))?;
+                let open_field = |i: usize| data_key.open(SEALED_FIELDS[i], row.get(15 + i));
+                Ok([
+                    open_field(0)?,
+                    open_field(1)?,
+                    open_field(2)?,
+                    open_field(3)?,
+                ])
+            };
+            open().map_err(|source| KycRepositoryError::Decryption { id, source })?
+        }
+    };
+
+    Ok(KycRecord {
+        id,
+        user_id: row.get(1),
+        full_name,
+        date_of_birth,
+        id_type: row.get(2),
+        id_number,
+        address,
+        verification_status: row.get(3),
+        rejection_reason: row.get::<_, Option<String>>(4).map(|reason| {
+            reason
+                .parse()
+                .expect("rejection reason is checked by the table")
+        }),
+        rejection_note: row.get(5),
+        created_at: row.get(6),
+        updated_at: row.get(7),
+        is_current: row.get(8),
+    })
+}
@@ -1,5 +1,18 @@
 pub mod activity_repository;
+pub mod auth_nonce_repository;
+pub mod beneficiary_repository;
+pub mod check_in_repository;
+pub mod claim_dispute_repository;
+pub mod claim_evidence_repository;
 pub mod claim_repository;
+pub mod execution_repository;
+pub mod faq_repository;
+pub mod guardian_repository;
+pub mod kyc_document_repository;
+pub mod kyc_provider_repository;
 pub mod kyc_repository;
 pub mod notification_repository;
+pub mod plan_repository;
+pub mod support_ticket_repository;
+pub mod user_repository;
 pub mod withdrawal_history_repository;
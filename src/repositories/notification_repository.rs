@@ -1,91 +1,89 @@
 use crate::models::notification::{CreateNotification, Notification, UpdateNotification};
-use deadpool_postgres::Client;
-use tokio_postgres::Error;
-// use chrono::Utc;
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
 
-pub async fn get_all(client: &Client) -> Result<Vec<Notification>, Error> {
+const NOTIFICATION_COLUMNS: &str = "id, user_id, title, body, is_read, created_at, updated_at";
+
+/// The notifications sent to `user_id`, and announcements to everyone.
+pub async fn get_for_user(client: &Client, user_id: i32) -> Result<Vec<Notification>, Error> {
     let stmt = client
-        .prepare("SELECT id, title, body, is_read, created_at, updated_at FROM notifications")
+        .prepare(&format!(
+            "SELECT {} FROM notifications
+             WHERE user_id = $1 OR user_id IS NULL
+             ORDER BY created_at DESC, id DESC",
+            NOTIFICATION_COLUMNS
+        ))
         .await?;
-    let rows = client.query(&stmt, &[]).await?;
-
-    Ok(rows
-        .iter()
-        .map(|row| Notification {
-            id: row.get(0),
-            title: row.get(1),
-            body: row.get(2),
-            is_read: row.get(3),
-            created_at: row.get(4),
-            updated_at: row.get(5),
-        })
-        .collect())
+    let rows = client.query(&stmt, &[&user_id]).await?;
+
+    Ok(rows.iter().map(row_to_notification).collect())
 }
 
-pub async fn get_by_id(client: &Client, id: i32) -> Result<Notification, Error> {
-    let stmt = client.prepare("SELECT id, title, body, is_read, created_at, updated_at FROM notifications WHERE id = $1").await?;
-    let row = client.query_one(&stmt, &[&id]).await?;
+/// Returns `None` unless the notification was sent to `user_id` or everyone.
+pub async fn get_by_id(
+    client: &Client,
+    id: i32,
+    user_id: i32,
+) -> Result<Option<Notification>, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "SELECT {} FROM notifications
+             WHERE id = $1 AND (user_id = $2 OR user_id IS NULL)",
+            NOTIFICATION_COLUMNS
+        ))
+        .await?;
+    let row = client.query_opt(&stmt, &[&id, &user_id]).await?;
 
-    Ok(Notification {
-        id: row.get(0),
-        title: row.get(1),
-        body: row.get(2),
-        is_read: row.get(3),
-        created_at: row.get(4),
-        updated_at: row.get(5),
-    })
+    Ok(row.as_ref().map(row_to_notification))
 }
 
-pub async fn create(
-    client: &Client,
+pub async fn create<C: GenericClient>(
+    client: &C,
     notification: &CreateNotification,
 ) -> Result<Notification, Error> {
     let stmt = client
-        .prepare(
-            "
-        INSERT INTO notifications (title, body) 
-        VALUES ($1, $2) 
-        RETURNING id, title, body, is_read, created_at, updated_at
-    ",
-        )
+        .prepare(&format!(
+            "INSERT INTO notifications (user_id, title, body)
+             VALUES ($1, $2, $3)
+             RETURNING {}",
+            NOTIFICATION_COLUMNS
+        ))
         .await?;
 
     let row = client
-        .query_one(&stmt, &[&notification.title, &notification.body])
+        .query_one(
+            &stmt,
+            &[
+                &notification.user_id,
+                &notification.title,
+                &notification.body,
+            ],
+        )
         .await?;
 
-    Ok(Notification {
-        id: row.get(0),
-        title: row.get(1),
-        body: row.get(2),
-        is_read: row.get(3),
-        created_at: row.get(4),
-        updated_at: row.get(5),
-    })
+    Ok(row_to_notification(&row))
 }
 
 pub async fn update(
     client: &Client,
     id: i32,
     notification: &UpdateNotification,
-) -> Result<Notification, Error> {
+) -> Result<Option<Notification>, Error> {
     let stmt = client
-        .prepare(
-            "
-        UPDATE notifications 
-        SET 
-            title = COALESCE($1, title), 
-            body = COALESCE($2, body), 
-            is_read = COALESCE($3, is_read),
-            updated_at = NOW()
-        WHERE id = $4
-        RETURNING id, title, bod, is_read, created_at, updated_at
-    ",
-        )
+        .prepare(&format!(
+            "UPDATE notifications
+             SET title = COALESCE($1, title),
+                 body = COALESCE($2, body),
+                 is_read = COALESCE($3, is_read),
+                 updated_at = NOW()
+             WHERE id = $4
+             RETURNING {}",
+            NOTIFICATION_COLUMNS
+        ))
         .await?;
 
     let row = client
-        .query_one(
+        .query_opt(
             &stmt,
             &[
                 &notification.title,
@@ -96,14 +94,7 @@ pub async fn update(
         )
         .await?;
 
-    Ok(Notification {
-        id: row.get(0),
-        title: row.get(1),
-        body: row.get(2),
-        is_read: row.get(3),
-        created_at: row.get(4),
-        updated_at: row.get(5),
-    })
+    Ok(row.as_ref().map(row_to_notification))
 }
 
 pub async fn delete(client: &Client, id: i32) -> Result<(), Error> {
@@ -114,26 +105,35 @@ pub async fn delete(client: &Client, id: i32) -> Result<(), Error> {
     Ok(())
 }
 
-pub async fn mark_as_read(client: &Client, id: i32) -> Result<Notification, Error> {
+/// Returns `None` unless the notification was sent to `user_id` or everyone.
+pub async fn mark_as_read(
+    client: &Client,
+    id: i32,
+    user_id: i32,
+) -> Result<Option<Notification>, Error> {
     let stmt = client
-        .prepare(
-            "
-        UPDATE notifications 
-        SET is_read = TRUE 
-        WHERE id = $1 
-        RETURNING id, title, body, is_read, created_at, updated_at
-    ",
-        )
+        .prepare(&format!(
+            "UPDATE notifications
+             SET is_read = TRUE
+             WHERE id = $1 AND (user_id = $2 OR user_id IS NULL)
+             RETURNING {}",
+            NOTIFICATION_COLUMNS
+        ))
         .await?;
 
-    let row = client.query_one(&stmt, &[&id]).await?;
+    let row = client.query_opt(&stmt, &[&id, &user_id]).await?;
+
+    Ok(row.as_ref().map(row_to_notification))
+}
 
-    Ok(Notification {
+fn row_to_notification(row: &Row) -> Notification {
+    Notification {
         id: row.get(0),
-        title: row.get(1),
-        body: row.get(2),
-        is_read: row.get(3),
-        created_at: row.get(4),
-        updated_at: row.get(5),
-    })
+        user_id: row.get(1),
+        title: row.get(2),
+        body: row.get(3),
+        is_read: row.get(4),
+        created_at: row.get(5),
+        updated_at: row.get(6),
+    }
 }
@@ -0,0 +1,164 @@
+use crate::models::plan_models::{CreatePlan, Plan, PlanAsset, PlanStatus, UpdatePlan};
+use crate::repositories::beneficiary_repository;
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::types::Json;
+use tokio_postgres::{Error, Row};
+
+const PLAN_COLUMNS: &str =
+    "id, owner_id, name, description, assets, status, created_at, updated_at";
+
+pub async fn get_all(client: &Client) -> Result<Vec<Plan>, Error> {
+    let stmt = client
+        .prepare(&format!("SELECT {} FROM plans ORDER BY id", PLAN_COLUMNS))
+        .await?;
+
+    let rows = client.query(&stmt, &[]).await?;
+
+    with_beneficiaries(client, rows).await
+}
+
+pub async fn get_by_owner(client: &Client, owner_id: i32) -> Result<Vec<Plan>, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "SELECT {} FROM plans WHERE owner_id = $1 ORDER BY id",
+            PLAN_COLUMNS
+        ))
+        .await?;
+
+    let rows = client.query(&stmt, &[&owner_id]).await?;
+
+    with_beneficiaries(client, rows).await
+}
+
+pub async fn get_by_id(client: &Client, id: i32) -> Result<Option<Plan>, Error> {
+    let stmt = client
+        .prepare(&format!("SELECT {} FROM plans WHERE id = $1", PLAN_COLUMNS))
+        .await?;
+
+    let rows = client.query(&stmt, &[&id]).await?;
+
+    Ok(with_beneficiaries(client, rows).await?.pop())
+}
+
+pub async fn create(client: &mut Client, owner_id: i32, plan: &CreatePlan) -> Result<Plan, Error> {
+    let transaction = client.transaction().await?;
+
+    let row = transaction
+        .query_one(
+            &format!(
+                "INSERT INTO plans (owner_id, name, description, assets, status)
+                 VALUES ($1, $2, $3, $4, $5)
+                 RETURNING {}",
+                PLAN_COLUMNS
+            ),
+            &[
+                &owner_id,
+                &plan.name,
+                &plan.description,
+                &Json(&plan.assets),
+                &PlanStatus::Draft,
+            ],
+        )
+        .await?;
+
+    let mut created = row_to_plan(&row);
+    created.beneficiaries =
+        beneficiary_repository::insert_all(&transaction, created.id, &plan.beneficiaries).await?;
+
+    transaction.commit().await?;
+
+    Ok(created)
+}
+
+pub async fn update(client: &mut Client, id: i32, plan: &UpdatePlan) -> Result<Plan, Error> {
+    let transaction = client.transaction().await?;
+
+    let row = transaction
+        .query_one(
+            &format!(
+                "UPDATE plans
+                 SET
+                    name = COALESCE($1, name),
+                    description = COALESCE($2, description),
+                    assets = COALESCE($3, assets),
+                    updated_at = NOW()
+                 WHERE id = $4
+                 RETURNING {}",
+                PLAN_COLUMNS
+            ),
+            &[
+                &plan.name,
+                &plan.description,
+                &plan.assets.as_ref().map(Json),
+                &id,
+            ],
+        )
+        .await?;
+
+    let mut updated = row_to_plan(&row);
+
+    updated.beneficiaries = match &plan.beneficiaries {
+        Some(beneficiaries) => {
+            transaction
+                .execute("DELETE FROM beneficiaries WHERE plan_id = $1", &[&id])
+                .await?;
+            beneficiary_repository::insert_all(&transaction, id, beneficiaries).await?
+        }
+        None => beneficiary_repository::get_by_plans(&transaction, &[id]).await?,
+    };
+
+    transaction.commit().await?;
+
+    Ok(updated)
+}
+
+/// Moves a plan from `current` to `next`, unless its status has changed in the
+/// meantime. Returns whether the plan was updated.
+pub async fn transition_status<C: GenericClient>(
+    client: &C,
+    id: i32,
+    current: PlanStatus,
+    next: PlanStatus,
+) -> Result<bool, Error> {
+    let updated = client
+        .execute(
+            "UPDATE plans SET status = $1, updated_at = NOW() WHERE id = $2 AND status = $3",
+            &[&next, &id, &current],
+        )
+        .await?;
+    Ok(updated == 1)
+}
+
+pub async fn delete(client: &Client, id: i32) -> Result<(), Error> {
+    let stmt = client.prepare("DELETE FROM plans WHERE id = $1").await?;
+    client.execute(&stmt, &[&id]).await?;
+    Ok(())
+}
+
+async fn with_beneficiaries(client: &Client, rows: Vec<Row>) -> Result<Vec<Plan>, Error> {
+    let mut plans: Vec<Plan> = rows.iter().map(row_to_plan).collect();
+    let ids: Vec<i32> = plans.iter().map(|plan| plan.id).collect();
+
+    for beneficiary in beneficiary_repository::get_by_plans(client, &ids).await? {
+        if let Some(plan) = plans.iter_mut().find(|p| p.id == beneficiary.plan_id) {
+            plan.beneficiaries.push(beneficiary);
+        }
+    }
+
+    Ok(plans)
+}
+
+fn row_to_plan(row: &Row) -> Plan {
+    let assets: Json<Vec<PlanAsset>> = row.get(4);
+    Plan {
+        id: row.get(0),
+        owner_id: row.get(1),
+        name: row.get(2),
+        description: row.get(3),
+        assets: assets.0,
+        beneficiaries: Vec::new(),
+        status: row.get(5),
+        created_at: row.get(6),
+        updated_at: row.get(7),
+    }
+}
@@ -0,0 +1,218 @@
+use crate::models::support_models::{
+    CreateTicket, ReplyAuthor, SupportTicket, TicketReply, TicketStatus, UpdateTicket,
+};
+use deadpool_postgres::Client;
+use tokio_postgres::{Error, Row};
+
+const TICKET_COLUMNS: &str = "id, user_id, subject, description, category, priority, status, \
+     assignee_id, created_at, updated_at";
+
+const REPLY_COLUMNS: &str = "id, ticket_id, author_id, author_role, body, created_at";
+
+pub async fn create(
+    client: &Client,
+    user_id: i32,
+    ticket: &CreateTicket,
+) -> Result<SupportTicket, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "INSERT INTO support_tickets (user_id, subject, description, category, priority)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING {}",
+            TICKET_COLUMNS
+        ))
+        .await?;
+
+    let row = client
+        .query_one(
+            &stmt,
+            &[
+                &user_id,
+                &ticket.subject,
+                &ticket.description,
+                &ticket.category.as_str(),
+                &ticket.priority.as_str(),
+            ],
+        )
+        .await?;
+
+    Ok(row_to_ticket(&row))
+}
+
+/// Lists tickets, newest activity first. `None` filters match everything.
+pub async fn list(
+    client: &Client,
+    user_id: Option<i32>,
+    status: Option<TicketStatus>,
+    assignee_id: Option<i32>,
+    page: i64,
+    page_size: i64,
+) -> Result<(Vec<SupportTicket>, i64), Error> {
+    let status = status.map(|status| status.as_str());
+    let filter = "($1::INTEGER IS NULL OR user_id = $1)
+         AND ($2::VARCHAR IS NULL OR status = $2)
+         AND ($3::INTEGER IS NULL OR assignee_id = $3)";
+
+    // Get total count of tickets for pagination
+    let count_stmt = client
+        .prepare(&format!(
+            "SELECT COUNT(*) FROM support_tickets WHERE {}",
+            filter
+        ))
+        .await?;
+
+    let total_row = client
+        .query_one(&count_stmt, &[&user_id, &status, &assignee_id])
+        .await?;
+
+    let total: i64 = total_row.get(0);
+
+    let offset = (page - 1) * page_size;
+
+    let stmt = client
+        .prepare(&format!(
+            "SELECT {}
+             FROM support_tickets
+             WHERE {}
+             ORDER BY updated_at DESC, id DESC
+             LIMIT $4 OFFSET $5",
+            TICKET_COLUMNS, filter
+        ))
+        .await?;
+
+    let rows = client
+        .query(
+            &stmt,
+            &[&user_id, &status, &assignee_id, &page_size, &offset],
+        )
+        .await?;
+
+    Ok((rows.iter().map(row_to_ticket).collect(), total))
+}
+
+pub async fn get_by_id(client: &Client, id: i32) -> Result<Option<SupportTicket>, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "SELECT {} FROM support_tickets WHERE id = $1",
+            TICKET_COLUMNS
+        ))
+        .await?;
+
+    let row = client.query_opt(&stmt, &[&id]).await?;
+
+    Ok(row.as_ref().map(row_to_ticket))
+}
+
+/// Applies an agent's triage, returning `None` if there is no such ticket.
+pub async fn update(
+    client: &Client,
+    id: i32,
+    update: &UpdateTicket,
+) -> Result<Option<SupportTicket>, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "UPDATE support_tickets
+             SET status = COALESCE($1, status),
+                 priority = COALESCE($2, priority),
+                 assignee_id = COALESCE($3, assignee_id),
+                 updated_at = NOW()
+             WHERE id = $4
+             RETURNING {}",
+            TICKET_COLUMNS
+        ))
+        .await?;
+
+    let row = client
+        .query_opt(
+            &stmt,
+            &[
+                &update.status.map(|status| status.as_str()),
+                &update.priority.map(|priority| priority.as_str()),
+                &update.assignee_id,
+                &id,
+            ],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(row_to_ticket))
+}
+
+pub async fn get_replies(client: &Client, ticket_id: i32) -> Result<Vec<TicketReply>, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "SELECT {} FROM ticket_replies WHERE ticket_id = $1 ORDER BY created_at, id",
+            REPLY_COLUMNS
+        ))
+        .await?;
+
+    let rows = client.query(&stmt, &[&ticket_id]).await?;
+
+    Ok(rows.iter().map(row_to_reply).collect())
+}
+
+/// Adds a reply and moves the ticket to `status` in one transaction.
+pub async fn add_reply(
+    client: &mut Client,
+    ticket_id: i32,
+    author_id: i32,
+    author_role: ReplyAuthor,
+    body: &str,
+    status: TicketStatus,
+) -> Result<TicketReply, Error> {
+    let transaction = client.transaction().await?;
+
+    let row = transaction
+        .query_one(
+            &format!(
+                "INSERT INTO ticket_replies (ticket_id, author_id, author_role, body)
+                 VALUES ($1, $2, $3, $4)
+                 RETURNING {}",
+                REPLY_COLUMNS
+            ),
+            &[&ticket_id, &author_id, &author_role.as_str(), &body],
+        )
+        .await?;
+
+    transaction
+        .execute(
+            "UPDATE support_tickets SET status = $1, updated_at = NOW() WHERE id = $2",
+            &[&status.as_str(), &ticket_id],
+        )
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(row_to_reply(&row))
+}
+
+fn row_to_ticket(row: &Row) -> SupportTicket {
+    let category: &str = row.get(4);
+    let priority: &str = row.get(5);
+    let status: &str = row.get(6);
+    SupportTicket {
+        id: row.get(0),
+        user_id: row.get(1),
+        subject: row.get(2),
+        description: row.get(3),
+        category: category.parse().expect("category is checked by the table"),
+        priority: priority.parse().expect("priority is checked by the table"),
+        status: status.parse().expect("status is checked by the table"),
+        assignee_id: row.get(7),
+        created_at: row.get(8),
+        updated_at: row.get(9),
+    }
+}
+
+fn row_to_reply(row: &Row) -> TicketReply {
+    let author_role: &str = row.get(3);
+    TicketReply {
+        id: row.get(0),
+        ticket_id: row.get(1),
+        author_id: row.get(2),
+        author_role: author_role
+            .parse()
+            .expect("author_role is checked by the table"),
+        body: row.get(4),
+        created_at: row.get(5),
+    }
+}
@@ -0,0 +1,113 @@
+use crate::models::auth_models::{Role, User};
+use deadpool_postgres::{Client, GenericClient};
+use tokio_postgres::{Error, Row};
+
+const USER_COLUMNS: &str = "id, email, wallet_address, roles, created_at";
+
+pub async fn create_user(client: &Client, email: &str, password_hash: &str) -> Result<User, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "INSERT INTO users (email, password_hash)
+             VALUES ($1, $2)
+             RETURNING {}",
+            USER_COLUMNS
+        ))
+        .await?;
+
+    let row = client.query_one(&stmt, &[&email, &password_hash]).await?;
+
+    Ok(row_to_user(&row))
+}
+
+/// Looks a user up by email, returning the stored password hash alongside.
+/// Accounts created through wallet login have no password.
+pub async fn get_credentials_by_email(
+    client: &Client,
+    email: &str,
+) -> Result<Option<(User, Option<String>)>, Error> {
+    let stmt = client
+        .prepare(&format!(
+            "SELECT {}, password_hash
+             FROM users
+             WHERE email = $1",
+            USER_COLUMNS
+        ))
+        .await?;
+
+    let row = client.query_opt(&stmt, &[&email]).await?;
+
+    Ok(row.map(|row| (row_to_user(&row), row.get(5))))
+}
+
+/// Returns the user owning `wallet_address`, creating the account on first
+/// login. The address is expected in normalized form.
+pub async fn get_or_create_by_wallet(client: &Client, wallet_address: &str) -> Result<User, Error> {
+    // The no-op update makes RETURNING yield the existing row on conflict
+    let stmt = client
+        .prepare(&format!(
+            "INSERT INTO users (wallet_address)
+             VALUES ($1)
+             ON CONFLICT (wallet_address) DO UPDATE SET wallet_address = EXCLUDED.wallet_address
+             RETURNING {}",
+            USER_COLUMNS
+        ))
+        .await?;
+
+    let row = client.query_one(&stmt, &[&wallet_address]).await?;
+
+    Ok(row_to_user(&row))
+}
+
+pub async fn get_by_id(client: &Client, id: i32) -> Result<Option<User>, Error> {
+    let stmt = client
+        .prepare(&format!("SELECT {} FROM users WHERE id = $1", USER_COLUMNS))
+        .await?;
+
+    let row = client.query_opt(&stmt, &[&id]).await?;
+
+    Ok(row.as_ref().map(row_to_user))
+}
+
+/// Everyone holding `role` itself. Admins are not included unless `role` is
+/// admin.
+pub async fn get_ids_with_role<C: GenericClient>(
+    client: &C,
+    role: Role,
+) -> Result<Vec<i32>, Error> {
+    let stmt = client
+        .prepare("SELECT id FROM users WHERE $1 = ANY(roles) ORDER BY id")
+        .await?;
+
+    let rows = client.query(&stmt, &[&role.as_str()]).await?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Replaces the user's roles, returning `None` if there is no such user.
+pub async fn set_roles(client: &Client, id: i32, roles: &[Role]) -> Result<Option<User>, Error> {
+    let roles: Vec<&str> = roles.iter().map(Role::as_str).collect();
+    let stmt = client
+        .prepare(&format!(
+            "UPDATE users SET roles = $1
+             WHERE id = $2
+             RETURNING {}",
+            USER_COLUMNS
+        ))
+        .await?;
+
+    let row = client.query_opt(&stmt, &[&roles, &id]).await?;
+
+    Ok(row.as_ref().map(row_to_user))
+}
+
+fn row_to_user(row: &Row) -> User {
+    // Unknown role names are skipped rather than failing the whole lookup
+    let roles: Vec<String> = row.get(3);
+    User {
+        id: row.get(0),
+        email: row.get(1),
+        wallet_address: row.get(2),
+        roles: roles.iter().filter_map(|role| role.parse().ok()).collect(),
+        created_at: row.get(4),
+    }
+}
@@ -1,172 +1,325 @@
-use crate::models::withdrawal_history_models::{CreateWithdrawalRecordRequest, WithdrawalRecord};
-use deadpool_postgres::Client;
-use tokio_postgres::Error;
+use crate::models::execution_models::Payout;
+use crate::models::money::{Amount, Money};
+use crate::models::withdrawal_history_models::{
+    CreateWithdrawalRequest, UpdateWithdrawalStatus, WithdrawalRecord, WithdrawalStatus,
+};
+use deadpool_postgres::{Client, GenericClient};
+use std::collections::HashMap;
+use tokio_postgres::{Error, Row};
 
-pub async fn record_withdrawal(
-    client: &Client,
-    create_activity_request: &CreateWithdrawalRecordRequest,
+const WITHDRAWAL_COLUMNS: &str = "id, user_id, plan_id, beneficiary_id, wallet_id, asset, amount, \
+     status, tx_hash, failure_reason, created_at, updated_at, execution_id";
+
+// Withdrawals user $1 may see: those they requested, those paying them, and
+// those out of their plans. A NULL $1 sees every withdrawal.
+const VISIBLE_TO: &str = "($1::INT IS NULL
+     OR user_id = $1
+     OR plan_id IN (SELECT id FROM plans WHERE owner_id = $1)
+     OR beneficiary_id IN (SELECT id FROM beneficiaries WHERE user_id = $1))";
+
+/// Records a withdrawal requested by `user_id`, paid to `wallet_id`.
+pub async fn record_withdrawal<C: GenericClient>(
+    client: &C,
+    user_id: i32,
+    request: &CreateWithdrawalRequest,
+    wallet_id: &str,
 ) -> Result<WithdrawalRecord, Error> {
     let statement = client
-        .prepare(
-            "INSERT INTO withdrawal_history (plan_id, wallet_id, amount, payer_name)
-             VALUES ($1, $2, $3, $4)
-             RETURNING id, plan_id, wallet_id, amount, payer_name, created_at",
-        )
+        .prepare(&format!(
+            "INSERT INTO withdrawal_history (user_id, plan_id, beneficiary_id, wallet_id, asset, amount)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING {}",
+            WITHDRAWAL_COLUMNS
+        ))
         .await?;
 
     let row = client
         .query_one(
             &statement,
             &[
-                &create_activity_request.user_id,
-                &create_activity_request.activity_type,
-                &create_activity_request.details,
-                &create_activity_request.action_type,
-                &create_activity_request.action_link,
+                &user_id,
+                &request.plan_id,
+                &request.beneficiary_id,
+                &wallet_id,
+                &request.money.asset,
+                &request.money.amount,
             ],
         )
         .await?;
 
-    Ok(WithdrawalRecord {
-        id: row.get(0),
-        plan_id: row.get(1),
-        wallet_id: row.get(2),
-        amount: row.get(3),
-        payer_name: row.get(4),
-        created_at: row.get(5),
-    })
+    Ok(row_to_withdrawal(&row))
+}
+
+/// Records a withdrawal only if, together with everything else requested for
+/// the same beneficiary and asset, it stays within `unlocked`. Returns `None`
+/// when it doesn't. Requests for one beneficiary are serialized, so two of
+/// them can't both spend the same balance.
+pub async fn record_within_unlocked(
+    client: &mut Client,
+    user_id: i32,
+    request: &CreateWithdrawalRequest,
+    wallet_id: &str,
+    unlocked: Amount,
+) -> Result<Option<WithdrawalRecord>, Error> {
+    let transaction = client.transaction().await?;
+
+    transaction
+        .execute(
+            "SELECT 1 FROM beneficiaries WHERE id = $1 FOR UPDATE",
+            &[&request.beneficiary_id],
+        )
+        .await?;
+    let withdrawn = withdrawn_by_beneficiary(&transaction, request.beneficiary_id)
+        .await?
+        .get(&request.money.asset)
+        .copied()
+        .unwrap_or_default();
+    let within = withdrawn
+        .checked_add(request.money.amount)
+        .is_some_and(|total| total <= unlocked);
+    if !within {
+        return Ok(None);
+    }
+
+    let withdrawal = record_withdrawal(&transaction, user_id, request, wallet_id).await?;
+    transaction.commit().await?;
+
+    Ok(Some(withdrawal))
+}
+
+/// Everything requested for a beneficiary per asset, failed withdrawals
+/// aside.
+pub async fn withdrawn_by_beneficiary<C: GenericClient>(
+    client: &C,
+    beneficiary_id: i32,
+) -> Result<HashMap<String, Amount>, Error> {
+    let rows = client
+        .query(
+            "SELECT asset, SUM(amount) FROM withdrawal_history
+             WHERE beneficiary_id = $1 AND status <> 'failed'
+             GROUP BY asset",
+            &[&beneficiary_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// Records a payout scheduled by a plan execution. It starts out `requested`
+/// like any other withdrawal.
+pub async fn record_payout<C: GenericClient>(
+    client: &C,
+    execution_id: i64,
+    plan_id: i32,
+    payout: &Payout,
+) -> Result<WithdrawalRecord, Error> {
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO withdrawal_history
+                     (user_id, plan_id, beneficiary_id, wallet_id, asset, amount, execution_id)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 RETURNING {}",
+                WITHDRAWAL_COLUMNS
+            ),
+            &[
+                &payout.user_id,
+                &plan_id,
+                &payout.beneficiary_id,
+                &payout.wallet_address,
+                &payout.asset,
+                &payout.amount,
+                &execution_id,
+            ],
+        )
+        .await?;
+
+    Ok(row_to_withdrawal(&row))
+}
+
+/// Hands the payouts to a beneficiary that nobody could claim yet to their
+/// newly linked account. Returns how many there were.
+pub async fn claim_payouts<C: GenericClient>(
+    client: &C,
+    beneficiary_id: i32,
+    user_id: i32,
+) -> Result<u64, Error> {
+    client
+        .execute(
+            "UPDATE withdrawal_history SET user_id = $1, updated_at = NOW()
+             WHERE beneficiary_id = $2 AND execution_id IS NOT NULL AND user_id IS NULL",
+            &[&user_id, &beneficiary_id],
+        )
+        .await
 }
 
+pub async fn get_by_execution(
+    client: &Client,
+    execution_id: i64,
+) -> Result<Vec<WithdrawalRecord>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM withdrawal_history WHERE execution_id = $1 ORDER BY id",
+                WITHDRAWAL_COLUMNS
+            ),
+            &[&execution_id],
+        )
+        .await?;
+
+    Ok(rows.iter().map(row_to_withdrawal).collect())
+}
+
+/// A page of the withdrawals `viewer` may see, or of all of them for `None`.
 pub async fn get_withdrawal_history(
     client: &Client,
+    viewer: Option<i32>,
     page: i64,
     page_size: i64,
 ) -> Result<(Vec<WithdrawalRecord>, i64), Error> {
     // Get total count of records for pagination
     let count_stmt = client
-        .prepare("SELECT COUNT(*) FROM withdrawal_history")
+        .prepare(&format!(
+            "SELECT COUNT(*) FROM withdrawal_history WHERE {}",
+            VISIBLE_TO
+        ))
         .await?;
 
-    // let total_row = client.query_one(&count_stmt, &[&user_id]).await?;
-    let total_row = client.query_one(&count_stmt, &[]).await?;
+    let total_row = client.query_one(&count_stmt, &[&viewer]).await?;
 
     let total: i64 = total_row.get(0);
 
     let offset = (page - 1) * page_size;
 
     let stmt = client
-        .prepare(
-            "SELECT id, plan_id, wallet_id, amount, payer_name, created_at
+        .prepare(&format!(
+            "SELECT {}
              FROM withdrawal_history
-             ORDER BY date DESC
+             WHERE {}
+             ORDER BY created_at DESC, id DESC
              LIMIT $2 OFFSET $3",
-        )
+            WITHDRAWAL_COLUMNS, VISIBLE_TO
+        ))
         .await?;
 
-    let rows = client.query(&stmt, &[&page_size, &offset]).await?;
+    let rows = client.query(&stmt, &[&viewer, &page_size, &offset]).await?;
 
-    let mut activities = Vec::new();
-
-    for row in rows {
-        activities.push(WithdrawalRecord {
-            id: row.get(0),
-            plan_id: row.get(1),
-            wallet_id: row.get(2),
-            amount: row.get(3),
-            payer_name: row.get(4),
-            created_at: row.get(5),
-        });
-    }
-
-    Ok((activities, total))
+    Ok((rows.iter().map(row_to_withdrawal).collect(), total))
 }
 
-pub async fn delete_withdrawal(client: &Client, id: i64) -> Result<(), Error> {
+/// Returns `false` if there was no such withdrawal.
+pub async fn delete_withdrawal(client: &Client, id: i64) -> Result<bool, Error> {
     let stmt = client
         .prepare("DELETE FROM withdrawal_history WHERE id = $1")
         .await?;
 
-    client.execute(&stmt, &[&id]).await?;
-
-    Ok(())
+    Ok(client.execute(&stmt, &[&id]).await? == 1)
 }
 
-pub async fn get_withdrawal_by_id(client: &Client, id: i64) -> Result<WithdrawalRecord, Error> {
+pub async fn get_withdrawal_by_id(
+    client: &Client,
+    id: i64,
+) -> Result<Option<WithdrawalRecord>, Error> {
     let stmt = client
-        .prepare("SELECT id, plan_id, wallet_id, amount, payer_name, created_at FROM withdrawal_history WHERE id = $1")
+        .prepare(&format!(
+            "SELECT {} FROM withdrawal_history WHERE id = $1",
+            WITHDRAWAL_COLUMNS
+        ))
         .await?;
 
-    let row = client.query_one(&stmt, &[&id]).await?;
+    let row = client.query_opt(&stmt, &[&id]).await?;
 
-    Ok(WithdrawalRecord {
-        id: row.get(0),
-        plan_id: row.get(1),
-        wallet_id: row.get(2),
-        amount: row.get(3),
-        payer_name: row.get(4),
-        created_at: row.get(5),
-    })
+    Ok(row.as_ref().map(row_to_withdrawal))
+}
+
+pub async fn is_visible_to(client: &Client, id: i64, viewer: i32) -> Result<bool, Error> {
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT 1 FROM withdrawal_history WHERE id = $2 AND {}",
+                VISIBLE_TO
+            ),
+            &[&Some(viewer), &id],
+        )
+        .await?;
+
+    Ok(row.is_some())
 }
 
-pub async fn update_withdrawal(
+/// Moves a withdrawal on from `current`. Returns `None` if it is no longer in
+/// that status, so concurrent updates can't skip a step.
+pub async fn update_status(
     client: &Client,
-    // id: i64,
-    withdrawal_record: &WithdrawalRecord,
-) -> Result<WithdrawalRecord, Error> {
+    id: i64,
+    current: WithdrawalStatus,
+    update: &UpdateWithdrawalStatus,
+) -> Result<Option<WithdrawalRecord>, Error> {
     let stmt = client
-        .prepare(
+        .prepare(&format!(
             "UPDATE withdrawal_history
-             SET plan_id = $1, wallet_id = $2, amount = $3, payer_name = $4
-             WHERE id = $5
-             RETURNING id, plan_id, wallet_id, amount, payer_name, created_at",
-        )
+             SET status = $1,
+                 tx_hash = COALESCE($2, tx_hash),
+                 failure_reason = COALESCE($3, failure_reason),
+                 updated_at = NOW()
+             WHERE id = $4 AND status = $5
+             RETURNING {}",
+            WITHDRAWAL_COLUMNS
+        ))
         .await?;
 
     let row = client
-        .query_one(
+        .query_opt(
             &stmt,
             &[
-                &withdrawal_record.plan_id,
-                &withdrawal_record.wallet_id,
-                &withdrawal_record.amount,
-                &withdrawal_record.payer_name,
-                &withdrawal_record.id,
+                &update.status,
+                &update.tx_hash,
+                &update.failure_reason,
+                &id,
+                &current,
             ],
         )
         .await?;
 
-    Ok(WithdrawalRecord {
-        id: row.get(0),
-        plan_id: row.get(1),
-        wallet_id: row.get(2),
-        amount: row.get(3),
-        payer_name: row.get(4),
-        created_at: row.get(5),
-    })
+    Ok(row.as_ref().map(row_to_withdrawal))
 }
 
+/// The withdrawals `user_id` requested that `viewer` may see, or all of them
+/// for `None`.
 pub async fn get_withdrawal_history_by_user_id(
     client: &Client,
-    user_id: &i64,
+    user_id: i32,
+    viewer: Option<i32>,
 ) -> Result<Vec<WithdrawalRecord>, Error> {
     let stmt = client
-        .prepare("SELECT id, plan_id, wallet_id, amount, payer_name, created_at FROM withdrawal_history WHERE user_id = $1")
+        .prepare(&format!(
+            "SELECT {} FROM withdrawal_history
+             WHERE user_id = $2 AND {}
+             ORDER BY created_at DESC, id DESC",
+            WITHDRAWAL_COLUMNS, VISIBLE_TO
+        ))
         .await?;
 
-    let rows = client.query(&stmt, &[&user_id]).await?;
+    let rows = client.query(&stmt, &[&viewer, &user_id]).await?;
 
-    let mut activities = Vec::new();
+    Ok(rows.iter().map(row_to_withdrawal).collect())
+}
 
-    for row in rows {
-        activities.push(WithdrawalRecord {
-            id: row.get(0),
-            plan_id: row.get(1),
-            wallet_id: row.get(2),
-            amount: row.get(3),
-            payer_name: row.get(4),
-            created_at: row.get(5),
-        });
+fn row_to_withdrawal(row: &Row) -> WithdrawalRecord {
+    WithdrawalRecord {
+        id: row.get(0),
+        user_id: row.get(1),
+        plan_id: row.get(2),
+        beneficiary_id: row.get(3),
+        wallet_id: row.get(4),
+        money: Money {
+            amount: row.get(6),
+            asset: row.get(5),
+        },
+        status: row.get(7),
+        tx_hash: row.get(8),
+        failure_reason: row.get(9),
+        created_at: row.get(10),
+        updated_at: row.get(11),
+        execution_id: row.get(12),
     }
-
-    Ok(activities)
 }
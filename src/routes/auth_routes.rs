@@ -0,0 +1,6 @@
+use crate::controller::auth_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    auth_controller::config(cfg);
+}
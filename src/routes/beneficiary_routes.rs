@@ -0,0 +1,6 @@
+use crate::controller::beneficiary_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    beneficiary_controller::config(cfg);
+}
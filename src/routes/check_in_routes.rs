@@ -0,0 +1,6 @@
+use crate::controller::check_in_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    check_in_controller::config(cfg);
+}
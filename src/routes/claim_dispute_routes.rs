@@ -0,0 +1,6 @@
+use crate::controller::claim_dispute_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    claim_dispute_controller::config(cfg);
+}
@@ -0,0 +1,6 @@
+use crate::controller::claim_evidence_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    claim_evidence_controller::config(cfg);
+}
@@ -0,0 +1,6 @@
+use crate::controller::guardian_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    guardian_controller::config(cfg);
+}
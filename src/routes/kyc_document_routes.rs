@@ -0,0 +1,6 @@
+use crate::controller::kyc_document_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    kyc_document_controller::config(cfg);
+}
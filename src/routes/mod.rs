@@ -1,13 +1,36 @@
 pub mod activity_log_routes;
 pub mod activity_routes;
+pub mod auth_routes;
+pub mod beneficiary_routes;
+pub mod check_in_routes;
+pub mod claim_dispute_routes;
+pub mod claim_evidence_routes;
 pub mod faq_routes;
+pub mod guardian_routes;
+pub mod kyc_document_routes;
 pub mod kyc_routes;
+pub mod plan_routes;
+pub mod user_support_routes;
 pub mod withdrawal_history_routes;
 
 use actix_web::web;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     activity_routes::configure(cfg);
+    auth_routes::configure(cfg);
+    // Nested under /claims, ahead of the claim scope registered in main
+    claim_dispute_routes::configure(cfg);
+    claim_evidence_routes::configure(cfg);
+    activity_log_routes::configure(cfg);
+    faq_routes::configure(cfg);
+    // Nested under /api/kyc, ahead of the KYC scope
+    kyc_document_routes::configure(cfg);
     kyc_routes::configure(cfg);
+    // Nested under /plans, so these have to be registered before the plan scope
+    beneficiary_routes::configure(cfg);
+    check_in_routes::configure(cfg);
+    guardian_routes::configure(cfg);
+    plan_routes::configure(cfg);
+    user_support_routes::configure(cfg);
     withdrawal_history_routes::configure(cfg);
 }
@@ -0,0 +1,6 @@
+use crate::controller::plan_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    plan_controller::config(cfg);
+}
@@ -0,0 +1,6 @@
+use crate::controller::user_support_controller;
+use actix_web::web;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    user_support_controller::config(cfg);
+}
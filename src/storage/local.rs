@@ -0,0 +1,64 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use super::{ObjectStore, StorageError, validate_key};
+
+/// Keeps objects as plain files under a root directory, one file per key.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: PathBuf) -> LocalStore {
+        LocalStore { root }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+impl ObjectStore for LocalStore {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        // Written to a temporary file first and renamed into place, so a
+        // crash never leaves a half-written object behind the key
+        let temporary = dir.join(format!(
+            ".{}.{:016x}.tmp",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            rand::random::<u64>()
+        ));
+        let written = fs::File::create(&temporary).and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        });
+        if let Err(err) = written.and_then(|_| fs::rename(&temporary, &path)) {
+            let _ = fs::remove_file(&temporary);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        match fs::read(self.path(key)?) {
+            Ok(bytes) => Ok(bytes),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                Err(StorageError::NotFound(key.to_string()))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.path(key)?) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
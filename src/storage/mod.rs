@@ -0,0 +1,55 @@
+pub mod local;
+
+use std::sync::Arc;
+
+use crate::config::env::{StorageBackend, StorageSettings};
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("No object stored under {0}")]
+    NotFound(String),
+    #[error("Invalid object key: {0}")]
+    InvalidKey(String),
+    #[error("Storage I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Where uploaded files live. Keys are `/`-separated paths such as
+/// `claims/12/3f9c...`, made of ASCII letters, digits, `.`, `_` and `-`.
+///
+/// Calls block, so handlers run them through `web::block`.
+pub trait ObjectStore: Send + Sync {
+    /// Stores `bytes` under `key`, replacing whatever was there. Readers see
+    /// either the old object or the whole new one.
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), StorageError>;
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Removing a key that holds nothing is not an error.
+    fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Builds the backend the settings ask for.
+pub fn from_settings(settings: &StorageSettings) -> Arc<dyn ObjectStore> {
+    match settings.backend {
+        StorageBackend::Local => Arc::new(local::LocalStore::new(settings.local_root.clone())),
+    }
+}
+
+/// Checks a key against the rules on `ObjectStore`.
+pub fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
@@ -0,0 +1,185 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, HttpResponse, test, web};
+    use chrono::Utc;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+
+    use crate::middlewares::auth_middleware::{
+        AuthConfig, AuthenticatedUser, JwtAuth, RequireRole,
+    };
+    use crate::models::auth_models::{Role, TokenClaims};
+    use crate::tests::{TEST_JWT_SECRET, bearer, test_auth_config};
+
+    async fn whoami(user: AuthenticatedUser) -> HttpResponse {
+        HttpResponse::Ok().json(serde_json::json!({"user_id": user.user_id}))
+    }
+
+    async fn public() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    fn signed_token(secret: &str, exp_offset_secs: i64) -> String {
+        let now = Utc::now().timestamp();
+        let claims = TokenClaims {
+            sub: "7".to_string(),
+            roles: vec![Role::Owner],
+            iat: now,
+            exp: now + exp_offset_secs,
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_issued_token_round_trips() {
+        let config = test_auth_config();
+        let token = config
+            .issue_token(7, &[Role::Owner, Role::KycReviewer])
+            .unwrap();
+
+        let user = config.verify_token(&token).unwrap();
+        assert_eq!(user.user_id, 7);
+        assert_eq!(user.roles, vec![Role::Owner, Role::KycReviewer]);
+    }
+
+    #[actix_web::test]
+    async fn test_expired_token_is_rejected() {
+        let token = signed_token(TEST_JWT_SECRET, -3600);
+        assert_eq!(
+            test_auth_config().verify_token(&token).unwrap_err(),
+            "Token has expired"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_token_signed_with_other_secret_is_rejected() {
+        let token = signed_token("another-secret", 3600);
+        assert!(test_auth_config().verify_token(&token).is_err());
+
+        let other = AuthConfig::new("another-secret", chrono::Duration::minutes(5));
+        assert!(other.verify_token(&token).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_middleware_guards_authenticated_handlers() {
+        let app = test::init_service(
+            App::new()
+                .wrap(JwtAuth::new(test_auth_config()))
+                .route("/me", web::get().to(whoami))
+                .route("/public", web::get().to(public)),
+        )
+        .await;
+
+        // Anonymous requests still reach public handlers
+        let req = test::TestRequest::get().uri("/public").to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+        // ...but not the ones that need a user
+        let req = test::TestRequest::get().uri("/me").to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/me")
+            .insert_header(("Authorization", bearer(7, &[Role::Owner])))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["user_id"], 7);
+
+        // A bad token is rejected even on public handlers
+        let req = test::TestRequest::get()
+            .uri("/public")
+            .insert_header(("Authorization", "Bearer not-a-token"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/me")
+            .insert_header((
+                "Authorization",
+                format!("Bearer {}", signed_token(TEST_JWT_SECRET, -3600)),
+            ))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/me")
+            .insert_header(("Authorization", "Basic dXNlcjpwYXNz"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_role_names_round_trip() {
+        for role in [
+            Role::Owner,
+            Role::Beneficiary,
+            Role::KycReviewer,
+            Role::ClaimReviewer,
+            Role::SupportAgent,
+            Role::Admin,
+        ] {
+            assert_eq!(role.as_str().parse::<Role>().unwrap(), role);
+            assert_eq!(
+                serde_json::to_value(role).unwrap(),
+                serde_json::json!(role.as_str())
+            );
+        }
+        assert!("superuser".parse::<Role>().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_require_role_guards_restricted_routes() {
+        let app = test::init_service(
+            App::new().wrap(JwtAuth::new(test_auth_config())).service(
+                web::scope("/faqs")
+                    .route("", web::get().to(public))
+                    .service(
+                        web::resource("")
+                            .guard(actix_web::guard::Post())
+                            .wrap(RequireRole::any_of(&[Role::SupportAgent]))
+                            .route(web::post().to(whoami)),
+                    ),
+            ),
+        )
+        .await;
+
+        // The guard only applies to the wrapped resource
+        let req = test::TestRequest::get().uri("/faqs").to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+        let req = test::TestRequest::post().uri("/faqs").to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+        let req = test::TestRequest::post()
+            .uri("/faqs")
+            .insert_header((
+                "Authorization",
+                bearer(7, &[Role::Owner, Role::Beneficiary]),
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "Missing required role: support_agent");
+        assert_eq!(body["required_roles"], serde_json::json!(["support_agent"]));
+
+        let req = test::TestRequest::post()
+            .uri("/faqs")
+            .insert_header(("Authorization", bearer(7, &[Role::SupportAgent])))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+        // Admins pass every role check
+        let req = test::TestRequest::post()
+            .uri("/faqs")
+            .insert_header(("Authorization", bearer(7, &[Role::Admin])))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    }
+}
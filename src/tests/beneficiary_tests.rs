@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::beneficiary_models::{
+        Allocation, NewBeneficiary, UpdateBeneficiary, validate_allocations,
+    };
+    use crate::models::money::Amount;
+    use crate::models::plan_models::PlanAsset;
+    use crate::models::vesting_models::ReleaseSchedule;
+
+    fn percentage(share_bps: i32) -> Allocation {
+        Allocation::Percentage { share_bps }
+    }
+
+    fn fixed(asset: &str, amount: i64) -> Allocation {
+        Allocation::Fixed {
+            asset: asset.to_string(),
+            amount: Amount::from(amount),
+        }
+    }
+
+    fn assets() -> Vec<PlanAsset> {
+        vec![PlanAsset {
+            asset: "STRK".to_string(),
+            amount: Amount::from(1_000),
+        }]
+    }
+
+    #[test]
+    fn test_complete_set_must_sum_to_exactly_100() {
+        let ok = [percentage(6_000), percentage(4_000)];
+        let short = [percentage(6_000), percentage(3_999)];
+        let over = [percentage(6_000), percentage(4_001)];
+
+        assert!(validate_allocations(&ok.iter().collect::<Vec<_>>(), &assets()).is_ok());
+        assert!(validate_allocations(&short.iter().collect::<Vec<_>>(), &assets()).is_err());
+        assert!(validate_allocations(&over.iter().collect::<Vec<_>>(), &assets()).is_err());
+    }
+
+    #[test]
+    fn test_partial_set_is_rejected() {
+        // Drafts get no leeway either
+        let short = [percentage(6_000)];
+        let fixed_only = [fixed("STRK", 400)];
+
+        assert!(validate_allocations(&short.iter().collect::<Vec<_>>(), &assets()).is_err());
+        assert!(validate_allocations(&fixed_only.iter().collect::<Vec<_>>(), &assets()).is_ok());
+    }
+
+    #[test]
+    fn test_fixed_allocations_are_bounded_by_plan_assets() {
+        let within = [fixed("STRK", 400), fixed("STRK", 600), percentage(10_000)];
+        let exceeding = [fixed("STRK", 400), fixed("STRK", 601)];
+        let unknown_asset = [fixed("ETH", 1)];
+        let non_positive = [fixed("STRK", 0)];
+
+        assert!(validate_allocations(&within.iter().collect::<Vec<_>>(), &assets()).is_ok());
+        assert!(validate_allocations(&exceeding.iter().collect::<Vec<_>>(), &assets()).is_err());
+        assert!(
+            validate_allocations(&unknown_asset.iter().collect::<Vec<_>>(), &assets()).is_err()
+        );
+        assert!(validate_allocations(&non_positive.iter().collect::<Vec<_>>(), &assets()).is_err());
+    }
+
+    #[test]
+    fn test_update_keeps_untouched_fields() {
+        let existing = crate::models::beneficiary_models::Beneficiary {
+            id: 1,
+            plan_id: 1,
+            user_id: Some(7),
+            name: "Ada".to_string(),
+            wallet_address: "0x0123".to_string(),
+            relationship: Some("daughter".to_string()),
+            email: None,
+            allocation: percentage(5_000),
+            release: ReleaseSchedule::Tranches {
+                count: 4,
+                interval_days: 365,
+            },
+            position: 0,
+        };
+        let update = UpdateBeneficiary {
+            user_id: None,
+            name: None,
+            wallet_address: None,
+            relationship: None,
+            email: Some("ada@example.com".to_string()),
+            allocation: None,
+            release: None,
+        };
+
+        let updated: NewBeneficiary = update.apply(&existing).unwrap();
+        assert_eq!(updated.user_id, Some(7));
+        assert_eq!(updated.name, "Ada");
+        assert_eq!(updated.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(updated.allocation, percentage(5_000));
+        assert_eq!(updated.release, existing.release);
+    }
+}
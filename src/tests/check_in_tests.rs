@@ -0,0 +1,317 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{App, test, web};
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::controller::check_in_controller;
+    use crate::jobs::check_in_scheduler;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::check_in_models::{CheckInSchedule, ConfigureCheckIn, Escalation};
+    use crate::models::plan_models::{CreatePlan, PlanStatus};
+    use crate::repositories::{check_in_repository, plan_repository};
+    use crate::tests::{bearer, test_auth_config};
+    use crate::utils::clock::{Clock, ManualClock};
+
+    fn schedule(clock: &ManualClock) -> CheckInSchedule {
+        CheckInSchedule {
+            plan_id: 1,
+            interval_secs: 7 * 24 * 3600,
+            grace_secs: 24 * 3600,
+            last_check_in_at: clock.now(),
+            escalation: Escalation::None,
+            escalated_at: None,
+            created_at: clock.now(),
+            updated_at: clock.now(),
+        }
+    }
+
+    // Applies whatever step is due, the way a scheduler pass would
+    fn tick(schedule: &mut CheckInSchedule, clock: &ManualClock) -> Option<Escalation> {
+        let step = schedule.escalation_due(clock.now())?;
+        schedule.escalation = step;
+        schedule.escalated_at = Some(clock.now());
+        Some(step)
+    }
+
+    #[actix_web::test]
+    async fn test_escalation_ladder() {
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+        let mut schedule = schedule(&clock);
+
+        clock.advance(Duration::days(7) - Duration::seconds(1));
+        assert_eq!(tick(&mut schedule, &clock), None);
+
+        clock.advance(Duration::seconds(1));
+        assert_eq!(tick(&mut schedule, &clock), Some(Escalation::Reminder));
+        // One step per grace window, however often the scheduler runs
+        assert_eq!(tick(&mut schedule, &clock), None);
+
+        clock.advance(Duration::days(1));
+        assert_eq!(tick(&mut schedule, &clock), Some(Escalation::Warning));
+
+        clock.advance(Duration::days(1));
+        assert_eq!(
+            tick(&mut schedule, &clock),
+            Some(Escalation::TriggerPending)
+        );
+
+        // Not triggered until the final grace window is over
+        clock.advance(Duration::days(1) - Duration::seconds(1));
+        assert_eq!(tick(&mut schedule, &clock), None);
+
+        clock.advance(Duration::seconds(1));
+        assert_eq!(tick(&mut schedule, &clock), Some(Escalation::Triggered));
+
+        clock.advance(Duration::days(365));
+        assert_eq!(tick(&mut schedule, &clock), None);
+    }
+
+    #[actix_web::test]
+    async fn test_late_scheduler_still_gives_full_grace() {
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+        let mut schedule = schedule(&clock);
+
+        // The scheduler was down for a month
+        clock.advance(Duration::days(30));
+        assert_eq!(tick(&mut schedule, &clock), Some(Escalation::Reminder));
+        assert_eq!(tick(&mut schedule, &clock), None);
+
+        let (next, due_at) = schedule.next_step().unwrap();
+        assert_eq!(next, Escalation::Warning);
+        assert_eq!(due_at, clock.now() + Duration::days(1));
+    }
+
+    #[actix_web::test]
+    async fn test_configure_validation() {
+        let valid = ConfigureCheckIn {
+            interval_secs: 3600,
+            grace_secs: 600,
+        };
+        assert!(valid.validate().is_ok());
+
+        let no_interval = ConfigureCheckIn {
+            interval_secs: 0,
+            ..valid
+        };
+        assert!(no_interval.validate().is_err());
+
+        let negative_grace = ConfigureCheckIn {
+            interval_secs: 3600,
+            grace_secs: -1,
+        };
+        assert!(negative_grace.validate().is_err());
+
+        // Past a century the escalation dates stop being computable
+        let century = ConfigureCheckIn {
+            interval_secs: 100 * 365 * 24 * 3600,
+            grace_secs: 100 * 365 * 24 * 3600,
+        };
+        assert!(century.validate().is_ok());
+        for (interval_secs, grace_secs) in [(i64::MAX, 600), (3600, century.grace_secs + 1)] {
+            let too_long = ConfigureCheckIn {
+                interval_secs,
+                grace_secs,
+            };
+            assert!(too_long.validate().is_err());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_out_of_range_due_dates_are_not_computed() {
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+        let mut schedule = schedule(&clock);
+        schedule.interval_secs = i64::MAX;
+        assert_eq!(schedule.next_step(), None);
+        assert_eq!(schedule.escalation_due(clock.now()), None);
+
+        schedule.interval_secs = 9_000_000_000_000;
+        assert_eq!(schedule.next_step(), None);
+    }
+
+    #[actix_web::test]
+    async fn test_escalation_names() {
+        for step in [
+            Escalation::None,
+            Escalation::Reminder,
+            Escalation::Warning,
+            Escalation::TriggerPending,
+            Escalation::Triggered,
+        ] {
+            assert_eq!(step.as_str().parse::<Escalation>(), Ok(step));
+            assert_eq!(serde_json::to_value(step).unwrap(), step.as_str());
+        }
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_missed_check_ins_trigger_plan() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::from(clock.clone() as Arc<dyn Clock>))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(check_in_controller::config),
+        )
+        .await;
+
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let owner = bearer(owner_id, &[Role::Owner]);
+        let mut client = pool.get().await.unwrap();
+        let plan_request = CreatePlan {
+            name: "Check-in plan".to_string(),
+            description: None,
+            assets: Vec::new(),
+            beneficiaries: Vec::new(),
+        };
+        let plan = plan_repository::create(&mut client, owner_id, &plan_request)
+            .await
+            .unwrap();
+        plan_repository::transition_status(&client, plan.id, PlanStatus::Draft, PlanStatus::Active)
+            .await
+            .unwrap();
+        let uri = format!("/plans/{}/check-in", plan.id);
+
+        // A schedule stored before periods were capped is skipped, not fatal
+        let broken = CreatePlan {
+            name: "Broken check-ins".to_string(),
+            ..plan_request.clone()
+        };
+        let broken = plan_repository::create(&mut client, owner_id, &broken)
+            .await
+            .unwrap();
+        plan_repository::transition_status(
+            &client,
+            broken.id,
+            PlanStatus::Draft,
+            PlanStatus::Active,
+        )
+        .await
+        .unwrap();
+        let forever = ConfigureCheckIn {
+            interval_secs: i64::MAX,
+            grace_secs: 3600,
+        };
+        check_in_repository::configure(&client, broken.id, &forever, clock.now())
+            .await
+            .unwrap();
+
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", bearer(owner_id + 1, &[Role::Owner])))
+            .set_json(serde_json::json!({"interval_secs": 86400, "grace_secs": 3600}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", owner.clone()))
+            .set_json(serde_json::json!({"interval_secs": 86400, "grace_secs": 3600}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let status: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(status["escalation"], "none");
+        assert_eq!(status["next_escalation"], "reminder");
+
+        let escalation = || async {
+            let req = test::TestRequest::get()
+                .uri(&uri)
+                .insert_header(("Authorization", owner.clone()))
+                .to_request();
+            let status: serde_json::Value =
+                test::read_body_json(test::call_service(&app, req).await).await;
+            status["escalation"].as_str().unwrap().to_string()
+        };
+        let plan_status = || async {
+            let client = pool.get().await.unwrap();
+            plan_repository::get_by_id(&client, plan.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .status
+        };
+
+        check_in_scheduler::run_pass(&pool, clock.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(escalation().await, "none");
+
+        for (advance, expected) in [
+            (Duration::days(1), "reminder"),
+            (Duration::hours(1), "warning"),
+            (Duration::hours(1), "trigger_pending"),
+        ] {
+            clock.advance(advance);
+            check_in_scheduler::run_pass(&pool, clock.as_ref())
+                .await
+                .unwrap();
+            assert_eq!(escalation().await, expected);
+        }
+        assert_eq!(plan_status().await, PlanStatus::Active);
+
+        clock.advance(Duration::minutes(59));
+        check_in_scheduler::run_pass(&pool, clock.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(escalation().await, "trigger_pending");
+
+        clock.advance(Duration::minutes(1));
+        check_in_scheduler::run_pass(&pool, clock.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(escalation().await, "triggered");
+        assert_eq!(plan_status().await, PlanStatus::Triggered);
+
+        // Every step was announced to the owner
+        let notices: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM notifications WHERE user_id = $1",
+                &[&owner_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(notices, 4);
+        let activities: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM user_activities
+                 WHERE user_id = $1 AND activity_type = 'check_in'",
+                &[&owner_id.to_string()],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(activities, 4);
+
+        // Checking in shows the owner is alive and restores the plan
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let status: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(status["escalation"], "none");
+        assert_eq!(plan_status().await, PlanStatus::Active);
+
+        client
+            .execute("DELETE FROM plans WHERE id = $1", &[&broken.id])
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+    use chrono::{Duration, Utc};
+    use deadpool_postgres::Pool;
+
+    use crate::controller::{claim_controller, claim_dispute_controller};
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::claim::{ClaimSla, ClaimStatus};
+    use crate::models::claim_dispute_models::{
+        DisputeKind, DisputeLimits, DisputeOutcome, FileDispute, ResolveDispute,
+    };
+    use crate::repositories::claim_repository;
+    use crate::tests::{bearer, test_auth_config};
+
+    // A pending claim against the plan
+    async fn file_claim(pool: &Pool, claimant_id: i32, plan_id: i32) -> i32 {
+        let client = pool.get().await.unwrap();
+        client
+            .query_one(
+                "INSERT INTO claims (user_id, plan_id, amount, asset, description, due_at)
+                 VALUES ($1, $2, 100, 'ETH', 'Estate share', NOW() + INTERVAL '7 days')
+                 RETURNING id",
+                &[&claimant_id, &plan_id],
+            )
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    // Reviews the claim and decides it
+    async fn decide(pool: &Pool, id: i32, decision: ClaimStatus, reviewer_id: i32) {
+        let mut client = pool.get().await.unwrap();
+        for (current, next) in [
+            (ClaimStatus::Pending, ClaimStatus::UnderReview),
+            (ClaimStatus::UnderReview, decision),
+        ] {
+            claim_repository::transition(&mut client, id, current, next, reviewer_id, None)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_decisions_map_to_dispute_kinds() {
+        assert_eq!(
+            DisputeKind::for_decision(ClaimStatus::Rejected),
+            Some(DisputeKind::Appeal)
+        );
+        assert_eq!(
+            DisputeKind::for_decision(ClaimStatus::Approved),
+            Some(DisputeKind::Challenge)
+        );
+        for undecided in [
+            ClaimStatus::Pending,
+            ClaimStatus::UnderReview,
+            ClaimStatus::Disputed,
+            ClaimStatus::Paid,
+        ] {
+            assert_eq!(DisputeKind::for_decision(undecided), None);
+        }
+
+        assert_eq!(
+            DisputeOutcome::Upheld.next_status(ClaimStatus::Rejected),
+            ClaimStatus::Rejected
+        );
+        assert_eq!(
+            DisputeOutcome::Reopened.next_status(ClaimStatus::Approved),
+            ClaimStatus::UnderReview
+        );
+        for kind in [DisputeKind::Appeal, DisputeKind::Challenge] {
+            assert_eq!(kind.as_str().parse::<DisputeKind>(), Ok(kind));
+        }
+        for outcome in [DisputeOutcome::Upheld, DisputeOutcome::Reopened] {
+            assert_eq!(outcome.as_str().parse::<DisputeOutcome>(), Ok(outcome));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_disputes_need_reasons() {
+        let blank = FileDispute {
+            reason: "  ".to_string(),
+        };
+        assert!(blank.validate().is_err());
+        let settled = ResolveDispute {
+            outcome: DisputeOutcome::Upheld,
+            reason: String::new(),
+        };
+        assert!(settled.validate().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_appeal_window() {
+        let limits = DisputeLimits {
+            appeal_window_days: 30,
+        };
+        let rejected_at = Utc::now();
+        assert_eq!(
+            limits.appeal_closes_at(rejected_at),
+            rejected_at + Duration::days(30)
+        );
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_appeals_and_challenges() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(ClaimSla {
+                    low_hours: 336,
+                    normal_hours: 168,
+                    high_hours: 72,
+                    urgent_hours: 24,
+                }))
+                .app_data(web::Data::new(DisputeLimits {
+                    appeal_window_days: 30,
+                }))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(claim_dispute_controller::config)
+                .configure(claim_controller::config),
+        )
+        .await;
+
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let claimant_id = owner_id + 1;
+        let sibling_id = owner_id + 2;
+        let decider_id = owner_id + 3;
+        let claimant = bearer(claimant_id, &[Role::Beneficiary]);
+        let sibling = bearer(sibling_id, &[Role::Beneficiary]);
+        let stranger = bearer(owner_id + 4, &[Role::Beneficiary]);
+        let decider = bearer(decider_id, &[Role::ClaimReviewer]);
+        let second_reviewer = bearer(owner_id + 5, &[Role::ClaimReviewer]);
+
+        let client = pool.get().await.unwrap();
+        let plan_id: i32 = client
+            .query_one(
+                "INSERT INTO plans (owner_id, name) VALUES ($1, 'Family estate') RETURNING id",
+                &[&owner_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        for user_id in [claimant_id, sibling_id] {
+            client
+                .execute(
+                    "INSERT INTO beneficiaries (plan_id, user_id, name, wallet_address, share_bps)
+                     VALUES ($1, $2, 'Heir', '0x1111111111111111111111111111111111111111', 5000)",
+                    &[&plan_id, &user_id],
+                )
+                .await
+                .unwrap();
+        }
+
+        let file = |who: &str, claim_id: i32| {
+            test::TestRequest::post()
+                .uri(&format!("/claims/{}/disputes", claim_id))
+                .insert_header(("Authorization", who.to_string()))
+                .set_json(serde_json::json!({"reason": "The certificate was misread"}))
+                .to_request()
+        };
+        let resolve = |who: &str, claim_id: i32, id: i64, outcome: &str| {
+            test::TestRequest::post()
+                .uri(&format!("/claims/{}/disputes/{}/resolve", claim_id, id))
+                .insert_header(("Authorization", who.to_string()))
+                .set_json(serde_json::json!({"outcome": outcome, "reason": "Checked again"}))
+                .to_request()
+        };
+        let status_of = |claim_id: i32| {
+            let pool = pool.clone();
+            async move {
+                let client = pool.get().await.unwrap();
+                claim_repository::get_by_id(&client, claim_id)
+                    .await
+                    .unwrap()
+                    .unwrap()
+            }
+        };
+
+        // Only the claimant appeals a rejection
+        let rejected = file_claim(&pool, claimant_id, plan_id).await;
+        let resp = test::call_service(&app, file(&claimant, rejected)).await;
+        assert_eq!(resp.status().as_u16(), 409);
+        decide(&pool, rejected, ClaimStatus::Rejected, decider_id).await;
+        let resp = test::call_service(&app, file(&sibling, rejected)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        let resp = test::call_service(&app, file(&claimant, rejected)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let appeal: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(appeal["kind"], "appeal");
+        assert_eq!(appeal["contested_status"], "rejected");
+        assert_eq!(appeal["decided_by"], decider_id);
+        assert!(appeal["outcome"].is_null());
+        let appeal_id = appeal["id"].as_i64().unwrap();
+        let claim = status_of(rejected).await;
+        assert_eq!(claim.status, ClaimStatus::Disputed);
+        assert_eq!(claim.assignee_id, None);
+
+        // Once only, and not around the dispute endpoints
+        let resp = test::call_service(&app, file(&claimant, rejected)).await;
+        assert_eq!(resp.status().as_u16(), 409);
+        let req = test::TestRequest::put()
+            .uri(&format!("/claims/{}", rejected))
+            .insert_header(("Authorization", decider.clone()))
+            .set_json(serde_json::json!({"status": "rejected", "reason": "Still unclear"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+
+        let list = |who: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/claims/{}/disputes", rejected))
+                .insert_header(("Authorization", who.to_string()))
+                .to_request()
+        };
+        let resp = test::call_service(&app, list(&claimant)).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let listed: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        let resp = test::call_service(&app, list(&stranger)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        // A different reviewer has to settle it
+        let resp = test::call_service(&app, resolve(&decider, rejected, appeal_id, "upheld")).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp =
+            test::call_service(&app, resolve(&claimant, rejected, appeal_id, "upheld")).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(
+            &app,
+            resolve(&second_reviewer, rejected, appeal_id, "upheld"),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let settled: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(settled["outcome"], "upheld");
+        assert_eq!(settled["resolution"], "Checked again");
+        assert_eq!(status_of(rejected).await.status, ClaimStatus::Rejected);
+
+        // An upheld rejection is final
+        let resp = test::call_service(&app, file(&claimant, rejected)).await;
+        assert_eq!(resp.status().as_u16(), 409);
+        let resp = test::call_service(
+            &app,
+            resolve(&second_reviewer, rejected, appeal_id, "reopened"),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        // Appeals have to come within the window
+        let late = file_claim(&pool, claimant_id, plan_id).await;
+        decide(&pool, late, ClaimStatus::Rejected, decider_id).await;
+        client
+            .execute(
+                "UPDATE claim_status_history SET created_at = NOW() - INTERVAL '31 days'
+                 WHERE claim_id = $1",
+                &[&late],
+            )
+            .await
+            .unwrap();
+        let resp = test::call_service(&app, file(&claimant, late)).await;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        // Co-beneficiaries challenge approvals, the claimant does not
+        let approved = file_claim(&pool, claimant_id, plan_id).await;
+        decide(&pool, approved, ClaimStatus::Approved, decider_id).await;
+        let resp = test::call_service(&app, file(&claimant, approved)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(&app, file(&stranger, approved)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(&app, file(&sibling, approved)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let challenge: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(challenge["kind"], "challenge");
+        assert_eq!(challenge["filed_by"], sibling_id);
+        let notified: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND body LIKE $2",
+                &[&claimant_id, &format!("%your claim {}.%", approved)],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(notified, 1);
+
+        let resp = test::call_service(
+            &app,
+            resolve(
+                &second_reviewer,
+                approved,
+                challenge["id"].as_i64().unwrap(),
+                "reopened",
+            ),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(status_of(approved).await.status, ClaimStatus::UnderReview);
+
+        client
+            .execute(
+                "DELETE FROM claims WHERE id = ANY($1)",
+                &[&vec![rejected, late, approved]],
+            )
+            .await
+            .unwrap();
+        client
+            .execute("DELETE FROM plans WHERE id = $1", &[&plan_id])
+            .await
+            .unwrap();
+    }
+}
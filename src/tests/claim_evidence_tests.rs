@@ -0,0 +1,259 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use actix_web::{App, test, web};
+
+    use crate::controller::claim_evidence_controller;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::claim_evidence_models::{
+        EvidenceLimits, clean_file_name, sniff_content_type,
+    };
+    use crate::storage::local::LocalStore;
+    use crate::storage::{ObjectStore, StorageError, validate_key};
+    use crate::tests::{bearer, test_auth_config};
+
+    const PDF: &[u8] = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog >>\nendobj\n%%EOF\n";
+
+    fn temp_root() -> PathBuf {
+        std::env::temp_dir().join(format!("inheritx-store-{:016x}", rand::random::<u64>()))
+    }
+
+    fn multipart(field: &str, file_name: &str, bytes: &[u8]) -> (String, Vec<u8>) {
+        let boundary = "evidence-boundary";
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{field}\"; filename=\"{file_name}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+        (format!("multipart/form-data; boundary={boundary}"), body)
+    }
+
+    #[actix_web::test]
+    async fn test_content_type_is_sniffed_from_the_bytes() {
+        assert_eq!(sniff_content_type(PDF), Some("application/pdf"));
+        assert_eq!(
+            sniff_content_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_content_type(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_content_type(b"<html><body>"), None);
+        assert_eq!(sniff_content_type(b"%PD"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_file_names_are_cleaned() {
+        assert_eq!(
+            clean_file_name(Some("death-certificate.pdf")),
+            "death-certificate.pdf"
+        );
+        assert_eq!(clean_file_name(Some("../../etc/passwd")), "passwd");
+        assert_eq!(clean_file_name(Some("C:\\scans\\order.pdf")), "order.pdf");
+        assert_eq!(clean_file_name(Some("a\"b\r\n.pdf")), "ab.pdf");
+        assert_eq!(clean_file_name(Some("..")), "evidence");
+        assert_eq!(clean_file_name(None), "evidence");
+    }
+
+    #[actix_web::test]
+    async fn test_local_store_round_trip() {
+        let root = temp_root();
+        let store = LocalStore::new(root.clone());
+
+        store.put("claims/1/abc", b"first").unwrap();
+        store.put("claims/1/abc", b"second").unwrap();
+        assert_eq!(store.get("claims/1/abc").unwrap(), b"second");
+
+        store.delete("claims/1/abc").unwrap();
+        assert!(matches!(
+            store.get("claims/1/abc"),
+            Err(StorageError::NotFound(_))
+        ));
+        // Deleting twice is fine
+        store.delete("claims/1/abc").unwrap();
+
+        // Nothing but the object itself is left behind
+        let entries = std::fs::read_dir(root.join("claims/1")).unwrap().count();
+        assert_eq!(entries, 0);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_keys_cannot_escape_the_root() {
+        for key in [
+            "",
+            "../secret",
+            "claims/../../secret",
+            "/etc/passwd",
+            "claims//1",
+            "claims/1/",
+            "claims/1 2",
+        ] {
+            assert!(validate_key(key).is_err(), "{:?}", key);
+        }
+        assert!(validate_key("claims/12/3f9c-a_b.pdf").is_ok());
+
+        let store = LocalStore::new(temp_root());
+        assert!(matches!(
+            store.put("../outside", b"x"),
+            Err(StorageError::InvalidKey(_))
+        ));
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_evidence_upload_and_access() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let root = temp_root();
+        let store: Arc<dyn ObjectStore> = Arc::new(LocalStore::new(root.clone()));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::from(store))
+                .app_data(web::Data::new(EvidenceLimits { max_bytes: 1024 }))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(claim_evidence_controller::config),
+        )
+        .await;
+
+        let claimant_id = rand::random::<u16>() as i32 + 100_000;
+        let claimant = bearer(claimant_id, &[Role::Beneficiary]);
+        let client = pool.get().await.unwrap();
+        let claim_id: i32 = client
+            .query_one(
+                "INSERT INTO claims (user_id, amount, asset, description, due_at)
+                 VALUES ($1, 100, 'ETH', 'Estate share', NOW() + INTERVAL '7 days')
+                 RETURNING id",
+                &[&claimant_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+
+        let upload = |who: &str, field: &str, bytes: &[u8]| {
+            let (content_type, body) = multipart(field, "certificate.pdf", bytes);
+            test::TestRequest::post()
+                .uri(&format!("/claims/{}/evidence", claim_id))
+                .insert_header(("Authorization", who.to_string()))
+                .insert_header(("Content-Type", content_type))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, upload(&claimant, "file", PDF)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let evidence: serde_json::Value = test::read_body_json(resp).await;
+        let id = evidence["id"].as_i64().unwrap();
+        assert_eq!(evidence["content_type"], "application/pdf");
+        assert_eq!(evidence["file_name"], "certificate.pdf");
+        assert_eq!(evidence["size_bytes"], PDF.len());
+        assert_eq!(evidence["uploaded_by"], claimant_id);
+        assert_eq!(evidence["sha256"].as_str().unwrap().len(), 64);
+        assert!(evidence.get("storage_key").is_none());
+
+        // Too large, not a document, wrong field, someone else's claim
+        let resp = test::call_service(&app, upload(&claimant, "file", &[b'%'; 2048])).await;
+        assert_eq!(resp.status().as_u16(), 413);
+        let resp = test::call_service(&app, upload(&claimant, "file", b"<html>")).await;
+        assert_eq!(resp.status().as_u16(), 415);
+        let resp = test::call_service(&app, upload(&claimant, "other", PDF)).await;
+        assert_eq!(resp.status().as_u16(), 400);
+        let stranger = bearer(claimant_id + 1, &[Role::Beneficiary]);
+        let resp = test::call_service(&app, upload(&stranger, "file", PDF)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        let download = |who: String| {
+            test::TestRequest::get()
+                .uri(&format!("/claims/{}/evidence/{}", claim_id, id))
+                .insert_header(("Authorization", who))
+                .to_request()
+        };
+        let reviewer = bearer(claimant_id + 2, &[Role::Admin]);
+        let resp = test::call_service(&app, download(reviewer.clone())).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/pdf"
+        );
+        assert_eq!(
+            resp.headers().get("Content-Disposition").unwrap(),
+            "attachment; filename=\"certificate.pdf\""
+        );
+        assert_eq!(test::read_body(resp).await.as_ref(), PDF);
+        let resp = test::call_service(&app, download(stranger.clone())).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/claims/{}/evidence", claim_id))
+            .insert_header(("Authorization", claimant.clone()))
+            .to_request();
+        let listed: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+
+        // Once a reviewer has seen it, evidence can be added to but not taken away
+        client
+            .execute(
+                "UPDATE claims SET status = 'needs_information' WHERE id = $1",
+                &[&claim_id],
+            )
+            .await
+            .unwrap();
+        let resp = test::call_service(&app, upload(&claimant, "file", PDF)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let req = test::TestRequest::delete()
+            .uri(&format!("/claims/{}/evidence/{}", claim_id, id))
+            .insert_header(("Authorization", claimant.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+
+        // Once decided, the claim's evidence is frozen
+        client
+            .execute(
+                "UPDATE claims SET status = 'approved' WHERE id = $1",
+                &[&claim_id],
+            )
+            .await
+            .unwrap();
+        let resp = test::call_service(&app, upload(&claimant, "file", PDF)).await;
+        assert_eq!(resp.status().as_u16(), 409);
+        let req = test::TestRequest::delete()
+            .uri(&format!("/claims/{}/evidence/{}", claim_id, id))
+            .insert_header(("Authorization", claimant.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+        let resp = test::call_service(&app, download(claimant.clone())).await;
+        assert_eq!(resp.status().as_u16(), 200);
+
+        // A disputed claim takes new evidence, but keeps what it was decided on
+        client
+            .execute(
+                "UPDATE claims SET status = 'disputed' WHERE id = $1",
+                &[&claim_id],
+            )
+            .await
+            .unwrap();
+        let resp = test::call_service(&app, upload(&claimant, "file", PDF)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let req = test::TestRequest::delete()
+            .uri(&format!("/claims/{}/evidence/{}", claim_id, id))
+            .insert_header(("Authorization", claimant.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+
+        client
+            .execute("DELETE FROM claims WHERE id = $1", &[&claim_id])
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{App, test, web};
+    use chrono::{Duration, TimeZone, Utc};
+    use deadpool_postgres::Pool;
+
+    use crate::controller::claim_controller;
+    use crate::jobs::claim_sla_monitor;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::claim::{ClaimPriority, ClaimSla, ClaimStatus};
+    use crate::repositories::{claim_repository, user_repository};
+    use crate::tests::{bearer, test_auth_config};
+    use crate::utils::clock::ManualClock;
+
+    const SLA: ClaimSla = ClaimSla {
+        low_hours: 336,
+        normal_hours: 168,
+        high_hours: 72,
+        urgent_hours: 24,
+    };
+
+    // A pending claim, due a week after it was filed
+    async fn file_claim(pool: &Pool, claimant_id: i32, filed_days_ago: i64) -> i32 {
+        let client = pool.get().await.unwrap();
+        let filed_at = Utc::now() - Duration::days(filed_days_ago);
+        client
+            .query_one(
+                "INSERT INTO claims (user_id, amount, asset, description, created_at, due_at)
+                 VALUES ($1, 100, 'ETH', 'Estate share', $2, $2::TIMESTAMPTZ + INTERVAL '7 days')
+                 RETURNING id",
+                &[&claimant_id, &filed_at],
+            )
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    async fn reviewer(pool: &Pool, roles: &[Role]) -> i32 {
+        let client = pool.get().await.unwrap();
+        let email = format!("reviewer-{:016x}@example.com", rand::random::<u64>());
+        let user = user_repository::create_user(&client, &email, "x")
+            .await
+            .unwrap();
+        user_repository::set_roles(&client, user.id, roles)
+            .await
+            .unwrap();
+        user.id
+    }
+
+    async fn delete_claims(pool: &Pool, ids: &[i32]) {
+        let client = pool.get().await.unwrap();
+        client
+            .execute("DELETE FROM claims WHERE id = ANY($1)", &[&ids])
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_open_claims_are_the_undecided_ones() {
+        let open: Vec<ClaimStatus> = [
+            ClaimStatus::Pending,
+            ClaimStatus::UnderReview,
+            ClaimStatus::NeedsInformation,
+            ClaimStatus::Approved,
+            ClaimStatus::Rejected,
+            ClaimStatus::Disputed,
+            ClaimStatus::Paid,
+        ]
+        .into_iter()
+        .filter(ClaimStatus::is_open)
+        .collect();
+
+        assert_eq!(
+            open,
+            [
+                ClaimStatus::Pending,
+                ClaimStatus::UnderReview,
+                ClaimStatus::NeedsInformation,
+                ClaimStatus::Disputed,
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_priorities_set_the_deadline() {
+        for (priority, hours) in [
+            (ClaimPriority::Low, 336),
+            (ClaimPriority::Normal, 168),
+            (ClaimPriority::High, 72),
+            (ClaimPriority::Urgent, 24),
+        ] {
+            assert_eq!(SLA.hours(priority), hours);
+            assert_eq!(priority.as_str().parse::<ClaimPriority>(), Ok(priority));
+            assert_eq!(
+                serde_json::to_value(priority).unwrap(),
+                serde_json::json!(priority.as_str())
+            );
+        }
+        assert_eq!(ClaimPriority::default(), ClaimPriority::Normal);
+        assert!("critical".parse::<ClaimPriority>().is_err());
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_concurrent_reviewers_never_share_a_claim() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let claimant_id = rand::random::<u16>() as i32 + 100_000;
+        let mut filed = Vec::new();
+        for _ in 0..4 {
+            filed.push(file_claim(&pool, claimant_id, 3650).await);
+        }
+
+        // As many reviewers as claims, all asking at once. The claims were
+        // filed long ago, so they are at the head of the queue.
+        let takes = (1..=filed.len() as i32).map(|n| {
+            let pool = pool.clone();
+            async move {
+                let mut client = pool.get().await.unwrap();
+                claim_repository::take_next(&mut client, claimant_id + n)
+                    .await
+                    .unwrap()
+                    .unwrap()
+            }
+        });
+        let taken = futures_util::future::join_all(takes).await;
+
+        let mut ids: Vec<i32> = taken.iter().map(|claim| claim.id).collect();
+        ids.sort();
+        assert_eq!(ids, filed);
+        let mut reviewers: Vec<Option<i32>> = taken.iter().map(|claim| claim.assignee_id).collect();
+        reviewers.sort();
+        reviewers.dedup();
+        assert_eq!(reviewers.len(), filed.len());
+        for claim in &taken {
+            assert_eq!(claim.status, ClaimStatus::UnderReview);
+            assert_ne!(claim.assigned_at, None);
+        }
+
+        let client = pool.get().await.unwrap();
+        let history = claim_repository::get_history(&client, filed[0])
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].from_status, Some(ClaimStatus::Pending));
+        assert_eq!(history[0].to_status, ClaimStatus::UnderReview);
+
+        delete_claims(&pool, &filed).await;
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_supervisors_manage_the_queue() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(SLA))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(claim_controller::config),
+        )
+        .await;
+
+        let claimant_id = rand::random::<u16>() as i32 + 100_000;
+        let id = file_claim(&pool, claimant_id, 1).await;
+        let alice_id = reviewer(&pool, &[Role::ClaimReviewer]).await;
+        let alice = bearer(alice_id, &[Role::ClaimReviewer]);
+        let bob_id = reviewer(&pool, &[Role::ClaimReviewer]).await;
+        let bob = bearer(bob_id, &[Role::ClaimReviewer]);
+        let owner_id = reviewer(&pool, &[Role::Owner]).await;
+        let supervisor = bearer(owner_id + 1_000_000, &[Role::Admin]);
+
+        let assign = |who: &str, assignee: serde_json::Value| {
+            test::TestRequest::put()
+                .uri(&format!("/claims/{}/assignee", id))
+                .insert_header(("Authorization", who.to_string()))
+                .set_json(serde_json::json!({"assignee_id": assignee}))
+                .to_request()
+        };
+        let resp = test::call_service(&app, assign(&alice, alice_id.into())).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(&app, assign(&supervisor, owner_id.into())).await;
+        assert_eq!(resp.status().as_u16(), 400);
+
+        let resp = test::call_service(&app, assign(&supervisor, alice_id.into())).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let claim: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(claim["assignee_id"], alice_id);
+        assert_eq!(claim["status"], "pending");
+
+        let queue = |who: &str| {
+            test::TestRequest::get()
+                .uri("/claims/queue/mine")
+                .insert_header(("Authorization", who.to_string()))
+                .to_request()
+        };
+        let mine: serde_json::Value =
+            test::read_body_json(test::call_service(&app, queue(&alice)).await).await;
+        assert_eq!(mine.as_array().unwrap().len(), 1);
+        assert_eq!(mine[0]["id"], id);
+        let resp =
+            test::call_service(&app, queue(&bearer(claimant_id, &[Role::Beneficiary]))).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        // Only the assigned reviewer works the claim
+        let review = |who: &str| {
+            test::TestRequest::put()
+                .uri(&format!("/claims/{}", id))
+                .insert_header(("Authorization", who.to_string()))
+                .set_json(serde_json::json!({"status": "under_review"}))
+                .to_request()
+        };
+        assert_eq!(
+            test::call_service(&app, review(&bob))
+                .await
+                .status()
+                .as_u16(),
+            403
+        );
+        let resp = test::call_service(&app, assign(&supervisor, bob_id.into())).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            test::call_service(&app, review(&bob))
+                .await
+                .status()
+                .as_u16(),
+            200
+        );
+        let mine: serde_json::Value =
+            test::read_body_json(test::call_service(&app, queue(&alice)).await).await;
+        assert!(mine.as_array().unwrap().is_empty());
+
+        let client = pool.get().await.unwrap();
+        let notices: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM notifications
+                 WHERE user_id = ANY($1) AND title = 'Claim assigned to you'",
+                &[&vec![alice_id, bob_id]],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(notices, 2);
+
+        // Urgent claims are due a day after filing
+        let req = test::TestRequest::put()
+            .uri(&format!("/claims/{}/priority", id))
+            .insert_header(("Authorization", supervisor.clone()))
+            .set_json(serde_json::json!({"priority": "urgent"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let claim = claim_repository::get_by_id(&client, id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(claim.priority, ClaimPriority::Urgent);
+        assert_eq!(claim.due_at - claim.created_at, Duration::hours(24));
+
+        // Decided claims have left the queue
+        client
+            .execute(
+                "UPDATE claims SET status = 'rejected' WHERE id = $1",
+                &[&id],
+            )
+            .await
+            .unwrap();
+        let resp = test::call_service(&app, assign(&supervisor, alice_id.into())).await;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        delete_claims(&pool, &[id]).await;
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_overdue_claims_are_flagged_once() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let supervisor_id = reviewer(&pool, &[Role::Admin]).await;
+        let claimant_id = rand::random::<u16>() as i32 + 100_000;
+        // Due six days from now
+        let id = file_claim(&pool, claimant_id, 1).await;
+        let client = pool.get().await.unwrap();
+        let due_at = claim_repository::get_by_id(&client, id)
+            .await
+            .unwrap()
+            .unwrap()
+            .due_at;
+
+        let clock = Arc::new(ManualClock::new(due_at - Duration::minutes(1)));
+        let flagged = |claim_id: i32| {
+            let pool = pool.clone();
+            async move {
+                let client = pool.get().await.unwrap();
+                claim_repository::get_by_id(&client, claim_id)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .overdue_at
+            }
+        };
+        let notices = || async {
+            client
+                .query_one(
+                    "SELECT COUNT(*) FROM notifications
+                     WHERE user_id = $1 AND body LIKE $2",
+                    &[&supervisor_id, &format!("Claim {} %", id)],
+                )
+                .await
+                .unwrap()
+                .get::<_, i64>(0)
+        };
+
+        claim_sla_monitor::run_pass(&pool, clock.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(flagged(id).await, None);
+        assert_eq!(notices().await, 0);
+
+        clock.advance(Duration::minutes(1));
+        claim_sla_monitor::run_pass(&pool, clock.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(
+            flagged(id).await.map(|at| at.timestamp()),
+            Some(due_at.timestamp())
+        );
+        assert_eq!(notices().await, 1);
+
+        clock.advance(Duration::hours(1));
+        claim_sla_monitor::run_pass(&pool, clock.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(notices().await, 1);
+
+        // A later deadline lifts the flag
+        let claim = claim_repository::prioritize(&client, id, ClaimPriority::Low, &SLA)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(claim.overdue_at, None);
+        assert_eq!(claim.due_at - claim.created_at, Duration::hours(336));
+
+        // Decided claims are never overdue
+        client
+            .execute(
+                "UPDATE claims SET status = 'approved', due_at = $2 WHERE id = $1",
+                &[&id, &Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap()],
+            )
+            .await
+            .unwrap();
+        claim_sla_monitor::run_pass(&pool, clock.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(flagged(id).await, None);
+
+        delete_claims(&pool, &[id]).await;
+    }
+}
@@ -0,0 +1,334 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+
+    use crate::controller::{claim_controller, plan_controller};
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::claim::{ClaimSla, ClaimStatus, UpdateClaim};
+    use crate::tests::{bearer, test_auth_config, trigger_plan};
+
+    const ALL: [ClaimStatus; 7] = [
+        ClaimStatus::Pending,
+        ClaimStatus::UnderReview,
+        ClaimStatus::NeedsInformation,
+        ClaimStatus::Approved,
+        ClaimStatus::Rejected,
+        ClaimStatus::Disputed,
+        ClaimStatus::Paid,
+    ];
+
+    #[actix_web::test]
+    async fn test_claim_review_transitions() {
+        assert!(ClaimStatus::Pending.can_transition_to(ClaimStatus::UnderReview));
+        assert!(ClaimStatus::UnderReview.can_transition_to(ClaimStatus::NeedsInformation));
+        assert!(ClaimStatus::NeedsInformation.can_transition_to(ClaimStatus::UnderReview));
+        assert!(ClaimStatus::UnderReview.can_transition_to(ClaimStatus::Approved));
+        assert!(ClaimStatus::Approved.can_transition_to(ClaimStatus::Paid));
+        assert!(ClaimStatus::Rejected.can_transition_to(ClaimStatus::Disputed));
+        assert!(ClaimStatus::Disputed.can_transition_to(ClaimStatus::UnderReview));
+
+        // Decisions are only made from review
+        assert!(!ClaimStatus::Pending.can_transition_to(ClaimStatus::Approved));
+        assert!(!ClaimStatus::NeedsInformation.can_transition_to(ClaimStatus::Approved));
+        // Decided claims don't go back to the start
+        assert!(!ClaimStatus::Rejected.can_transition_to(ClaimStatus::Pending));
+        assert!(!ClaimStatus::Approved.can_transition_to(ClaimStatus::Rejected));
+        assert!(!ClaimStatus::Rejected.can_transition_to(ClaimStatus::Paid));
+    }
+
+    #[actix_web::test]
+    async fn test_paid_claims_are_final() {
+        for next in ALL {
+            assert!(!ClaimStatus::Paid.can_transition_to(next), "{}", next);
+        }
+        for status in ALL {
+            assert!(!status.can_transition_to(status), "{}", status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_claimant_transitions_are_legal() {
+        for current in ALL {
+            for next in ALL {
+                if current.is_claimant_transition(next) {
+                    assert!(current.can_transition_to(next), "{} -> {}", current, next);
+                }
+            }
+        }
+        assert!(ClaimStatus::NeedsInformation.is_claimant_transition(ClaimStatus::UnderReview));
+        assert!(!ClaimStatus::Rejected.is_claimant_transition(ClaimStatus::Disputed));
+        assert!(!ClaimStatus::UnderReview.is_claimant_transition(ClaimStatus::Approved));
+    }
+
+    #[actix_web::test]
+    async fn test_status_names_round_trip() {
+        for status in ALL {
+            assert_eq!(status.as_str().parse::<ClaimStatus>(), Ok(status));
+            assert_eq!(
+                serde_json::to_value(status).unwrap(),
+                serde_json::json!(status.as_str())
+            );
+        }
+        assert!("underreview".parse::<ClaimStatus>().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_some_transitions_need_a_reason() {
+        let update = |status: ClaimStatus, reason: Option<&str>| UpdateClaim {
+            status: Some(status),
+            description: None,
+            reason: reason.map(str::to_string),
+        };
+
+        assert!(update(ClaimStatus::UnderReview, None).validate().is_ok());
+        assert!(update(ClaimStatus::Approved, None).validate().is_ok());
+        assert!(update(ClaimStatus::Rejected, None).validate().is_err());
+        assert!(
+            update(ClaimStatus::Rejected, Some("  "))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            update(
+                ClaimStatus::NeedsInformation,
+                Some("Upload the death certificate")
+            )
+            .validate()
+            .is_ok()
+        );
+        assert!(update(ClaimStatus::Disputed, None).validate().is_err());
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_claim_review_lifecycle() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(ClaimSla {
+                    low_hours: 336,
+                    normal_hours: 168,
+                    high_hours: 72,
+                    urgent_hours: 24,
+                }))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(plan_controller::config)
+                .configure(claim_controller::config),
+        )
+        .await;
+
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let owner = bearer(owner_id, &[Role::Owner]);
+        let claimant_id = owner_id + 1;
+        let claimant = bearer(claimant_id, &[Role::Beneficiary]);
+        let reviewer = bearer(owner_id + 2, &[Role::Admin]);
+
+        let req = test::TestRequest::post()
+            .uri("/plans")
+            .insert_header(("Authorization", owner.clone()))
+            .set_json(serde_json::json!({
+                "name": "Family estate",
+                "assets": [{"asset": "ETH", "amount": 1000}],
+                "beneficiaries": [{
+                    "user_id": claimant_id,
+                    "name": "Alice",
+                    "wallet_address": "0x1111111111111111111111111111111111111111",
+                    "allocation": {"type": "percentage", "share_bps": 10000}
+                }]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let plan: serde_json::Value = test::read_body_json(resp).await;
+        let plan_id = plan["id"].as_i64().unwrap();
+
+        let file = |asset: &str| {
+            test::TestRequest::post()
+                .uri("/claims")
+                .insert_header(("Authorization", claimant.clone()))
+                .set_json(serde_json::json!({
+                    "user_id": claimant_id,
+                    "plan_id": plan_id,
+                    "asset": asset,
+                    "amount": "1000000000000000000000",
+                    "description": "Estate share"
+                }))
+                .to_request()
+        };
+        let resp = test::call_service(&app, file("BTC")).await;
+        assert_eq!(resp.status().as_u16(), 400);
+        let resp = test::call_service(&app, file("ETH")).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let body = test::read_body(resp).await;
+        // Larger than any i64, and returned exactly
+        let raw = std::str::from_utf8(&body).unwrap();
+        assert!(
+            raw.contains(r#""amount":"1000000000000000000000","#),
+            "{}",
+            raw
+        );
+        let claim: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let id = claim["id"].as_i64().unwrap();
+        assert_eq!(claim["status"], "pending");
+        assert_eq!(claim["asset"], "ETH");
+
+        // Claimants list their own claims, reviewers everyone's
+        let list = |uri: String, who: &str| {
+            test::TestRequest::get()
+                .uri(&uri)
+                .insert_header(("Authorization", who.to_string()))
+                .to_request()
+        };
+        let stranger = bearer(owner_id + 3, &[Role::Beneficiary]);
+        let mine = format!("/claims/user/{}", claimant_id);
+        let mine_pending = format!("/claims/user/{}/status/pending", claimant_id);
+        for uri in [mine.clone(), mine_pending.clone()] {
+            let listed: Vec<serde_json::Value> =
+                test::call_and_read_body_json(&app, list(uri.clone(), &claimant)).await;
+            assert_eq!(listed.len(), 1);
+            let resp = test::call_service(&app, list(uri.clone(), &stranger)).await;
+            assert_eq!(resp.status().as_u16(), 403);
+            let resp = test::call_service(&app, list(uri, &reviewer)).await;
+            assert_eq!(resp.status().as_u16(), 200);
+        }
+        for uri in ["/claims", "/claims/status/pending"] {
+            let resp = test::call_service(&app, list(uri.to_string(), &claimant)).await;
+            assert_eq!(resp.status().as_u16(), 403);
+            let resp = test::call_service(&app, list(uri.to_string(), &reviewer)).await;
+            assert_eq!(resp.status().as_u16(), 200);
+        }
+        let req = test::TestRequest::get().uri(&mine).to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+        let update = |who: &str, body: serde_json::Value| {
+            test::TestRequest::put()
+                .uri(&format!("/claims/{}", id))
+                .insert_header(("Authorization", who.to_string()))
+                .set_json(body)
+                .to_request()
+        };
+        let status_of = |resp| async { test::read_body_json::<serde_json::Value, _>(resp).await };
+
+        // The description is the claimant's to edit, while the claim is pending
+        let resp = test::call_service(
+            &app,
+            update(&reviewer, serde_json::json!({"description": "Rewritten"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(
+            &app,
+            update(
+                &claimant,
+                serde_json::json!({"description": "Estate share, half of it"}),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            status_of(resp).await["description"],
+            "Estate share, half of it"
+        );
+
+        // Claimants don't review their own claims
+        let resp = test::call_service(
+            &app,
+            update(&claimant, serde_json::json!({"status": "under_review"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 403);
+        // Nor can a reviewer skip review
+        let resp = test::call_service(
+            &app,
+            update(&reviewer, serde_json::json!({"status": "approved"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        for (who, body) in [
+            (&reviewer, serde_json::json!({"status": "under_review"})),
+            (
+                &reviewer,
+                serde_json::json!({
+                    "status": "needs_information",
+                    "reason": "Upload the death certificate"
+                }),
+            ),
+            (&claimant, serde_json::json!({"status": "under_review"})),
+        ] {
+            let resp = test::call_service(&app, update(who, body.clone())).await;
+            assert_eq!(resp.status().as_u16(), 200, "{}", body);
+            assert_eq!(status_of(resp).await["status"], body["status"]);
+        }
+
+        // The owner is still alive
+        let resp = test::call_service(
+            &app,
+            update(&reviewer, serde_json::json!({"status": "approved"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 409);
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/activate", plan_id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+        trigger_plan(&pool, plan_id as i32).await;
+        let resp = test::call_service(
+            &app,
+            update(&reviewer, serde_json::json!({"status": "approved"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 200);
+        // Nor once it is decided
+        let resp = test::call_service(
+            &app,
+            update(&claimant, serde_json::json!({"description": "Rewritten"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        let history = |who: String| {
+            test::TestRequest::get()
+                .uri(&format!("/claims/{}/history", id))
+                .insert_header(("Authorization", who))
+                .to_request()
+        };
+        let resp =
+            test::call_service(&app, history(bearer(owner_id + 3, &[Role::Beneficiary]))).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(&app, history(claimant.clone())).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let changes: serde_json::Value = test::read_body_json(resp).await;
+        let moves: Vec<(serde_json::Value, serde_json::Value)> = changes
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|change| (change["from_status"].clone(), change["to_status"].clone()))
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                (serde_json::Value::Null, "pending".into()),
+                ("pending".into(), "under_review".into()),
+                ("under_review".into(), "needs_information".into()),
+                ("needs_information".into(), "under_review".into()),
+                ("under_review".into(), "approved".into()),
+            ]
+        );
+        assert_eq!(changes[2]["reason"], "Upload the death certificate");
+        assert_eq!(changes[3]["actor_id"], claimant_id);
+
+        let client = pool.get().await.unwrap();
+        client
+            .execute("DELETE FROM claims WHERE id = $1", &[&(id as i32)])
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use log::LevelFilter;
+
+    use crate::config::env::{KycProviderBackend, Settings, StorageBackend, TlsMode};
+    use crate::models::claim::ClaimSla;
+    use crate::models::kyc_document_models::KycDocumentLimits;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+    const MASTER_KEY: &str = "fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210";
+    const BLIND_INDEX_KEY: &str =
+        "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn minimal_env() -> HashMap<String, String> {
+        env(&[
+            ("DATABASE_HOST", "db.internal"),
+            ("DATABASE_USER", "inheritx"),
+            ("DATABASE_PASSWORD", "hunter2"),
+            ("DATABASE_NAME", "inheritx_db"),
+            ("JWT_SECRET", SECRET),
+            ("KYC_MASTER_KEYS", &format!("prod-1:{}", MASTER_KEY)),
+            ("KYC_BLIND_INDEX_KEY", BLIND_INDEX_KEY),
+        ])
+    }
+
+    #[actix_web::test]
+    async fn test_defaults_fill_in_optional_keys() {
+        let settings = Settings::from_sources(None, &minimal_env()).unwrap();
+
+        assert_eq!(settings.database.host, "db.internal");
+        assert_eq!(settings.database.port, 5432);
+        assert_eq!(settings.database.pool_size, 16);
+        assert_eq!(settings.database.tls_mode, TlsMode::Disable);
+        assert_eq!(settings.bind_address.to_string(), "127.0.0.1:8080");
+        assert_eq!(settings.log_level, LevelFilter::Info);
+        assert!(settings.starknet_account_class_hash.is_none());
+        assert!(settings.features.wallet_login);
+        assert!(settings.features.run_migrations);
+        assert!(settings.features.check_in_scheduler);
+        assert_eq!(settings.check_in_poll_secs, 60);
+        assert_eq!(settings.storage.backend, StorageBackend::Local);
+        assert_eq!(settings.storage.local_root, PathBuf::from("storage"));
+        assert_eq!(settings.claim_evidence_max_bytes, 10 << 20);
+        assert_eq!(
+            settings.claim_sla,
+            ClaimSla {
+                low_hours: 336,
+                normal_hours: 168,
+                high_hours: 72,
+                urgent_hours: 24,
+            }
+        );
+        assert_eq!(settings.claim_sla_poll_secs, 300);
+        assert_eq!(settings.claim_appeal_window_days, 30);
+        assert_eq!(
+            settings.kyc_documents,
+            KycDocumentLimits {
+                max_bytes: 10 << 20,
+                min_side_px: 480,
+                max_side_px: 8000,
+            }
+        );
+        assert!(settings.features.claim_sla_monitor);
+        assert_eq!(settings.kyc_master_keys.current_key_id(), "prod-1");
+        assert_eq!(settings.kyc_reencrypt_poll_secs, 300);
+        assert!(settings.features.kyc_reencryption);
+        assert!(!settings.features.kyc_provider);
+        assert_eq!(settings.kyc_provider.backend, KycProviderBackend::Mock);
+        assert!(settings.kyc_provider.webhook_secret.is_none());
+        assert_eq!(settings.kyc_provider.webhook_tolerance_secs, 300);
+    }
+
+    #[actix_web::test]
+    async fn test_example_file_loads() {
+        let file = include_str!("../../config.example.toml");
+        let settings = Settings::from_sources(Some(file), &HashMap::new()).unwrap();
+
+        assert_eq!(settings.database.host, "localhost");
+        assert_eq!(settings.claim_sla.urgent_hours, 24);
+        assert_eq!(settings.claim_evidence_max_bytes, 10 << 20);
+        assert_eq!(settings.claim_appeal_window_days, 30);
+        assert_eq!(settings.kyc_documents.max_side_px, 8000);
+        assert_eq!(settings.kyc_master_keys.key_ids(), ["dev-1"]);
+    }
+
+    #[actix_web::test]
+    async fn test_environment_overrides_file() {
+        let file = r#"
+            bind_address = "0.0.0.0:9000"
+            log_level = "debug"
+            jwt_secret = "0123456789abcdef0123456789abcdef"
+
+            [database]
+            host = "from-file"
+            port = 6432
+            user = "inheritx"
+            password = "from-file"
+            name = "inheritx_db"
+            tls_mode = "verify-full"
+
+            [features]
+            wallet_login = false
+
+            [kyc]
+            master_keys = "old:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210"
+            blind_index_key = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff"
+        "#;
+        let env = env(&[
+            ("DATABASE_HOST", "from-env"),
+            ("LOG_LEVEL", "warn"),
+            (
+                "KYC_MASTER_KEYS",
+                "new:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef, old:fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210",
+            ),
+        ]);
+
+        let settings = Settings::from_sources(Some(file), &env).unwrap();
+
+        assert_eq!(settings.database.host, "from-env");
+        assert_eq!(settings.database.port, 6432);
+        assert_eq!(settings.database.password, "from-file");
+        assert_eq!(settings.database.tls_mode, TlsMode::VerifyFull);
+        assert_eq!(settings.bind_address.to_string(), "0.0.0.0:9000");
+        assert_eq!(settings.log_level, LevelFilter::Warn);
+        assert!(!settings.features.wallet_login);
+        assert_eq!(settings.kyc_master_keys.key_ids(), ["new", "old"]);
+    }
+
+    #[actix_web::test]
+    async fn test_every_problem_is_reported() {
+        let env = env(&[
+            ("DATABASE_HOST", "db.internal"),
+            ("DATABASE_PORT", "not-a-port"),
+            ("DATABASE_POOL_SIZE", "0"),
+            ("DATABASE_TLS_MODE", "sometimes"),
+            ("JWT_SECRET", "short"),
+            ("STARKNET_ACCOUNT_CLASS_HASH", "xyz"),
+            ("CLAIMS_SLA_URGENT_HOURS", "0"),
+            ("KYC_DOCUMENT_MIN_SIDE_PX", "600"),
+            ("KYC_DOCUMENT_MAX_SIDE_PX", "500"),
+            ("KYC_MASTER_KEYS", "prod-1:0123456789abcdef"),
+            ("KYC_BLIND_INDEX_KEY", "0123456789abcdef"),
+            ("KYC_PROVIDER", "acme"),
+            ("KYC_PROVIDER_WEBHOOK_SECRET", "0123456789abcdef"),
+            ("KYC_PROVIDER_WEBHOOK_TOLERANCE_SECS", "0"),
+        ]);
+
+        let err = Settings::from_sources(None, &env).unwrap_err();
+        let keys: Vec<&str> = err
+            .problems
+            .iter()
+            .map(|problem| problem.split(':').next().unwrap())
+            .collect();
+
+        assert_eq!(
+            keys,
+            [
+                "DATABASE_PORT",
+                "DATABASE_USER",
+                "DATABASE_PASSWORD",
+                "DATABASE_NAME",
+                "DATABASE_TLS_MODE",
+                "STARKNET_ACCOUNT_CLASS_HASH",
+                "KYC_MASTER_KEYS",
+                "KYC_BLIND_INDEX_KEY",
+                "KYC_PROVIDER",
+                "KYC_PROVIDER_WEBHOOK_SECRET",
+                "DATABASE_POOL_SIZE",
+                "CLAIMS_SLA_URGENT_HOURS",
+                "KYC_DOCUMENT_MAX_SIDE_PX",
+                "KYC_PROVIDER_WEBHOOK_TOLERANCE_SECS",
+                "JWT_SECRET",
+            ]
+        );
+        assert!(err.to_string().starts_with("Invalid configuration:\n  - "));
+        // A malformed key is reported without the key itself
+        assert!(!err.to_string().contains("0123456789abcdef"));
+    }
+
+    #[actix_web::test]
+    async fn test_kyc_provider_needs_a_webhook_secret() {
+        let mut env = minimal_env();
+        env.insert("FEATURES_KYC_PROVIDER".to_string(), "true".to_string());
+
+        let err = Settings::from_sources(None, &env).unwrap_err();
+        assert_eq!(
+            err.problems,
+            ["KYC_PROVIDER_WEBHOOK_SECRET: is required when FEATURES_KYC_PROVIDER is on"]
+        );
+
+        let webhook_secret = "whsec-0123456789abcdef0123456789abcdef";
+        env.insert(
+            "KYC_PROVIDER_WEBHOOK_SECRET".to_string(),
+            webhook_secret.to_string(),
+        );
+        let settings = Settings::from_sources(None, &env).unwrap();
+        assert!(settings.features.kyc_provider);
+        assert!(settings.kyc_provider.webhook_secret.is_some());
+        assert!(!format!("{:?}", settings).contains(webhook_secret));
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_file_is_rejected() {
+        let err = Settings::from_sources(Some("[database"), &minimal_env()).unwrap_err();
+        assert_eq!(err.problems.len(), 1);
+        assert!(err.problems[0].starts_with("INHERITX_CONFIG:"));
+    }
+
+    #[actix_web::test]
+    async fn test_debug_output_hides_secrets() {
+        let settings = Settings::from_sources(None, &minimal_env()).unwrap();
+        let debug = format!("{:?}", settings);
+
+        assert!(!debug.contains("hunter2"));
+        assert!(!debug.contains(SECRET));
+        assert!(!debug.contains(MASTER_KEY));
+        assert!(!debug.contains(BLIND_INDEX_KEY));
+        assert!(debug.contains("prod-1"));
+        assert!(debug.contains("db.internal"));
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, HttpResponse, ResponseError, test, web};
+
+    use crate::middlewares::error_middleware::{AppError, error_envelope};
+    use crate::models::auth_models::Role;
+    use crate::models::faq::FaqRequest;
+
+    async fn missing() -> Result<HttpResponse, AppError> {
+        Err(AppError::NotFound("Plan not found".to_string()))
+    }
+
+    async fn echo(faq: web::Json<FaqRequest>) -> HttpResponse {
+        HttpResponse::Ok().json(faq.into_inner())
+    }
+
+    async fn body_of(resp: HttpResponse) -> serde_json::Value {
+        let bytes = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_app_errors_render_as_envelope() {
+        let cases = [
+            (
+                AppError::NotFound("Plan not found".to_string()),
+                404,
+                "not_found",
+            ),
+            (
+                AppError::Validation("Name is required".to_string()),
+                400,
+                "validation_failed",
+            ),
+            (
+                AppError::Conflict("Already active".to_string()),
+                409,
+                "conflict",
+            ),
+            (
+                AppError::Forbidden("Not yours".to_string()),
+                403,
+                "forbidden",
+            ),
+            (
+                AppError::Unauthorized("Token has expired".to_string()),
+                401,
+                "unauthorized",
+            ),
+            (
+                AppError::PayloadTooLarge("File is too large".to_string()),
+                413,
+                "payload_too_large",
+            ),
+            (
+                AppError::UnsupportedMediaType("Not a PDF".to_string()),
+                415,
+                "unsupported_media_type",
+            ),
+        ];
+
+        for (error, status, code) in cases {
+            let message = error.to_string();
+            let resp = error.error_response();
+            assert_eq!(resp.status().as_u16(), status);
+
+            let body = body_of(resp).await;
+            assert_eq!(body["error"], message.as_str());
+            assert_eq!(body["code"], code);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_missing_role_lists_required_roles() {
+        let resp = AppError::MissingRole(vec![Role::KycReviewer, Role::Admin]).error_response();
+        assert_eq!(resp.status().as_u16(), 403);
+
+        let body = body_of(resp).await;
+        assert_eq!(
+            body["error"],
+            "Missing required role: kyc_reviewer or admin"
+        );
+        assert_eq!(body["code"], "missing_role");
+        assert_eq!(
+            body["required_roles"],
+            serde_json::json!(["kyc_reviewer", "admin"])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_framework_errors_are_wrapped() {
+        let app = test::init_service(
+            App::new()
+                .wrap(error_envelope())
+                .route("/missing", web::get().to(missing))
+                .route("/faqs", web::post().to(echo)),
+        )
+        .await;
+
+        // Handler errors pass through untouched
+        let req = test::TestRequest::get().uri("/missing").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["error"], "Plan not found");
+
+        // A body that does not deserialize
+        let req = test::TestRequest::post()
+            .uri("/faqs")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(r#"{"question": "What is InheritX?"}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 400);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "bad_request");
+        assert!(body["error"].as_str().unwrap().contains("answer"));
+
+        // No such route
+        let req = test::TestRequest::get().uri("/nowhere").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "not_found");
+    }
+}
@@ -0,0 +1,411 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+    use chrono::Utc;
+
+    use crate::controller::{beneficiary_controller, plan_controller};
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::beneficiary_models::{Allocation, Beneficiary};
+    use crate::models::execution_models::compute_payouts;
+    use crate::models::money::Amount;
+    use crate::models::plan_models::{Plan, PlanAsset, PlanStatus};
+    use crate::models::vesting_models::ReleaseSchedule;
+    use crate::tests::{bearer, test_auth_config, trigger_plan};
+
+    fn beneficiary(id: i32, allocation: Allocation) -> Beneficiary {
+        Beneficiary {
+            id,
+            plan_id: 1,
+            user_id: Some(100 + id),
+            name: format!("Beneficiary {}", id),
+            wallet_address: format!("0x{:040x}", id),
+            relationship: None,
+            email: None,
+            allocation,
+            release: ReleaseSchedule::LumpSum,
+            position: id,
+        }
+    }
+
+    fn plan(assets: &[(&str, i64)], beneficiaries: Vec<Beneficiary>) -> Plan {
+        Plan {
+            id: 1,
+            owner_id: 7,
+            name: "Estate".to_string(),
+            description: None,
+            assets: assets
+                .iter()
+                .map(|(asset, amount)| PlanAsset {
+                    asset: asset.to_string(),
+                    amount: Amount::from(*amount),
+                })
+                .collect(),
+            beneficiaries,
+            status: PlanStatus::Triggered,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn amounts(plan: &Plan) -> Vec<(i32, String, i128)> {
+        compute_payouts(plan)
+            .unwrap()
+            .into_iter()
+            .map(|payout| (payout.beneficiary_id, payout.asset, payout.amount.into()))
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_fixed_allocations_are_paid_before_shares() {
+        let plan = plan(
+            &[("ETH", 1_000), ("STRK", 50)],
+            vec![
+                beneficiary(1, Allocation::Percentage { share_bps: 5_000 }),
+                beneficiary(
+                    2,
+                    Allocation::Fixed {
+                        asset: "ETH".to_string(),
+                        amount: Amount::from(400),
+                    },
+                ),
+                beneficiary(3, Allocation::Percentage { share_bps: 5_000 }),
+            ],
+        );
+
+        assert_eq!(
+            amounts(&plan),
+            [
+                (1, "ETH".to_string(), 300),
+                (2, "ETH".to_string(), 400),
+                (3, "ETH".to_string(), 300),
+                (1, "STRK".to_string(), 25),
+                (3, "STRK".to_string(), 25),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_rounding_remainder_is_paid_out() {
+        // 100 split three ways is 33.33 each, so one unit is left over
+        let plan = plan(
+            &[("ETH", 100)],
+            vec![
+                beneficiary(1, Allocation::Percentage { share_bps: 3_333 }),
+                beneficiary(2, Allocation::Percentage { share_bps: 3_334 }),
+                beneficiary(3, Allocation::Percentage { share_bps: 3_333 }),
+            ],
+        );
+
+        // Beneficiary 2 is owed 33.34 and loses the most to rounding
+        let payouts = amounts(&plan);
+        assert_eq!(
+            payouts,
+            [
+                (1, "ETH".to_string(), 33),
+                (2, "ETH".to_string(), 34),
+                (3, "ETH".to_string(), 33),
+            ]
+        );
+        assert_eq!(payouts.iter().map(|p| p.2).sum::<i128>(), 100);
+
+        // Equal fractions go to the earlier beneficiaries
+        let plan = self::plan(
+            &[("ETH", 2)],
+            vec![
+                beneficiary(1, Allocation::Percentage { share_bps: 2_500 }),
+                beneficiary(2, Allocation::Percentage { share_bps: 2_500 }),
+                beneficiary(3, Allocation::Percentage { share_bps: 2_500 }),
+                beneficiary(4, Allocation::Percentage { share_bps: 2_500 }),
+            ],
+        );
+        assert_eq!(
+            amounts(&plan),
+            [(1, "ETH".to_string(), 1), (2, "ETH".to_string(), 1)]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_large_amounts_do_not_overflow() {
+        let mut plan = plan(
+            &[("ETH", 0)],
+            vec![
+                beneficiary(1, Allocation::Percentage { share_bps: 9_999 }),
+                beneficiary(2, Allocation::Percentage { share_bps: 1 }),
+            ],
+        );
+        plan.assets[0].amount = Amount::MAX;
+
+        let total: i128 = amounts(&plan).iter().map(|p| p.2).sum();
+        assert_eq!(total, Amount::MAX.into());
+    }
+
+    #[actix_web::test]
+    async fn test_payouts_without_account_stay_unclaimed() {
+        let mut heir = beneficiary(1, Allocation::Percentage { share_bps: 10_000 });
+        heir.user_id = None;
+        let plan = plan(&[("ETH", 10)], vec![heir]);
+
+        let payouts = compute_payouts(&plan).unwrap();
+        assert_eq!(payouts[0].user_id, None);
+    }
+
+    #[actix_web::test]
+    async fn test_incomplete_allocations_are_rejected() {
+        let plan = plan(
+            &[("ETH", 10)],
+            vec![beneficiary(1, Allocation::Percentage { share_bps: 5_000 })],
+        );
+        assert!(compute_payouts(&plan).is_err());
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_execution_is_idempotent() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(plan_controller::config),
+        )
+        .await;
+
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let owner = bearer(owner_id, &[Role::Owner]);
+
+        let req = test::TestRequest::post()
+            .uri("/plans")
+            .insert_header(("Authorization", owner.clone()))
+            .set_json(serde_json::json!({
+                "name": "Family estate",
+                "assets": [{"asset": "ETH", "amount": 1000}],
+                "beneficiaries": [
+                    {
+                        "user_id": owner_id + 1,
+                        "name": "Alice",
+                        "wallet_address": "0x1111111111111111111111111111111111111111",
+                        "allocation": {"type": "percentage", "share_bps": 3333}
+                    },
+                    {
+                        "name": "Bob",
+                        "wallet_address": "0x2222222222222222222222222222222222222222",
+                        "allocation": {"type": "percentage", "share_bps": 6667}
+                    }
+                ]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        let id = created["id"].as_i64().unwrap();
+
+        // Only the owner and admins see the plan listed
+        for (who, listed) in [
+            (owner.clone(), true),
+            (bearer(owner_id + 2, &[Role::Owner]), false),
+            (bearer(owner_id + 2, &[Role::Admin]), true),
+        ] {
+            let req = test::TestRequest::get()
+                .uri("/plans")
+                .insert_header(("Authorization", who))
+                .to_request();
+            let plans: serde_json::Value =
+                test::read_body_json(test::call_service(&app, req).await).await;
+            let ids: Vec<i64> = plans
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|plan| plan["id"].as_i64().unwrap())
+                .collect();
+            assert_eq!(ids.contains(&id), listed);
+        }
+
+        // Not triggered yet
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/execute", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/activate", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+        // Owners can't trigger their own plans
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/trigger", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+        trigger_plan(&pool, id as i32).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/execute", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let execution: serde_json::Value = test::read_body_json(resp).await;
+        let payouts = execution["payouts"].as_array().unwrap();
+        let amounts: Vec<&str> = payouts
+            .iter()
+            .map(|payout| payout["amount"].as_str().unwrap())
+            .collect();
+        assert_eq!(amounts, ["333", "667"]);
+        assert!(payouts.iter().all(|payout| payout["status"] == "requested"));
+        // Bob has no account yet, so his payout is left unclaimed
+        assert_eq!(payouts[0]["user_id"], owner_id + 1);
+        assert!(payouts[1]["user_id"].is_null());
+
+        // Running it again hands back the same execution
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/execute", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let again: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(again["id"], execution["id"]);
+        assert_eq!(again["payouts"], execution["payouts"]);
+
+        let client = pool.get().await.unwrap();
+        let recorded: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM withdrawal_history WHERE plan_id = $1",
+                &[&(id as i32)],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(recorded, 2);
+        let steps: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM user_activities
+                 WHERE activity_type = 'plan_execution' AND action_link = $1",
+                &[&format!("/plans/{}/execution", id)],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        // Alice's payout and the owner's summary, Bob has no feed to log to
+        assert_eq!(steps, 2);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/plans/{}/execution", id))
+            .insert_header(("Authorization", bearer(owner_id + 2, &[Role::Owner])))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/plans/{}/execution", id))
+            .insert_header(("Authorization", bearer(owner_id + 2, &[Role::Admin])))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_linking_an_account_claims_unclaimed_payouts() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                // Ahead of the plan scope, as in routes::configure
+                .configure(beneficiary_controller::config)
+                .configure(plan_controller::config),
+        )
+        .await;
+
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let bob_id = owner_id + 1;
+        let owner = bearer(owner_id, &[Role::Owner]);
+
+        let req = test::TestRequest::post()
+            .uri("/plans")
+            .insert_header(("Authorization", owner.clone()))
+            .set_json(serde_json::json!({
+                "name": "Bob's inheritance",
+                "assets": [{"asset": "ETH", "amount": 100}],
+                "beneficiaries": [{
+                    "name": "Bob",
+                    "wallet_address": "0x2222222222222222222222222222222222222222",
+                    "allocation": {"type": "percentage", "share_bps": 10000}
+                }]
+            }))
+            .to_request();
+        let created: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let id = created["id"].as_i64().unwrap();
+        let bob = created["beneficiaries"][0]["id"].as_i64().unwrap();
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/activate", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+        trigger_plan(&pool, id as i32).await;
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/execute", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        let execution: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(execution["payouts"][0]["user_id"].is_null());
+
+        let uri = format!("/plans/{}/beneficiaries/{}/account", id, bob);
+        let link = |user_id: i32| serde_json::json!({ "user_id": user_id });
+
+        // Nobody but the owner and admins links accounts
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", bearer(bob_id, &[Role::Owner])))
+            .set_json(link(bob_id))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        // Bob signs up and the owner links him, after execution
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", owner.clone()))
+            .set_json(link(bob_id))
+            .to_request();
+        let linked: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(linked["user_id"], bob_id);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/plans/{}/execution", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        let execution: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(execution["payouts"][0]["user_id"], bob_id);
+
+        let client = pool.get().await.unwrap();
+        let notified: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM user_activities
+                 WHERE user_id = $1 AND action_type = 'payouts_claimed'",
+                &[&bob_id.to_string()],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(notified, 1);
+
+        // His payouts can't be handed to someone else afterwards
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", bearer(owner_id + 2, &[Role::Admin])))
+            .set_json(link(owner_id + 2))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+    }
+}
@@ -0,0 +1,417 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{App, test, web};
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::controller::{check_in_controller, guardian_controller};
+    use crate::jobs::check_in_scheduler;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::check_in_models::ConfigureCheckIn;
+    use crate::models::guardian_models::{
+        Attestation, ConfigureGuardianPolicy, GuardianPolicy, GuardianRound, NewGuardian,
+        RoundStatus, TriggerPolicy,
+    };
+    use crate::models::plan_models::{CreatePlan, Plan, PlanStatus};
+    use crate::repositories::{check_in_repository, guardian_repository, plan_repository};
+    use crate::tests::{bearer, test_auth_config};
+    use crate::utils::clock::{Clock, ManualClock};
+
+    #[actix_web::test]
+    async fn test_policy_validation() {
+        let policy = ConfigureGuardianPolicy {
+            policy: TriggerPolicy::Guardians,
+            quorum: 2,
+            window_secs: 3600,
+        };
+        assert!(policy.validate(3).is_ok());
+        assert!(policy.validate(2).is_ok());
+        // Nobody could ever reach the quorum
+        assert!(policy.validate(1).is_err());
+
+        let inactivity = ConfigureGuardianPolicy {
+            policy: TriggerPolicy::Inactivity,
+            ..policy
+        };
+        assert!(inactivity.validate(0).is_ok());
+
+        let no_window = ConfigureGuardianPolicy {
+            policy: TriggerPolicy::Either,
+            quorum: 1,
+            window_secs: 0,
+        };
+        assert!(no_window.validate(1).is_err());
+
+        // Past a century a round's expiry stops being computable
+        for window_secs in [100 * 365 * 24 * 3600 + 1, i64::MAX] {
+            let endless = ConfigureGuardianPolicy {
+                policy: TriggerPolicy::Either,
+                quorum: 1,
+                window_secs,
+            };
+            assert!(endless.validate(1).is_err());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_round_expiry_never_overflows() {
+        let opened_at = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let mut policy = GuardianPolicy {
+            plan_id: 1,
+            policy: TriggerPolicy::Guardians,
+            quorum: 1,
+            window_secs: 3600,
+            created_at: opened_at,
+            updated_at: opened_at,
+        };
+        assert_eq!(
+            policy.round_expires_at(opened_at),
+            Some(opened_at + Duration::hours(1))
+        );
+        for window_secs in [9_000_000_000_000, i64::MAX] {
+            policy.window_secs = window_secs;
+            assert_eq!(policy.round_expires_at(opened_at), None);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_owner_cannot_guard_own_plan() {
+        let guardian = NewGuardian {
+            user_id: 7,
+            name: "Self".to_string(),
+            email: None,
+        };
+        assert!(guardian.validate(7).is_err());
+        assert!(guardian.validate(8).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_names() {
+        for policy in [
+            TriggerPolicy::Inactivity,
+            TriggerPolicy::Guardians,
+            TriggerPolicy::Either,
+        ] {
+            assert_eq!(policy.as_str().parse::<TriggerPolicy>(), Ok(policy));
+            assert_eq!(serde_json::to_value(policy).unwrap(), policy.as_str());
+        }
+        for status in [
+            RoundStatus::Open,
+            RoundStatus::Confirmed,
+            RoundStatus::Cancelled,
+            RoundStatus::Expired,
+        ] {
+            assert_eq!(status.as_str().parse::<RoundStatus>(), Ok(status));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_revoked_attestations_do_not_count() {
+        let opened_at = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let round = GuardianRound {
+            id: 1,
+            plan_id: 1,
+            status: RoundStatus::Open,
+            opened_at,
+            expires_at: opened_at + Duration::days(2),
+            closed_at: None,
+            attestations: vec![
+                Attestation {
+                    guardian_id: 1,
+                    attested_at: opened_at,
+                    revoked_at: None,
+                },
+                Attestation {
+                    guardian_id: 2,
+                    attested_at: opened_at,
+                    revoked_at: Some(opened_at + Duration::hours(1)),
+                },
+            ],
+        };
+        assert_eq!(round.standing(), 1);
+
+        let open = round.clone().at(opened_at + Duration::days(1));
+        assert_eq!(open.status, RoundStatus::Open);
+        let expired = round.at(opened_at + Duration::days(2));
+        assert_eq!(expired.status, RoundStatus::Expired);
+        assert_eq!(expired.closed_at, Some(expired.expires_at));
+    }
+
+    async fn active_plan(pool: &deadpool_postgres::Pool, owner_id: i32) -> Plan {
+        let mut client = pool.get().await.unwrap();
+        let plan = CreatePlan {
+            name: "Guarded plan".to_string(),
+            description: None,
+            assets: Vec::new(),
+            beneficiaries: Vec::new(),
+        };
+        let plan = plan_repository::create(&mut client, owner_id, &plan)
+            .await
+            .unwrap();
+        plan_repository::transition_status(&client, plan.id, PlanStatus::Draft, PlanStatus::Active)
+            .await
+            .unwrap();
+        plan_repository::get_by_id(&client, plan.id)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_guardian_quorum_triggers_plan() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::from(clock.clone() as Arc<dyn Clock>))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(check_in_controller::config)
+                .configure(guardian_controller::config),
+        )
+        .await;
+
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let owner = bearer(owner_id, &[Role::Owner]);
+        let guardian = |n: i32| bearer(owner_id + n, &[Role::Beneficiary]);
+        let plan = active_plan(&pool, owner_id).await;
+        let base = format!("/plans/{}/guardians", plan.id);
+
+        let call = |method: &str, uri: String, who: String, body: Option<serde_json::Value>| {
+            let req = match method {
+                "GET" => test::TestRequest::get(),
+                "POST" => test::TestRequest::post(),
+                "PUT" => test::TestRequest::put(),
+                _ => test::TestRequest::delete(),
+            }
+            .uri(&uri)
+            .insert_header(("Authorization", who));
+            match body {
+                Some(body) => req.set_json(body).to_request(),
+                None => req.to_request(),
+            }
+        };
+        let policy = |quorum: i32| serde_json::json!({"policy": "guardians", "quorum": quorum, "window_secs": 86400});
+
+        // A quorum needs guardians to reach it
+        let req = call(
+            "PUT",
+            format!("{}/policy", base),
+            owner.clone(),
+            Some(policy(2)),
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 400);
+
+        for n in 1..=4 {
+            let body =
+                serde_json::json!({"user_id": owner_id + n, "name": format!("Guardian {}", n)});
+            let req = call("POST", base.clone(), owner.clone(), Some(body));
+            assert_eq!(test::call_service(&app, req).await.status().as_u16(), 201);
+        }
+        let body = serde_json::json!({"user_id": owner_id + 1, "name": "Again"});
+        let req = call("POST", base.clone(), owner.clone(), Some(body));
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+        let body = serde_json::json!({"user_id": owner_id, "name": "Myself"});
+        let req = call("POST", base.clone(), owner.clone(), Some(body));
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 400);
+
+        // Without check-ins the owner could not call off a round
+        let req = call(
+            "PUT",
+            format!("{}/policy", base),
+            owner.clone(),
+            Some(policy(2)),
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+        let req = call(
+            "PUT",
+            format!("/plans/{}/check-in", plan.id),
+            owner.clone(),
+            Some(serde_json::json!({"interval_secs": 86400 * 30, "grace_secs": 86400})),
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+        let req = call(
+            "PUT",
+            format!("{}/policy", base),
+            owner.clone(),
+            Some(policy(4)),
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+        let req = call("GET", base.clone(), guardian(1), None);
+        let guardians: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(guardians.as_array().unwrap().len(), 4);
+        let fourth = guardians[3]["id"].as_i64().unwrap();
+
+        // Every guardian is needed for a quorum of four
+        let req = call(
+            "DELETE",
+            format!("{}/{}", base, fourth),
+            owner.clone(),
+            None,
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+        let req = call(
+            "PUT",
+            format!("{}/policy", base),
+            owner.clone(),
+            Some(policy(2)),
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+        let req = call(
+            "DELETE",
+            format!("{}/{}", base, fourth),
+            owner.clone(),
+            None,
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 204);
+
+        let attest = format!("{}/attest", base);
+        let round = |who: String| {
+            let req = call("GET", format!("{}/round", base), who, None);
+            async {
+                test::read_body_json::<serde_json::Value, _>(test::call_service(&app, req).await)
+                    .await
+            }
+        };
+
+        let req = call(
+            "POST",
+            attest.clone(),
+            bearer(owner_id + 50, &[Role::Beneficiary]),
+            None,
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        // Attestations can be taken back while the round is open
+        let req = call("POST", attest.clone(), guardian(1), None);
+        let first: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(first["status"], "open");
+        let req = call("DELETE", attest.clone(), guardian(1), None);
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+        let req = call("DELETE", attest.clone(), guardian(1), None);
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+        let req = call("POST", attest.clone(), guardian(1), None);
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+        // The owner checking in calls the round off
+        let req = call(
+            "POST",
+            format!("/plans/{}/check-in", plan.id),
+            owner.clone(),
+            None,
+        );
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+        let cancelled = round(owner.clone()).await;
+        assert_eq!(cancelled["id"], first["id"]);
+        assert_eq!(cancelled["status"], "cancelled");
+
+        // A new round runs out of time before a second guardian attests
+        let req = call("POST", attest.clone(), guardian(1), None);
+        let second: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_ne!(second["id"], first["id"]);
+        clock.advance(Duration::days(1));
+        assert_eq!(round(guardian(2)).await["status"], "expired");
+
+        let req = call("POST", attest.clone(), guardian(2), None);
+        let third: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(third["status"], "open");
+        assert_eq!(third["attestations"].as_array().unwrap().len(), 1);
+
+        let req = call("POST", attest.clone(), guardian(3), None);
+        let confirmed: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(confirmed["id"], third["id"]);
+        assert_eq!(confirmed["status"], "confirmed");
+
+        let client = pool.get().await.unwrap();
+        let status = plan_repository::get_by_id(&client, plan.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        assert_eq!(status, PlanStatus::Triggered);
+
+        // Three rounds opened and one confirmed
+        let notices: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM notifications WHERE user_id = $1",
+                &[&owner_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(notices, 4);
+
+        let req = call("POST", attest.clone(), guardian(1), None);
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_guardian_only_plans_ignore_inactivity() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let clock = ManualClock::new(Utc::now());
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let plan = active_plan(&pool, owner_id).await;
+
+        let client = pool.get().await.unwrap();
+        let schedule = ConfigureCheckIn {
+            interval_secs: 3600,
+            grace_secs: 60,
+        };
+        check_in_repository::configure(&client, plan.id, &schedule, clock.now())
+            .await
+            .unwrap();
+        let guardian = NewGuardian {
+            user_id: owner_id + 1,
+            name: "Guardian".to_string(),
+            email: None,
+        };
+        guardian_repository::create(&client, plan.id, &guardian)
+            .await
+            .unwrap();
+        let policy = ConfigureGuardianPolicy {
+            policy: TriggerPolicy::Guardians,
+            quorum: 1,
+            window_secs: 3600,
+        };
+        guardian_repository::configure_policy(&client, plan.id, &policy)
+            .await
+            .unwrap();
+
+        clock.advance(Duration::hours(1));
+        for _ in 0..5 {
+            check_in_scheduler::run_pass(&pool, &clock).await.unwrap();
+            clock.advance(Duration::minutes(1));
+        }
+
+        // The owner was warned, but only guardians can trigger this plan
+        let schedule = check_in_repository::get_by_plan(&client, plan.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(schedule.escalation.as_str(), "trigger_pending");
+        let status = plan_repository::get_by_id(&client, plan.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        assert_eq!(status, PlanStatus::Active);
+    }
+}
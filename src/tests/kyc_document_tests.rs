@@ -0,0 +1,287 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use actix_web::{App, test, web};
+    use image::{ImageFormat, Rgb, RgbImage};
+    use sha2::{Digest, Sha256};
+
+    use crate::controller::kyc_document_controller;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::kyc_document_models::{
+        ImageRejection, KycDocumentKind, KycDocumentLimits, clean_image,
+    };
+    use crate::storage::ObjectStore;
+    use crate::storage::local::LocalStore;
+    use crate::tests::{bearer, test_auth_config, test_keyring};
+
+    const LIMITS: KycDocumentLimits = KycDocumentLimits {
+        max_bytes: 1 << 20,
+        min_side_px: 100,
+        max_side_px: 2000,
+    };
+
+    /// Text the EXIF block carries, which must not survive.
+    const LOCATION: &[u8] = b"Taken at 51.5074N 0.1278W";
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 128]));
+        let mut out = Cursor::new(Vec::new());
+        image.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    // A JPEG with an EXIF block holding an orientation and a description
+    fn jpeg_with_exif(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+        let mut description = LOCATION.to_vec();
+        description.push(0);
+
+        // Big-endian TIFF header, then one IFD with two entries
+        let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x02".to_vec();
+        tiff.extend_from_slice(&[0x01, 0x0e, 0, 2]);
+        tiff.extend_from_slice(&(description.len() as u32).to_be_bytes());
+        tiff.extend_from_slice(&38u32.to_be_bytes());
+        tiff.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1]);
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        tiff.extend_from_slice(&description);
+
+        let mut segment = b"\xff\xe1".to_vec();
+        segment.extend_from_slice(&(tiff.len() as u16 + 8).to_be_bytes());
+        segment.extend_from_slice(b"Exif\0\0");
+        segment.extend_from_slice(&tiff);
+
+        let jpeg = encode(width, height, ImageFormat::Jpeg);
+        [&jpeg[..2], &segment, &jpeg[2..]].concat()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[actix_web::test]
+    async fn test_metadata_is_stripped_and_orientation_kept() {
+        let upload = jpeg_with_exif(300, 200, 6);
+        assert!(contains(&upload, b"Exif"));
+        assert!(contains(&upload, LOCATION));
+
+        let clean = clean_image(&upload, &LIMITS).unwrap();
+        assert_eq!(clean.content_type, "image/jpeg");
+        assert!(!contains(&clean.bytes, b"Exif"));
+        assert!(!contains(&clean.bytes, LOCATION));
+        // Orientation 6 turns the image a quarter, so it comes out portrait
+        assert_eq!((clean.width, clean.height), (200, 300));
+
+        // The output is a plain image of the same size
+        let decoded = image::load_from_memory(&clean.bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (200, 300));
+
+        let png = clean_image(&encode(150, 120, ImageFormat::Png), &LIMITS).unwrap();
+        assert_eq!(png.content_type, "image/png");
+        assert_eq!((png.width, png.height), (150, 120));
+    }
+
+    #[actix_web::test]
+    async fn test_unusable_images_are_refused() {
+        assert_eq!(
+            clean_image(b"%PDF-1.7\n", &LIMITS).unwrap_err(),
+            ImageRejection::UnsupportedType
+        );
+        assert_eq!(
+            clean_image(b"GIF89a\x01\0\x01\0", &LIMITS).unwrap_err(),
+            ImageRejection::UnsupportedType
+        );
+
+        let png = encode(300, 200, ImageFormat::Png);
+        assert!(matches!(
+            clean_image(&png[..png.len() / 3], &LIMITS),
+            Err(ImageRejection::Unreadable(_))
+        ));
+        assert_eq!(
+            clean_image(&encode(300, 50, ImageFormat::Png), &LIMITS).unwrap_err(),
+            ImageRejection::TooSmall {
+                width: 300,
+                height: 50,
+                min: 100
+            }
+        );
+        assert_eq!(
+            clean_image(&encode(2100, 100, ImageFormat::Png), &LIMITS).unwrap_err(),
+            ImageRejection::TooLarge { max: 2000 }
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_document_kinds() {
+        for kind in [
+            KycDocumentKind::IdFront,
+            KycDocumentKind::IdBack,
+            KycDocumentKind::Selfie,
+        ] {
+            assert_eq!(kind.as_str().parse::<KycDocumentKind>(), Ok(kind));
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::json!(kind.as_str())
+            );
+        }
+        assert!("passport".parse::<KycDocumentKind>().is_err());
+    }
+
+    fn multipart(bytes: &[u8]) -> (String, Vec<u8>) {
+        let boundary = "kyc-boundary";
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"id.jpg\"\r\nContent-Type: image/jpeg\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+        (format!("multipart/form-data; boundary={boundary}"), body)
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_document_upload_and_review() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let root: PathBuf =
+            std::env::temp_dir().join(format!("inheritx-kyc-{:016x}", rand::random::<u64>()));
+        let store: Arc<dyn ObjectStore> = Arc::new(LocalStore::new(root.clone()));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::from(store.clone()))
+                .app_data(web::Data::new(LIMITS))
+                .app_data(web::Data::new(test_keyring()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(kyc_document_controller::config),
+        )
+        .await;
+
+        let applicant_id = rand::random::<u16>() as i32 + 100_000;
+        let applicant = bearer(applicant_id, &[Role::Beneficiary]);
+        let stranger = bearer(applicant_id + 1, &[Role::Beneficiary]);
+        let reviewer = bearer(applicant_id + 2, &[Role::KycReviewer]);
+        let client = pool.get().await.unwrap();
+        let kyc_id: i32 = client
+            .query_one(
+                "INSERT INTO kyc_records (user_id, full_name, date_of_birth, id_type, id_number, address)
+                 VALUES ($1, 'Test User', '01-01-1990', 'passport', 'AB123456', '1 Main St')
+                 RETURNING id",
+                &[&applicant_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+
+        let upload = |who: &str, kind: &str, bytes: &[u8]| {
+            let (content_type, body) = multipart(bytes);
+            test::TestRequest::put()
+                .uri(&format!("/api/kyc/{}/documents/{}", kyc_id, kind))
+                .insert_header(("Authorization", who.to_string()))
+                .insert_header(("Content-Type", content_type))
+                .set_payload(body)
+                .to_request()
+        };
+        let photo = jpeg_with_exif(300, 200, 1);
+
+        let resp = test::call_service(&app, upload(&stranger, "id_front", &photo)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(&app, upload(&applicant, "id_front", b"%PDF-1.7")).await;
+        assert_eq!(resp.status().as_u16(), 415);
+        let small = encode(50, 50, ImageFormat::Jpeg);
+        let resp = test::call_service(&app, upload(&applicant, "id_front", &small)).await;
+        assert_eq!(resp.status().as_u16(), 400);
+        let resp = test::call_service(&app, upload(&applicant, "passport", &photo)).await;
+        assert_eq!(resp.status().as_u16(), 404);
+
+        let resp = test::call_service(&app, upload(&applicant, "id_front", &photo)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let document: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(document["kind"], "id_front");
+        assert_eq!(document["width"], 300);
+        assert_eq!(document["height"], 200);
+        assert!(document.get("storage_key").is_none());
+
+        // A second front replaces the first, object included
+        let resp = test::call_service(&app, upload(&applicant, "id_front", &photo)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let resp = test::call_service(&app, upload(&applicant, "selfie", &photo)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let stored = std::fs::read_dir(root.join(format!("kyc/{}", kyc_id)))
+            .unwrap()
+            .count();
+        assert_eq!(stored, 2);
+
+        let list = |who: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/kyc/{}/documents", kyc_id))
+                .insert_header(("Authorization", who.to_string()))
+                .to_request()
+        };
+        let listed: serde_json::Value =
+            test::read_body_json(test::call_service(&app, list(&applicant)).await).await;
+        assert_eq!(listed.as_array().unwrap().len(), 2);
+        let resp = test::call_service(&app, list(&stranger)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        // Only reviewers download, and get exactly what was hashed
+        let download = |who: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/kyc/{}/documents/id_front", kyc_id))
+                .insert_header(("Authorization", who.to_string()))
+                .to_request()
+        };
+        let resp = test::call_service(&app, download(&applicant)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+        let resp = test::call_service(&app, download(&reviewer)).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(resp.headers().get("Content-Type").unwrap(), "image/jpeg");
+        let bytes = test::read_body(resp).await;
+        assert!(!contains(&bytes, LOCATION));
+        let front = listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|document| document["kind"] == "id_front")
+            .unwrap();
+        assert_eq!(front["sha256"], hex::encode(Sha256::digest(&bytes)));
+
+        // Changing the stored object is caught
+        let key: String = client
+            .query_one(
+                "SELECT storage_key FROM kyc_documents WHERE kyc_id = $1 AND kind = 'id_front'",
+                &[&kyc_id],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        store.put(&key, &photo).unwrap();
+        let resp = test::call_service(&app, download(&reviewer)).await;
+        assert_eq!(resp.status().as_u16(), 500);
+
+        // Once in review, the images are fixed
+        client
+            .execute(
+                "UPDATE kyc_records SET verification_status = 'in_review' WHERE id = $1",
+                &[&kyc_id],
+            )
+            .await
+            .unwrap();
+        let resp = test::call_service(&app, upload(&applicant, "id_back", &photo)).await;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        client
+            .execute("DELETE FROM kyc_records WHERE id = $1", &[&kyc_id])
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
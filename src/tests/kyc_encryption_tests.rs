@@ -0,0 +1,258 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+
+    use crate::controller::kyc_controller;
+    use crate::jobs::kyc_reencryption;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::kyc_models::CreateKycRequest;
+    use crate::repositories::kyc_repository::{self, KycRepositoryError};
+    use crate::tests::{bearer, test_auth_config, test_blind_index_key, test_keyring};
+    use crate::utils::envelope::{EnvelopeError, Keyring};
+
+    const KEY_A: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const KEY_B: &str = "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f";
+
+    fn keyring(ids: &[(&str, &str)]) -> Keyring {
+        ids.iter()
+            .map(|(id, key)| format!("{}:{}", id, key))
+            .collect::<Vec<_>>()
+            .join(",")
+            .parse()
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_fields_are_sealed_under_their_own_key() {
+        let keyring = keyring(&[("k1", KEY_A)]);
+        let (data_key, wrapped) = keyring.new_data_key();
+
+        let sealed = data_key.seal("full_name", "Alice Example");
+        assert!(!String::from_utf8_lossy(&sealed).contains("Alice"));
+        assert_eq!(
+            data_key.open("full_name", &sealed).unwrap(),
+            "Alice Example"
+        );
+        // Fresh nonce every time
+        assert_ne!(sealed, data_key.seal("full_name", "Alice Example"));
+
+        // Bound to its field, and to its bytes
+        assert_eq!(
+            data_key.open("address", &sealed),
+            Err(EnvelopeError::Corrupt)
+        );
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            data_key.open("full_name", &tampered),
+            Err(EnvelopeError::Corrupt)
+        );
+        assert_eq!(
+            data_key.open("full_name", b"short"),
+            Err(EnvelopeError::Corrupt)
+        );
+
+        // Another record's key does not open it
+        let (other_key, _) = keyring.new_data_key();
+        assert_eq!(
+            other_key.open("full_name", &sealed),
+            Err(EnvelopeError::Corrupt)
+        );
+
+        let unwrapped = keyring.unwrap_data_key("k1", &wrapped).unwrap();
+        assert_eq!(
+            unwrapped.open("full_name", &sealed).unwrap(),
+            "Alice Example"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_data_keys_move_to_a_new_master_key() {
+        let old = keyring(&[("k1", KEY_A)]);
+        let (data_key, wrapped) = old.new_data_key();
+        let sealed = data_key.seal("id_number", "AB123456");
+
+        let rotated = keyring(&[("k2", KEY_B), ("k1", KEY_A)]);
+        assert_eq!(rotated.current_key_id(), "k2");
+        let rewrapped = rotated.rewrap("k1", &wrapped).unwrap();
+        let data_key = rotated.unwrap_data_key("k2", &rewrapped).unwrap();
+        assert_eq!(data_key.open("id_number", &sealed).unwrap(), "AB123456");
+
+        // Without the new key the old keyring cannot follow
+        assert!(matches!(
+            old.unwrap_data_key("k2", &rewrapped),
+            Err(EnvelopeError::UnknownKey(id)) if id == "k2"
+        ));
+        // A wrapped key is bound to the master key id it was wrapped under
+        let renamed = keyring(&[("k3", KEY_A)]);
+        assert_eq!(
+            renamed.unwrap_data_key("k3", &wrapped).err(),
+            Some(EnvelopeError::Corrupt)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_master_keys_are_parsed_without_leaking() {
+        let keys = keyring(&[("k2", KEY_B), ("k1", KEY_A)]);
+        assert_eq!(keys.key_ids(), ["k2", "k1"]);
+        let debug = format!("{:?}", keys);
+        assert!(debug.contains("k2"));
+        assert!(!debug.contains(KEY_A) && !debug.contains(KEY_B));
+
+        for (value, expected) in [
+            ("k1", "expected comma separated id:hex pairs"),
+            ("k1:abcd", "key \"k1\" must be 32 bytes of hex"),
+            (&format!("k1:{}zz", &KEY_A[2..]), "must be 32 bytes of hex"),
+            (&format!(":{}", KEY_A), "must be 1 to 32 letters"),
+            (&format!("a b:{}", KEY_A), "must be 1 to 32 letters"),
+            (&format!("k1:{},k1:{}", KEY_A, KEY_B), "is listed twice"),
+        ] {
+            let err = value.parse::<Keyring>().unwrap_err();
+            assert!(err.contains(expected), "{}: {}", value, err);
+            assert!(!err.contains(&KEY_A[..16]));
+        }
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_kyc_records_are_encrypted_at_rest() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(kyc_controller::config),
+        )
+        .await;
+
+        let user_id = rand::random::<u16>() as i32 + 100_000;
+        let submission = CreateKycRequest {
+            user_id,
+            full_name: "Alice Example".to_string(),
+            date_of_birth: "01-01-1990".to_string(),
+            id_type: "passport".to_string(),
+            id_number: "AB123456".to_string(),
+            address: "1 Main St, Springfield".to_string(),
+        };
+        let req = test::TestRequest::post()
+            .uri("/api/kyc/create")
+            .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
+            .set_json(&submission)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(created["full_name"], "Alice Example");
+        assert_eq!(created["address"], "1 Main St, Springfield");
+        let kyc_id = created["id"].as_i64().unwrap() as i32;
+
+        // Nothing readable is left in the row
+        let client = pool.get().await.unwrap();
+        let row = client
+            .query_one(
+                "SELECT full_name, id_number, master_key_id, encrypted_full_name::TEXT, encrypted_id_number::TEXT
+                 FROM kyc_records WHERE id = $1",
+                &[&kyc_id],
+            )
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, Option<String>>(0), None);
+        assert_eq!(row.get::<_, Option<String>>(1), None);
+        assert_eq!(row.get::<_, String>(2), "test-a");
+        assert!(!row.get::<_, String>(3).contains(&hex::encode("Alice")));
+        assert!(!row.get::<_, String>(4).contains(&hex::encode("AB123456")));
+
+        // A record from before encryption is still served as it is
+        let legacy_id: i32 = client
+            .query_one(
+                "INSERT INTO kyc_records (user_id, full_name, date_of_birth, id_type, id_number, address)
+                 VALUES ($1, 'Bob Example', '02-02-1980', 'national_id', 'XY987654', '2 High St')
+                 RETURNING id",
+                &[&(user_id + 1)],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        let legacy = kyc_repository::get_kyc_by_id(&client, &test_keyring(), legacy_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(legacy.full_name, "Bob Example");
+
+        // Rotating to test-b seals the legacy record and rewraps the other
+        let rotated = keyring(&[("test-b", KEY_B), ("test-a", KEY_A)]);
+        assert!(
+            kyc_reencryption::run_pass(&pool, &rotated, &test_blind_index_key())
+                .await
+                .unwrap()
+                >= 2
+        );
+        let rows = client
+            .query(
+                "SELECT master_key_id, full_name FROM kyc_records WHERE id = ANY($1)",
+                &[&vec![kyc_id, legacy_id]],
+            )
+            .await
+            .unwrap();
+        for row in rows {
+            assert_eq!(row.get::<_, String>(0), "test-b");
+            assert_eq!(row.get::<_, Option<String>>(1), None);
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/kyc/status?id={}", kyc_id))
+            .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let fetched: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(fetched["full_name"], created["full_name"]);
+        assert_eq!(fetched["id_number"], "AB123456");
+        let legacy = kyc_repository::get_kyc_by_id(&client, &rotated, legacy_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(legacy.id_number, "XY987654");
+
+        // Dropping test-b too early leaves the records unreadable
+        let retired = keyring(&[("test-a", KEY_A)]);
+        assert!(matches!(
+            kyc_repository::get_kyc_by_id(&client, &retired, kyc_id).await,
+            Err(KycRepositoryError::Decryption {
+                source: EnvelopeError::UnknownKey(_),
+                ..
+            })
+        ));
+
+        // Swapped fields no longer open
+        client
+            .execute(
+                "UPDATE kyc_records SET encrypted_full_name = encrypted_address,
+                     encrypted_address = encrypted_full_name
+                 WHERE id = $1",
+                &[&kyc_id],
+            )
+            .await
+            .unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/kyc/status?id={}", kyc_id))
+            .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 500);
+
+        client
+            .execute(
+                "DELETE FROM kyc_records WHERE id = ANY($1)",
+                &[&vec![kyc_id, legacy_id]],
+            )
+            .await
+            .unwrap();
+    }
+}
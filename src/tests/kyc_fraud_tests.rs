@@ -0,0 +1,205 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+
+    use crate::controller::kyc_controller;
+    use crate::jobs::kyc_reencryption;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::kyc_models::{
+        CreateKycRequest, KycRejectionReason, KycStatus, normalize_id_number, normalize_id_type,
+    };
+    use crate::repositories::kyc_repository;
+    use crate::tests::{bearer, test_auth_config, test_blind_index_key, test_keyring};
+    use crate::utils::blind_index::BlindIndexKey;
+
+    fn submission(user_id: i32, id_type: &str, id_number: &str) -> CreateKycRequest {
+        CreateKycRequest {
+            user_id,
+            full_name: "Test User".to_string(),
+            date_of_birth: "01-01-1990".to_string(),
+            id_type: id_type.to_string(),
+            id_number: id_number.to_string(),
+            address: "1 Main St".to_string(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_id_numbers_are_normalized_per_type() {
+        assert_eq!(normalize_id_type(" Passport "), "passport");
+        assert_eq!(normalize_id_type("National-ID"), "national_id");
+        assert_eq!(normalize_id_number("passport", "ab 123-456"), "AB123456");
+        assert_eq!(normalize_id_number("national_id", "x.12/34"), "X1234");
+        // Only the digits of a social security number count
+        assert_eq!(normalize_id_number("SSN", "078-05-1120"), "078051120");
+        assert_eq!(normalize_id_number("ssn", "SSN 078 05 1120"), "078051120");
+
+        assert!(submission(1, "passport", " - / ").validate().is_err());
+        assert!(submission(1, "ssn", "n/a").validate().is_err());
+        assert!(submission(1, "passport", "AB123456").validate().is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_blind_index_matches_only_the_same_document() {
+        let key = test_blind_index_key();
+        let index = |id_type: &str, id_number: &str| {
+            key.index(
+                &normalize_id_type(id_type),
+                &normalize_id_number(id_type, id_number),
+            )
+        };
+
+        assert_eq!(
+            index("passport", "AB123456"),
+            index("Passport", "ab 123 456")
+        );
+        assert_eq!(index("ssn", "078-05-1120"), index("ssn", "078051120"));
+        assert_ne!(index("passport", "AB123456"), index("passport", "AB123457"));
+        assert_ne!(
+            index("passport", "AB123456"),
+            index("national_id", "AB123456")
+        );
+        assert_eq!(index("passport", "AB123456").len(), 32);
+
+        // Without the key the index can't be matched
+        let other: BlindIndexKey = "ff".repeat(32).parse().unwrap();
+        assert_ne!(
+            index("passport", "AB123456"),
+            other.index("passport", "AB123456")
+        );
+
+        assert!("abcd".parse::<BlindIndexKey>().is_err());
+        assert!(!format!("{:?}", key).contains("4041"));
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_duplicate_identities_are_flagged() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(kyc_controller::config),
+        )
+        .await;
+
+        // A passport number no other test uses
+        let passport = format!("ZZ{:08}", rand::random::<u32>() % 100_000_000);
+        let first_user = rand::random::<u16>() as i32 + 100_000;
+        let second_user = first_user + 1;
+        let submit = |user_id: i32, id_number: &str| {
+            test::TestRequest::post()
+                .uri("/api/kyc/create")
+                .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
+                .set_json(submission(user_id, "passport", id_number))
+                .to_request()
+        };
+        let created = |resp| async move {
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            body["id"].as_i64().unwrap() as i32
+        };
+
+        let resp = test::call_service(&app, submit(first_user, "--")).await;
+        assert_eq!(resp.status().as_u16(), 400);
+
+        let resp = test::call_service(&app, submit(first_user, &passport)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let first = created(resp).await;
+        // The same user submitting again is not a duplicate identity
+        let client = pool.get().await.unwrap();
+        kyc_repository::update_kyc_verification_status(
+            &client,
+            &test_keyring(),
+            first,
+            KycStatus::Pending,
+            KycStatus::Rejected,
+            Some(KycRejectionReason::DocumentUnreadable),
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let resp = test::call_service(&app, submit(first_user, &passport)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let resubmitted = created(resp).await;
+
+        // Another user with the same passport, written differently, is let
+        // through without being told
+        let spaced = format!("{} {}", &passport[..4], passport[4..].to_lowercase());
+        let resp = test::call_service(&app, submit(second_user, &spaced)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let second = body["id"].as_i64().unwrap() as i32;
+        assert!(body.get("fraud_signals").is_none());
+
+        let signals = |who: String, kyc_id: i32| {
+            test::TestRequest::get()
+                .uri(&format!("/api/kyc/fraud-signals?kyc_id={}", kyc_id))
+                .insert_header(("Authorization", who))
+                .to_request()
+        };
+        let reviewer = bearer(first_user + 2, &[Role::KycReviewer]);
+        let resp =
+            test::call_service(&app, signals(bearer(second_user, &[Role::Owner]), second)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        let flagged: serde_json::Value =
+            test::read_body_json(test::call_service(&app, signals(reviewer.clone(), second)).await)
+                .await;
+        let mut related: Vec<i64> = flagged
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|signal| {
+                assert_eq!(signal["kind"], "duplicate_identity");
+                signal["related_kyc_id"].as_i64().unwrap()
+            })
+            .collect();
+        related.sort();
+        assert_eq!(related, [first as i64, resubmitted as i64]);
+
+        // Each earlier record points back at the new one
+        let flagged: serde_json::Value =
+            test::read_body_json(test::call_service(&app, signals(reviewer.clone(), first)).await)
+                .await;
+        assert_eq!(flagged.as_array().unwrap().len(), 1);
+        assert_eq!(flagged[0]["related_kyc_id"], second);
+
+        // Records from before the index get theirs from the background job,
+        // and are found by later submissions
+        let legacy_passport = format!("{}L", passport);
+        let legacy: i32 = client
+            .query_one(
+                "INSERT INTO kyc_records (user_id, full_name, date_of_birth, id_type, id_number, address)
+                 VALUES ($1, 'Old User', '01-01-1970', 'passport', $2, '3 Low St')
+                 RETURNING id",
+                &[&(first_user + 3), &legacy_passport],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        kyc_reencryption::run_pass(&pool, &test_keyring(), &test_blind_index_key())
+            .await
+            .unwrap();
+        let resp = test::call_service(&app, submit(first_user + 4, &legacy_passport)).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let newest = created(resp).await;
+        let flagged: serde_json::Value =
+            test::read_body_json(test::call_service(&app, signals(reviewer, newest)).await).await;
+        assert_eq!(flagged[0]["related_kyc_id"], legacy);
+
+        client
+            .execute(
+                "DELETE FROM kyc_records WHERE id = ANY($1)",
+                &[&vec![first, resubmitted, second, legacy, newest]],
+            )
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{App, test, web};
+    use chrono::{Duration, Utc};
+
+    use crate::controller::kyc_controller;
+    use crate::kyc_provider::mock::{FIXTURES, MockOutcome, MockProvider};
+    use crate::kyc_provider::{KycProvider, KycProviderError};
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::kyc_models::{CreateKycRequest, KycRecord, KycRejectionReason, KycStatus};
+    use crate::models::kyc_provider_models::{KycDecision, KycWebhookConfig};
+    use crate::repositories::kyc_repository;
+    use crate::tests::{bearer, test_auth_config, test_blind_index_key, test_keyring};
+    use crate::utils::clock::{Clock, ManualClock};
+    use crate::utils::webhook_signature::{SIGNATURE_HEADER, TIMESTAMP_HEADER, WebhookSecret};
+
+    const WEBHOOK_SECRET: &str = "test-webhook-secret-0123456789abcdef";
+
+    fn record(id: i32, id_number: &str) -> KycRecord {
+        KycRecord {
+            id,
+            user_id: 1,
+            full_name: "Test User".to_string(),
+            date_of_birth: "01-01-1990".to_string(),
+            id_type: "passport".to_string(),
+            id_number: id_number.to_string(),
+            address: "1 Main St".to_string(),
+            verification_status: KycStatus::Pending,
+            rejection_reason: None,
+            rejection_note: None,
+            created_at: Utc::now(),
+            updated_at: None,
+            is_current: true,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_signatures_cover_timestamp_and_body() {
+        let secret: WebhookSecret = WEBHOOK_SECRET.parse().unwrap();
+        let body = br#"{"event_id":"evt-1","reference":"mock-1-approved"}"#;
+        let signature = secret.sign(1_700_000_000, body);
+
+        assert!(secret.verify(1_700_000_000, body, &signature));
+        assert!(!secret.verify(1_700_000_001, body, &signature));
+        assert!(!secret.verify(1_700_000_000, b"{}", &signature));
+        assert!(!secret.verify(1_700_000_000, body, "not-hex"));
+        assert!(!secret.verify(1_700_000_000, body, ""));
+
+        let other: WebhookSecret = "another-webhook-secret-0123456789ab".parse().unwrap();
+        assert!(!other.verify(1_700_000_000, body, &signature));
+
+        assert!("too-short".parse::<WebhookSecret>().is_err());
+        assert!(!format!("{:?}", secret).contains(WEBHOOK_SECRET));
+    }
+
+    #[actix_web::test]
+    async fn test_mock_provider_follows_its_fixtures() {
+        let provider = MockProvider;
+
+        let reference = provider.submit(&record(7, "AB123456")).unwrap();
+        assert_eq!(reference, "mock-7-approved");
+        assert_eq!(provider.submit(&record(7, "AB123456")).unwrap(), reference);
+        assert_eq!(
+            provider.fetch_result(&reference).unwrap(),
+            Some(KycDecision::Approved)
+        );
+
+        // Fixtures match however the ID number is written
+        assert_eq!(
+            MockProvider::outcome(&record(8, "mock-expired")),
+            MockOutcome::Rejected(KycRejectionReason::DocumentExpired)
+        );
+        for (id_number, outcome) in FIXTURES {
+            assert_eq!(MockProvider::outcome(&record(8, id_number)), outcome);
+        }
+
+        let reference = provider.submit(&record(8, "MOCKNAMEMISMATCH")).unwrap();
+        assert_eq!(reference, "mock-8-name_mismatch");
+        match provider.fetch_result(&reference).unwrap() {
+            Some(KycDecision::Rejected { reason, note }) => {
+                assert_eq!(reason, KycRejectionReason::NameMismatch);
+                assert!(note.is_some());
+            }
+            other => panic!("expected a rejection, got {:?}", other),
+        }
+
+        let reference = provider.submit(&record(9, "MOCKPENDING")).unwrap();
+        assert_eq!(provider.fetch_result(&reference).unwrap(), None);
+
+        assert!(matches!(
+            provider.submit(&record(10, "MOCKUNAVAILABLE")),
+            Err(KycProviderError::Unavailable(_))
+        ));
+        for reference in [
+            "mock-x-approved",
+            "mock-11-bogus",
+            "acme-11-approved",
+            "mock-",
+        ] {
+            assert!(matches!(
+                provider.fetch_result(reference),
+                Err(KycProviderError::UnknownReference(_))
+            ));
+        }
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_provider_checks_are_decided_by_webhook() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let secret: WebhookSecret = WEBHOOK_SECRET.parse().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .app_data(web::Data::from(clock.clone() as Arc<dyn Clock>))
+                .app_data(web::Data::from(
+                    Arc::new(MockProvider) as Arc<dyn KycProvider>
+                ))
+                .app_data(web::Data::new(KycWebhookConfig {
+                    secret: secret.clone(),
+                    tolerance: Duration::minutes(5),
+                }))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(kyc_controller::config),
+        )
+        .await;
+
+        let user_id = rand::random::<u16>() as i32 + 100_000;
+        let applicant = bearer(user_id, &[Role::Owner]);
+        let submit = |user_id: i32, id_number: &str| {
+            test::TestRequest::post()
+                .uri("/api/kyc/create")
+                .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
+                .set_json(CreateKycRequest {
+                    user_id,
+                    full_name: "Test User".to_string(),
+                    date_of_birth: "01-01-1990".to_string(),
+                    id_type: "passport".to_string(),
+                    id_number: id_number.to_string(),
+                    address: "1 Main St".to_string(),
+                })
+                .to_request()
+        };
+        let created = |resp| async move {
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            body["id"].as_i64().unwrap() as i32
+        };
+        let start_check = |kyc_id: i32, who: String| {
+            test::TestRequest::post()
+                .uri(&format!("/api/kyc/{}/provider-check", kyc_id))
+                .insert_header(("Authorization", who))
+                .to_request()
+        };
+        let event = |event_id: &str, reference: &str| {
+            serde_json::json!({"event_id": event_id, "reference": reference}).to_string()
+        };
+        let signed = |body: &str| {
+            let timestamp = clock.now().timestamp();
+            test::TestRequest::post()
+                .uri("/api/kyc/provider-webhook")
+                .insert_header((TIMESTAMP_HEADER, timestamp.to_string()))
+                .insert_header((SIGNATURE_HEADER, secret.sign(timestamp, body.as_bytes())))
+                .set_payload(body.to_string())
+                .to_request()
+        };
+
+        // Without the feature the routes are not there
+        let disabled = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .app_data(web::Data::from(clock.clone() as Arc<dyn Clock>))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(kyc_controller::config),
+        )
+        .await;
+        let resp = test::call_service(&disabled, start_check(1, applicant.clone())).await;
+        assert_eq!(resp.status().as_u16(), 404);
+        let resp = test::call_service(&disabled, signed(&event("evt", "mock-1-approved"))).await;
+        assert_eq!(resp.status().as_u16(), 404);
+
+        let resp = test::call_service(&app, submit(user_id, "AB123456")).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let approved = created(resp).await;
+
+        let stranger = bearer(user_id + 1, &[Role::Owner]);
+        let resp = test::call_service(&app, start_check(approved, stranger)).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        let resp = test::call_service(&app, start_check(approved, applicant.clone())).await;
+        assert_eq!(resp.status().as_u16(), 202);
+        let check: serde_json::Value = test::read_body_json(resp).await;
+        let reference = format!("mock-{}-approved", approved);
+        assert_eq!(check["reference"], reference.as_str());
+        assert_eq!(check["status"], "pending");
+
+        // Once in review the submission can't be handed over again
+        let resp = test::call_service(&app, start_check(approved, applicant.clone())).await;
+        assert_eq!(resp.status().as_u16(), 409);
+
+        // Calls without a valid, recent signature are turned away
+        let body = event(&format!("evt-{}-approved", approved), &reference);
+        let unsigned = test::TestRequest::post()
+            .uri("/api/kyc/provider-webhook")
+            .set_payload(body.clone())
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, unsigned).await.status().as_u16(),
+            401
+        );
+
+        let forged = test::TestRequest::post()
+            .uri("/api/kyc/provider-webhook")
+            .insert_header((TIMESTAMP_HEADER, clock.now().timestamp().to_string()))
+            .insert_header((SIGNATURE_HEADER, "00".repeat(32)))
+            .set_payload(body.clone())
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, forged).await.status().as_u16(),
+            401
+        );
+
+        let stale = signed(&body);
+        clock.advance(Duration::minutes(10));
+        assert_eq!(test::call_service(&app, stale).await.status().as_u16(), 401);
+
+        let resp = test::call_service(&app, signed(&event("evt-unknown", "mock-0-approved"))).await;
+        assert_eq!(resp.status().as_u16(), 404);
+
+        let resp = test::call_service(&app, signed(&body)).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let result: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(result["outcome"], "processed");
+        assert_eq!(result["check"]["status"], "approved");
+
+        let client = pool.get().await.unwrap();
+        let kyc = kyc_repository::get_kyc_by_id(&client, &test_keyring(), approved)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(kyc.verification_status, KycStatus::Verified);
+        assert!(
+            kyc_repository::is_kyc_verified(&client, &user_id)
+                .await
+                .unwrap()
+        );
+
+        // Sending the event again, or another one for the finished check,
+        // changes nothing
+        for body in [body.clone(), event("evt-late", &reference)] {
+            let resp = test::call_service(&app, signed(&body)).await;
+            assert_eq!(resp.status().as_u16(), 200);
+            let result: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(result["outcome"], "duplicate");
+        }
+
+        // A rejection tells the user why
+        let rejected_user = user_id + 2;
+        let resp = test::call_service(&app, submit(rejected_user, "MOCK-EXPIRED")).await;
+        let rejected = created(resp).await;
+        let resp = test::call_service(
+            &app,
+            start_check(rejected, bearer(user_id + 3, &[Role::KycReviewer])),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 202);
+        let reference = format!("mock-{}-document_expired", rejected);
+        let resp = test::call_service(
+            &app,
+            signed(&event(&format!("evt-{}", rejected), &reference)),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let kyc = kyc_repository::get_kyc_by_id(&client, &test_keyring(), rejected)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(kyc.verification_status, KycStatus::Rejected);
+        assert_eq!(
+            kyc.rejection_reason,
+            Some(KycRejectionReason::DocumentExpired)
+        );
+        let notified: i64 = client
+            .query_one(
+                "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND body = $2",
+                &[
+                    &rejected_user,
+                    &KycRejectionReason::DocumentExpired.message(),
+                ],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(notified, 1);
+
+        // A check the provider is still on stays open
+        let resp = test::call_service(&app, submit(user_id + 4, "MOCKPENDING")).await;
+        let pending = created(resp).await;
+        let resp = test::call_service(
+            &app,
+            start_check(pending, bearer(user_id + 4, &[Role::Owner])),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 202);
+        let reference = format!("mock-{}-pending", pending);
+        let resp = test::call_service(
+            &app,
+            signed(&event(&format!("evt-{}", pending), &reference)),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 202);
+        let result: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(result["outcome"], "pending");
+
+        // A provider that refuses the submission leaves it pending
+        let resp = test::call_service(&app, submit(user_id + 5, "MOCKUNAVAILABLE")).await;
+        let refused = created(resp).await;
+        let resp = test::call_service(
+            &app,
+            start_check(refused, bearer(user_id + 5, &[Role::Owner])),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 500);
+        let kyc = kyc_repository::get_kyc_by_id(&client, &test_keyring(), refused)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(kyc.verification_status, KycStatus::Pending);
+
+        client
+            .execute(
+                "DELETE FROM notifications WHERE user_id = $1",
+                &[&rejected_user],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "DELETE FROM kyc_records WHERE id = ANY($1)",
+                &[&vec![approved, rejected, pending, refused]],
+            )
+            .await
+            .unwrap();
+    }
+}
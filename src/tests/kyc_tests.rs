@@ -1,29 +1,106 @@
 #[cfg(test)]
 mod tests {
-    use actix_web::{test, web, App};
+    use actix_web::{App, test, web};
     use deadpool_postgres::Pool;
-    
+
     use crate::controller::kyc_controller;
-    use crate::models::kyc_models::{CreateKycRequest, KycVerificationRequest};
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::kyc_models::{
+        CreateKycRequest, KycRejectionReason, KycStatus, KycVerificationRequest,
+    };
+    use crate::repositories::kyc_repository;
+    use crate::tests::{bearer, test_auth_config, test_blind_index_key, test_keyring};
+
+    // These tests talk to the database from docker-compose.yml, run them with
+    // `./db.sh start && cargo test -- --ignored`.
+    async fn create_test_pool() -> Pool {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+        pool
+    }
+
+    #[actix_web::test]
+    async fn test_kyc_status_transitions() {
+        use KycStatus::*;
+
+        for (current, next) in [
+            (NotStarted, Pending),
+            (Pending, InReview),
+            (InReview, Verified),
+            (InReview, Rejected),
+            (Verified, Expired),
+            (Verified, Suspended),
+            (Suspended, Verified),
+            (Rejected, Pending),
+            (Expired, Pending),
+        ] {
+            assert!(current.can_transition_to(next), "{} -> {}", current, next);
+        }
+        for (current, next) in [
+            (Pending, Verified),
+            (Rejected, Verified),
+            (Expired, Verified),
+            (Verified, Pending),
+            (Verified, Verified),
+            (NotStarted, Verified),
+        ] {
+            assert!(!current.can_transition_to(next), "{} -> {}", current, next);
+        }
+
+        assert!(!Pending.is_reviewer_decision());
+        assert!(Rejected.is_reviewer_decision());
+        assert_eq!("in_review".parse::<KycStatus>(), Ok(InReview));
+        assert!("approved".parse::<KycStatus>().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_rejections_need_a_catalogue_reason() {
+        let request = |status, reason| KycVerificationRequest {
+            id: 1,
+            verification_status: status,
+            rejection_reason: reason,
+            rejection_note: None,
+        };
 
-    fn create_test_pool() -> Pool {
-        todo!("Implement test database setup")
+        assert!(request(KycStatus::Rejected, None).validate().is_err());
+        assert!(
+            request(KycStatus::Rejected, Some(KycRejectionReason::NameMismatch))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request(KycStatus::Verified, Some(KycRejectionReason::Other))
+                .validate()
+                .is_err()
+        );
+
+        for reason in KycRejectionReason::ALL {
+            assert_eq!(reason.as_str().parse::<KycRejectionReason>(), Ok(reason));
+            assert!(!reason.message().is_empty());
+        }
     }
 
     #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
     async fn test_create_kyc() {
-        let pool = create_test_pool();
+        let pool = create_test_pool().await;
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .wrap(JwtAuth::new(test_auth_config()))
                 .configure(kyc_controller::config),
         )
         .await;
 
+        let user_id = rand::random::<u16>() as i32 + 100_000;
         let req = test::TestRequest::post()
             .uri("/api/kyc/create")
+            .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
             .set_json(CreateKycRequest {
-                user_id: 1,
+                user_id,
                 full_name: "Test User".to_string(),
                 date_of_birth: "01-01-1990".to_string(),
                 id_type: "passport".to_string(),
@@ -34,23 +111,49 @@ mod tests {
 
         let resp = test::call_service(&app, req).await;
         assert!(resp.status().is_success());
+        let created: serde_json::Value = test::read_body_json(resp).await;
+
+        // The record is only shown to its user and to reviewers
+        let status = |who: Option<String>| {
+            let req =
+                test::TestRequest::get().uri(&format!("/api/kyc/status?id={}", created["id"]));
+            match who {
+                Some(who) => req.insert_header(("Authorization", who)).to_request(),
+                None => req.to_request(),
+            }
+        };
+        for (who, expected) in [
+            (Some(bearer(user_id, &[Role::Owner])), 200),
+            (Some(bearer(user_id + 1, &[Role::KycReviewer])), 200),
+            (Some(bearer(user_id + 1, &[Role::Owner])), 403),
+            (None, 401),
+        ] {
+            let resp = test::call_service(&app, status(who)).await;
+            assert_eq!(resp.status().as_u16(), expected);
+        }
     }
 
     #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
     async fn test_verify_kyc() {
-        let pool = create_test_pool();
+        let pool = create_test_pool().await;
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .wrap(JwtAuth::new(test_auth_config()))
                 .configure(kyc_controller::config),
         )
         .await;
 
         // First create a KYC record
+        let user_id = rand::random::<u16>() as i32 + 100_000;
         let create_req = test::TestRequest::post()
             .uri("/api/kyc/create")
+            .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
             .set_json(CreateKycRequest {
-                user_id: 1,
+                user_id,
                 full_name: "Test User".to_string(),
                 date_of_birth: "01-01-1990".to_string(),
                 id_type: "passport".to_string(),
@@ -66,112 +169,200 @@ mod tests {
         let kyc_response: serde_json::Value = test::read_body_json(resp).await;
         let kyc_id = kyc_response["id"].as_i64().unwrap();
 
-        // Now verify the KYC
-        let verify_req = test::TestRequest::post()
-            .uri("/api/kyc/verify")
-            .set_json(KycVerificationRequest {
-                id: kyc_id,
-                verification_status: "verified".to_string(),
-            })
-            .to_request();
+        // Verifying needs a review first
+        let verify = |status: KycStatus| {
+            test::TestRequest::post()
+                .uri("/api/kyc/verify")
+                .insert_header(("Authorization", bearer(2, &[Role::KycReviewer])))
+                .set_json(KycVerificationRequest {
+                    id: kyc_id as i32,
+                    verification_status: status,
+                    rejection_reason: None,
+                    rejection_note: None,
+                })
+                .to_request()
+        };
+        let resp = test::call_service(&app, verify(KycStatus::Verified)).await;
+        assert_eq!(resp.status().as_u16(), 409);
+        let resp = test::call_service(&app, verify(KycStatus::InReview)).await;
+        assert!(resp.status().is_success());
 
-        let resp = test::call_service(&app, verify_req).await;
+        let resp = test::call_service(&app, verify(KycStatus::Verified)).await;
         assert!(resp.status().is_success());
 
         let verification_response: serde_json::Value = test::read_body_json(resp).await;
         assert_eq!(
-            verification_response["verification_status"].as_str().unwrap(),
+            verification_response["verification_status"]
+                .as_str()
+                .unwrap(),
             "verified"
         );
     }
 
     #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
     async fn test_get_kyc_by_user() {
-        let pool = create_test_pool();
+        let pool = create_test_pool().await;
         let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .wrap(JwtAuth::new(test_auth_config()))
                 .configure(kyc_controller::config),
         )
         .await;
 
-        // First create a KYC record
-        let create_req = test::TestRequest::post()
-            .uri("/api/kyc/create")
-            .set_json(CreateKycRequest {
-                user_id: 1,
-                full_name: "Test User".to_string(),
-                date_of_birth: "01-01-1990".to_string(),
-                id_type: "passport".to_string(),
-                id_number: "AB123456".to_string(),
-                address: "123 Test St, Test City".to_string(),
-            })
-            .to_request();
+        let user_id = rand::random::<u16>() as i32 + 100_000;
+        let submit = |id_number: &str| {
+            test::TestRequest::post()
+                .uri("/api/kyc/create")
+                .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
+                .set_json(CreateKycRequest {
+                    user_id,
+                    full_name: "Test User".to_string(),
+                    date_of_birth: "01-01-1990".to_string(),
+                    id_type: "passport".to_string(),
+                    id_number: id_number.to_string(),
+                    address: "123 Test St, Test City".to_string(),
+                })
+                .to_request()
+        };
+        let review = |kyc_id: &serde_json::Value, status: &str| {
+            let mut body = serde_json::json!({"id": kyc_id, "verification_status": status});
+            if status == "rejected" {
+                body["rejection_reason"] = "document_expired".into();
+            }
+            test::TestRequest::post()
+                .uri("/api/kyc/verify")
+                .insert_header(("Authorization", bearer(user_id + 1, &[Role::KycReviewer])))
+                .set_json(body)
+                .to_request()
+        };
+        let get_by_user = || {
+            test::TestRequest::get()
+                .uri(&format!("/api/kyc/user?user_id={}", user_id))
+                .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
+                .to_request()
+        };
 
-        let resp = test::call_service(&app, create_req).await;
-        assert!(resp.status().is_success());
+        // Nothing submitted yet
+        let resp = test::call_service(&app, get_by_user()).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let kyc_response: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(kyc_response["verification_status"], "not_started");
+        assert!(kyc_response["current"].is_null());
 
-        // Now get KYC by user ID
-        let get_req = test::TestRequest::get()
-            .uri("/api/kyc/user?user_id=1")
-            .to_request();
+        let resp = test::call_service(&app, submit("AB123456")).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let first: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(first["is_current"], true);
+        // A pending submission is not replaced
+        let resp = test::call_service(&app, submit("AB123456")).await;
+        assert_eq!(resp.status().as_u16(), 409);
 
-        let resp = test::call_service(&app, get_req).await;
-        assert!(resp.status().is_success());
+        let resp = test::call_service(&app, review(&first["id"], "rejected")).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let resp = test::call_service(&app, submit("CD654321")).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let second: serde_json::Value = test::read_body_json(resp).await;
 
-        let kyc_response: serde_json::Value = test::read_body_json(resp).await;
-        assert_eq!(kyc_response["user_id"].as_i64().unwrap(), 1);
-    }
+        let kyc_response: serde_json::Value =
+            test::read_body_json(test::call_service(&app, get_by_user()).await).await;
+        assert_eq!(kyc_response["user_id"].as_i64().unwrap(), user_id as i64);
+        assert_eq!(kyc_response["verification_status"], "pending");
+        assert_eq!(kyc_response["current"]["id"], second["id"]);
+        assert_eq!(kyc_response["current"]["id_number"], "CD654321");
+        // The rejected submission is kept
+        let history = kyc_response["history"].as_array().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0]["id"], first["id"]);
+        assert_eq!(history[0]["verification_status"], "rejected");
+        assert_eq!(history[0]["rejection_reason"]["code"], "document_expired");
+        // without the personal details it was submitted with
+        for field in ["full_name", "date_of_birth", "id_number", "address"] {
+            assert!(history[0].get(field).is_none(), "{}", field);
+        }
 
-    #[actix_web::test]
-    async fn test_withdrawal_with_unverified_kyc() {
-        let pool = create_test_pool();
-        let app = test::init_service(
-            App::new()
-                .app_data(web::Data::new(pool.clone()))
-                .configure(crate::controller::withdrawal_history_controller::config),
-        )
-        .await;
+        // Only the user and reviewers see where their KYC stands
+        let uri = format!("/api/kyc/user?user_id={}", user_id);
+        for (who, expected) in [
+            (bearer(user_id + 1, &[Role::KycReviewer]), 200),
+            (bearer(user_id + 1, &[Role::Owner]), 403),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&uri)
+                .insert_header(("Authorization", who))
+                .to_request();
+            assert_eq!(
+                test::call_service(&app, req).await.status().as_u16(),
+                expected
+            );
+        }
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+        // and is done with
+        let resp = test::call_service(&app, review(&first["id"], "in_review")).await;
+        assert_eq!(resp.status().as_u16(), 409);
 
-        // Create a withdrawal request for a user with unverified KYC
-        let withdrawal_req = test::TestRequest::post()
-            .uri("/api/withdrawals/record")
-            .set_json(serde_json::json!({
-                "wallet_id": 1, // User with unverified KYC
-                "plan_id": 1,
-                "amount": 100.0,
-                "payer_name": "Test User"
-            }))
-            .to_request();
+        // Only the current submission decides whether the user is verified
+        let client = pool.get().await.unwrap();
+        for status in ["in_review", "verified"] {
+            let resp = test::call_service(&app, review(&second["id"], status)).await;
+            assert_eq!(resp.status().as_u16(), 200);
+        }
+        assert!(
+            kyc_repository::is_kyc_verified(&client, &user_id)
+                .await
+                .unwrap()
+        );
+        let resp = test::call_service(&app, review(&second["id"], "expired")).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let resp = test::call_service(&app, submit("CD654321")).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        assert!(
+            !kyc_repository::is_kyc_verified(&client, &user_id)
+                .await
+                .unwrap()
+        );
 
-        let resp = test::call_service(&app, withdrawal_req).await;
-        
-        // Should be forbidden (403) since KYC is not verified
-        assert_eq!(resp.status().as_u16(), 403);
+        let kyc_response: serde_json::Value =
+            test::read_body_json(test::call_service(&app, get_by_user()).await).await;
+        assert_eq!(kyc_response["verification_status"], "pending");
+        let statuses: Vec<&str> = kyc_response["history"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|kyc| kyc["verification_status"].as_str().unwrap())
+            .collect();
+        assert_eq!(statuses, ["expired", "rejected"]);
 
-        let error_response: serde_json::Value = test::read_body_json(resp).await;
-        assert_eq!(
-            error_response["error"].as_str().unwrap(),
-            "KYC verification required"
-        );
+        client
+            .execute("DELETE FROM kyc_records WHERE user_id = $1", &[&user_id])
+            .await
+            .unwrap();
     }
 
     #[actix_web::test]
-    async fn test_withdrawal_with_verified_kyc() {
-        let pool = create_test_pool();
-        let mut app = test::init_service(
+    #[ignore = "requires a running Postgres database"]
+    async fn test_rejection_tells_the_user_what_to_fix() {
+        let pool = create_test_pool().await;
+        let app = test::init_service(
             App::new()
                 .app_data(web::Data::new(pool.clone()))
-                .configure(kyc_controller::config)
-                .configure(crate::controller::withdrawal_history_controller::config),
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(kyc_controller::config),
         )
         .await;
 
-        // First create a KYC record
+        let user_id = rand::random::<u16>() as i32 + 100_000;
         let create_req = test::TestRequest::post()
             .uri("/api/kyc/create")
+            .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
             .set_json(CreateKycRequest {
-                user_id: 1,
+                user_id,
                 full_name: "Test User".to_string(),
                 date_of_birth: "01-01-1990".to_string(),
                 id_type: "passport".to_string(),
@@ -179,39 +370,67 @@ mod tests {
                 address: "123 Test St, Test City".to_string(),
             })
             .to_request();
+        let kyc: serde_json::Value =
+            test::read_body_json(test::call_service(&app, create_req).await).await;
+        assert_eq!(kyc["verification_status"], "pending");
+        assert!(kyc["rejection_reason"].is_null());
 
-        let resp = test::call_service(&app, create_req).await;
-        assert!(resp.status().is_success());
-
-        let kyc_response: serde_json::Value = test::read_body_json(resp).await;
-        let kyc_id = kyc_response["id"].as_i64().unwrap();
-
-        // Now verify the KYC
-        let verify_req = test::TestRequest::post()
-            .uri("/api/kyc/verify")
-            .set_json(KycVerificationRequest {
-                id: kyc_id,
-                verification_status: "verified".to_string(),
-            })
-            .to_request();
+        let reject = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/api/kyc/verify")
+                .insert_header(("Authorization", bearer(user_id + 1, &[Role::KycReviewer])))
+                .set_json(body)
+                .to_request()
+        };
+        let resp = test::call_service(
+            &app,
+            reject(serde_json::json!({"id": kyc["id"], "verification_status": "rejected"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 400);
+        let resp = test::call_service(
+            &app,
+            reject(serde_json::json!({"id": kyc["id"], "verification_status": "approved"})),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 400);
 
-        let resp = test::call_service(&app, verify_req).await;
-        assert!(resp.status().is_success());
+        let resp = test::call_service(
+            &app,
+            reject(serde_json::json!({
+                "id": kyc["id"],
+                "verification_status": "rejected",
+                "rejection_reason": "document_expired",
+                "rejection_note": "The passport expired in 2019"
+            })),
+        )
+        .await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let rejected: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(rejected["verification_status"], "rejected");
+        assert_eq!(rejected["rejection_reason"]["code"], "document_expired");
+        assert_eq!(
+            rejected["rejection_reason"]["message"],
+            KycRejectionReason::DocumentExpired.message()
+        );
+        assert_eq!(rejected["rejection_note"], "The passport expired in 2019");
 
-        // Now try to make a withdrawal with verified KYC
-        let withdrawal_req = test::TestRequest::post()
-            .uri("/api/withdrawals/record")
-            .set_json(serde_json::json!({
-                "wallet_id": 1, // User with verified KYC
-                "plan_id": 1,
-                "amount": 100.0,
-                "payer_name": "Test User"
-            }))
+        let req = test::TestRequest::get()
+            .uri("/api/kyc/rejection-reasons")
+            .insert_header(("Authorization", bearer(user_id, &[Role::Owner])))
             .to_request();
+        let catalogue: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(
+            catalogue.as_array().unwrap().len(),
+            KycRejectionReason::ALL.len()
+        );
+        assert_eq!(catalogue[0]["code"], "document_unreadable");
 
-        let resp = test::call_service(&app, withdrawal_req).await;
-        
-        // Should be created (201) since KYC is verified
-        assert_eq!(resp.status().as_u16(), 201);
+        let client = pool.get().await.unwrap();
+        client
+            .execute("DELETE FROM kyc_records WHERE user_id = $1", &[&user_id])
+            .await
+            .unwrap();
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use crate::db::migrations::{self, MIGRATIONS, MigrationError, MigrationState};
+    use crate::tests::test_database_settings;
+
+    #[actix_web::test]
+    async fn test_migrations_are_numbered_in_order() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1);
+            assert!(
+                migration
+                    .name
+                    .starts_with(&format!("{:04}_", migration.version)),
+                "{} does not match its version",
+                migration.name
+            );
+            assert!(!migration.up.trim().is_empty());
+            assert!(!migration.down.trim().is_empty());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_checksum_tracks_contents() {
+        let checksum = MIGRATIONS[0].checksum();
+        assert_eq!(checksum.len(), 64);
+        assert_eq!(checksum, MIGRATIONS[0].checksum());
+        assert_ne!(checksum, MIGRATIONS[1].checksum());
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`. Works on a scratch database
+    // so that reverting doesn't pull tables out from under other tests.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_up_status_down_round_trip() {
+        let admin = crate::db::create_pool(&test_database_settings()).await;
+        let admin = admin.get().await.unwrap();
+        // One statement per call, CREATE DATABASE can't run in a transaction
+        admin
+            .batch_execute("DROP DATABASE IF EXISTS inheritx_migrations_test WITH (FORCE)")
+            .await
+            .unwrap();
+        admin
+            .batch_execute("CREATE DATABASE inheritx_migrations_test")
+            .await
+            .unwrap();
+
+        let mut settings = test_database_settings();
+        settings.name = "inheritx_migrations_test".to_string();
+        let pool = crate::db::create_pool(&settings).await;
+
+        let applied = migrations::up(&pool).await.unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert!(migrations::up(&pool).await.unwrap().is_empty());
+
+        let statuses = migrations::status(&pool).await.unwrap();
+        assert!(statuses.iter().all(|s| s.state == MigrationState::Applied));
+
+        let reverted = migrations::down_to(&pool, 1).await.unwrap();
+        assert_eq!(reverted.len(), MIGRATIONS.len() - 1);
+        let statuses = migrations::status(&pool).await.unwrap();
+        assert_eq!(statuses[0].state, MigrationState::Applied);
+        assert!(
+            statuses[1..]
+                .iter()
+                .all(|s| s.state == MigrationState::Pending)
+        );
+
+        assert!(matches!(
+            migrations::down_to(&pool, 999).await,
+            Err(MigrationError::UnknownVersion(999))
+        ));
+
+        // An applied migration that was edited afterwards
+        let client = pool.get().await.unwrap();
+        client
+            .execute(
+                "UPDATE schema_migrations SET checksum = 'edited' WHERE version = 1",
+                &[],
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            migrations::up(&pool).await,
+            Err(MigrationError::ChecksumMismatch { version: 1, .. })
+        ));
+        let statuses = migrations::status(&pool).await.unwrap();
+        assert_eq!(statuses[0].state, MigrationState::Modified);
+
+        drop(client);
+        drop(pool);
+        admin
+            .batch_execute("DROP DATABASE inheritx_migrations_test WITH (FORCE)")
+            .await
+            .unwrap();
+    }
+}
@@ -1 +1,88 @@
-pub mod kyc_tests; 
\ No newline at end of file
+mod auth_tests;
+mod beneficiary_tests;
+mod check_in_tests;
+mod claim_dispute_tests;
+mod claim_evidence_tests;
+mod claim_queue_tests;
+mod claim_tests;
+mod config_tests;
+mod error_tests;
+mod execution_tests;
+mod guardian_tests;
+mod kyc_document_tests;
+mod kyc_encryption_tests;
+mod kyc_fraud_tests;
+mod kyc_provider_tests;
+mod kyc_tests;
+mod migration_tests;
+mod money_tests;
+mod notification_tests;
+mod plan_tests;
+mod support_tests;
+mod vesting_tests;
+mod wallet_auth_tests;
+mod withdrawal_tests;
+
+use deadpool_postgres::Pool;
+
+use crate::config::env::{DatabaseSettings, TlsMode};
+use crate::middlewares::auth_middleware::AuthConfig;
+use crate::models::auth_models::Role;
+use crate::models::plan_models::PlanStatus;
+use crate::repositories::plan_repository;
+use crate::utils::blind_index::BlindIndexKey;
+use crate::utils::envelope::Keyring;
+
+pub const TEST_JWT_SECRET: &str = "test-secret";
+
+pub fn test_auth_config() -> AuthConfig {
+    AuthConfig::new(TEST_JWT_SECRET, chrono::Duration::minutes(5))
+}
+
+/// `Authorization` header value for a user with the given roles.
+pub fn bearer(user_id: i32, roles: &[Role]) -> String {
+    let token = test_auth_config().issue_token(user_id, roles).unwrap();
+    format!("Bearer {}", token)
+}
+
+/// Master keys KYC records are encrypted under in tests. Both stay listed,
+/// so records remain readable whichever one a rotation test made current.
+pub const TEST_MASTER_KEYS: &str = "test-a:000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f,\
+     test-b:202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f";
+
+pub fn test_keyring() -> Keyring {
+    TEST_MASTER_KEYS.parse().unwrap()
+}
+
+pub fn test_blind_index_key() -> BlindIndexKey {
+    "404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f"
+        .parse()
+        .unwrap()
+}
+
+/// The database from docker-compose.yml.
+pub fn test_database_settings() -> DatabaseSettings {
+    DatabaseSettings {
+        host: "localhost".to_string(),
+        port: 5432,
+        user: "postgres".to_string(),
+        password: "postgres".to_string(),
+        name: "inheritx_db".to_string(),
+        pool_size: 4,
+        tls_mode: TlsMode::Disable,
+    }
+}
+
+/// Triggers an active plan, as missed check-ins or the guardians would.
+pub async fn trigger_plan(pool: &Pool, plan_id: i32) {
+    let client = pool.get().await.unwrap();
+    let moved = plan_repository::transition_status(
+        &client,
+        plan_id,
+        PlanStatus::Active,
+        PlanStatus::Triggered,
+    )
+    .await
+    .unwrap();
+    assert!(moved);
+}
@@ -0,0 +1,194 @@
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use tokio_postgres::types::{FromSql, ToSql, Type};
+
+    use crate::models::money::{Amount, Money};
+
+    fn amount(s: &str) -> Amount {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_amounts_parse_whole_numbers_only() {
+        assert_eq!(amount("0"), Amount::ZERO);
+        assert_eq!(amount("-42"), Amount::from(-42));
+        assert_eq!(
+            amount("99999999999999999999999999999999999999"),
+            Amount::MAX
+        );
+        assert_eq!(
+            amount("1000000000000000000000").to_string(),
+            "1000000000000000000000"
+        );
+
+        for invalid in ["", "-", "1.5", "1e18", " 1", "0x10"] {
+            assert!(invalid.parse::<Amount>().is_err(), "{:?}", invalid);
+        }
+        // 39 digits no longer fit the column
+        assert!(
+            "100000000000000000000000000000000000000"
+                .parse::<Amount>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_refuses_overflow() {
+        assert_eq!(
+            Amount::from(2).checked_add(Amount::from(3)),
+            Some(Amount::from(5))
+        );
+        assert_eq!(
+            Amount::from(2).checked_sub(Amount::from(3)),
+            Some(Amount::from(-1))
+        );
+        assert_eq!(Amount::MAX.checked_add(Amount::from(1)), None);
+        assert_eq!(
+            Amount::ZERO
+                .checked_sub(Amount::MAX)
+                .unwrap()
+                .checked_sub(Amount::from(1)),
+            None
+        );
+        assert_eq!(Amount::new(i128::MAX), None);
+    }
+
+    #[test]
+    fn test_money_json_shape() {
+        let money: Money =
+            serde_json::from_value(serde_json::json!({"asset": "ETH", "amount": 1500})).unwrap();
+        assert_eq!(money.amount, Amount::from(1500));
+        // Written as a string, as most clients can't hold large integers exactly
+        assert_eq!(
+            serde_json::to_string(&money).unwrap(),
+            r#"{"amount":"1500","asset":"ETH"}"#
+        );
+
+        let wei: Money = serde_json::from_value(serde_json::json!({
+            "asset": "ETH",
+            "amount": "250000000000000000000000"
+        }))
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&wei).unwrap(),
+            r#"{"amount":"250000000000000000000000","asset":"ETH"}"#
+        );
+
+        let fractional =
+            serde_json::from_value::<Money>(serde_json::json!({"asset": "ETH", "amount": 1.5}));
+        assert!(fractional.is_err());
+    }
+
+    #[test]
+    fn test_amount_json_round_trips() {
+        let large = Amount::new(10_i128.pow(30)).unwrap();
+        for value in [Amount::ZERO, Amount::from(-7), large, Amount::MAX] {
+            let json = serde_json::to_value(value).unwrap();
+            assert_eq!(json, serde_json::Value::String(value.to_string()));
+            assert_eq!(serde_json::from_value::<Amount>(json).unwrap(), value);
+        }
+        assert_eq!(
+            serde_json::to_string(&large).unwrap(),
+            r#""1000000000000000000000000000000""#
+        );
+    }
+
+    #[test]
+    fn test_amounts_share_without_overflow() {
+        assert_eq!(
+            Amount::from(100).mul_div(3_333, 10_000),
+            (Amount::from(33), 3_300)
+        );
+        assert_eq!(Amount::from(1_000).mul_div(1, 3), (Amount::from(333), 1));
+        assert_eq!(Amount::MAX.mul_div(1, 1), (Amount::MAX, 0));
+        let (half, lost) = Amount::MAX.mul_div(5_000, 10_000);
+        assert_eq!(half.to_string(), "49999999999999999999999999999999999999");
+        assert_eq!(lost, 5_000);
+    }
+
+    #[test]
+    fn test_money_validation() {
+        let money = Money {
+            amount: Amount::from(1),
+            asset: "ETH".to_string(),
+        };
+        assert!(money.validate().is_ok());
+        assert!(
+            Money {
+                amount: Amount::ZERO,
+                ..money.clone()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            Money {
+                asset: " ".to_string(),
+                ..money
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_numeric_encoding_round_trips() {
+        for value in [
+            "0",
+            "1",
+            "-1",
+            "9999",
+            "10000",
+            "100000000",
+            "-123456789012345678901234567890",
+            "99999999999999999999999999999999999999",
+        ] {
+            let mut buf = BytesMut::new();
+            amount(value).to_sql(&Type::NUMERIC, &mut buf).unwrap();
+            assert_eq!(
+                Amount::from_sql(&Type::NUMERIC, &buf).unwrap(),
+                amount(value),
+                "{}",
+                value
+            );
+        }
+        assert!(!<Amount as ToSql>::accepts(&Type::INT8));
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_amounts_match_postgres_numerics() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        let client = pool.get().await.unwrap();
+
+        for value in [
+            "0",
+            "-7",
+            "10000",
+            "1000000000000000000000",
+            "99999999999999999999999999999999999999",
+        ] {
+            let row = client
+                .query_one(
+                    "SELECT $1::NUMERIC(38, 0), $1::NUMERIC(38, 0)::TEXT, $2::TEXT::NUMERIC(38, 0)",
+                    &[&amount(value), &value],
+                )
+                .await
+                .unwrap();
+            assert_eq!(row.get::<_, Amount>(0), amount(value));
+            assert_eq!(row.get::<_, String>(1), value);
+            assert_eq!(row.get::<_, Amount>(2), amount(value));
+        }
+
+        // Only whole numbers come back as amounts
+        let row = client
+            .query_one("SELECT 2.50::NUMERIC, 3.00::NUMERIC", &[])
+            .await
+            .unwrap();
+        assert!(row.try_get::<_, Amount>(0).is_err());
+        assert_eq!(row.get::<_, Amount>(1), Amount::from(3));
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+
+    use crate::controller::notification_controller;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::notification::CreateNotification;
+    use crate::repositories::notification_repository;
+    use crate::tests::{bearer, test_auth_config};
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_notifications_are_scoped_to_their_recipient() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(notification_controller::config),
+        )
+        .await;
+
+        let alice_id = rand::random::<u16>() as i32 + 100_000;
+        let bob_id = alice_id + 1;
+        let alice = bearer(alice_id, &[Role::Owner]);
+        let bob = bearer(bob_id, &[Role::Owner]);
+        let admin = bearer(alice_id + 2, &[Role::Admin]);
+
+        let client = pool.get().await.unwrap();
+        let notify = |user_id: Option<i32>| CreateNotification {
+            user_id,
+            title: "Notice".to_string(),
+            body: "Something happened".to_string(),
+        };
+        let to_alice = notification_repository::create(&client, &notify(Some(alice_id)))
+            .await
+            .unwrap();
+        let to_everyone = notification_repository::create(&client, &notify(None))
+            .await
+            .unwrap();
+
+        // Bob sees the announcement but not Alice's notification
+        let req = test::TestRequest::get()
+            .uri("/notifications")
+            .insert_header(("Authorization", bob.clone()))
+            .to_request();
+        let listed: Vec<serde_json::Value> = test::call_and_read_body_json(&app, req).await;
+        let ids: Vec<i64> = listed.iter().map(|n| n["id"].as_i64().unwrap()).collect();
+        assert!(ids.contains(&(to_everyone.id as i64)));
+        assert!(!ids.contains(&(to_alice.id as i64)));
+
+        let uri = format!("/notifications/{}", to_alice.id);
+        for (method, uri) in [
+            (test::TestRequest::get(), uri.clone()),
+            (test::TestRequest::post(), format!("{}/read", uri)),
+        ] {
+            let req = method
+                .uri(&uri)
+                .insert_header(("Authorization", bob.clone()))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status().as_u16(), 404);
+        }
+
+        // Alice can read it and mark it read
+        let req = test::TestRequest::post()
+            .uri(&format!("{}/read", uri))
+            .insert_header(("Authorization", alice.clone()))
+            .to_request();
+        let read: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(read["is_read"], true);
+
+        // Recipients can't rewrite or delete what they were sent
+        let edit = serde_json::json!({ "title": "Edited" });
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", alice.clone()))
+            .set_json(&edit)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+        let req = test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header(("Authorization", alice.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        let req = test::TestRequest::get().uri("/notifications").to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+        // Admins can correct them
+        let req = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", admin.clone()))
+            .set_json(&edit)
+            .to_request();
+        let edited: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(edited["title"], "Edited");
+        for id in [to_alice.id, to_everyone.id] {
+            let req = test::TestRequest::delete()
+                .uri(&format!("/notifications/{}", id))
+                .insert_header(("Authorization", admin.clone()))
+                .to_request();
+            assert!(test::call_service(&app, req).await.status().is_success());
+        }
+    }
+}
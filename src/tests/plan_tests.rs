@@ -0,0 +1,29 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::plan_models::{CreatePlan, PlanStatus};
+
+    #[test]
+    fn test_plan_lifecycle_transitions() {
+        assert!(PlanStatus::Draft.can_transition_to(PlanStatus::Active));
+        assert!(PlanStatus::Active.can_transition_to(PlanStatus::Triggered));
+        assert!(PlanStatus::Triggered.can_transition_to(PlanStatus::Executed));
+        assert!(PlanStatus::Triggered.can_transition_to(PlanStatus::Active));
+        assert!(PlanStatus::Active.can_transition_to(PlanStatus::Cancelled));
+
+        assert!(!PlanStatus::Draft.can_transition_to(PlanStatus::Executed));
+        assert!(!PlanStatus::Executed.can_transition_to(PlanStatus::Active));
+        assert!(!PlanStatus::Cancelled.can_transition_to(PlanStatus::Active));
+        assert!(!PlanStatus::Triggered.can_transition_to(PlanStatus::Cancelled));
+    }
+
+    #[test]
+    fn test_create_plan_requires_name() {
+        let plan = CreatePlan {
+            name: "  ".to_string(),
+            description: None,
+            assets: Vec::new(),
+            beneficiaries: Vec::new(),
+        };
+        assert!(plan.validate().is_err());
+    }
+}
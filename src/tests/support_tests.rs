@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+
+    use crate::controller::user_support_controller;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::support_models::{
+        CreateTicket, TicketCategory, TicketFilter, TicketPriority, TicketStatus,
+    };
+    use crate::tests::{bearer, test_auth_config};
+
+    fn ticket() -> CreateTicket {
+        CreateTicket {
+            subject: "Cannot activate my plan".to_string(),
+            description: "The activate button does nothing".to_string(),
+            category: TicketCategory::Plan,
+            priority: TicketPriority::Normal,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_ticket_validation() {
+        assert!(ticket().validate().is_ok());
+        assert!(
+            CreateTicket {
+                subject: " ".to_string(),
+                ..ticket()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            CreateTicket {
+                description: String::new(),
+                ..ticket()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            CreateTicket {
+                subject: "x".repeat(256),
+                ..ticket()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_closed_tickets_are_final() {
+        assert!(TicketStatus::Open.can_transition_to(TicketStatus::Resolved));
+        assert!(TicketStatus::Resolved.can_transition_to(TicketStatus::Open));
+        assert!(TicketStatus::Pending.can_transition_to(TicketStatus::Closed));
+        assert!(!TicketStatus::Closed.can_transition_to(TicketStatus::Open));
+    }
+
+    #[actix_web::test]
+    async fn test_priority_defaults_to_normal() {
+        let ticket: CreateTicket = serde_json::from_value(serde_json::json!({
+            "subject": "Hello",
+            "description": "World",
+            "category": "kyc"
+        }))
+        .unwrap();
+        assert_eq!(ticket.priority, TicketPriority::Normal);
+        assert_eq!(ticket.category, TicketCategory::Kyc);
+    }
+
+    #[actix_web::test]
+    async fn test_filter_parses_from_query() {
+        let query =
+            web::Query::<TicketFilter>::from_query("status=pending&assignee_id=7&page=2").unwrap();
+        assert_eq!(query.status, Some(TicketStatus::Pending));
+        assert_eq!(query.assignee_id, Some(7));
+        assert_eq!(query.page, Some(2));
+        assert!(web::Query::<TicketFilter>::from_query("status=lost").is_err());
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_ticket_conversation() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(user_support_controller::config),
+        )
+        .await;
+
+        let user_id = rand::random::<u16>() as i32 + 100_000;
+        let user = bearer(user_id, &[Role::Owner]);
+        let agent_id = user_id + 1;
+        let agent = bearer(agent_id, &[Role::SupportAgent]);
+
+        let req = test::TestRequest::post()
+            .uri("/support/tickets")
+            .insert_header(("Authorization", user.clone()))
+            .set_json(ticket())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(created["status"], "open");
+        let id = created["id"].as_i64().unwrap();
+
+        // Someone else's ticket
+        let req = test::TestRequest::get()
+            .uri(&format!("/support/tickets/{}", id))
+            .insert_header(("Authorization", bearer(user_id + 2, &[Role::Owner])))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        // Only agents triage
+        let req = test::TestRequest::put()
+            .uri(&format!("/support/tickets/{}", id))
+            .insert_header(("Authorization", user.clone()))
+            .set_json(serde_json::json!({"assignee_id": agent_id}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/support/tickets/{}", id))
+            .insert_header(("Authorization", agent.clone()))
+            .set_json(serde_json::json!({"assignee_id": agent_id, "priority": "high"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let updated: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(updated["priority"], "high");
+
+        // The agent's queue
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/support/tickets?status=open&assignee_id={}",
+                agent_id
+            ))
+            .insert_header(("Authorization", agent.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let queue: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(queue["total"], 1);
+        assert_eq!(queue["tickets"][0]["id"], id);
+
+        // The agent answers, then the user follows up
+        for (author, expected_status) in [(&agent, "pending"), (&user, "open")] {
+            let req = test::TestRequest::post()
+                .uri(&format!("/support/tickets/{}/reply", id))
+                .insert_header(("Authorization", author.clone()))
+                .set_json(serde_json::json!({"body": "Some reply"}))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status().as_u16(), 201);
+
+            let req = test::TestRequest::get()
+                .uri(&format!("/support/tickets/{}", id))
+                .insert_header(("Authorization", user.clone()))
+                .to_request();
+            let thread: serde_json::Value =
+                test::read_body_json(test::call_service(&app, req).await).await;
+            assert_eq!(thread["status"], expected_status);
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/support/tickets/{}", id))
+            .insert_header(("Authorization", user.clone()))
+            .to_request();
+        let thread: serde_json::Value =
+            test::read_body_json(test::call_service(&app, req).await).await;
+        let authors: Vec<&str> = thread["replies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|reply| reply["author_role"].as_str().unwrap())
+            .collect();
+        assert_eq!(authors, ["agent", "user"]);
+
+        // Closed tickets take no more replies
+        let req = test::TestRequest::put()
+            .uri(&format!("/support/tickets/{}", id))
+            .insert_header(("Authorization", agent.clone()))
+            .set_json(serde_json::json!({"status": "closed"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/support/tickets/{}/reply", id))
+            .insert_header(("Authorization", user.clone()))
+            .set_json(serde_json::json!({"body": "Hello?"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+    }
+}
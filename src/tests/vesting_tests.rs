@@ -0,0 +1,337 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use actix_web::{App, test, web};
+    use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+
+    use crate::controller::{beneficiary_controller, plan_controller};
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::beneficiary_models::{Allocation, Beneficiary};
+    use crate::models::execution_models::{compute_payouts, released_on_execution};
+    use crate::models::money::Amount;
+    use crate::models::plan_models::{Plan, PlanAsset, PlanStatus};
+    use crate::models::vesting_models::{ReleaseSchedule, release_status};
+    use crate::tests::{bearer, test_auth_config, trigger_plan};
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    fn plan(releases: Vec<ReleaseSchedule>) -> Plan {
+        Plan {
+            id: 1,
+            owner_id: 7,
+            name: "Estate".to_string(),
+            description: None,
+            assets: vec![PlanAsset {
+                asset: "ETH".to_string(),
+                amount: Amount::from(1_000 * releases.len() as i64),
+            }],
+            beneficiaries: releases
+                .into_iter()
+                .enumerate()
+                .map(|(index, release)| Beneficiary {
+                    id: index as i32 + 1,
+                    plan_id: 1,
+                    user_id: None,
+                    name: format!("Heir {}", index),
+                    wallet_address: format!("0x{:040x}", index),
+                    relationship: None,
+                    email: None,
+                    allocation: Allocation::Fixed {
+                        asset: "ETH".to_string(),
+                        amount: Amount::from(1_000),
+                    },
+                    release,
+                    position: index as i32,
+                })
+                .collect(),
+            status: PlanStatus::Triggered,
+            created_at: start(),
+            updated_at: start(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_lump_sum_is_released_on_execution() {
+        let release = ReleaseSchedule::LumpSum;
+        assert_eq!(
+            release.unlocked(Amount::from(1_000), start(), start() - Duration::seconds(1)),
+            Amount::ZERO
+        );
+        assert_eq!(
+            release.unlocked(Amount::from(1_000), start(), start()),
+            Amount::from(1_000)
+        );
+        assert_eq!(release.fully_released_at(start()), Some(start()));
+    }
+
+    #[actix_web::test]
+    async fn test_tranches_release_equal_parts() {
+        let release = ReleaseSchedule::Tranches {
+            count: 3,
+            interval_days: 30,
+        };
+        let at = |days: i64| {
+            i128::from(release.unlocked(
+                Amount::from(1_000),
+                start(),
+                start() + Duration::days(days),
+            ))
+        };
+
+        assert_eq!(at(0), 333);
+        assert_eq!(at(29), 333);
+        assert_eq!(at(30), 666);
+        // The last tranche carries the rounding remainder
+        assert_eq!(at(60), 1_000);
+        assert_eq!(at(600), 1_000);
+        assert_eq!(
+            release.fully_released_at(start()),
+            Some(start() + Duration::days(60))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_release_at_age() {
+        let release = ReleaseSchedule::AtAge {
+            birth_date: NaiveDate::from_ymd_opt(2020, 6, 15).unwrap(),
+            age: 18,
+        };
+        let eighteenth = Utc.with_ymd_and_hms(2038, 6, 15, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            release.unlocked(
+                Amount::from(1_000),
+                start(),
+                eighteenth - Duration::seconds(1)
+            ),
+            Amount::ZERO
+        );
+        assert_eq!(
+            release.unlocked(Amount::from(1_000), start(), eighteenth),
+            Amount::from(1_000)
+        );
+        assert_eq!(release.fully_released_at(start()), Some(eighteenth));
+
+        // An adult heir gets everything on execution
+        let adult = ReleaseSchedule::AtAge {
+            birth_date: NaiveDate::from_ymd_opt(1990, 6, 15).unwrap(),
+            age: 18,
+        };
+        assert_eq!(
+            adult.unlocked(Amount::from(1_000), start(), start()),
+            Amount::from(1_000)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_cliff_then_linear_release() {
+        let release = ReleaseSchedule::CliffLinear {
+            cliff_days: 90,
+            duration_days: 360,
+        };
+        let at = |days: i64| {
+            i128::from(release.unlocked(
+                Amount::from(3_600),
+                start(),
+                start() + Duration::days(days),
+            ))
+        };
+
+        assert_eq!(at(0), 0);
+        assert_eq!(at(89), 0);
+        assert_eq!(at(90), 900);
+        assert_eq!(at(180), 1_800);
+        assert_eq!(at(360), 3_600);
+        assert_eq!(at(1_000), 3_600);
+    }
+
+    #[actix_web::test]
+    async fn test_schedule_validation() {
+        let invalid = [
+            ReleaseSchedule::Tranches {
+                count: 0,
+                interval_days: 30,
+            },
+            ReleaseSchedule::Tranches {
+                count: 2,
+                interval_days: 0,
+            },
+            ReleaseSchedule::Tranches {
+                count: 1_000,
+                interval_days: 365,
+            },
+            ReleaseSchedule::CliffLinear {
+                cliff_days: 10,
+                duration_days: 0,
+            },
+            ReleaseSchedule::CliffLinear {
+                cliff_days: 400,
+                duration_days: 360,
+            },
+            ReleaseSchedule::AtAge {
+                birth_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                age: 500,
+            },
+        ];
+        for schedule in invalid {
+            assert!(schedule.validate().is_err(), "{:?}", schedule);
+        }
+
+        let single = ReleaseSchedule::Tranches {
+            count: 1,
+            interval_days: 0,
+        };
+        assert!(single.validate().is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_schedule_json_shape() {
+        let schedule: ReleaseSchedule = serde_json::from_value(serde_json::json!({
+            "type": "cliff_linear",
+            "cliff_days": 30,
+            "duration_days": 365
+        }))
+        .unwrap();
+        assert_eq!(
+            schedule,
+            ReleaseSchedule::CliffLinear {
+                cliff_days: 30,
+                duration_days: 365
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_executor_pays_only_what_is_released() {
+        let plan = plan(vec![
+            ReleaseSchedule::LumpSum,
+            ReleaseSchedule::Tranches {
+                count: 4,
+                interval_days: 365,
+            },
+            ReleaseSchedule::AtAge {
+                birth_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                age: 21,
+            },
+        ]);
+
+        let payouts = released_on_execution(&plan, compute_payouts(&plan).unwrap(), start());
+        let amounts: Vec<(i32, i128)> = payouts
+            .iter()
+            .map(|payout| (payout.beneficiary_id, payout.amount.into()))
+            .collect();
+        assert_eq!(amounts, [(1, 1_000), (2, 250)]);
+    }
+
+    #[actix_web::test]
+    async fn test_release_status_subtracts_withdrawals() {
+        let plan = plan(vec![ReleaseSchedule::Tranches {
+            count: 4,
+            interval_days: 365,
+        }]);
+        let as_of = start() + Duration::days(400);
+
+        let not_started = release_status(&plan, 1, None, &HashMap::new(), as_of).unwrap();
+        assert_eq!(not_started.assets[0].unlocked, Amount::ZERO);
+        assert_eq!(not_started.assets[0].fully_released_at, None);
+
+        let withdrawn = HashMap::from([("ETH".to_string(), Amount::from(300))]);
+        let status = release_status(&plan, 1, Some(start()), &withdrawn, as_of).unwrap();
+        let eth = &status.assets[0];
+        assert_eq!(eth.entitled, Amount::from(1_000));
+        assert_eq!(eth.unlocked, Amount::from(500));
+        assert_eq!(eth.withdrawn, Amount::from(300));
+        assert_eq!(eth.available, Amount::from(200));
+
+        assert!(release_status(&plan, 99, Some(start()), &withdrawn, as_of).is_err());
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_release_as_of_any_date() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(beneficiary_controller::config)
+                .configure(plan_controller::config),
+        )
+        .await;
+
+        let owner_id = rand::random::<u16>() as i32 + 100_000;
+        let heir_id = owner_id + 1;
+        let owner = bearer(owner_id, &[Role::Owner]);
+
+        let req = test::TestRequest::post()
+            .uri("/plans")
+            .insert_header(("Authorization", owner.clone()))
+            .set_json(serde_json::json!({
+                "name": "Staged estate",
+                "assets": [{"asset": "ETH", "amount": 1200}],
+                "beneficiaries": [{
+                    "user_id": heir_id,
+                    "name": "Minor heir",
+                    "wallet_address": "0x1111111111111111111111111111111111111111",
+                    "allocation": {"type": "percentage", "share_bps": 10000},
+                    "release": {"type": "tranches", "count": 4, "interval_days": 30}
+                }]
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        let id = created["id"].as_i64().unwrap();
+        let beneficiary_id = created["beneficiaries"][0]["id"].as_i64().unwrap();
+        assert_eq!(created["beneficiaries"][0]["release"]["type"], "tranches");
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/activate", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        trigger_plan(&pool, id as i32).await;
+        let req = test::TestRequest::post()
+            .uri(&format!("/plans/{}/execute", id))
+            .insert_header(("Authorization", owner.clone()))
+            .to_request();
+        let execution: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(execution["payouts"][0]["amount"], "300");
+
+        let release_at = |as_of: DateTime<Utc>, who: String| {
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/plans/{}/beneficiaries/{}/release?as_of={}",
+                    id,
+                    beneficiary_id,
+                    as_of.format("%Y-%m-%dT%H:%M:%SZ")
+                ))
+                .insert_header(("Authorization", who))
+                .to_request()
+        };
+
+        // The heir looks ahead two months: three tranches, one already paid
+        let req = release_at(
+            Utc::now() + Duration::days(61),
+            bearer(heir_id, &[Role::Beneficiary]),
+        );
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let release: serde_json::Value = test::read_body_json(resp).await;
+        let eth = &release["assets"][0];
+        assert_eq!(eth["unlocked"], "900");
+        assert_eq!(eth["withdrawn"], "300");
+        assert_eq!(eth["available"], "600");
+
+        let req = release_at(Utc::now(), bearer(heir_id + 1, &[Role::Beneficiary]));
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+    }
+}
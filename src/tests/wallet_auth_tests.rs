@@ -0,0 +1,241 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+    use chrono::{TimeZone, Utc};
+    use k256::ecdsa::SigningKey;
+    use starknet_crypto::{FieldElement, get_public_key, rfc6979_generate_k, sign};
+
+    use crate::controller::auth_controller;
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::{AuthNonce, Chain, NonceResponse, WalletVerifyRequest};
+    use crate::tests::test_auth_config;
+    use crate::utils::wallet_signature::{
+        evm_address, normalize_address, personal_message_hash, starknet_account_address,
+        starknet_keccak, verify_evm, verify_starknet,
+    };
+
+    // Well-known test key, never holds funds
+    const EVM_PRIVATE_KEY: &str =
+        "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const EVM_ADDRESS: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+
+    const STARK_PRIVATE_KEY: &str =
+        "0x0139fe4d6f02e666e86a6f58e65060f115cd3c185bd9e98bd829636931458f79";
+    const ACCOUNT_CLASS_HASH: &str = "0x0123456789abcdef";
+
+    fn evm_key() -> SigningKey {
+        SigningKey::from_slice(&hex::decode(EVM_PRIVATE_KEY).unwrap()).unwrap()
+    }
+
+    fn evm_sign(message: &str) -> String {
+        let (signature, recovery_id) = evm_key()
+            .sign_prehash_recoverable(&personal_message_hash(message))
+            .unwrap();
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    fn stark_account() -> (FieldElement, FieldElement, String) {
+        let private_key = FieldElement::from_hex_be(STARK_PRIVATE_KEY).unwrap();
+        let public_key = get_public_key(&private_key);
+        let class_hash = FieldElement::from_hex_be(ACCOUNT_CLASS_HASH).unwrap();
+        let address = starknet_account_address(&public_key, &class_hash);
+        (private_key, public_key, format!("{:#066x}", address))
+    }
+
+    fn stark_sign(private_key: &FieldElement, message: &str) -> Vec<String> {
+        let hash = starknet_keccak(message.as_bytes());
+        let k = rfc6979_generate_k(&hash, private_key, None);
+        let signature = sign(private_key, &hash, &k).unwrap();
+        vec![format!("{:#x}", signature.r), format!("{:#x}", signature.s)]
+    }
+
+    fn sample_nonce(chain: Chain, wallet_address: &str) -> AuthNonce {
+        AuthNonce {
+            nonce: "8f14e45fceea167a5a36dedd4bea2543".to_string(),
+            wallet_address: normalize_address(chain, wallet_address).unwrap(),
+            chain,
+            issued_at: Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap(),
+            expires_at: Utc.with_ymd_and_hms(2025, 1, 2, 3, 9, 5).unwrap(),
+            used_at: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_sign_in_message_is_stable() {
+        let nonce = sample_nonce(Chain::Evm, EVM_ADDRESS);
+        assert_eq!(
+            nonce.message(),
+            "InheritX wants you to sign in with your evm account:\n\
+             0x2c7536e3605d9c16a7a3d7b1898e529396a65c23\n\n\
+             Nonce: 8f14e45fceea167a5a36dedd4bea2543\n\
+             Issued At: 2025-01-02T03:04:05Z\n\
+             Expiration Time: 2025-01-02T03:09:05Z"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_address_normalization() {
+        assert_eq!(
+            normalize_address(Chain::Evm, EVM_ADDRESS).unwrap(),
+            "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"
+        );
+        assert!(normalize_address(Chain::Evm, "0x1234").is_err());
+        assert!(normalize_address(Chain::Evm, "2c7536e3605d9c16a7a3d7b1898e529396a65c23").is_err());
+
+        assert_eq!(
+            normalize_address(Chain::Starknet, "0x7b").unwrap(),
+            format!("0x{:0>64}", "7b")
+        );
+        assert!(normalize_address(Chain::Starknet, "not-a-felt").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_evm_key_derives_known_address() {
+        assert_eq!(
+            evm_address(evm_key().verifying_key()),
+            normalize_address(Chain::Evm, EVM_ADDRESS).unwrap()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_evm_signature_verifies() {
+        let message = sample_nonce(Chain::Evm, EVM_ADDRESS).message();
+        let signature = evm_sign(&message);
+
+        assert!(verify_evm(EVM_ADDRESS, &message, &signature).is_ok());
+
+        // Signed by someone else
+        let other = "0x0000000000000000000000000000000000000001";
+        assert!(verify_evm(other, &message, &signature).is_err());
+
+        // Over a different message
+        assert!(verify_evm(EVM_ADDRESS, &format!("{} ", message), &signature).is_err());
+
+        // Truncated
+        assert!(verify_evm(EVM_ADDRESS, &message, &signature[..100]).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_starknet_signature_verifies() {
+        let (private_key, public_key, address) = stark_account();
+        let class_hash = FieldElement::from_hex_be(ACCOUNT_CLASS_HASH).unwrap();
+        let message = sample_nonce(Chain::Starknet, &address).message();
+        let signature = stark_sign(&private_key, &message);
+        let public_key = format!("{:#x}", public_key);
+
+        assert!(verify_starknet(&address, &public_key, &class_hash, &message, &signature).is_ok());
+
+        // Different message
+        assert!(verify_starknet(&address, &public_key, &class_hash, "other", &signature).is_err());
+
+        // A key that does not control the account, even with a valid signature
+        let other_private = FieldElement::from_hex_be(
+            "0x2dccce1da22003777062ee0870e9881b460a8b7eca276870f57c601f182136c",
+        )
+        .unwrap();
+        let other_public = format!("{:#x}", get_public_key(&other_private));
+        let other_signature = stark_sign(&other_private, &message);
+        assert_eq!(
+            verify_starknet(
+                &address,
+                &other_public,
+                &class_hash,
+                &message,
+                &other_signature
+            )
+            .unwrap_err(),
+            "Public key does not control this account"
+        );
+
+        // Accounts of another class live at other addresses
+        let other_class = FieldElement::from_hex_be("0x42").unwrap();
+        assert!(
+            verify_starknet(&address, &public_key, &other_class, &message, &signature).is_err()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_wallet_login_can_be_disabled() {
+        // The pool connects lazily, so no database is needed here
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        let mut auth_config = test_auth_config();
+        auth_config.wallet_login = false;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(auth_config.clone()))
+                .wrap(JwtAuth::new(auth_config))
+                .configure(auth_controller::config),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/auth/nonce?wallet_address={}&chain=evm",
+                EVM_ADDRESS
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "Wallet login is disabled");
+    }
+
+    // Talks to the database from docker-compose.yml, run it with
+    // `./db.sh start && cargo test -- --ignored`.
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_wallet_login_rejects_replayed_signature() {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(test_auth_config()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(auth_controller::config),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/auth/nonce?wallet_address={}&chain=evm",
+                EVM_ADDRESS
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let challenge: NonceResponse = test::read_body_json(resp).await;
+
+        let request = WalletVerifyRequest {
+            wallet_address: EVM_ADDRESS.to_string(),
+            chain: Chain::Evm,
+            nonce: challenge.nonce.clone(),
+            signature: vec![evm_sign(&challenge.message)],
+            public_key: None,
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/auth/verify")
+            .set_json(&request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["access_token"].is_string());
+
+        // The same signature a second time
+        let req = test::TestRequest::post()
+            .uri("/auth/verify")
+            .set_json(&request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 401);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "Nonce has already been used");
+    }
+}
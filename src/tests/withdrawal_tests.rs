@@ -0,0 +1,429 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{App, test, web};
+    use deadpool_postgres::Pool;
+
+    use crate::controller::{kyc_controller, withdrawal_history_controller};
+    use crate::middlewares::auth_middleware::JwtAuth;
+    use crate::models::auth_models::Role;
+    use crate::models::beneficiary_models::{Allocation, NewBeneficiary};
+    use crate::models::kyc_models::{CreateKycRequest, KycStatus, KycVerificationRequest};
+    use crate::models::money::{Amount, Money};
+    use crate::models::plan_models::{CreatePlan, Plan, PlanAsset};
+    use crate::models::vesting_models::ReleaseSchedule;
+    use crate::models::withdrawal_history_models::{
+        CreateWithdrawalRequest, UpdateWithdrawalStatus, WithdrawalStatus,
+    };
+    use crate::repositories::plan_repository;
+    use crate::tests::{bearer, test_auth_config, test_blind_index_key, test_keyring};
+
+    fn request() -> CreateWithdrawalRequest {
+        CreateWithdrawalRequest {
+            plan_id: 1,
+            beneficiary_id: 1,
+            money: eth(1_000),
+        }
+    }
+
+    fn eth(amount: i64) -> Money {
+        Money {
+            amount: Amount::from(amount),
+            asset: "ETH".to_string(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_withdrawal_lifecycle_transitions() {
+        use WithdrawalStatus::*;
+
+        assert!(Requested.can_transition_to(Approved));
+        assert!(Approved.can_transition_to(Broadcast));
+        assert!(Broadcast.can_transition_to(Confirmed));
+        for status in [Requested, Approved, Broadcast] {
+            assert!(status.can_transition_to(Failed));
+        }
+
+        assert!(!Requested.can_transition_to(Broadcast));
+        assert!(!Requested.can_transition_to(Confirmed));
+        assert!(!Confirmed.can_transition_to(Failed));
+        assert!(!Failed.can_transition_to(Requested));
+    }
+
+    #[actix_web::test]
+    async fn test_create_withdrawal_validation() {
+        assert!(request().validate().is_ok());
+        assert!(
+            CreateWithdrawalRequest {
+                money: eth(0),
+                ..request()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            CreateWithdrawalRequest {
+                money: Money {
+                    asset: String::new(),
+                    ..eth(1_000)
+                },
+                ..request()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_status_update_needs_details() {
+        let update = |status, tx_hash: Option<&str>, failure_reason: Option<&str>| {
+            UpdateWithdrawalStatus {
+                status,
+                tx_hash: tx_hash.map(str::to_string),
+                failure_reason: failure_reason.map(str::to_string),
+            }
+            .validate()
+        };
+
+        assert!(update(WithdrawalStatus::Approved, None, None).is_ok());
+        assert!(update(WithdrawalStatus::Broadcast, None, None).is_err());
+        assert!(update(WithdrawalStatus::Broadcast, Some("0xabc"), None).is_ok());
+        assert!(update(WithdrawalStatus::Failed, None, Some("  ")).is_err());
+        assert!(update(WithdrawalStatus::Failed, None, Some("Reverted")).is_ok());
+    }
+
+    // These tests talk to the database from docker-compose.yml, run them with
+    // `./db.sh start && cargo test -- --ignored`.
+    async fn create_test_pool() -> Pool {
+        let pool = crate::db::create_pool(&crate::tests::test_database_settings()).await;
+        crate::db::run_migrations(&pool).await;
+        pool
+    }
+
+    // KYC records are looked up by user, so every test works with new users
+    fn fresh_user_id() -> i32 {
+        rand::random::<u16>() as i32 + 100_000
+    }
+
+    /// An executed plan leaving 5000 ETH to one heir in five yearly tranches,
+    /// three of which have been released.
+    async fn create_plan(pool: &Pool, owner_id: i32, beneficiary_user_id: i32) -> Plan {
+        let mut client = pool.get().await.unwrap();
+        let plan = CreatePlan {
+            name: "Withdrawal test".to_string(),
+            description: None,
+            assets: vec![PlanAsset {
+                asset: "ETH".to_string(),
+                amount: Amount::from(5_000),
+            }],
+            beneficiaries: vec![NewBeneficiary {
+                user_id: Some(beneficiary_user_id),
+                name: "Heir".to_string(),
+                wallet_address: "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23".to_string(),
+                relationship: None,
+                email: None,
+                allocation: Allocation::Percentage { share_bps: 10_000 },
+                release: ReleaseSchedule::Tranches {
+                    count: 5,
+                    interval_days: 365,
+                },
+            }],
+        };
+        let plan = plan_repository::create(&mut client, owner_id, &plan)
+            .await
+            .unwrap();
+
+        client
+            .execute(
+                "UPDATE plans SET status = 'executed' WHERE id = $1",
+                &[&plan.id],
+            )
+            .await
+            .unwrap();
+        client
+            .execute(
+                "INSERT INTO plan_executions (plan_id, executed_by, executed_at)
+                 VALUES ($1, $2, NOW() - INTERVAL '800 days')",
+                &[&plan.id, &owner_id],
+            )
+            .await
+            .unwrap();
+        plan
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_withdrawal_with_unverified_kyc() {
+        let pool = create_test_pool().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(withdrawal_history_controller::config),
+        )
+        .await;
+
+        // An heir without any KYC record
+        let owner_id = fresh_user_id();
+        let heir_id = owner_id + 1;
+        let plan = create_plan(&pool, owner_id, heir_id).await;
+        let withdrawal_req = test::TestRequest::post()
+            .uri("/api/withdrawals/record")
+            .insert_header(("Authorization", bearer(heir_id, &[Role::Beneficiary])))
+            .set_json(CreateWithdrawalRequest {
+                plan_id: plan.id,
+                beneficiary_id: plan.beneficiaries[0].id,
+                ..request()
+            })
+            .to_request();
+
+        let resp = test::call_service(&app, withdrawal_req).await;
+
+        // Should be forbidden (403) since KYC is not verified
+        assert_eq!(resp.status().as_u16(), 403);
+
+        let error_response: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            error_response["error"].as_str().unwrap(),
+            "KYC verification required"
+        );
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_withdrawal_with_verified_kyc() {
+        let pool = create_test_pool().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(test_keyring()))
+                .app_data(web::Data::new(test_blind_index_key()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(kyc_controller::config)
+                .configure(withdrawal_history_controller::config),
+        )
+        .await;
+
+        let owner_id = fresh_user_id();
+        let heir_id = owner_id + 1;
+        let plan = create_plan(&pool, owner_id, heir_id).await;
+
+        // First create a KYC record for the heir
+        let create_req = test::TestRequest::post()
+            .uri("/api/kyc/create")
+            .insert_header(("Authorization", bearer(heir_id, &[Role::Beneficiary])))
+            .set_json(CreateKycRequest {
+                user_id: heir_id,
+                full_name: "Test User".to_string(),
+                date_of_birth: "01-01-1990".to_string(),
+                id_type: "passport".to_string(),
+                id_number: "AB123456".to_string(),
+                address: "123 Test St, Test City".to_string(),
+            })
+            .to_request();
+
+        let resp = test::call_service(&app, create_req).await;
+        assert!(resp.status().is_success());
+
+        let kyc_response: serde_json::Value = test::read_body_json(resp).await;
+        let kyc_id = kyc_response["id"].as_i64().unwrap();
+
+        // Now review and verify the KYC
+        for status in [KycStatus::InReview, KycStatus::Verified] {
+            let verify_req = test::TestRequest::post()
+                .uri("/api/kyc/verify")
+                .insert_header(("Authorization", bearer(2, &[Role::KycReviewer])))
+                .set_json(KycVerificationRequest {
+                    id: kyc_id as i32,
+                    verification_status: status,
+                    rejection_reason: None,
+                    rejection_note: None,
+                })
+                .to_request();
+
+            let resp = test::call_service(&app, verify_req).await;
+            assert!(resp.status().is_success());
+        }
+
+        // Now try to make a withdrawal with verified KYC
+        let withdrawal = CreateWithdrawalRequest {
+            plan_id: plan.id,
+            beneficiary_id: plan.beneficiaries[0].id,
+            ..request()
+        };
+
+        // The owner can't withdraw the heir's allocation
+        let withdrawal_req = test::TestRequest::post()
+            .uri("/api/withdrawals/record")
+            .insert_header(("Authorization", bearer(owner_id, &[Role::Owner])))
+            .set_json(&withdrawal)
+            .to_request();
+        let resp = test::call_service(&app, withdrawal_req).await;
+        assert_eq!(resp.status().as_u16(), 403);
+
+        // A wallet in the request is ignored, the heir's is paid
+        let mut body = serde_json::to_value(&withdrawal).unwrap();
+        body["wallet_id"] = "0x000000000000000000000000000000000000dead".into();
+        let withdrawal_req = test::TestRequest::post()
+            .uri("/api/withdrawals/record")
+            .insert_header(("Authorization", bearer(heir_id, &[Role::Beneficiary])))
+            .set_json(&body)
+            .to_request();
+
+        let resp = test::call_service(&app, withdrawal_req).await;
+
+        // Should be created (201) since KYC is verified
+        assert_eq!(resp.status().as_u16(), 201);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "requested");
+        assert_eq!(body["user_id"], heir_id);
+        assert_eq!(body["amount"], "1000");
+        assert_eq!(body["wallet_id"], plan.beneficiaries[0].wallet_address);
+
+        // 3000 has been released and 1000 of it is already on its way
+        let withdrawal_req = test::TestRequest::post()
+            .uri("/api/withdrawals/record")
+            .insert_header(("Authorization", bearer(heir_id, &[Role::Beneficiary])))
+            .set_json(CreateWithdrawalRequest {
+                money: eth(2_001),
+                ..withdrawal.clone()
+            })
+            .to_request();
+        let resp = test::call_service(&app, withdrawal_req).await;
+        assert_eq!(resp.status().as_u16(), 400);
+
+        let withdrawal_req = test::TestRequest::post()
+            .uri("/api/withdrawals/record")
+            .insert_header(("Authorization", bearer(heir_id, &[Role::Beneficiary])))
+            .set_json(CreateWithdrawalRequest {
+                money: eth(2_000),
+                ..withdrawal.clone()
+            })
+            .to_request();
+        let resp = test::call_service(&app, withdrawal_req).await;
+        assert_eq!(resp.status().as_u16(), 201);
+
+        // An asset the plan doesn't hold
+        let withdrawal_req = test::TestRequest::post()
+            .uri("/api/withdrawals/record")
+            .insert_header(("Authorization", bearer(heir_id, &[Role::Beneficiary])))
+            .set_json(CreateWithdrawalRequest {
+                money: Money {
+                    asset: "BTC".to_string(),
+                    ..eth(1_000)
+                },
+                ..withdrawal
+            })
+            .to_request();
+        let resp = test::call_service(&app, withdrawal_req).await;
+        assert_eq!(resp.status().as_u16(), 400);
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires a running Postgres database"]
+    async fn test_withdrawal_status_follows_lifecycle() {
+        let pool = create_test_pool().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .wrap(JwtAuth::new(test_auth_config()))
+                .configure(withdrawal_history_controller::config),
+        )
+        .await;
+
+        let owner_id = fresh_user_id();
+        let plan = create_plan(&pool, owner_id, owner_id + 1).await;
+        let client = pool.get().await.unwrap();
+        let withdrawal = crate::repositories::withdrawal_history_repository::record_withdrawal(
+            &client,
+            owner_id + 1,
+            &CreateWithdrawalRequest {
+                plan_id: plan.id,
+                beneficiary_id: plan.beneficiaries[0].id,
+                ..request()
+            },
+            &plan.beneficiaries[0].wallet_address,
+        )
+        .await
+        .unwrap();
+
+        // Only the parties to the payout and admins can see it
+        for (who, visible) in [
+            (bearer(owner_id, &[Role::Owner]), true),
+            (bearer(owner_id + 1, &[Role::Beneficiary]), true),
+            (bearer(owner_id + 2, &[Role::Owner]), false),
+            (bearer(1, &[Role::Admin]), true),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/withdrawals/single?id={}", withdrawal.id))
+                .insert_header(("Authorization", who.clone()))
+                .to_request();
+            let status = test::call_service(&app, req).await.status().as_u16();
+            assert_eq!(status, if visible { 200 } else { 403 });
+
+            for uri in [
+                "/api/withdrawals/history?page=1&page_size=100".to_string(),
+                format!("/api/withdrawals/user?id={}", owner_id + 1),
+            ] {
+                let req = test::TestRequest::get()
+                    .uri(&uri)
+                    .insert_header(("Authorization", who.clone()))
+                    .to_request();
+                let body: serde_json::Value =
+                    test::read_body_json(test::call_service(&app, req).await).await;
+                let records = body.get("records").unwrap_or(&body);
+                let listed = records
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|record| record["id"] == withdrawal.id);
+                assert_eq!(listed, visible, "{}", uri);
+            }
+        }
+        let req = test::TestRequest::get()
+            .uri("/api/withdrawals/single?id=1")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 401);
+
+        let admin = bearer(1, &[Role::Admin]);
+        let set_status = |body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(&format!("/api/withdrawals/{}/status", withdrawal.id))
+                .insert_header(("Authorization", admin.clone()))
+                .set_json(body)
+                .to_request()
+        };
+
+        // Only admins move withdrawals along
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/withdrawals/{}/status", withdrawal.id))
+            .insert_header(("Authorization", bearer(owner_id, &[Role::Owner])))
+            .set_json(serde_json::json!({"status": "approved"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+        // Steps can't be skipped
+        let req = set_status(serde_json::json!({"status": "confirmed"}));
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+
+        let req = set_status(serde_json::json!({"status": "approved"}));
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+        let req = set_status(serde_json::json!({"status": "broadcast"}));
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 400);
+
+        let req = set_status(serde_json::json!({"status": "broadcast", "tx_hash": "0xabc"}));
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "broadcast");
+        assert_eq!(body["tx_hash"], "0xabc");
+
+        // Once in motion it can't be deleted
+        let req = test::TestRequest::post()
+            .uri("/api/withdrawals/delete")
+            .insert_header(("Authorization", bearer(owner_id + 1, &[Role::Beneficiary])))
+            .set_json(serde_json::json!({"id": withdrawal.id}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 409);
+    }
+}
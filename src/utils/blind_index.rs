@@ -0,0 +1,49 @@
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Bytes of the key, and of every index it makes.
+const KEY_LEN: usize = 32;
+
+/// Key for blind indexes, from `KYC_BLIND_INDEX_KEY`: keyed hashes of values
+/// that are stored encrypted, so equal values can be found without
+/// decrypting anything. Without the key, an index cannot be matched against
+/// guesses.
+#[derive(Clone)]
+pub struct BlindIndexKey {
+    key: [u8; KEY_LEN],
+}
+
+impl BlindIndexKey {
+    /// HMAC-SHA256 of `value` under this key. `domain` separates values of
+    /// different kinds, so equal values of different kinds do not match.
+    pub fn index(&self, domain: &str, value: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key size");
+        mac.update(domain.as_bytes());
+        mac.update(&[0]);
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+/// Parses 32 bytes of hex. Errors never repeat the key.
+impl FromStr for BlindIndexKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        hex::decode(s)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(|key| BlindIndexKey { key })
+            .ok_or_else(|| format!("expected {} bytes of hex", KEY_LEN))
+    }
+}
+
+// Written out by hand to keep the key out of logs
+impl fmt::Debug for BlindIndexKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BlindIndexKey([redacted])")
+    }
+}
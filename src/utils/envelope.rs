@@ -0,0 +1,186 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+/// Bytes of the random nonce in front of every ciphertext.
+const NONCE_LEN: usize = 12;
+
+/// Bytes of an AES-256 key, for master and data keys alike.
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum EnvelopeError {
+    #[error("No master key with id {0:?} is configured")]
+    UnknownKey(String),
+    #[error("Ciphertext is malformed or was tampered with")]
+    Corrupt,
+}
+
+/// The master keys that wrap per-record data keys, from `KYC_MASTER_KEYS`.
+///
+/// The first key wraps every new data key. The others are only used to
+/// unwrap, so records written under them stay readable until the
+/// re-encryption job has moved them onto the first.
+#[derive(Clone)]
+pub struct Keyring {
+    keys: Arc<[MasterKey]>,
+}
+
+struct MasterKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl Keyring {
+    /// Id of the key new data keys are wrapped with.
+    pub fn current_key_id(&self) -> &str {
+        &self.keys[0].id
+    }
+
+    /// Ids of every configured key, the current one first.
+    pub fn key_ids(&self) -> Vec<String> {
+        self.keys.iter().map(|key| key.id.clone()).collect()
+    }
+
+    /// A fresh data key for one record, along with that key wrapped by the
+    /// current master key, which is what gets stored.
+    pub fn new_data_key(&self) -> (DataKey, Vec<u8>) {
+        let bytes = rand::random::<[u8; KEY_LEN]>();
+        let wrapped = self.wrap(&bytes);
+        (DataKey::new(&bytes), wrapped)
+    }
+
+    /// Unwraps a stored data key with the master key it was wrapped by.
+    pub fn unwrap_data_key(
+        &self,
+        master_key_id: &str,
+        wrapped: &[u8],
+    ) -> Result<DataKey, EnvelopeError> {
+        let bytes = self.unwrap(master_key_id, wrapped)?;
+        Ok(DataKey::new(&bytes))
+    }
+
+    /// Wraps a stored data key again with the current master key, leaving
+    /// the data it encrypts untouched.
+    pub fn rewrap(&self, master_key_id: &str, wrapped: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+        let bytes = self.unwrap(master_key_id, wrapped)?;
+        Ok(self.wrap(&bytes))
+    }
+
+    fn wrap(&self, data_key: &[u8; KEY_LEN]) -> Vec<u8> {
+        let current = &self.keys[0];
+        seal(&current.cipher, current.id.as_bytes(), data_key)
+    }
+
+    fn unwrap(&self, master_key_id: &str, wrapped: &[u8]) -> Result<[u8; KEY_LEN], EnvelopeError> {
+        let master_key = self
+            .keys
+            .iter()
+            .find(|key| key.id == master_key_id)
+            .ok_or_else(|| EnvelopeError::UnknownKey(master_key_id.to_string()))?;
+        open(&master_key.cipher, master_key_id.as_bytes(), wrapped)?
+            .try_into()
+            .map_err(|_| EnvelopeError::Corrupt)
+    }
+}
+
+/// Parses `id:hex[,id:hex...]`, each key being 32 bytes of hex. Ids are up
+/// to 32 letters, digits, `_` or `-`. Errors never repeat the key material.
+impl FromStr for Keyring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys: Vec<MasterKey> = Vec::new();
+        for entry in s.split(',').map(str::trim) {
+            let Some((id, hex_key)) = entry.split_once(':') else {
+                return Err("expected comma separated id:hex pairs".to_string());
+            };
+            let valid_id = !id.is_empty()
+                && id.len() <= 32
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid_id {
+                return Err(format!(
+                    "key id {:?} must be 1 to 32 letters, digits, _ or -",
+                    id
+                ));
+            }
+            if keys.iter().any(|key| key.id == id) {
+                return Err(format!("key id {:?} is listed twice", id));
+            }
+            let cipher = hex::decode(hex_key)
+                .ok()
+                .filter(|bytes| bytes.len() == KEY_LEN)
+                .and_then(|bytes| Aes256Gcm::new_from_slice(&bytes).ok())
+                .ok_or_else(|| format!("key {:?} must be {} bytes of hex", id, KEY_LEN))?;
+            keys.push(MasterKey {
+                id: id.to_string(),
+                cipher,
+            });
+        }
+
+        Ok(Keyring { keys: keys.into() })
+    }
+}
+
+// Written out by hand to keep the keys out of logs
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring")
+            .field("key_ids", &self.key_ids())
+            .finish()
+    }
+}
+
+/// The key a single record's fields are encrypted with.
+pub struct DataKey {
+    cipher: Aes256Gcm,
+}
+
+impl DataKey {
+    fn new(bytes: &[u8; KEY_LEN]) -> DataKey {
+        DataKey {
+            cipher: Aes256Gcm::new(bytes.into()),
+        }
+    }
+
+    /// Encrypts one field. The field name is authenticated with it, so a
+    /// value moved into another column of the record no longer opens.
+    pub fn seal(&self, field: &str, plaintext: &str) -> Vec<u8> {
+        seal(&self.cipher, field.as_bytes(), plaintext.as_bytes())
+    }
+
+    pub fn open(&self, field: &str, sealed: &[u8]) -> Result<String, EnvelopeError> {
+        let plaintext = open(&self.cipher, field.as_bytes(), sealed)?;
+        String::from_utf8(plaintext).map_err(|_| EnvelopeError::Corrupt)
+    }
+}
+
+// The nonce goes in front of the ciphertext
+fn seal(cipher: &Aes256Gcm, aad: &[u8], msg: &[u8]) -> Vec<u8> {
+    let nonce = rand::random::<[u8; NONCE_LEN]>();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg, aad })
+        .expect("AES-GCM encrypts any message this size");
+    [&nonce[..], &ciphertext].concat()
+}
+
+fn open(cipher: &Aes256Gcm, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(EnvelopeError::Corrupt);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| EnvelopeError::Corrupt)
+}
@@ -0,0 +1,5 @@
+pub mod blind_index;
+pub mod clock;
+pub mod envelope;
+pub mod wallet_signature;
+pub mod webhook_signature;
@@ -0,0 +1,152 @@
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+use starknet_crypto::{FieldElement, pedersen_hash};
+
+use crate::models::auth_models::Chain;
+
+/// Canonical form of a wallet address, used both as the nonce key and as the
+/// stored `users.wallet_address`: lowercase `0x` + 40 hex digits on EVM, and
+/// the zero-padded 64 digit felt on Starknet.
+pub fn normalize_address(chain: Chain, address: &str) -> Result<String, String> {
+    match chain {
+        Chain::Evm => {
+            let hex = address
+                .strip_prefix("0x")
+                .filter(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                .ok_or_else(|| format!("Invalid EVM address: {}", address))?;
+            Ok(format!("0x{}", hex.to_ascii_lowercase()))
+        }
+        Chain::Starknet => parse_felt(address)
+            .map(|felt| format!("{:#066x}", felt))
+            .map_err(|_| format!("Invalid Starknet address: {}", address)),
+    }
+}
+
+/// Verifies an EIP-191 `personal_sign` signature (65 bytes, `r || s || v`)
+/// over `message` and checks that it recovers to `address`.
+pub fn verify_evm(address: &str, message: &str, signature: &str) -> Result<(), String> {
+    let bytes = hex::decode(signature.trim_start_matches("0x"))
+        .map_err(|_| "Signature must be hex encoded".to_string())?;
+    if bytes.len() != 65 {
+        return Err("Signature must be 65 bytes".to_string());
+    }
+
+    let mut signature =
+        Signature::from_slice(&bytes[..64]).map_err(|_| "Malformed signature".to_string())?;
+    // Wallets use 27/28, raw signers 0/1
+    let v = match bytes[64] {
+        27 | 28 => bytes[64] - 27,
+        v => v,
+    };
+    let mut recovery_id = RecoveryId::from_byte(v).ok_or("Malformed signature recovery id")?;
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+
+    let key = VerifyingKey::recover_from_prehash(
+        &personal_message_hash(message),
+        &signature,
+        recovery_id,
+    )
+    .map_err(|_| "Invalid signature".to_string())?;
+
+    if evm_address(&key) == normalize_address(Chain::Evm, address)? {
+        Ok(())
+    } else {
+        Err("Invalid signature".to_string())
+    }
+}
+
+/// Hash signed by `personal_sign` / `eth_sign`.
+pub fn personal_message_hash(message: &str) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()));
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+pub fn evm_address(key: &VerifyingKey) -> String {
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    format!("0x{}", hex::encode(&hash[12..]))
+}
+
+/// Verifies a Stark ECDSA signature (`[r, s]`) over the Starknet keccak of
+/// `message`.
+///
+/// Starknet accounts are contracts, so the address is not a function of the
+/// key alone. We accept accounts deployed from `account_class_hash` with the
+/// public key as both salt and sole constructor argument (the OpenZeppelin
+/// account layout), and check that `public_key` deploys to `address`.
+pub fn verify_starknet(
+    address: &str,
+    public_key: &str,
+    account_class_hash: &FieldElement,
+    message: &str,
+    signature: &[String],
+) -> Result<(), String> {
+    let [r, s] = signature else {
+        return Err("Starknet signatures must be [r, s]".to_string());
+    };
+    let r = parse_felt(r).map_err(|_| "Malformed signature".to_string())?;
+    let s = parse_felt(s).map_err(|_| "Malformed signature".to_string())?;
+    let public_key = parse_felt(public_key).map_err(|_| "Malformed public key".to_string())?;
+    let address = parse_felt(address).map_err(|_| "Invalid Starknet address".to_string())?;
+
+    if starknet_account_address(&public_key, account_class_hash) != address {
+        return Err("Public key does not control this account".to_string());
+    }
+
+    match starknet_crypto::verify(&public_key, &starknet_keccak(message.as_bytes()), &r, &s) {
+        Ok(true) => Ok(()),
+        _ => Err("Invalid signature".to_string()),
+    }
+}
+
+/// Keccak-256 truncated to 250 bits, as used for Starknet selectors.
+pub fn starknet_keccak(data: &[u8]) -> FieldElement {
+    let mut hash: [u8; 32] = Keccak256::digest(data).into();
+    hash[0] &= 0x03;
+    FieldElement::from_bytes_be(&hash).expect("250 bit value is a valid felt")
+}
+
+/// Counterfactual address of an account deployed by the zero address with
+/// `salt = public_key` and constructor calldata `[public_key]`.
+pub fn starknet_account_address(
+    public_key: &FieldElement,
+    account_class_hash: &FieldElement,
+) -> FieldElement {
+    let prefix = FieldElement::from_byte_slice_be(b"STARKNET_CONTRACT_ADDRESS")
+        .expect("prefix fits in a felt");
+    let calldata_hash = hash_on_elements(&[*public_key]);
+    let hash = hash_on_elements(&[
+        prefix,
+        FieldElement::ZERO,
+        *public_key,
+        *account_class_hash,
+        calldata_hash,
+    ]);
+
+    // Addresses live below 2^251 - 256
+    let upper_bound = FieldElement::from_hex_be(
+        "0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00",
+    )
+    .expect("valid bound");
+    if hash >= upper_bound {
+        hash - upper_bound
+    } else {
+        hash
+    }
+}
+
+pub fn parse_felt(value: &str) -> Result<FieldElement, String> {
+    FieldElement::from_hex_be(value).map_err(|_| format!("Invalid field element: {}", value))
+}
+
+fn hash_on_elements(elements: &[FieldElement]) -> FieldElement {
+    let hash = elements.iter().fold(FieldElement::ZERO, |acc, element| {
+        pedersen_hash(&acc, element)
+    });
+    pedersen_hash(&hash, &FieldElement::from(elements.len()))
+}